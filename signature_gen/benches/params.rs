@@ -0,0 +1,81 @@
+//! Benchmarks for the parameter generation hot paths. Run with `cargo bench`.
+//!
+//! Everything except key generation starts from fixture keys, so the other
+//! measurements aren't dominated by prime search.
+
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use num_bigint::BigUint;
+use rand::SeedableRng;
+use rsa::pkcs8::DecodePrivateKey;
+use rsa::traits::PublicKeyParts;
+use rsa::RsaPrivateKey;
+
+use noir_bignum_paramgen::{compute_barrett_reduction_parameter, split_into_120_bit_limbs};
+
+use signature_gen::params::{num_limbs, KeyParams};
+use signature_gen::schema::Names;
+use signature_gen::templates::{NoirTemplate, RuntimeTemplate};
+
+const FIXTURE_KEYS: [(usize, &str); 3] = [
+    (1024, include_str!("../tests/fixtures/rsa_1024.pem")),
+    (2048, include_str!("../tests/fixtures/rsa_2048.pem")),
+    (4096, include_str!("../tests/fixtures/rsa_4096.pem")),
+];
+
+fn moduli() -> Vec<(usize, BigUint)> {
+    FIXTURE_KEYS
+        .iter()
+        .map(|(bits, pem)| (*bits, RsaPrivateKey::from_pkcs8_pem(pem).unwrap().n().clone()))
+        .collect()
+}
+
+fn bench_keygen(c: &mut Criterion) {
+    let mut group = c.benchmark_group("keygen");
+    group.sample_size(10);
+    for bits in [1024, 2048, 4096] {
+        let mut rng = rand_chacha::ChaCha20Rng::seed_from_u64(0);
+        group.bench_with_input(BenchmarkId::from_parameter(bits), &bits, |b, &bits| {
+            b.iter(|| RsaPrivateKey::new(&mut rng, bits).unwrap())
+        });
+    }
+    group.finish();
+}
+
+fn bench_barrett(c: &mut Criterion) {
+    let mut group = c.benchmark_group("compute_barrett_reduction_parameter");
+    for (bits, modulus) in moduli() {
+        group.bench_with_input(BenchmarkId::from_parameter(bits), &modulus, |b, modulus| {
+            b.iter(|| compute_barrett_reduction_parameter(black_box(modulus)))
+        });
+    }
+    group.finish();
+}
+
+fn bench_split(c: &mut Criterion) {
+    let mut group = c.benchmark_group("split_into_120_bit_limbs");
+    for (bits, modulus) in moduli() {
+        // Reported as limbs per second, so changes to the limb width show up directly
+        group.throughput(Throughput::Elements(num_limbs(bits) as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(bits), &modulus, |b, modulus| {
+            b.iter(|| split_into_120_bit_limbs(black_box(modulus), bits))
+        });
+    }
+    group.finish();
+}
+
+fn bench_formatting(c: &mut Criterion) {
+    let mut group = c.benchmark_group("formatting");
+    for (bits, modulus) in moduli() {
+        let key = KeyParams::from_modulus(&modulus, bits);
+        group.bench_with_input(BenchmarkId::new("toml", bits), &key, |b, key| {
+            b.iter(|| key.render_toml(Names::Canonical))
+        });
+        group.bench_with_input(BenchmarkId::new("noir-runtime", bits), &key, |b, key| {
+            b.iter(|| RuntimeTemplate.params(key, "params"))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_keygen, bench_barrett, bench_split, bench_formatting);
+criterion_main!(benches);
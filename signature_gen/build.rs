@@ -0,0 +1,13 @@
+use std::process::Command;
+
+fn main() {
+    // Record the commit the tool is built from in the provenance metadata of emitted files
+    if let Ok(output) = Command::new("git").args(["rev-parse", "HEAD"]).output() {
+        if output.status.success() {
+            let hash = String::from_utf8_lossy(&output.stdout);
+            println!("cargo:rustc-env=SIGNATURE_GEN_GIT_HASH={}", hash.trim());
+        }
+    }
+    println!("cargo:rerun-if-changed=../.git/HEAD");
+    println!("cargo:rerun-if-changed=../.git/refs/heads");
+}
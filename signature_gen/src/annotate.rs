@@ -0,0 +1,275 @@
+//! `--annotate`: comments explaining every emitted field
+//!
+//! The explanations are computed from the same `KeyParams` and
+//! `SignatureParams` values that are rendered, and attached to the rendered
+//! output by field name, so the annotated output is the plain output plus
+//! comment lines. A field without an explanation is a bug caught by the tests.
+
+use crate::hash::HashAlg;
+use crate::meta::META;
+use crate::params::{KeyParams, SignatureParams};
+use crate::schema::{
+    ACTIVE_COUNT, HASH, LEGACY_BN, LEGACY_SIGNATURE, LEGACY_SIGNATURE_LIMBS, MESSAGE, MESSAGE_LEN, MODULUS_LIMBS,
+    PUBKEY_HASH, PUBKEY_HASH_HEX, PUBKEY_POSEIDON, REDC_LIMBS, SALT, SIGNATURE_LIMBS,
+};
+
+/// Snippet bindings and the fields they hold, longest name first so that
+/// `message_len` is not taken for a suffixed `message`
+const SNIPPET_BINDINGS: [(&str, &[&str]); 8] = [
+    ("pubkey_poseidon", &[PUBKEY_POSEIDON]),
+    ("pubkey_hash", &[PUBKEY_HASH]),
+    ("message_len", &[MESSAGE_LEN]),
+    ("signature", &[SIGNATURE_LIMBS]),
+    ("instance", &[MODULUS_LIMBS, REDC_LIMBS]),
+    ("message", &[MESSAGE]),
+    ("params", &[MODULUS_LIMBS, REDC_LIMBS]),
+    ("salt", &[SALT]),
+];
+
+pub struct Annotator {
+    bits: usize,
+    num_limbs: usize,
+    params_type: String,
+    /// Hash of top-level fields; per-hash tables and bindings name their own
+    hash_alg: Option<HashAlg>,
+    message_max_len: Option<usize>,
+}
+
+impl Annotator {
+    pub fn for_key(key: &KeyParams) -> Annotator {
+        Annotator {
+            bits: key.bits,
+            num_limbs: key.num_limbs(),
+            params_type: key.params_type(),
+            hash_alg: None,
+            message_max_len: None,
+        }
+    }
+
+    /// Annotator for one key's inputs, or for `[[keys]]` entries of the same size
+    pub fn for_signature_params(params: &SignatureParams) -> Annotator {
+        Annotator {
+            hash_alg: params.signatures.first().map(|signature| signature.hash_alg),
+            message_max_len: params.message.as_ref().map(|message| message.max_len),
+            ..Annotator::for_key(&params.key)
+        }
+    }
+
+    /// Explanation of a field, or `None` if it is not one this tool emits
+    pub fn field(&self, name: &str, hash_alg: Option<HashAlg>) -> Option<Vec<String>> {
+        let hash_alg = hash_alg.or(self.hash_alg);
+        let lines = match name {
+            HASH => vec![
+                match hash_alg {
+                    Some(hash_alg) => format!(
+                        "Message hash: the {} digest of the message, {} bytes.",
+                        hash_alg.name().to_uppercase(),
+                        hash_alg.output_len()
+                    ),
+                    None => "Message hash: the digest of the message.".to_string(),
+                },
+                "Bytes are plain integers; feeds the `msg_hash` argument of the rsa verifier.".to_string(),
+            ],
+            MODULUS_LIMBS => vec![
+                format!(
+                    "RSA modulus n, {} bits, as {} 120-bit limbs, least significant first.",
+                    self.bits, self.num_limbs
+                ),
+                "Limbs are hex strings since they do not fit TOML's 64-bit integers.".to_string(),
+                format!("Feeds the modulus of `{}`.", self.params_type),
+            ],
+            REDC_LIMBS => vec![
+                format!(
+                    "Barrett reduction parameter: floor(2^{} / n), i.e. 2^(2 * {} + 6) / n, in {} limbs.",
+                    2 * self.bits + 6,
+                    self.bits,
+                    self.num_limbs
+                ),
+                format!("Feeds the redc parameter of `{}` and must match the modulus.", self.params_type),
+            ],
+            SIGNATURE_LIMBS | LEGACY_SIGNATURE_LIMBS => vec![
+                format!("RSA signature s < n as {} 120-bit limbs, least significant first.", self.num_limbs),
+                "Feeds the limbs of the signature `RuntimeBigNum`, checked against s^e mod n.".to_string(),
+            ],
+            LEGACY_BN => vec![
+                "Pre-0.4.0 BigNum parameters: [modulus limbs, Barrett reduction parameter limbs].".to_string(),
+            ],
+            SALT => vec!["PSS salt used when signing; the verifier only needs its length.".to_string()],
+            MESSAGE => vec![match self.message_max_len {
+                Some(max_len) => format!("Message bytes for in-circuit hashing, zero-padded to {} bytes.", max_len),
+                None => "Message bytes for in-circuit hashing, zero-padded to the capacity.".to_string(),
+            }],
+            MESSAGE_LEN => vec!["Number of meaningful bytes at the start of `message`.".to_string()],
+            ACTIVE_COUNT => vec![
+                "Number of real vectors; the remaining slots of every array repeat slot 0 and may be skipped."
+                    .to_string(),
+            ],
+            PUBKEY_HASH => vec!["SHA-256 of the modulus as big-endian bytes padded to the key size.".to_string()],
+            PUBKEY_HASH_HEX => vec!["`pubkey_hash` as a hex string, for comparing by eye.".to_string()],
+            PUBKEY_POSEIDON => vec![format!(
+                "Poseidon2 hash of the {} modulus limbs as a BN254 field element, in decimal.",
+                self.num_limbs
+            )],
+            _ => return None,
+        };
+        Some(lines)
+    }
+
+    /// Explanation of a table header, or `None` if it is not one this tool emits
+    pub fn section(&self, name: &str) -> Option<Vec<String>> {
+        let line = match name {
+            "keys" => "Inputs of one key; all keys sign the same message hash.".to_string(),
+            LEGACY_SIGNATURE => "Pre-0.4.0 signature table.".to_string(),
+            META => "Provenance of this file. Not a circuit input; nargo ignores it.".to_string(),
+            _ => format!("Signature made with {}.", HashAlg::from_name(name)?.name().to_uppercase()),
+        };
+        Some(vec![line])
+    }
+
+    /// Insert `#` comments above the fields and table headers of a TOML document
+    /// rendered by this tool. Fields of the metadata table are left as they are.
+    pub fn annotate_toml(&self, document: &str) -> String {
+        let mut out = String::new();
+        let mut section = String::new();
+        for line in document.lines() {
+            let comment = if let Some(header) = line.strip_prefix('[') {
+                section = header.trim_matches(|c| c == '[' || c == ']').to_string();
+                self.section(&section)
+            } else if section == META {
+                None
+            } else {
+                line.split_once(" = ").and_then(|(name, _)| self.field(name, HashAlg::from_name(&section)))
+            };
+            for comment_line in comment.into_iter().flatten() {
+                out.push_str("# ");
+                out.push_str(&comment_line);
+                out.push('\n');
+            }
+            out.push_str(line);
+            out.push('\n');
+        }
+        out
+    }
+
+    /// Insert `//` comments above the `let` bindings of a Noir snippet
+    pub fn annotate_snippet(&self, snippet: &str) -> String {
+        let mut out = String::new();
+        for line in snippet.lines() {
+            if let Some(binding) = line.strip_prefix("let ") {
+                let binding = binding.split(|c: char| c == ':' || c == ' ').next().unwrap_or_default();
+                for comment_line in self.binding(binding).into_iter().flatten() {
+                    out.push_str("// ");
+                    out.push_str(&comment_line);
+                    out.push('\n');
+                }
+            }
+            out.push_str(line);
+            out.push('\n');
+        }
+        out
+    }
+
+    fn binding(&self, binding: &str) -> Option<Vec<String>> {
+        let (base, fields) = SNIPPET_BINDINGS.iter().find(|(base, _)| {
+            binding == *base || binding.strip_prefix(base).is_some_and(|rest| rest.starts_with('_'))
+        })?;
+        // Bindings of several signatures are suffixed with the hash name
+        let hash_alg = binding[base.len()..].strip_prefix('_').and_then(HashAlg::from_name);
+        let mut lines = Vec::new();
+        for field in fields.iter() {
+            lines.extend(self.field(field, hash_alg)?);
+        }
+        Some(lines)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use num_bigint::BigUint;
+    use toml::Value;
+
+    use crate::commitment::PubkeyHash;
+    use crate::params::{render_multi_key_toml, MessageBytes, SignatureVector};
+    use crate::schema::Names;
+    use crate::templates::{RuntimeTemplate, SnippetTemplate};
+
+    fn fixture(hashes: &[HashAlg]) -> SignatureParams {
+        let modulus = (BigUint::from(1u32) << 1023) + 1u32;
+        let mut key = KeyParams::from_modulus(&modulus, 1024);
+        key.set_pubkey_hash(PubkeyHash::Sha256);
+        key.set_pubkey_poseidon();
+        SignatureParams {
+            signatures: hashes
+                .iter()
+                .map(|&hash_alg| SignatureVector {
+                    hash_alg,
+                    hash: hash_alg.digest(b"hello"),
+                    signature_limbs: key.modulus_limbs.clone(),
+                    salt: Some(vec![1, 2, 3]),
+                })
+                .collect(),
+            key,
+            message: Some(MessageBytes {
+                bytes: b"hello".to_vec(),
+                max_len: 8,
+            }),
+            warnings: Vec::new(),
+        }
+    }
+
+    fn assert_annotated_toml(plain: &str, annotator: &Annotator) {
+        let annotated = annotator.annotate_toml(plain);
+        assert_eq!(annotated.parse::<Value>().unwrap(), plain.parse::<Value>().unwrap());
+
+        // Every field and table of the plain output gets a comment right above it
+        let lines: Vec<&str> = annotated.lines().collect();
+        for (i, line) in lines.iter().enumerate() {
+            if !line.is_empty() && !line.starts_with('#') {
+                assert!(i > 0 && lines[i - 1].starts_with("# "), "`{}` is not annotated", line);
+            }
+        }
+    }
+
+    #[test]
+    fn test_annotated_toml_parses_and_covers_every_field() {
+        for hashes in [&[HashAlg::Sha256][..], &[HashAlg::Sha256, HashAlg::Sha512]] {
+            let params = fixture(hashes);
+            let annotator = Annotator::for_signature_params(&params);
+            for names in [Names::Canonical, Names::Legacy] {
+                assert_annotated_toml(&params.render_toml(names), &annotator);
+            }
+        }
+
+        let entries = [fixture(&[HashAlg::Sha384]), fixture(&[HashAlg::Sha384])];
+        let annotator = Annotator::for_signature_params(&entries[0]);
+        assert_annotated_toml(&render_multi_key_toml(&entries, Names::Canonical), &annotator);
+        assert_annotated_toml(&entries[0].key.render_toml(Names::Canonical), &Annotator::for_key(&entries[0].key));
+    }
+
+    #[test]
+    fn test_annotations_describe_the_values() {
+        let params = fixture(&[HashAlg::Sha256, HashAlg::Sha512]);
+        let annotated = Annotator::for_signature_params(&params).annotate_toml(&params.render_toml(Names::Canonical));
+        assert!(annotated.contains("# Barrett reduction parameter: floor(2^2054 / n)"));
+        assert!(annotated.contains("# RSA modulus n, 1024 bits, as 9 120-bit limbs"));
+        assert!(annotated.contains("# Message hash: the SHA512 digest of the message, 64 bytes."));
+        assert!(annotated.contains("# Message bytes for in-circuit hashing, zero-padded to 8 bytes."));
+    }
+
+    #[test]
+    fn test_annotated_snippets_comment_every_binding() {
+        let params = fixture(&[HashAlg::Sha256, HashAlg::Sha384]);
+        let annotator = Annotator::for_signature_params(&params);
+        for snippet in [params.render_snippet(&SnippetTemplate), params.render_snippet(&RuntimeTemplate)] {
+            let annotated = annotator.annotate_snippet(&snippet);
+            let lines: Vec<&str> = annotated.lines().collect();
+            for (i, line) in lines.iter().enumerate() {
+                if line.starts_with("let ") {
+                    assert!(lines[i - 1].starts_with("// "), "`{}` is not annotated", line);
+                }
+            }
+            assert!(annotated.contains("// Message hash: the SHA384 digest of the message, 48 bytes."));
+        }
+    }
+}
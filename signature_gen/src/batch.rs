@@ -0,0 +1,347 @@
+//! `sign --batch`: one vector per message of a list, each written to its own file
+//!
+//! The list is a text file of one message per line, or a TOML or JSON file of
+//! `entries`, each a `message` with optionally a `name` for its file and the
+//! `bits`, `hash` and `scheme` to sign it with instead of those of the run.
+//! Every entry is signed by a `sign` run of its own, so its file is exactly
+//! what the single-shot command prints and its `[meta]` records that command.
+//! Without `--key` or `--seed` each run generates its own key, with them the
+//! entries of a size share one. An entry that fails is reported in the index
+//! while the others go on.
+
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use toml::value::Table;
+use toml::Value;
+
+use crate::hash::HashAlg;
+use crate::params::to_toml_string;
+
+/// The file listing every entry, written next to their files
+pub const INDEX: &str = "index.toml";
+/// Longest file name taken from a message
+const MAX_SLUG_LEN: usize = 48;
+const FIELDS: [&str; 5] = ["name", "message", "bits", "hash", "scheme"];
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Entry {
+    /// The stem of its file, unique in the batch
+    pub name: String,
+    pub message: String,
+    pub bits: Option<u32>,
+    pub hash: Option<HashAlg>,
+    /// PSS or PKCS#1 v1.5, whatever `--pss` says
+    pub pss: Option<bool>,
+}
+
+impl Entry {
+    /// The `sign` command of this entry: that of the run, `base`, without the
+    /// options the entry overrides, followed by the entry's own
+    pub fn command(&self, base: &[String]) -> Vec<String> {
+        let mut with_value: Vec<&str> = Vec::new();
+        let mut flags: Vec<&str> = Vec::new();
+        if self.bits.is_some() {
+            with_value.extend(["--bits", "-b"]);
+        }
+        if self.hash.is_some() {
+            with_value.extend(["--hash", "--hashes"]);
+        }
+        if self.pss.is_some() {
+            flags.extend(["--pss", "-p"]);
+        }
+        if self.pss == Some(false) {
+            with_value.extend(["--salt-len", "--salt-hex"]);
+        }
+
+        let mut command = vec!["sign".to_string()];
+        let mut args = base.iter();
+        while let Some(arg) = args.next() {
+            if with_value.contains(&arg.as_str()) {
+                args.next();
+            } else if !flags.contains(&arg.as_str())
+                && !with_value.iter().any(|option| arg.starts_with(&format!("{}=", option)))
+            {
+                command.push(arg.clone());
+            }
+        }
+        // Attached, so that a message starting with `-` is not taken for an option
+        command.push(format!("--msg={}", self.message));
+        if let Some(bits) = self.bits {
+            command.extend(["--bits".to_string(), bits.to_string()]);
+        }
+        if let Some(hash) = self.hash {
+            command.extend(["--hash".to_string(), hash.name().to_string()]);
+        }
+        if self.pss == Some(true) {
+            command.push("--pss".to_string());
+        }
+        command
+    }
+}
+
+/// The lowercase letters and digits of `message`, every other run of characters
+/// turned into one `-`
+pub fn slug(message: &str) -> String {
+    let mut slug = String::new();
+    for c in message.chars() {
+        if slug.len() >= MAX_SLUG_LEN {
+            break;
+        }
+        if c.is_ascii_alphanumeric() {
+            slug.push(c.to_ascii_lowercase());
+        } else if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+    match slug.trim_end_matches('-') {
+        "" => "message".to_string(),
+        slug => slug.to_string(),
+    }
+}
+
+/// An entry of a TOML or JSON batch file, `number` counting from 1
+fn parse_entry(number: usize, table: &Table) -> Result<(Option<String>, Entry), String> {
+    if let Some(field) = table.keys().find(|field| !FIELDS.contains(&field.as_str())) {
+        return Err(format!("entry {} has an unknown field `{}`, expected {}", number, field, FIELDS.join(", ")));
+    }
+    let string = |field: &str| match table.get(field) {
+        None => Ok(None),
+        Some(Value::String(value)) => Ok(Some(value.clone())),
+        Some(_) => Err(format!("the {} of entry {} is not a string", field, number)),
+    };
+    let message = string("message")?.ok_or_else(|| format!("entry {} has no message", number))?;
+    let bits = match table.get("bits") {
+        None => None,
+        Some(Value::Integer(bits)) => {
+            Some(u32::try_from(*bits).map_err(|_| format!("the bits of entry {} are out of range", number))?)
+        }
+        Some(_) => return Err(format!("the bits of entry {} are not an integer", number)),
+    };
+    let hash = match string("hash")? {
+        None => None,
+        Some(name) => Some(HashAlg::from_name(&name).ok_or_else(|| {
+            format!("entry {} has the unsupported hash {}, expected one of sha1, sha256, sha384, sha512", number, name)
+        })?),
+    };
+    let pss = match string("scheme")?.as_deref() {
+        None => None,
+        Some("pss") => Some(true),
+        Some("pkcs1v15") => Some(false),
+        Some(scheme) => return Err(format!("entry {} has the scheme {}, expected pss or pkcs1v15", number, scheme)),
+    };
+    let entry = Entry { name: String::new(), message, bits, hash, pss };
+    Ok((string("name")?, entry))
+}
+
+fn valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && !name.starts_with('.')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || "-_.".contains(c))
+}
+
+/// Name every entry after its `name`, or the slug of its message made unique
+fn name_entries(named: Vec<(Option<String>, Entry)>) -> Result<Vec<Entry>, String> {
+    // The index is written next to the entries
+    let mut taken: HashSet<String> = HashSet::from(["index".to_string()]);
+    for name in named.iter().filter_map(|(name, _)| name.as_ref()) {
+        if !valid_name(name) {
+            return Err(format!("the name {:?} is not a file name of letters, digits, '-', '_' and '.'", name));
+        }
+        if !taken.insert(name.clone()) {
+            return Err(format!("the name {} is given to two entries, or is reserved for the index", name));
+        }
+    }
+    let mut entries = Vec::new();
+    for (name, mut entry) in named {
+        entry.name = match name {
+            Some(name) => name,
+            None => {
+                let slug = slug(&entry.message);
+                let name = (1..)
+                    .map(|n| if n == 1 { slug.clone() } else { format!("{}-{}", slug, n) })
+                    .find(|name| !taken.contains(name))
+                    .unwrap();
+                taken.insert(name.clone());
+                name
+            }
+        };
+        entries.push(entry);
+    }
+    Ok(entries)
+}
+
+/// The entries of the batch file at `path`, read as TOML or JSON by its
+/// extension and as one message per line otherwise
+pub fn parse(contents: &str, path: &Path) -> Result<Vec<Entry>, String> {
+    let extension = path.extension().and_then(|extension| extension.to_str());
+    let tables = match extension {
+        Some("toml") => {
+            let document: Value = contents.parse().map_err(|err| format!("Invalid TOML: {}", err))?;
+            document.get("entries").cloned()
+        }
+        Some("json") => {
+            let json: serde_json::Value =
+                serde_json::from_str(contents).map_err(|err| format!("Invalid JSON: {}", err))?;
+            let json = match json {
+                serde_json::Value::Array(_) => json,
+                mut document => document["entries"].take(),
+            };
+            match json {
+                serde_json::Value::Array(_) => {
+                    Some(Value::try_from(json).map_err(|err| format!("Invalid entries: {}", err))?)
+                }
+                _ => None,
+            }
+        }
+        _ => {
+            let lines = contents.lines().filter(|line| !line.is_empty());
+            let entries = lines.map(|message| {
+                (None, Entry { name: String::new(), message: message.to_string(), bits: None, hash: None, pss: None })
+            });
+            return name_entries(entries.collect());
+        }
+    };
+    let tables = match tables {
+        Some(Value::Array(tables)) => tables,
+        _ => return Err("expected a list of entries, as [[entries]] tables or a JSON array".to_string()),
+    };
+    let named = tables
+        .iter()
+        .enumerate()
+        .map(|(i, table)| match table {
+            Value::Table(table) => parse_entry(i + 1, table),
+            _ => Err(format!("entry {} is not a table", i + 1)),
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+    name_entries(named)
+}
+
+pub struct Outcome {
+    pub entry: Entry,
+    /// The file written, or why the entry failed
+    pub result: Result<PathBuf, String>,
+}
+
+/// `index.toml`: every entry with its file, or the error that stopped it
+fn render_index(outcomes: &[Outcome]) -> String {
+    let entries = outcomes
+        .iter()
+        .map(|outcome| {
+            let entry = &outcome.entry;
+            let mut table = Table::new();
+            table.insert("name".to_string(), Value::String(entry.name.clone()));
+            match &outcome.result {
+                Ok(path) => {
+                    let file = path.file_name().unwrap().to_string_lossy().into_owned();
+                    table.insert("file".to_string(), Value::String(file));
+                }
+                Err(err) => {
+                    table.insert("error".to_string(), Value::String(err.clone()));
+                }
+            }
+            table.insert("message".to_string(), Value::String(entry.message.clone()));
+            if let Some(bits) = entry.bits {
+                table.insert("bits".to_string(), Value::Integer(bits.into()));
+            }
+            if let Some(hash) = entry.hash {
+                table.insert("hash".to_string(), Value::String(hash.name().to_string()));
+            }
+            if let Some(pss) = entry.pss {
+                let scheme = if pss { "pss" } else { "pkcs1v15" };
+                table.insert("scheme".to_string(), Value::String(scheme.to_string()));
+            }
+            Value::Table(table)
+        })
+        .collect();
+    let mut document = Table::new();
+    document.insert("entries".to_string(), Value::Array(entries));
+    to_toml_string(document)
+}
+
+/// Sign every entry with `run`, which returns the output of a `sign` command,
+/// into `<name>.<extension>` under `out_dir`, then write the index there
+pub fn batch<F>(
+    entries: &[Entry],
+    base: &[String],
+    out_dir: &Path,
+    extension: &str,
+    run: F,
+) -> Result<Vec<Outcome>, String>
+where
+    F: Fn(&[String]) -> Result<String, String>,
+{
+    fs::create_dir_all(out_dir).map_err(|err| format!("Failed to create {}: {}", out_dir.display(), err))?;
+    let mut outcomes = Vec::new();
+    for entry in entries {
+        let path = out_dir.join(format!("{}.{}", entry.name, extension));
+        let result = run(&entry.command(base)).and_then(|output| {
+            fs::write(&path, output).map_err(|err| format!("Failed to write {}: {}", path.display(), err))?;
+            Ok(path)
+        });
+        outcomes.push(Outcome { entry: entry.clone(), result });
+    }
+    let index = out_dir.join(INDEX);
+    fs::write(&index, render_index(&outcomes)).map_err(|err| format!("Failed to write {}: {}", index.display(), err))?;
+    Ok(outcomes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_entries_get_their_own_files_and_failures_are_indexed() {
+        let list = parse("Hello World!\n\nhello world\n-dash\n", "messages.txt".as_ref()).unwrap();
+        let names: Vec<&str> = list.iter().map(|entry| entry.name.as_str()).collect();
+        assert_eq!(names, ["hello-world", "hello-world-2", "dash"]);
+
+        let manifest = r#"
+            [[entries]]
+            name = "tiny"
+            message = "too small"
+            bits = 1000
+
+            [[entries]]
+            message = "index"
+            hash = "sha512"
+            scheme = "pkcs1v15"
+        "#;
+        let entries = parse(manifest, "batch.toml".as_ref()).unwrap();
+        let json = r#"[{"name": "tiny", "message": "too small", "bits": 1000},
+                       {"message": "index", "hash": "sha512", "scheme": "pkcs1v15"}]"#;
+        assert_eq!(parse(json, "batch.json".as_ref()).unwrap(), entries);
+        assert_eq!(entries[1].name, "index-2");
+
+        // An entry overrides the options of the run it names, and keeps the others
+        let base = ["--pss", "--salt-len", "20", "--hashes=sha256", "--seed", "7"].map(String::from).to_vec();
+        assert_eq!(entries[1].command(&base), ["sign", "--seed", "7", "--msg=index", "--hash", "sha512"]);
+        assert_eq!(list[2].command(&base).last().unwrap(), "--msg=-dash");
+
+        let scratch = tempfile::tempdir().unwrap();
+        let dir = scratch.path();
+        let run = |command: &[String]| {
+            if command.iter().any(|arg| arg == "1000") {
+                Err("Unsupported key size 1000".to_string())
+            } else {
+                Ok(command.join(" "))
+            }
+        };
+        let outcomes = batch(&entries, &base, dir, "toml", run).unwrap();
+        assert_eq!(outcomes[0].result, Err("Unsupported key size 1000".to_string()));
+        let written = fs::read_to_string(dir.join("index-2.toml")).unwrap();
+        assert_eq!(written, entries[1].command(&base).join(" "));
+
+        let index: Value = fs::read_to_string(dir.join(INDEX)).unwrap().parse().unwrap();
+        let indexed = index["entries"].as_array().unwrap();
+        assert_eq!(indexed[0]["error"].as_str(), Some("Unsupported key size 1000"));
+        assert_eq!(indexed[1]["file"].as_str(), Some("index-2.toml"));
+        assert_eq!(indexed[1]["scheme"].as_str(), Some("pkcs1v15"));
+
+        assert!(parse("[[entries]]\nmessage = \"a\"\nbit = 1\n", "b.toml".as_ref()).unwrap_err().contains("`bit`"));
+        let duplicate = "[[entries]]\nname = \"a\"\nmessage = \"a\"\n[[entries]]\nname = \"a\"\nmessage = \"b\"\n";
+        assert!(parse(duplicate, "b.toml".as_ref()).unwrap_err().contains("two entries"));
+        assert!(parse(r#"[{"name": "../a", "message": "a"}]"#, "b.json".as_ref()).is_err());
+    }
+}
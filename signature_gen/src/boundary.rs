@@ -0,0 +1,181 @@
+//! `sign --edge-case`: valid vectors at boundaries the circuit must accept
+//!
+//! Unlike the `edge-cases` subcommand, which constructs keys of a given shape,
+//! these are signed by the ordinary `sign` flow and emitted in any of its
+//! formats, with the case and what it exercises recorded in the metadata. A
+//! signature whose top limb is zero is searched for by appending a counter to
+//! the message, at most `max_attempts` times, which is only feasible where
+//! that limb holds a byte.
+
+use num_traits::Zero;
+use rand_chacha::ChaCha20Rng;
+use rsa::RsaPrivateKey;
+
+use crate::errors::{Code, Error};
+use crate::hash::HashAlg;
+use crate::params::{num_limbs, SignatureParams};
+use crate::signature_params_for_digests;
+use crate::signing::Scheme;
+
+/// Signed messages up to this length, with the prefix, count as short for `e3-short-msg`
+pub const MAX_SHORT_MESSAGE_LEN: usize = 32;
+
+/// Widest top limb `leading-zero-sig` searches to zero, one message in 256 on average
+const MAX_ZERO_TOP_LIMB_BITS: usize = 8;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Boundary {
+    /// The most significant limb of the signature is zero
+    LeadingZeroSig,
+    /// Public exponent 3 over a short message
+    SmallExponent,
+    /// A modulus a few bits shorter than the BigNum instantiation
+    ShortTopLimb,
+    /// PSS with a zero-length salt
+    PssEmptySalt,
+}
+
+impl Boundary {
+    pub const ALL: [Boundary; 4] =
+        [Boundary::LeadingZeroSig, Boundary::SmallExponent, Boundary::ShortTopLimb, Boundary::PssEmptySalt];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Boundary::LeadingZeroSig => "leading-zero-sig",
+            Boundary::SmallExponent => "e3-short-msg",
+            Boundary::ShortTopLimb => "short-top-limb",
+            Boundary::PssEmptySalt => "pss-empty-salt",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Boundary> {
+        Boundary::ALL.into_iter().find(|boundary| boundary.name() == name)
+    }
+
+    /// What the vector exercises, for the metadata
+    pub fn description(self) -> &'static str {
+        match self {
+            Boundary::LeadingZeroSig => "the top limb of the signature is zero, as is its first byte",
+            Boundary::SmallExponent => "exponent 3 over a short message, two multiplications in the exponentiation",
+            Boundary::ShortTopLimb => "a modulus shorter than the instantiation, leaving its top limb nearly empty",
+            Boundary::PssEmptySalt => "PSS with a zero-length salt, so M' is 8 zero bytes followed by the hash",
+        }
+    }
+
+    /// Refuse a case the circuit cannot take at `bits`, or whose signed `message` does not have its shape
+    pub fn check(self, bits: usize, message: &[u8]) -> Result<(), String> {
+        let top_limb_bits = bits - 120 * (num_limbs(bits) - 1);
+        match self {
+            Boundary::LeadingZeroSig if top_limb_bits > MAX_ZERO_TOP_LIMB_BITS => Err(format!(
+                "leading-zero-sig is not supported at {} bits: the top limb holds {} bits, so one signature in 2^{} \
+                 has it zero. Only 2048-bit keys, whose top limb holds {}, are searched",
+                bits, top_limb_bits, top_limb_bits, MAX_ZERO_TOP_LIMB_BITS
+            )),
+            // The modulus size is the ModBits of the RuntimeBigNum, not read from the modulus
+            Boundary::ShortTopLimb => Err(format!(
+                "short-top-limb is not supported: a {}-bit instantiation takes its Barrett shift 2 * {} + 6 and the \
+                 PSS emBits {} from ModBits, so a {}-bit modulus does not verify in it. The top limb of a {}-bit \
+                 modulus already holds {} bits",
+                bits,
+                bits,
+                bits - 1,
+                bits - 7,
+                bits,
+                top_limb_bits
+            )),
+            Boundary::SmallExponent if message.len() > MAX_SHORT_MESSAGE_LEN => Err(format!(
+                "e3-short-msg signs at most {} bytes, the message is {}",
+                MAX_SHORT_MESSAGE_LEN,
+                message.len()
+            )),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Sign `prefix || msg`, then `prefix || msg || " <attempt>"`, until the top
+/// limb of the signature is zero, returning the message signed with its vector
+pub fn leading_zero_signature(
+    rng: &mut ChaCha20Rng,
+    priv_key: &RsaPrivateKey,
+    hash_alg: HashAlg,
+    scheme: &Scheme,
+    prefix: &[u8],
+    msg: &[u8],
+    max_attempts: usize,
+) -> Result<(Vec<u8>, SignatureParams), Error> {
+    for attempt in 0..max_attempts {
+        let mut candidate = msg.to_vec();
+        if attempt > 0 {
+            candidate.extend_from_slice(format!(" {}", attempt).as_bytes());
+        }
+        let digest = hash_alg.digest(&[prefix, candidate.as_slice()].concat());
+        let entry = signature_params_for_digests(&mut *rng, priv_key, &[hash_alg], &[digest], scheme)?;
+        if entry.signatures[0].signature_limbs.last().map_or(false, Zero::is_zero) {
+            return Ok((candidate, entry));
+        }
+    }
+    let message = format!(
+        "leading-zero-sig: no signature has a zero top limb after {} messages, raise --max-attempts",
+        max_attempts
+    );
+    Err(Error::new(Code::Failed, message).with("edge_case", "leading-zero-sig"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use num_bigint::BigUint;
+    use rand::SeedableRng;
+    use rsa::pkcs8::DecodePrivateKey;
+
+    use crate::generate_key;
+    use crate::reference::{self, CircuitVector};
+    use crate::signature_params_for_key;
+    use crate::signing::PssSalt;
+
+    fn circuit_accepts(entry: &SignatureParams, exponent: u32, scheme: &Scheme) -> bool {
+        reference::verify(&CircuitVector::of_entry(entry, &BigUint::from(exponent), scheme)).is_ok()
+    }
+
+    #[test]
+    fn test_boundaries_are_valid_vectors_of_their_shape() {
+        let mut rng = ChaCha20Rng::seed_from_u64(0);
+        let priv_key = RsaPrivateKey::from_pkcs8_pem(include_str!("../tests/fixtures/rsa_2048.pem")).unwrap();
+        for scheme in [Scheme::Pkcs1v15, Scheme::Pss(PssSalt::Random(32))] {
+            let (msg, entry) =
+                leading_zero_signature(&mut rng, &priv_key, HashAlg::Sha256, &scheme, b"p:", b"hello", 4096).unwrap();
+            assert!(msg.starts_with(b"hello"));
+            assert_eq!(entry.signatures[0].hash, HashAlg::Sha256.digest(&[&b"p:"[..], msg.as_slice()].concat()));
+            // Eight bits in the top limb of a 2048-bit key, all of them zero
+            assert_eq!(*entry.signatures[0].signature_limbs.last().unwrap(), BigUint::from(0u32));
+            assert_eq!(entry.signatures[0].signature_bytes(256)[0], 0);
+            assert!(circuit_accepts(&entry, 65537, &scheme));
+        }
+        let err = leading_zero_signature(&mut rng, &priv_key, HashAlg::Sha256, &Scheme::Pkcs1v15, b"", b"x", 0);
+        assert!(err.unwrap_err().message.contains("raise --max-attempts"));
+        // Elsewhere the top limb is too wide to be zeroed by varying the message
+        assert!(Boundary::LeadingZeroSig.check(2048, b"hello").is_ok());
+        for (bits, top_limb_bits) in [(1024, 64), (3072, 72), (4096, 16)] {
+            let err = Boundary::LeadingZeroSig.check(bits, b"hello").unwrap_err();
+            assert!(err.contains(&format!("the top limb holds {} bits", top_limb_bits)), "{}", err);
+        }
+
+        let e3 = generate_key(&mut rng, 1024, 3).unwrap();
+        let entry = signature_params_for_key(&mut rng, &e3, b"hi", &[HashAlg::Sha256], &Scheme::Pkcs1v15).unwrap();
+        assert!(circuit_accepts(&entry, 3, &Scheme::Pkcs1v15));
+        assert!(Boundary::SmallExponent.check(1024, b"hi").is_ok());
+        assert!(Boundary::SmallExponent.check(1024, &[0; MAX_SHORT_MESSAGE_LEN + 1]).is_err());
+
+        let scheme = Scheme::Pss(PssSalt::Random(0));
+        let entry = signature_params_for_key(&mut rng, &priv_key, b"hello", &[HashAlg::Sha256], &scheme).unwrap();
+        assert!(circuit_accepts(&entry, 65537, &scheme));
+        let wrong_salt = Scheme::Pss(PssSalt::Random(32));
+        assert!(!circuit_accepts(&entry, 65537, &wrong_salt));
+
+        assert!(Boundary::ShortTopLimb.check(2048, b"hello").unwrap_err().contains("2041-bit modulus"));
+        for boundary in Boundary::ALL {
+            assert_eq!(Boundary::from_name(boundary.name()), Some(boundary));
+        }
+    }
+}
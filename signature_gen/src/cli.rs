@@ -0,0 +1,3666 @@
+//! The `signature_gen` command line, behind `main.rs`
+
+use std::cell::RefCell;
+use std::io::Read;
+use std::str::FromStr;
+use std::sync::atomic::Ordering;
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+
+use base64ct::{Base64, Encoding};
+use num_bigint::BigUint;
+use num_traits::ToPrimitive;
+use rand::{RngCore, SeedableRng};
+use rand_chacha::ChaCha20Rng;
+use rsa::traits::PublicKeyParts;
+use rsa::{RsaPrivateKey, RsaPublicKey};
+
+use clap::{App, Arg};
+
+use noir_bignum_paramgen::split_into_120_bit_limbs;
+#[cfg(feature = "acvm")]
+use crate::execute;
+use crate::boundary::Boundary;
+use crate::commitment::PubkeyHash;
+use crate::errors::{Code, Error, ErrorFormat};
+use crate::hash::{parse_hash_list, HashAlg};
+use crate::invalid::Invalid;
+use crate::keys::{check_loaded_bits, key_bits, load_private_key, load_private_key_env, ExportFormat, Passphrase};
+use crate::params::{
+    render_aggregate_toml, render_multi_key_snippet, render_multi_key_toml, ExponentInput, KeyParams, LimbStyle,
+    MessageBytes, OutputFormat, SignatureParams,
+};
+use crate::signing::{PssSalt, Scheme};
+use crate::templates::{BignumVersion, NoirTemplate, NoirTest, SnippetTemplate};
+use crate::{
+    annotate, batch, boundary, convert, cross_check, difftest, dkim, doctor, edge_cases, errors, expected, infer,
+    inspect, invalid, iso9796, jcs, jwt, keygen, keys, manifest, meta, negative, params, presets, pss, raw_rsa,
+    reencode, regen, schema, serve, soak, stream, verifier_script, verify, x509,
+};
+use crate::{generate_key, signature_params_for_digests, signature_params_for_signature, CircuitInputs};
+
+/// `signature` left-padded to `key_size` bytes, as signers that strip leading zeros leave it short
+fn pad_signature(signature: Vec<u8>, key_size: usize) -> Result<Vec<u8>, String> {
+    if signature.len() > key_size {
+        return Err(format!("The signature is {} bytes but the key modulus is {} bytes", signature.len(), key_size));
+    }
+    let mut padded = vec![0u8; key_size - signature.len()];
+    padded.extend(signature);
+    Ok(padded)
+}
+
+/// The signature in `path`, as raw bytes or base64, for a key of `key_size` bytes
+fn read_signature_file(path: &str, key_size: usize) -> Result<Vec<u8>, String> {
+    let bytes = std::fs::read(path).map_err(|err| format!("Failed to read signature file {}: {}", path, err))?;
+    if bytes.len() == key_size {
+        return Ok(bytes);
+    }
+    let decoded = match std::str::from_utf8(&bytes) {
+        Ok(text) => {
+            let compact: String = text.chars().filter(|c| !c.is_whitespace()).collect();
+            Base64::decode_vec(&compact).ok()
+        }
+        Err(_) => None,
+    };
+    pad_signature(decoded.unwrap_or(bytes), key_size).map_err(|err| format!("{}: {}", path, err))
+}
+
+/// Parse a Prover.toml, exiting on failure
+fn read_prover_toml(path: &str) -> toml::value::Table {
+    let contents = std::fs::read_to_string(path).unwrap_or_else(|err| {
+        errors::fail(Error::new(Code::Failed, format!("Failed to read {}: {}", path, err)).with("path", path))
+    });
+    match contents.parse::<toml::Value>() {
+        Ok(toml::Value::Table(doc)) => doc,
+        Ok(_) => unreachable!(),
+        Err(err) => {
+            let message = format!("Failed to parse {}: {}", path, err);
+            errors::fail(Error::new(Code::InvalidArgument, message).with("path", path));
+        }
+    }
+}
+
+/// Write `params` in the pre-0.4.0 and the current BigNum layout, as `<prefix>.v03.<ext>` and
+/// `<prefix>.v04.<ext>`: the legacy and canonical TOML names, or the 0.3 and current Noir syntax
+fn write_compat_pair(prefix: &str, params: &SignatureParams, format: OutputFormat, meta: Option<&meta::Meta>) {
+    let extension = match format {
+        OutputFormat::AbiJson => "json",
+        _ if format.renders_toml() => "toml",
+        _ => "nr",
+    };
+    for layout in [convert::Layout::V03, convert::Layout::V04] {
+        let legacy = layout == convert::Layout::V03;
+        let mut output = if format.renders_toml() {
+            let names = if legacy { schema::Names::Legacy } else { schema::Names::Canonical };
+            match meta {
+                Some(meta) => format!("{}\n{}", params.render_toml(names), meta.render_toml()),
+                None => params.render_toml(names),
+            }
+        } else {
+            let version = if legacy { BignumVersion::V03 } else { BignumVersion::Current };
+            let snippet = params.render_snippet(version.template());
+            match meta {
+                Some(meta) => format!("{}{}", meta.render_comment(), snippet),
+                None => snippet,
+            }
+        };
+        if format == OutputFormat::AbiJson {
+            output = params::toml_to_abi_json(&output);
+        }
+        let path = format!("{}.{}.{}", prefix, layout.name(), extension);
+        std::fs::write(&path, output).unwrap_or_else(|err| {
+            errors::fail(Error::new(Code::Failed, format!("Failed to write {}: {}", path, err)).with("path", path))
+        });
+        println!("Wrote the {} layout to {}", layout.name(), path);
+    }
+}
+
+/// The `[expected]` table of `entries`, verified unless they were broken by `--invalid`, checked against
+/// their canonical rendering before it is emitted
+fn expected_table(entries: &[SignatureParams], invalid: Option<Invalid>) -> String {
+    let table = expected::render_toml(entries, invalid.is_none());
+    let mut inputs = match entries {
+        [signature_params] => signature_params.render_toml(schema::Names::Canonical),
+        _ => render_multi_key_toml(entries, schema::Names::Canonical),
+    };
+    if invalid.is_some() {
+        inputs = format!("expected_valid = false\n{}", inputs);
+    }
+    let document = format!("{}\n{}", inputs, table).parse::<toml::Value>().expect("emitted TOML does not parse");
+    if let Err(err) = expected::check(document.as_table().unwrap()) {
+        errors::fail(
+            Error::new(Code::VerificationFailed, format!("Inconsistent [expected] table: {}", err))
+                .with("check", "expected"),
+        );
+    }
+    table
+}
+
+/// Fail unless every key has the exponent the circuit is built for
+fn check_exponents<K: PublicKeyParts>(keys: &[K], expected: u32) -> Result<(), Error> {
+    match keys.iter().position(|key| key.e() != &BigUint::from(expected)) {
+        Some(i) => Err(Error::new(
+            Code::KeyMismatch,
+            format!(
+                "Key {} has public exponent {} but the circuit expects {}; pass --expect-exponent {} if the circuit \
+                 is built for it, or --emit-exponent-input to emit the exponent as an input for an exponent-generic \
+                 circuit",
+                i + 1,
+                keys[i].e(),
+                expected,
+                keys[i].e()
+            ),
+        )
+        .with("key", i + 1)
+        .with("expected_exponent", expected)
+        .with("actual_exponent", keys[i].e().to_string())),
+        None => Ok(()),
+    }
+}
+
+/// The `--exponent` to generate keys with
+fn exponent(sub_m: &clap::ArgMatches) -> u32 {
+    let value = sub_m.value_of("exponent").unwrap();
+    keys::parse_exponent(value)
+        .unwrap_or_else(|err| errors::fail(Error::new(Code::InvalidArgument, err).with("exponent", value)))
+}
+
+/// The `--seed`, in decimal or hex, if one is given
+fn seed(sub_m: &clap::ArgMatches) -> Option<u64> {
+    sub_m.value_of("seed").map(|value| {
+        keys::parse_seed(value)
+            .unwrap_or_else(|err| errors::fail(Error::new(Code::InvalidArgument, err).with("seed", value)))
+    })
+}
+
+/// The value of the flag `name`, if it is given or has a default, refused
+/// unless it parses as a `T`
+fn parse_value<T: FromStr>(sub_m: &clap::ArgMatches, name: &'static str) -> Option<T> {
+    sub_m.value_of(name).map(|value| {
+        value.parse().unwrap_or_else(|_| {
+            let message = format!("Invalid --{} {}", name.replace('_', "-"), value);
+            errors::fail(Error::new(Code::InvalidArgument, message).with(name, value))
+        })
+    })
+}
+
+/// Sizes `sign` generates keys of
+const GENERATED_BITS: [u32; 4] = [1024, 2048, 3072, 4096];
+
+fn check_generated_bits(bits: u32) -> Result<(), Error> {
+    if GENERATED_BITS.contains(&bits) {
+        return Ok(());
+    }
+    Err(Error::new(Code::InvalidArgument, "Number of bits of RSA signature can only be 1024, 2048, 3072 or 4096")
+        .with("actual_bits", bits))
+}
+
+fn load_key_file(path: &str, passphrase: &Passphrase) -> Result<RsaPrivateKey, Error> {
+    let key = load_private_key(path, passphrase).map_err(|err| Error::new(Code::BadKeyFile, err).with("path", path))?;
+    check_loaded_bits(&key, path).map_err(|err| err.with("path", path))?;
+    Ok(key)
+}
+
+/// Fail unless `key` has the size of the BigNum instantiation found in `path`
+fn check_circuit_bits(key: &impl PublicKeyParts, path: &str, circuit: &infer::Instantiation) -> Result<(), Error> {
+    if key_bits(key) == circuit.bits {
+        return Ok(());
+    }
+    Err(Error::new(
+        Code::KeyMismatch,
+        format!(
+            "The key is {}-bit but the circuit in {} is instantiated for {} bits (BigNum<{}, {}>)",
+            key_bits(key),
+            path,
+            circuit.bits,
+            circuit.num_limbs,
+            circuit.bits
+        ),
+    )
+    .with("path", path)
+    .with("expected_bits", circuit.bits)
+    .with("actual_bits", key_bits(key)))
+}
+
+fn parse_limbs_to_biguint(limbs_str: &str) -> BigUint {
+    let mut result = BigUint::from(0u32);
+    for (i, limb_str) in limbs_str
+        .split(',')
+        .map(|s| s.trim())
+        .filter(|s| !s.is_empty())
+        .enumerate()
+    {
+        let limb_str = limb_str.strip_prefix("0x").unwrap_or(limb_str);
+        let limb = BigUint::parse_bytes(limb_str.as_bytes(), 16).unwrap_or_else(|| {
+            let message = format!("Invalid hex limb: {}", limb_str);
+            errors::fail(Error::new(Code::InvalidArgument, message).with("limbs", limbs_str))
+        });
+        result += limb << (120 * i);
+    }
+    result
+}
+
+fn generate_bignum_params_from_modulus(
+    modulus: &BigUint,
+    bit_size: usize,
+    format: OutputFormat,
+    template: &dyn NoirTemplate,
+    limb_style: LimbStyle,
+    pubkey_hash: Option<PubkeyHash>,
+    pubkey_poseidon: bool,
+    annotate: bool,
+) {
+    let mut key = KeyParams::from_modulus(modulus, bit_size);
+    key.limb_style = limb_style;
+    if let Some(hash) = pubkey_hash {
+        key.set_pubkey_hash(hash);
+    }
+    if pubkey_poseidon {
+        key.set_pubkey_poseidon();
+    }
+
+    let output = if format.renders_toml() {
+        key.render_toml(schema::Names::Canonical)
+    } else {
+        format!("{}\n{}", template.params(&key, "params"), key.render_snippet_commitments(""))
+    };
+    if format == OutputFormat::AbiJson {
+        print!("{}", params::toml_to_abi_json(&output));
+    } else if !annotate {
+        print!("{}", output);
+    } else if format == OutputFormat::Toml {
+        print!("{}", annotate::Annotator::for_key(&key).annotate_toml(&output));
+    } else {
+        print!("{}", annotate::Annotator::for_key(&key).annotate_snippet(&output));
+    }
+}
+
+fn output_format_args<'a, 'b>() -> Vec<Arg<'a, 'b>> {
+    vec![
+        Arg::with_name("toml")
+            .short("t")
+            .long("toml")
+            .help("Print output in TOML format (same as --format toml)"),
+        Arg::with_name("format")
+            .short("f")
+            .long("format")
+            .takes_value(true)
+            .possible_values(&["snippet", "noir-runtime", "toml", "abi-json"])
+            .default_value("snippet")
+            .help("Output format: a compact snippet, Noir code constructing BigNumParams and RuntimeBigNum, TOML, or the TOML inputs as JSON"),
+        Arg::with_name("bignum_version")
+            .long("bignum-version")
+            .takes_value(true)
+            .default_value("current")
+            .help("BigNum version whose syntax the noir-runtime format targets (0.3 or current)"),
+        canonical_limbs_arg(),
+        Arg::with_name("emit_pubkey_hash")
+            .long("emit-pubkey-hash")
+            .takes_value(true)
+            .possible_values(&["sha256"])
+            .help("Also emit a commitment to the public key: the hash of the big-endian modulus bytes, padded to the key size"),
+        Arg::with_name("annotate")
+            .long("annotate")
+            .help("Explain every emitted field in comments (# in TOML, // in Noir)"),
+        Arg::with_name("emit_pubkey_poseidon")
+            .long("emit-pubkey-poseidon")
+            .help("Also emit a Poseidon2 commitment to the modulus limbs, as computed in-circuit by Noir"),
+    ]
+}
+
+fn canonical_limbs_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("canonical_limbs")
+        .long("canonical-limbs")
+        .help("Zero-pad every limb to 30 hex characters so regenerated outputs diff limb by limb")
+}
+
+/// `--bits`, `--exponent` and `--seed` of a generated key, shared by `keygen` and `sign`
+fn key_generation_args<'a, 'b>() -> Vec<Arg<'a, 'b>> {
+    vec![
+        Arg::with_name("bits")
+            .short("b")
+            .long("bits")
+            .takes_value(true)
+            .help("Number of bits of RSA signature (1024, 2048, 3072 or 4096)")
+            .default_value("2048"),
+        Arg::with_name("exponent")
+            .short("e")
+            .long("exponent")
+            .takes_value(true)
+            .help("Exponent to use for the key")
+            .default_value("65537"),
+        Arg::with_name("seed")
+            .long("seed")
+            .takes_value(true)
+            .help("Seed for key generation and signing, in decimal or 0x-prefixed hex, making the output reproducible"),
+    ]
+}
+
+/// An importer, run as `sign` with its input as the option of `IMPORTERS`.
+/// Clap only parses it itself for its help, or when the input is missing.
+fn importer_command(name: &'static str, about: &'static str, usage: &'static str) -> App<'static, 'static> {
+    clap::SubCommand::with_name(name).about(about).usage(usage).arg(
+        Arg::with_name("input")
+            .required(true)
+            .help("The certificate or token, followed by any option of sign"),
+    )
+}
+
+/// Importers and the `sign` option taking their input
+const IMPORTERS: [(&str, &str); 2] = [("from-cert", "--x509"), ("from-jwt", "--jwt")];
+
+/// Every subcommand, to find those taking an option given to another one
+const SUBCOMMANDS: [&str; 20] = [
+    "keygen", "sign", "from-cert", "from-jwt", "params", "doctor", "convert", "diff", "reencode-signature", "serve",
+    "difftest", "run-negative", "iso9796", "edge-cases", "dkim-corpus", "raw-rsa", "regen", "verify-manifest",
+    "inspect", "verify",
+];
+
+/// Options of `sign` writing or running anything but its output, and where it goes, left out of a command
+/// recorded to rerun it
+const SIGN_SIDE_OUTPUTS: &[&str] = &[
+    "--export-key", "--save-key", "--emit-verifier-script", "--execute-artifact", "--output", "-o", "--out-dir",
+    "--manifest",
+];
+
+/// The command line as parsed, after `subcommand_args`, which `args_without` reruns
+static ARGS: OnceLock<Vec<String>> = OnceLock::new();
+
+/// Index of the subcommand in `args`, after the global --error-format that may come first
+fn subcommand_index(args: &[String]) -> usize {
+    let mut start = 1;
+    while let Some(arg) = args.get(start).filter(|arg| arg.starts_with("--error-format")) {
+        start += if arg.as_str() == "--error-format" { 2 } else { 1 };
+    }
+    start
+}
+
+/// `args` with an importer spelled as `sign`, and with `sign` in front of
+/// options given without a subcommand, as before there were any
+fn subcommand_args(mut args: Vec<String>) -> Vec<String> {
+    let start = subcommand_index(&args);
+    let first = args.get(start).cloned().unwrap_or_default();
+    match IMPORTERS.iter().find(|(name, _)| *name == first) {
+        Some((_, option)) => {
+            // Without an input, clap reports the importer's usage
+            if args.get(start + 1).map_or(false, |input| !input.starts_with('-')) {
+                args[start] = option.to_string();
+                args.insert(start, "sign".to_string());
+            }
+        }
+        None if first.starts_with('-') && !["-h", "--help"].contains(&first.as_str()) => {
+            eprintln!(
+                "Warning: options without a subcommand are deprecated and will stop working in the next release, \
+                 run `signature_gen sign` with them instead"
+            );
+            args.insert(start, "sign".to_string());
+        }
+        None => {}
+    }
+    args
+}
+
+/// The subcommands taking `option`, found by parsing it with each of them
+fn option_owners(option: &str) -> Vec<&'static str> {
+    let option = option.split('=').next().unwrap();
+    SUBCOMMANDS
+        .iter()
+        .copied()
+        .filter(|&name| match cli().get_matches_from_safe(["signature_gen", name, option]) {
+            Ok(_) => true,
+            Err(err) => err.kind != clap::ErrorKind::UnknownArgument,
+        })
+        .collect()
+}
+
+/// Parse the command line, naming the subcommands that take an option the given one does not
+fn parse_args(args: Vec<String>) -> clap::ArgMatches<'static> {
+    let args = ARGS.get_or_init(|| subcommand_args(args));
+    match cli().get_matches_from_safe(args) {
+        Ok(matches) => matches,
+        Err(err) if err.kind == clap::ErrorKind::UnknownArgument => {
+            let mut message = err.message.clone();
+            let option = err.info.as_ref().and_then(|info| info.first()).filter(|arg| arg.starts_with('-'));
+            if let Some(option) = option {
+                let owners = option_owners(option);
+                if !owners.is_empty() {
+                    let hint = format!("\n\n{} is an option of {}, run one of them instead", option, owners.join(", "));
+                    message.push_str(&hint);
+                }
+            }
+            // Reported before main reads --error-format from the matches, so it is looked up here
+            let json = args.windows(2).any(|pair| pair[0] == "--error-format" && pair[1] == "json")
+                || args.iter().any(|arg| arg == "--error-format=json");
+            errors::set_format(if json { ErrorFormat::Json } else { ErrorFormat::Text });
+            let error = Error::new(Code::InvalidArgument, message);
+            errors::fail(match option {
+                Some(option) => error.with("option", option.as_str()),
+                None => error,
+            })
+        }
+        Err(err) => err.exit(),
+    }
+}
+
+fn limb_style(sub_m: &clap::ArgMatches) -> LimbStyle {
+    if sub_m.is_present("canonical_limbs") {
+        LimbStyle::Canonical
+    } else {
+        LimbStyle::Minimal
+    }
+}
+
+fn pubkey_hash(sub_m: &clap::ArgMatches) -> Option<PubkeyHash> {
+    sub_m.value_of("emit_pubkey_hash").and_then(PubkeyHash::from_name)
+}
+
+/// The `--prefix` or `--prefix-hex` bytes, empty if neither is given
+fn message_prefix(sub_m: &clap::ArgMatches) -> Vec<u8> {
+    match (sub_m.value_of("prefix"), sub_m.value_of("prefix_hex")) {
+        (Some(prefix), _) => prefix.as_bytes().to_vec(),
+        (None, Some(prefix_hex)) => hex::decode(prefix_hex.trim_start_matches("0x")).unwrap_or_else(|err| {
+            errors::fail(Error::new(Code::InvalidArgument, format!("Invalid --prefix-hex: {}", err)))
+        }),
+        (None, None) => Vec::new(),
+    }
+}
+
+/// What is actually hashed and signed: `prefix || msg`
+fn prefixed(prefix: &[u8], msg: &[u8]) -> Vec<u8> {
+    [prefix, msg].concat()
+}
+
+/// The signed bytes emitted for in-circuit hashing, refused if they do not fit in `max_len`
+fn emitted_bytes(bytes: Vec<u8>, max_len: usize) -> MessageBytes {
+    if bytes.len() > max_len {
+        let message = format!("The message is {} bytes which exceeds --msg-max-len {}", bytes.len(), max_len);
+        let error = Error::new(Code::InvalidArgument, message).with("msg_len", bytes.len());
+        errors::fail(error.with("msg_max_len", max_len));
+    }
+    MessageBytes { bytes, max_len }
+}
+
+/// A signature that comes with its message and key, as in a certificate or an email
+struct EmbeddedSignature {
+    message: Vec<u8>,
+    hash_alg: HashAlg,
+    scheme: Scheme,
+    signature: Vec<u8>,
+    key: RsaPublicKey,
+    /// Where the key was read from, for errors
+    key_source: String,
+}
+
+/// `--x509`: the tbsCertificate and signature of a certificate, with the key of its `--issuer` or its own
+fn certificate_signature(path: &str, issuer: Option<&str>) -> EmbeddedSignature {
+    let bytes = std::fs::read(path).unwrap_or_else(|err| {
+        errors::fail(Error::new(Code::InvalidArgument, format!("Failed to read certificate {}: {}", path, err)))
+    });
+    let certificate = x509::Certificate::parse(&bytes)
+        .and_then(|certificate| certificate.signature_algorithm.scheme().map(|scheme| (certificate, scheme)));
+    let (certificate, scheme) = certificate.unwrap_or_else(|err| {
+        errors::fail(Error::new(Code::InvalidArgument, format!("{}: {}", path, err)).with("path", path))
+    });
+    if let x509::SignatureAlgorithm::Pss(params) = &certificate.signature_algorithm {
+        for warning in params.warnings() {
+            eprintln!("Warning: {}: {}", path, warning);
+        }
+    }
+    let (key_source, key) = match issuer {
+        Some(issuer) => (issuer, x509::read_issuer_key(issuer)),
+        None => (path, certificate.public_key().map_err(|err| format!("{}: {}", path, err))),
+    };
+    EmbeddedSignature {
+        hash_alg: certificate.signature_algorithm.hash_alg(),
+        scheme,
+        key: key.unwrap_or_else(|err| errors::fail(Error::new(Code::BadKeyFile, err).with("path", key_source))),
+        key_source: key_source.to_string(),
+        message: certificate.tbs,
+        signature: certificate.signature,
+    }
+}
+
+/// `--dkim`: the signed headers and signature of an email, with the key of `--dkim-pubkey` or `--dkim-dns`
+fn dkim_signature(path: &str, sub_m: &clap::ArgMatches) -> EmbeddedSignature {
+    let signed = std::fs::read(path)
+        .map_err(|err| format!("Failed to read {}: {}", path, err))
+        .and_then(|eml| dkim::signed_headers(&eml).map_err(|err| format!("{}: {}", path, err)))
+        .unwrap_or_else(|err| errors::fail(Error::new(Code::InvalidArgument, err).with("path", path)));
+    let (key_source, key) = match (sub_m.value_of("dkim_pubkey"), sub_m.value_of("dkim_dns")) {
+        (Some(record), _) => ("--dkim-pubkey", dkim::parse_dns_key(record)),
+        (None, Some(dns)) => {
+            let key = std::fs::read_to_string(dns)
+                .map_err(|err| format!("Failed to read {}: {}", dns, err))
+                .and_then(|record| dkim::parse_dns_key(&record).map_err(|err| format!("{}: {}", dns, err)));
+            (dns, key)
+        }
+        (None, None) => {
+            let message = "--dkim needs the signing key, pass --dkim-pubkey or --dkim-dns";
+            errors::fail(Error::new(Code::InvalidArgument, message).with("path", path))
+        }
+    };
+    EmbeddedSignature {
+        message: signed.data,
+        hash_alg: HashAlg::Sha256,
+        scheme: Scheme::Pkcs1v15,
+        signature: signed.signature,
+        key: key.unwrap_or_else(|err| errors::fail(Error::new(Code::BadKeyFile, err).with("path", key_source))),
+        key_source: key_source.to_string(),
+    }
+}
+
+/// `--jwt`: the signing input and signature of a token, with the key of `--jwk` and `--kid`
+fn jwt_signature(token: &str, sub_m: &clap::ArgMatches) -> EmbeddedSignature {
+    let token = jwt::parse(token).unwrap_or_else(|err| errors::fail(Error::new(Code::InvalidArgument, err)));
+    let path = sub_m.value_of("jwk").unwrap();
+    let kid = sub_m.value_of("kid").or(token.kid.as_deref());
+    let key = std::fs::read_to_string(path)
+        .map_err(|err| format!("Failed to read {}: {}", path, err))
+        .and_then(|jwk| jwt::select_key(&jwk, kid).map_err(|err| format!("{}: {}", path, err)))
+        .unwrap_or_else(|err| errors::fail(Error::new(Code::BadKeyFile, err).with("path", path)));
+    EmbeddedSignature {
+        message: token.signing_input,
+        hash_alg: token.hash_alg,
+        scheme: token.scheme,
+        signature: token.signature,
+        key,
+        key_source: match kid {
+            Some(kid) => format!("{} (kid {})", path, kid),
+            None => path.to_string(),
+        },
+    }
+}
+
+/// The `--prehashed` digest, which must be as long as the output of its one hash
+fn prehashed_digest(digest_hex: &str, hashes: &[HashAlg]) -> Vec<u8> {
+    let digest = hex::decode(digest_hex.strip_prefix("0x").unwrap_or(digest_hex)).unwrap_or_else(|err| {
+        errors::fail(Error::new(Code::InvalidArgument, format!("Invalid --prehashed: {}", err)))
+    });
+    let hash_alg = match hashes {
+        [hash_alg] => *hash_alg,
+        _ => errors::fail(Error::new(
+            Code::InvalidArgument,
+            format!("--prehashed takes the digest of one hash, --hashes gives {}", hashes.len()),
+        )),
+    };
+    if digest.len() != hash_alg.output_len() {
+        errors::fail(
+            Error::new(
+                Code::InvalidArgument,
+                format!(
+                    "--prehashed is {} bytes, but a {} digest is {}",
+                    digest.len(),
+                    hash_alg.name(),
+                    hash_alg.output_len()
+                ),
+            )
+            .with("hash", hash_alg.name())
+            .with("len", digest.len()),
+        );
+    }
+    digest
+}
+
+/// Write `key`, or its public key with `--export-public`, in the `--export-format`
+fn export_key(path: &str, key: &RsaPrivateKey, sub_m: &clap::ArgMatches) {
+    let format = ExportFormat::from_name(sub_m.value_of("export_format").unwrap()).unwrap();
+    let written = if sub_m.is_present("export_public") {
+        keys::export_public_key(&key.to_public_key(), format).and_then(|encoded| {
+            std::fs::write(path, encoded).map_err(|err| format!("Failed to write {}: {}", path, err))
+        })
+    } else {
+        keys::export_private_key(key, format).and_then(|encoded| write_private(path, &encoded))
+    };
+    if let Err(err) = written {
+        errors::fail(Error::new(Code::Failed, err).with("path", path));
+    }
+}
+
+/// Create `path` readable by its owner only and write `contents` to it
+fn write_private(path: &str, contents: &[u8]) -> Result<(), String> {
+    use std::io::Write;
+
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    options
+        .open(path)
+        .and_then(|mut file| file.write_all(contents))
+        .map_err(|err| format!("Failed to write {}: {}", path, err))
+}
+
+/// Run the Prover.toml `inputs` through the circuit of `artifact`, exiting unless they satisfy it
+#[cfg(feature = "acvm")]
+fn execute_artifact(artifact: &str, inputs: &str) {
+    match execute::execute(artifact.as_ref(), inputs) {
+        Ok(execute::Execution::Satisfied) => eprintln!("{}: the inputs satisfy the circuit", artifact),
+        Ok(execute::Execution::Failed { opcode, message }) => {
+            let location = opcode.map_or(String::new(), |opcode| format!(" at opcode {}", opcode));
+            let message = format!("{}: the inputs do not satisfy the circuit{}: {}", artifact, location, message);
+            errors::fail(Error::new(Code::VerificationFailed, message).with("artifact", artifact));
+        }
+        Err(err) => {
+            errors::fail(Error::new(Code::Failed, err).with("artifact", artifact));
+        }
+    }
+}
+
+#[cfg(not(feature = "acvm"))]
+fn execute_artifact(_artifact: &str, _inputs: &str) {
+    let message = "--execute-artifact needs signature_gen built with `--features acvm`";
+    errors::fail(Error::new(Code::InvalidArgument, message));
+}
+
+/// The `sign` arguments of this invocation without the soak options, to which
+/// `--seed <seed>` is appended to replay a single iteration
+fn soak_replay_args() -> Vec<String> {
+    args_without(&["--iterations", "--seed", "--failures-dir"])
+}
+
+/// The arguments after the subcommand, without the options `skipped` and their values
+fn args_without(skipped: &[&str]) -> Vec<String> {
+    let mut args = Vec::new();
+    let mut skip_value = false;
+    let args = ARGS.get().map_or(&[][..], Vec::as_slice);
+    for arg in args.iter().skip(subcommand_index(args) + 1) {
+        if skip_value {
+            skip_value = false;
+        } else if skipped.contains(&arg.as_str()) {
+            skip_value = true;
+        } else if !skipped.iter().any(|option| arg.starts_with(&format!("{}=", option))) {
+            args.push(arg.clone());
+        }
+    }
+    args
+}
+
+/// The output of this tool run with `command`, or its error output if it fails
+fn run_self(command: &[String]) -> Result<String, String> {
+    let exe = std::env::current_exe().map_err(|err| format!("Failed to locate signature_gen: {}", err))?;
+    let output = std::process::Command::new(exe)
+        .args(command)
+        .output()
+        .map_err(|err| format!("cannot run `{}`: {}", command.join(" "), err))?;
+    if !output.status.success() {
+        return Err(String::from_utf8_lossy(&output.stderr).trim().to_string());
+    }
+    String::from_utf8(output.stdout).map_err(|_| "the output is not UTF-8".to_string())
+}
+
+/// `--batch`: sign every entry of the file at `path` with its own `sign` run, skipping those that fail
+fn sign_batch(path: &str, sub_m: &clap::ArgMatches) {
+    let contents = std::fs::read_to_string(path).unwrap_or_else(|err| {
+        errors::fail(Error::new(Code::Failed, format!("Failed to read {}: {}", path, err)).with("path", path))
+    });
+    let entries = batch::parse(&contents, path.as_ref()).unwrap_or_else(|err| {
+        errors::fail(Error::new(Code::InvalidArgument, format!("{}: {}", path, err)).with("path", path))
+    });
+    let mut base = args_without(&["--batch", "--out-dir", "--manifest"]);
+    let (format, _) = output_format(sub_m);
+    let extension = match format {
+        _ if sub_m.is_present("json") => "json",
+        OutputFormat::AbiJson => "json",
+        OutputFormat::Toml => {
+            // Each run prints to stdout, where only --toml asks for a Prover.toml
+            if !sub_m.is_present("toml") && sub_m.occurrences_of("format") == 0 {
+                base.push("--toml".to_string());
+            }
+            "toml"
+        }
+        OutputFormat::Snippet | OutputFormat::NoirRuntime => "nr",
+    };
+    let out_dir = sub_m.value_of("out_dir").unwrap();
+    let outcomes = batch::batch(&entries, &base, out_dir.as_ref(), extension, run_self).unwrap_or_else(|err| {
+        errors::fail(Error::new(Code::Failed, err))
+    });
+    let mut failed = Vec::new();
+    let mut written = Vec::new();
+    for outcome in &outcomes {
+        match &outcome.result {
+            Ok(path) => {
+                println!("{}: saved to {}", outcome.entry.name, path.display());
+                written.push(path.clone());
+            }
+            Err(err) => {
+                eprintln!("{}: failed, {}", outcome.entry.name, err);
+                failed.push(outcome.entry.name.clone());
+            }
+        }
+    }
+    println!(
+        "{} written, {} failed, listed in {}",
+        outcomes.len() - failed.len(),
+        failed.len(),
+        std::path::Path::new(out_dir).join(batch::INDEX).display()
+    );
+    written.push(std::path::Path::new(out_dir).join(batch::INDEX));
+    record_in_manifest("sign", sub_m, &written, rerun_seed(sub_m, seed(sub_m)));
+    if !failed.is_empty() {
+        let message = format!("Failed entries: {}", failed.join(", "));
+        errors::fail(Error::new(Code::Failed, message).with("entries", failed));
+    }
+}
+
+/// Record the files a subcommand wrote in the `--manifest`, if one is given
+fn record_in_manifest(subcommand: &str, sub_m: &clap::ArgMatches, files: &[std::path::PathBuf], seed: Option<u64>) {
+    if let Some(path) = sub_m.value_of("manifest") {
+        let mut command = vec![subcommand.to_string()];
+        command.extend(match subcommand {
+            "sign" => args_without(SIGN_SIDE_OUTPUTS),
+            _ => args_without(&["--output-dir", "-o", "--out-dir", "--manifest"]),
+        });
+        manifest::update(path.as_ref(), files, &command, seed).unwrap_or_else(|err| {
+            errors::fail(Error::new(Code::Failed, err).with("path", path))
+        });
+    }
+}
+
+/// Whether `a` and `b` name the same file, which neither needs to exist yet
+fn same_file(a: &std::path::Path, b: &std::path::Path) -> bool {
+    let resolve = |path: &std::path::Path| {
+        let dir = match path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent,
+            _ => std::path::Path::new("."),
+        };
+        match (dir.canonicalize(), path.file_name()) {
+            (Ok(dir), Some(name)) => dir.join(name),
+            _ => path.to_path_buf(),
+        }
+    };
+    resolve(a) == resolve(b)
+}
+
+/// `--json`: the inputs of one signature and how it was made, as a single JSON object
+pub(crate) fn render_json(
+    entry: &SignatureParams,
+    exponent: &BigUint,
+    scheme: &Scheme,
+    invalid: Option<Invalid>,
+    boundary: Option<Boundary>,
+) -> String {
+    // The circuit inputs as the library serializes them, then what describes them
+    let mut json = serde_json::to_value(CircuitInputs::from(entry)).unwrap();
+    json["exponent"] = match &entry.key.exponent {
+        Some((ExponentInput::Limbs, e)) => {
+            let limbs = split_into_120_bit_limbs(e, entry.key.bits);
+            serde_json::json!(limbs.iter().map(|limb| entry.key.limb_style.format(limb)).collect::<Vec<_>>())
+        }
+        _ => serde_json::json!(exponent.to_u64()),
+    };
+    json["key_size_bits"] = serde_json::json!(entry.key.bits);
+    json["hash_alg"] = serde_json::json!(entry.signatures[0].hash_alg.name());
+    json["scheme"] = serde_json::json!(match scheme {
+        Scheme::Pkcs1v15 => "pkcs1v15",
+        Scheme::Pss(_) => "pss",
+    });
+    json["salt_len"] = serde_json::json!(scheme.salt_len());
+    if let Some(message) = &entry.message {
+        json["message"] = serde_json::json!(message.padded());
+        json["message_len"] = serde_json::json!(message.bytes.len());
+    }
+    if let Some(kind) = invalid {
+        json["expected_valid"] = serde_json::json!(false);
+        json["tamper"] = serde_json::json!(kind.name());
+    }
+    if let Some(boundary) = boundary {
+        json["edge_case"] = serde_json::json!(boundary.name());
+        json["edge_case_note"] = serde_json::json!(boundary.description());
+    }
+    format!("{}\n", serde_json::to_string_pretty(&json).unwrap())
+}
+
+/// Write `contents` to the `--output` file if there is one, otherwise to stdout
+fn write_or_print(path: Option<&str>, contents: &str) {
+    match path {
+        Some(path) => std::fs::write(path, contents).unwrap_or_else(|err| {
+            errors::fail(Error::new(Code::Failed, format!("Failed to write {}: {}", path, err)).with("path", path))
+        }),
+        None => print!("{}", contents),
+    }
+}
+
+/// Write the output of `sign` as `write_or_print` does, recording an `--output` file in the `--manifest`
+fn write_sign_output(sub_m: &clap::ArgMatches, contents: &str, seed: Option<u64>) {
+    write_or_print(sub_m.value_of("output"), contents);
+    if let Some(path) = sub_m.value_of("output") {
+        record_in_manifest("sign", sub_m, &[path.into()], rerun_seed(sub_m, seed));
+    }
+}
+
+/// The seed a `sign` file is recorded with in the `--manifest`. The metadata has the time of
+/// each run, so only a `--json` or `--no-meta` file is rerun byte for byte and keeps its seed.
+fn rerun_seed(sub_m: &clap::ArgMatches, seed: Option<u64>) -> Option<u64> {
+    seed.filter(|_| sub_m.is_present("json") || sub_m.is_present("no_meta"))
+}
+
+/// Resolve the output format and the Noir template it renders with
+fn output_format(sub_m: &clap::ArgMatches) -> (OutputFormat, &'static dyn NoirTemplate) {
+    // A file written with `sign --output` or `--batch` is a Prover.toml unless another format is asked for
+    let output_file = (sub_m.is_present("output") || sub_m.is_present("batch")) && sub_m.occurrences_of("format") == 0;
+    let format = if sub_m.is_present("toml") || output_file {
+        OutputFormat::Toml
+    } else {
+        OutputFormat::from_name(sub_m.value_of("format").unwrap()).unwrap()
+    };
+
+    let version = sub_m.value_of("bignum_version").unwrap();
+    let version = BignumVersion::from_name(version).unwrap_or_else(|| {
+        let message = format!("Unsupported --bignum-version {}, expected 0.3 or current", version);
+        errors::fail(Error::new(Code::InvalidArgument, message).with("bignum_version", version))
+    });
+
+    if format == OutputFormat::AbiJson && sub_m.is_present("annotate") {
+        let message = "--annotate cannot be combined with --format abi-json, which has no comments";
+        errors::fail(Error::new(Code::InvalidArgument, message));
+    }
+
+    let template: &'static dyn NoirTemplate = match format {
+        OutputFormat::NoirRuntime => version.template(),
+        _ => &SnippetTemplate,
+    };
+    (format, template)
+}
+
+fn cli() -> App<'static, 'static> {
+    App::new("RSA Signature Generator")
+        .subcommand(
+            clap::SubCommand::with_name("keygen")
+                .about("Generate an RSA key and save it, to sign with later through sign --key")
+                .args(&key_generation_args())
+                .arg(
+                    Arg::with_name("out")
+                        .short("o")
+                        .long("out")
+                        .takes_value(true)
+                        .required(true)
+                        .help("File to write the private key to, readable by its owner only"),
+                )
+                .arg(
+                    Arg::with_name("export_format")
+                        .long("format")
+                        .takes_value(true)
+                        .possible_values(&["pkcs8-pem", "pkcs1-pem", "der", "jwk"])
+                        .default_value("pkcs8-pem")
+                        .help("Format of the key files; pkcs8-pem and der are SPKI for the public key"),
+                )
+                .arg(
+                    Arg::with_name("public_out")
+                        .long("public-out")
+                        .takes_value(true)
+                        .help("Also write the public key to this file"),
+                ),
+        )
+        .subcommand(
+            clap::SubCommand::with_name("sign")
+                .about("Generate RSA signature parameters")
+                .arg(
+                    Arg::with_name("msg")
+                        .short("m")
+                        .long("msg")
+                        .takes_value(true)
+                        .help("Message to sign")
+                        .required_unless_one(&[
+                            "msg_json", "msg_list", "msg_file", "msg_hex", "prehashed", "x509", "dkim", "jwt", "batch",
+                        ])
+                        .conflicts_with_all(&["msg_json", "msg_list", "msg_file", "msg_hex", "prehashed"]),
+                )
+                .arg(
+                    Arg::with_name("msg_hex")
+                        .long("msg-hex")
+                        .takes_value(true)
+                        .conflicts_with_all(&["msg_json", "msg_list", "msg_file", "prehashed"])
+                        .help("Hex-encoded message to sign, for binary messages"),
+                )
+                .arg(
+                    Arg::with_name("msg_file")
+                        .long("msg-file")
+                        .takes_value(true)
+                        .conflicts_with_all(&["msg_json", "msg_list", "cross_check", "prehashed"])
+                        .help("File to sign, hashed in chunks so that its size does not matter, or - to read the message from stdin"),
+                )
+                .arg(
+                    Arg::with_name("prehashed")
+                        .long("prehashed")
+                        .takes_value(true)
+                        .conflicts_with_all(&[
+                            "msg_json", "msg_list", "prefix", "prefix_hex", "msg_max_len", "cross_check",
+                        ])
+                        .help("Hex-encoded digest to sign as it is, made with the single --hash"),
+                )
+                .arg(
+                    Arg::with_name("progress")
+                        .long("progress")
+                        .requires("msg_file")
+                        .help("Show hashing progress on stderr for large --msg-file files"),
+                )
+                .arg(
+                    Arg::with_name("msg_list")
+                        .long("msg-list")
+                        .takes_value(true)
+                        .requires("aggregate")
+                        .conflicts_with("msg_json")
+                        .help("File with one message per line, each signed into its own --aggregate slot"),
+                )
+                .arg(
+                    Arg::with_name("batch")
+                        .long("batch")
+                        .takes_value(true)
+                        .requires("out_dir")
+                        .conflicts_with_all(&[
+                            "msg", "msg_hex", "msg_file", "msg_json", "msg_list", "prehashed", "x509", "dkim", "jwt",
+                            "aggregate", "compat_pair", "noir_test", "output", "iterations", "export_key",
+                        ])
+                        .help("File of messages to sign into a file each under --out-dir: one per line, or TOML [[entries]] or a JSON array with a message and optionally a name, bits, hash and scheme"),
+                )
+                .arg(
+                    Arg::with_name("out_dir")
+                        .long("out-dir")
+                        .takes_value(true)
+                        .requires("batch")
+                        .help("Directory --batch writes a file per entry and index.toml to"),
+                )
+                .arg(
+                    Arg::with_name("aggregate")
+                        .long("aggregate")
+                        .takes_value(true)
+                        .conflicts_with_all(&["legacy_names", "msg_json"])
+                        .help("Emit every input as an array of K slots for a circuit verifying K signatures, padded with slot 0 and counted by active_count"),
+                )
+                .arg(
+                    Arg::with_name("msg_json")
+                        .long("msg-json")
+                        .takes_value(true)
+                        .help("JSON file to sign, whose bytes are also emitted for in-circuit hashing"),
+                )
+                .arg(
+                    Arg::with_name("prefix")
+                        .long("prefix")
+                        .takes_value(true)
+                        .conflicts_with("prefix_hex")
+                        .help("Domain-separation string prepended to the message before hashing and signing"),
+                )
+                .arg(
+                    Arg::with_name("prefix_hex")
+                        .long("prefix-hex")
+                        .takes_value(true)
+                        .help("Hex-encoded domain-separation bytes prepended to the message before hashing and signing"),
+                )
+                .arg(
+                    Arg::with_name("canonicalize")
+                        .long("canonicalize")
+                        .takes_value(true)
+                        .possible_values(&["jcs", "none"])
+                        .default_value("jcs")
+                        .help("Canonicalization applied to --msg-json before hashing (RFC 8785 JCS, or none to sign the file as is)"),
+                )
+                .arg(
+                    Arg::with_name("msg_max_len")
+                        .long("msg-max-len")
+                        .takes_value(true)
+                        .conflicts_with("msg_list")
+                        .help("Emit the signed message as `message`, zero-padded to this length, for circuits that hash it themselves (defaults to the message length with --msg-json)"),
+                )
+                .args(&output_format_args())
+                .args(&key_generation_args())
+                .arg(
+                    Arg::with_name("expect_exponent")
+                        .long("expect-exponent")
+                        .takes_value(true)
+                        .default_value("65537")
+                        .help("Exponent the circuit is hardwired for; imported keys with another exponent are rejected"),
+                )
+                .arg(
+                    Arg::with_name("emit_exponent_input")
+                        .long("emit-exponent-input")
+                        .takes_value(true)
+                        .possible_values(&["integer", "limbs"])
+                        .help("Emit the key's exponent as an `exponent` input for exponent-generic circuits, in every format"),
+                )
+                .arg(
+                    Arg::with_name("infer_from")
+                        .long("infer-from")
+                        .takes_value(true)
+                        .help("Noir source file or Nargo project whose BigNum<limbs, bits> instantiation sets the key size"),
+                )
+                .arg(
+                    Arg::with_name("pss")
+                        .short("p")
+                        .long("pss")
+                        .help("Use RSA PSS"),
+                )
+                .arg(
+                    Arg::with_name("salt_len")
+                        .short("s")
+                        .long("salt-len")
+                        .takes_value(true)
+                        .help("Salt length for RSA PSS in bytes, or max for the longest that fits the key (defaults to the hash length; only used with --pss)"),
+                )
+                .arg(
+                    Arg::with_name("salt_hex")
+                        .long("salt-hex")
+                        .takes_value(true)
+                        .requires("pss")
+                        .help("Fixed salt for RSA PSS as a hex string, making the signature deterministic"),
+                )
+                .arg(
+                    Arg::with_name("hashes")
+                        .long("hashes")
+                        .takes_value(true)
+                        .help("Comma-separated hashes to sign the message with using the same key (e.g. sha256,sha512)")
+                        .default_value("sha256"),
+                )
+                .arg(
+                    Arg::with_name("hash")
+                        .long("hash")
+                        .takes_value(true)
+                        .conflicts_with("hashes")
+                        .help("Hash to sign the message with: sha1, sha256, sha384 or sha512 (a single --hashes)"),
+                )
+                .arg(
+                    Arg::with_name("keys")
+                        .long("keys")
+                        .takes_value(true)
+                        .help("Number of keys to generate, each signing the same message")
+                        .default_value("1")
+                        .conflicts_with("key"),
+                )
+                .arg(
+                    Arg::with_name("key")
+                        .long("key")
+                        .takes_value(true)
+                        .multiple(true)
+                        .number_of_values(1)
+                        .help("Private key (PEM or DER, PKCS#1 or PKCS#8) to sign with, can be repeated"),
+                )
+                .arg(
+                    Arg::with_name("private_key_env")
+                        .long("private-key-env")
+                        .takes_value(true)
+                        .conflicts_with_all(&["key", "keys"])
+                        .help("Environment variable holding a private key as base64 DER (PKCS#1 or PKCS#8) to sign with"),
+                )
+                .arg(
+                    Arg::with_name("pubkey")
+                        .long("pubkey")
+                        .takes_value(true)
+                        .conflicts_with_all(&[
+                            "key", "keys", "private_key_env", "seed", "msg_list", "iterations", "export_key",
+                            "keygen_timeout",
+                        ])
+                        .help("Public key (PEM or DER, SPKI or PKCS#1) of a signature made elsewhere, emitting its inputs without generating a key or signing"),
+                )
+                .arg(
+                    Arg::with_name("x509")
+                        .long("x509")
+                        .takes_value(true)
+                        .conflicts_with_all(&[
+                            "msg", "msg_hex", "msg_file", "msg_json", "msg_list", "prehashed", "prefix", "prefix_hex",
+                            "pubkey", "key", "keys", "private_key_env", "seed", "iterations", "export_key",
+                            "keygen_timeout", "hash", "hashes", "pss", "salt_len", "salt_hex", "dkim",
+                        ])
+                        .help("X.509 certificate, PEM or DER, whose tbsCertificate is the message, signed with the hash and scheme of its signature algorithm"),
+                )
+                .arg(
+                    Arg::with_name("issuer")
+                        .long("issuer")
+                        .takes_value(true)
+                        .requires("x509")
+                        .help("Certificate or public key, PEM or DER, of the issuer that signed --x509 (defaults to the certificate's own key, for self-signed certificates)"),
+                )
+                .arg(
+                    Arg::with_name("dkim")
+                        .long("dkim")
+                        .takes_value(true)
+                        .conflicts_with_all(&[
+                            "msg", "msg_hex", "msg_file", "msg_json", "msg_list", "prehashed", "prefix", "prefix_hex",
+                            "pubkey", "key", "keys", "private_key_env", "seed", "iterations", "export_key",
+                            "keygen_timeout", "hash", "hashes", "pss", "salt_len", "salt_hex",
+                        ])
+                        .help("Email (.eml) whose rsa-sha256 DKIM-Signature to emit inputs for, the message being its canonicalized signed headers"),
+                )
+                .arg(
+                    Arg::with_name("dkim_pubkey")
+                        .long("dkim-pubkey")
+                        .takes_value(true)
+                        .requires("dkim")
+                        .conflicts_with("dkim_dns")
+                        .help("DKIM public key of --dkim, as the base64 p= value or the whole TXT record"),
+                )
+                .arg(
+                    Arg::with_name("dkim_dns")
+                        .long("dkim-dns")
+                        .takes_value(true)
+                        .requires("dkim")
+                        .help("File with the DNS TXT record of the DKIM key of --dkim, as dig prints it or a zone file holds it"),
+                )
+                .arg(
+                    Arg::with_name("jwt")
+                        .long("jwt")
+                        .takes_value(true)
+                        .requires("jwk")
+                        .conflicts_with_all(&[
+                            "msg", "msg_hex", "msg_file", "msg_json", "msg_list", "prehashed", "prefix", "prefix_hex",
+                            "pubkey", "key", "keys", "private_key_env", "seed", "iterations", "export_key",
+                            "keygen_timeout", "hash", "hashes", "pss", "salt_len", "salt_hex", "x509", "dkim",
+                        ])
+                        .help("Compact RS256 or PS256 JWT whose signature to emit inputs for, the message being its header.payload"),
+                )
+                .arg(
+                    Arg::with_name("jwk")
+                        .long("jwk")
+                        .takes_value(true)
+                        .requires("jwt")
+                        .help("File with the JWK, or the JWKS holding the key, that signed --jwt"),
+                )
+                .arg(
+                    Arg::with_name("kid")
+                        .long("kid")
+                        .takes_value(true)
+                        .requires("jwk")
+                        .help("kid of the key of --jwt in the --jwk set (defaults to the kid of the token's header)"),
+                )
+                .arg(
+                    Arg::with_name("signature")
+                        .long("signature")
+                        .takes_value(true)
+                        .requires("pubkey")
+                        .conflicts_with("signature_hex")
+                        .help("File with the signature to emit inputs for, as raw bytes or base64; it must verify under --pubkey"),
+                )
+                .arg(
+                    Arg::with_name("signature_hex")
+                        .long("signature-hex")
+                        .takes_value(true)
+                        .requires("pubkey")
+                        .help("Signature to emit inputs for as a hex string; it must verify under --pubkey"),
+                )
+                .arg(
+                    Arg::with_name("passphrase_env")
+                        .long("passphrase-env")
+                        .takes_value(true)
+                        .requires("key")
+                        .conflicts_with("passphrase_file")
+                        .help("Environment variable holding the passphrase of encrypted --key files"),
+                )
+                .arg(
+                    Arg::with_name("passphrase_file")
+                        .long("passphrase-file")
+                        .takes_value(true)
+                        .requires("key")
+                        .help("File holding the passphrase of encrypted --key files (prompted for on a terminal otherwise)"),
+                )
+                .arg(
+                    Arg::with_name("preset")
+                        .long("preset")
+                        .takes_value(true)
+                        .conflicts_with_all(&["legacy_names", "canonical_limbs", "aggregate"])
+                        .help("Emit the input names, nesting and commitments of a downstream circuit: zkemail, zkpassport or jwt"),
+                )
+                .arg(
+                    Arg::with_name("legacy_names")
+                        .long("legacy-names")
+                        .help("Emit TOML in the pre-0.4.0 shape (bn = [modulus, redc] and a [signature] table)"),
+                )
+                .arg(
+                    Arg::with_name("compat_pair")
+                        .long("compat-pair")
+                        .takes_value(true)
+                        .value_name("PREFIX")
+                        .conflicts_with_all(&["legacy_names", "preset", "aggregate", "iterations"])
+                        .help("Write the vector once per BigNum layout, to PREFIX.v03.<ext> and PREFIX.v04.<ext>, instead of printing it"),
+                )
+                .arg(
+                    Arg::with_name("tag")
+                        .long("tag")
+                        .takes_value(true)
+                        .multiple(true)
+                        .number_of_values(1)
+                        .conflicts_with("no_meta")
+                        .help("Label recorded in the metadata as name=value, for selecting files with `regen --filter`; can be repeated"),
+                )
+                .arg(
+                    Arg::with_name("output")
+                        .short("o")
+                        .long("output")
+                        .takes_value(true)
+                        .conflicts_with_all(&["compat_pair", "iterations"])
+                        .help("Write the output to this file instead of stdout, as a complete Prover.toml unless --format is given"),
+                )
+                .arg(
+                    Arg::with_name("manifest")
+                        .long("manifest")
+                        .takes_value(true)
+                        .help("Record the file written with --output, or the files of --batch, their SHA-256, the options and the seed in this manifest"),
+                )
+                .arg(
+                    Arg::with_name("json")
+                        .long("json")
+                        .conflicts_with_all(&[
+                            "toml", "format", "annotate", "preset", "legacy_names", "aggregate", "compat_pair",
+                            "expected", "iterations",
+                        ])
+                        .help("Print the inputs of one signature as a single JSON object (hex limbs, hash bytes, key size and scheme), for noir_js"),
+                )
+                .arg(
+                    Arg::with_name("noir_test")
+                        .long("noir-test")
+                        .takes_value(true)
+                        .min_values(0)
+                        .value_name("name")
+                        .conflicts_with_all(&[
+                            "toml", "format", "json", "annotate", "preset", "legacy_names", "aggregate", "compat_pair",
+                            "expected", "iterations",
+                        ])
+                        .help("Print a complete Noir #[test] verifying the signature in the syntax of --bignum-version, named after the tests of src/rsa.nr by default"),
+                )
+                .arg(
+                    Arg::with_name("invalid")
+                        .long("invalid")
+                        .takes_value(true)
+                        .possible_values(&["flipped-sig", "wrong-hash", "wrong-modulus", "bad-padding", "bad-redc"])
+                        .conflicts_with_all(&[
+                            "pubkey", "x509", "dkim", "jwt", "iterations", "cross_check", "emit_verifier_script",
+                            "execute_artifact", "compat_pair",
+                        ])
+                        .help("Break the vector for a should-fail test: flip a signature bit, pair it with the hash of another message, swap in the modulus of another key, sign a corrupted padding or perturb the redc parameter; labelled with expected_valid = false and the tamper kind"),
+                )
+                .arg(
+                    Arg::with_name("edge_case")
+                        .long("edge-case")
+                        .takes_value(true)
+                        .possible_values(&["leading-zero-sig", "e3-short-msg", "short-top-limb", "pss-empty-salt"])
+                        .conflicts_with_all(&[
+                            "msg_json", "msg_list", "msg_file", "prehashed", "pubkey", "x509", "dkim", "jwt",
+                            "iterations", "aggregate", "salt_len", "salt_hex",
+                        ])
+                        .help("Emit a valid vector at a boundary the circuit must handle: a signature with a zero top limb (varying the message, 2048-bit keys only), exponent 3 over a short message, or PSS with an empty salt; short-top-limb, a modulus shorter than the instantiation, is refused with the reason; recorded as edge_case in the metadata"),
+                )
+                .arg(
+                    Arg::with_name("max_attempts")
+                        .long("max-attempts")
+                        .takes_value(true)
+                        .default_value("4096")
+                        .help("Messages to try for --edge-case leading-zero-sig before giving up"),
+                )
+                .arg(
+                    Arg::with_name("no_meta")
+                        .long("no-meta")
+                        .help("Leave out the provenance metadata (tool version, timestamp, key fingerprints, options), for byte-stable golden files"),
+                )
+                .arg(
+                    Arg::with_name("expected")
+                        .long("expected")
+                        .conflicts_with("aggregate")
+                        .help("Append an [expected] table with the verdict and pubkey commitments a circuit with public outputs should produce"),
+                )
+                .arg(
+                    Arg::with_name("keygen_timeout")
+                        .long("keygen-timeout")
+                        .takes_value(true)
+                        .value_name("SECONDS")
+                        .help("Generate each key on a worker thread and give up on an attempt running longer than this"),
+                )
+                .arg(
+                    Arg::with_name("keygen_retries")
+                        .long("keygen-retries")
+                        .takes_value(true)
+                        .default_value("0")
+                        .requires("keygen_timeout")
+                        .help("Attempts to make with fresh randomness after one times out, before failing"),
+                )
+                .arg(
+                    Arg::with_name("verbose")
+                        .short("v")
+                        .long("verbose")
+                        .help("Report key generation progress and a timing summary on stderr"),
+                )
+                .arg(
+                    Arg::with_name("iterations")
+                        .long("iterations")
+                        .takes_value(true)
+                        .help("Soak mode: repeat generation, self-verification and a limb round trip N times and print statistics"),
+                )
+                .arg(
+                    Arg::with_name("failures_dir")
+                        .long("failures-dir")
+                        .takes_value(true)
+                        .default_value("soak-failures")
+                        .help("Directory where failing soak iterations are saved with their seed"),
+                )
+                .arg(
+                    Arg::with_name("cross_check")
+                        .long("cross-check")
+                        .takes_value(true)
+                        .possible_values(&["openssl"])
+                        .help("Also verify every signature with an independent implementation before printing"),
+                )
+                .arg(
+                    Arg::with_name("emit_verifier_script")
+                        .long("emit-verifier-script")
+                        .takes_value(true)
+                        .conflicts_with("iterations")
+                        .help("Also write a standalone Python 3 script that verifies the signatures with the standard library"),
+                )
+                .arg(
+                    Arg::with_name("export_key")
+                        .long("export-key")
+                        .visible_alias("save-key")
+                        .takes_value(true)
+                        .conflicts_with("iterations")
+                        .help("Write the signing key to this file, for use with other tools"),
+                )
+                .arg(
+                    Arg::with_name("export_format")
+                        .long("export-format")
+                        .takes_value(true)
+                        .possible_values(&["pkcs8-pem", "pkcs1-pem", "der", "jwk"])
+                        .default_value("pkcs8-pem")
+                        .help("Format of --export-key; pkcs8-pem and der are SPKI for public keys"),
+                )
+                .arg(
+                    Arg::with_name("export_public")
+                        .long("export-public")
+                        .requires("export_key")
+                        .help("Export only the public key"),
+                )
+                .arg(
+                    Arg::with_name("execute_artifact")
+                        .long("execute-artifact")
+                        .takes_value(true)
+                        .conflicts_with("iterations")
+                        .help("Execute the compiled circuit at this nargo artifact (target/<name>.json) on the inputs in-process, failing if they do not satisfy it"),
+                ),
+        )
+        .subcommand(importer_command(
+            "from-cert",
+            "Emit the inputs of the signature of an X.509 certificate, as sign --x509 does",
+            "signature_gen from-cert <cert> [options of sign]",
+        ))
+        .subcommand(importer_command(
+            "from-jwt",
+            "Emit the inputs of the signature of a JWT, as sign --jwt does",
+            "signature_gen from-jwt <token> --jwk <file> [options of sign]",
+        ))
+        .subcommand(
+            clap::SubCommand::with_name("params")
+                .about("Generate BigNum parameters (modulus and Barrett reduction) from a public key")
+                .arg(
+                    Arg::with_name("pubkey")
+                        .short("k")
+                        .long("pubkey")
+                        .takes_value(true)
+                        .help("RSA public key modulus as a hex string (with or without 0x prefix)")
+                        .conflicts_with("limbs"),
+                )
+                .arg(
+                    Arg::with_name("limbs")
+                        .short("l")
+                        .long("limbs")
+                        .takes_value(true)
+                        .help("RSA public key modulus as comma-separated 120-bit hex limbs (e.g. 0xaabb,0xccdd,...)")
+                        .conflicts_with("pubkey"),
+                )
+                .arg(
+                    Arg::with_name("bits")
+                        .short("b")
+                        .long("bits")
+                        .takes_value(true)
+                        .help("Bit size of the RSA key (e.g. 1024, 2048, 4096)")
+                        .required(true),
+                )
+                .args(&output_format_args()),
+        )
+        .subcommand(
+            clap::SubCommand::with_name("doctor")
+                .about("Check that nargo, the Noir library's bignum dependency and the emitted code are compatible")
+                .arg(
+                    Arg::with_name("manifest")
+                        .long("manifest")
+                        .takes_value(true)
+                        .default_value("../Nargo.toml")
+                        .help("Nargo.toml of the noir_rsa library"),
+                )
+                .arg(
+                    Arg::with_name("example_manifest")
+                        .long("example-manifest")
+                        .takes_value(true)
+                        .default_value("../example/Nargo.toml")
+                        .help("Nargo.toml of a project using the library, checked when it exists"),
+                )
+                .arg(
+                    Arg::with_name("nargo")
+                        .long("nargo")
+                        .takes_value(true)
+                        .default_value("nargo")
+                        .help("nargo binary to query for its version"),
+                )
+                .arg(
+                    Arg::with_name("bignum_version")
+                        .long("bignum-version")
+                        .takes_value(true)
+                        .default_value("current")
+                        .help("BigNum version the emitted code targets (0.3 or current)"),
+                ),
+        )
+        .subcommand(
+            clap::SubCommand::with_name("convert")
+                .about("Convert a Prover.toml between input layouts")
+                .arg(
+                    Arg::with_name("from")
+                        .long("from")
+                        .takes_value(true)
+                        .possible_values(&["v03", "v04"])
+                        .required(true)
+                        .help("Layout of the input file"),
+                )
+                .arg(
+                    Arg::with_name("to")
+                        .long("to")
+                        .takes_value(true)
+                        .possible_values(&["v03", "v04"])
+                        .required(true)
+                        .help("Layout to write"),
+                )
+                .arg(
+                    Arg::with_name("output")
+                        .short("o")
+                        .long("output")
+                        .takes_value(true)
+                        .help("Write the converted file here instead of stdout"),
+                )
+                .arg(canonical_limbs_arg())
+                .arg(
+                    Arg::with_name("file")
+                        .required(true)
+                        .help("Prover.toml to convert"),
+                ),
+        )
+        .subcommand(
+            clap::SubCommand::with_name("diff")
+                .about("Compare two Prover.toml files by the integers their limbs recombine to, whichever their layouts")
+                .arg(Arg::with_name("a").required(true).help("First Prover.toml"))
+                .arg(Arg::with_name("b").required(true).help("Second Prover.toml")),
+        )
+        .subcommand(
+            clap::SubCommand::with_name("reencode-signature")
+                .about("Convert a signature between raw big-endian, DER BIT STRING and limb forms, checking it against the modulus")
+                .arg(
+                    Arg::with_name("signature")
+                        .long("signature")
+                        .takes_value(true)
+                        .required_unless("signature_file")
+                        .conflicts_with("signature_file")
+                        .help("The signature as hex, DER hex or a comma-separated limb list"),
+                )
+                .arg(
+                    Arg::with_name("signature_file")
+                        .long("signature-file")
+                        .takes_value(true)
+                        .help("Read the signature from this file, binary or text"),
+                )
+                .arg(
+                    Arg::with_name("modulus")
+                        .long("modulus")
+                        .takes_value(true)
+                        .required_unless("pubkey_file")
+                        .conflicts_with("pubkey_file")
+                        .help("Modulus the signature belongs to, as hex"),
+                )
+                .arg(
+                    Arg::with_name("pubkey_file")
+                        .long("pubkey-file")
+                        .takes_value(true)
+                        .help("Read the modulus from this public or private key, PEM or DER"),
+                )
+                .arg(
+                    Arg::with_name("in_format")
+                        .long("in-format")
+                        .takes_value(true)
+                        .possible_values(&["raw", "der", "limbs"])
+                        .help("Form of the input signature [default: detected]"),
+                )
+                .arg(
+                    Arg::with_name("out_format")
+                        .long("out-format")
+                        .takes_value(true)
+                        .possible_values(&["raw-hex", "bytes", "limbs", "toml-fragment"])
+                        .default_value("raw-hex")
+                        .help("Form to print the signature in"),
+                )
+                .arg(canonical_limbs_arg()),
+        )
+        .subcommand(
+            clap::SubCommand::with_name("serve")
+                .about("Serve a JSON API computing circuit inputs for existing signatures")
+                .arg(
+                    Arg::with_name("listen")
+                        .long("listen")
+                        .takes_value(true)
+                        .default_value("127.0.0.1:8080")
+                        .help("Address to listen on"),
+                )
+                .arg(
+                    Arg::with_name("threads")
+                        .long("threads")
+                        .takes_value(true)
+                        .default_value("4")
+                        .help("Number of worker threads handling requests"),
+                ),
+        )
+        .subcommand(
+            clap::SubCommand::with_name("difftest")
+                .about("Run randomized vectors through both the Rust checks and the Noir circuit (needs nargo)")
+                .arg(
+                    Arg::with_name("iterations")
+                        .long("iterations")
+                        .takes_value(true)
+                        .default_value("100")
+                        .help("Number of vectors to generate"),
+                )
+                .arg(
+                    Arg::with_name("seed")
+                        .long("seed")
+                        .takes_value(true)
+                        .default_value("0")
+                        .help("Seed making the whole run reproducible"),
+                )
+                .arg(
+                    Arg::with_name("tamper_fraction")
+                        .long("tamper-fraction")
+                        .takes_value(true)
+                        .default_value("0.25")
+                        .help("Fraction of vectors tampered with after signing, which must fail on both sides"),
+                )
+                .arg(
+                    Arg::with_name("failures_dir")
+                        .long("failures-dir")
+                        .takes_value(true)
+                        .default_value("difftest-failures")
+                        .help("Directory where disagreeing vectors and their project state are saved"),
+                )
+                .arg(
+                    Arg::with_name("example_dir")
+                        .long("example-dir")
+                        .takes_value(true)
+                        .default_value("../example")
+                        .help("Example Noir project copied for each circuit shape"),
+                )
+                .arg(
+                    Arg::with_name("nargo")
+                        .long("nargo")
+                        .takes_value(true)
+                        .default_value("nargo")
+                        .help("nargo binary to run"),
+                ),
+        )
+        .subcommand(
+            clap::SubCommand::with_name("run-negative")
+                .about("Check that the circuit rejects every invalid vector of a directory, with valid controls interleaved")
+                .arg(
+                    Arg::with_name("vectors_dir")
+                        .required(true)
+                        .help("Directory of Prover.toml files labelled with expected_valid or an [edge_case] table"),
+                )
+                .arg(
+                    Arg::with_name("exponent")
+                        .short("e")
+                        .long("exponent")
+                        .takes_value(true)
+                        .default_value("65537")
+                        .help("Exponent of the keys, compiled into the circuit"),
+                )
+                .arg(
+                    Arg::with_name("pss")
+                        .short("p")
+                        .long("pss")
+                        .help("Treat vectors without a [meta] table as RSA PSS"),
+                )
+                .arg(
+                    Arg::with_name("salt_len")
+                        .short("s")
+                        .long("salt-len")
+                        .takes_value(true)
+                        .help("Salt length for RSA PSS in bytes (defaults to the hash length; only used with --pss)"),
+                )
+                .arg(
+                    Arg::with_name("control_every")
+                        .long("control-every")
+                        .takes_value(true)
+                        .default_value("4")
+                        .help("Run a freshly signed valid control vector after every this many vectors"),
+                )
+                .arg(
+                    Arg::with_name("seed")
+                        .long("seed")
+                        .takes_value(true)
+                        .default_value("0")
+                        .help("Seed for the control vectors"),
+                )
+                .arg(
+                    Arg::with_name("execute_artifact")
+                        .long("execute-artifact")
+                        .takes_value(true)
+                        .help("Execute every vector in-process against this compiled circuit instead of running nargo"),
+                )
+                .arg(
+                    Arg::with_name("example_dir")
+                        .long("example-dir")
+                        .takes_value(true)
+                        .default_value("../example")
+                        .help("Example Noir project copied for each circuit shape"),
+                )
+                .arg(
+                    Arg::with_name("nargo")
+                        .long("nargo")
+                        .takes_value(true)
+                        .default_value("nargo")
+                        .help("nargo binary to run"),
+                ),
+        )
+        .subcommand(
+            clap::SubCommand::with_name("iso9796")
+                .about("Sign with ISO/IEC 9796-2 scheme 1 (message recovery), as used by passport Active Authentication")
+                .arg(
+                    Arg::with_name("msg")
+                        .long("msg")
+                        .takes_value(true)
+                        .required_unless_one(&["msg_hex", "challenge"])
+                        .conflicts_with_all(&["msg_hex", "challenge"])
+                        .help("Message to sign; beyond the capacity of the key it is recovered partially"),
+                )
+                .arg(
+                    Arg::with_name("msg_hex")
+                        .long("msg-hex")
+                        .takes_value(true)
+                        .conflicts_with("challenge")
+                        .help("Hex-encoded message to sign"),
+                )
+                .arg(
+                    Arg::with_name("challenge")
+                        .long("challenge")
+                        .takes_value(true)
+                        .help("Hex-encoded Active Authentication challenge, signed as M2 after a random M1 filling the capacity"),
+                )
+                .arg(
+                    Arg::with_name("hash")
+                        .long("hash")
+                        .takes_value(true)
+                        .default_value("sha1")
+                        .help("Hash to sign with: sha1 (trailer 0xbc), or sha256, sha384 or sha512 (trailer 0xcc)"),
+                )
+                .arg(
+                    Arg::with_name("key")
+                        .long("key")
+                        .takes_value(true)
+                        .help("Private key (PEM or DER, PKCS#1 or PKCS#8) to sign with instead of generating one"),
+                )
+                .arg(
+                    Arg::with_name("bits")
+                        .short("b")
+                        .long("bits")
+                        .takes_value(true)
+                        .default_value("2048")
+                        .help("Bit size of the generated key"),
+                )
+                .arg(
+                    Arg::with_name("exponent")
+                        .short("e")
+                        .long("exponent")
+                        .takes_value(true)
+                        .default_value("65537")
+                        .help("Exponent of the generated key"),
+                )
+                .arg(
+                    Arg::with_name("seed")
+                        .long("seed")
+                        .takes_value(true)
+                        .help("Seed for key generation and the M1 nonce, making the output reproducible"),
+                ),
+        )
+        .subcommand(
+            clap::SubCommand::with_name("edge-cases")
+                .about("Construct or search for vectors near the boundaries of the BigNum representation")
+                .arg(
+                    Arg::with_name("bits")
+                        .short("b")
+                        .long("bits")
+                        .takes_value(true)
+                        .default_value("2048")
+                        .help("Bit size of the RSA keys"),
+                )
+                .arg(
+                    Arg::with_name("exponent")
+                        .short("e")
+                        .long("exponent")
+                        .takes_value(true)
+                        .default_value("65537")
+                        .help("Exponent to use for the keys"),
+                )
+                .arg(
+                    Arg::with_name("hash")
+                        .long("hash")
+                        .takes_value(true)
+                        .default_value("sha256")
+                        .help("Hash to sign with"),
+                )
+                .arg(
+                    Arg::with_name("pss")
+                        .short("p")
+                        .long("pss")
+                        .help("Use RSA PSS"),
+                )
+                .arg(
+                    Arg::with_name("salt_len")
+                        .short("s")
+                        .long("salt-len")
+                        .takes_value(true)
+                        .help("Salt length for RSA PSS in bytes (defaults to the hash length; only used with --pss)"),
+                )
+                .arg(
+                    Arg::with_name("cases")
+                        .long("cases")
+                        .takes_value(true)
+                        .default_value("top-limb-all-ones,modulus-above-power-of-two,signature-n-minus-1,barrett-correction")
+                        .help("Comma-separated edge cases to generate"),
+                )
+                .arg(
+                    Arg::with_name("max_attempts")
+                        .long("max-attempts")
+                        .takes_value(true)
+                        .default_value("10000")
+                        .help("Keys or signatures to try per edge case before giving up"),
+                )
+                .arg(
+                    Arg::with_name("seed")
+                        .long("seed")
+                        .takes_value(true)
+                        .default_value("0")
+                        .help("Seed making the whole run reproducible"),
+                )
+                .arg(
+                    Arg::with_name("output_dir")
+                        .short("o")
+                        .long("output-dir")
+                        .takes_value(true)
+                        .default_value("edge-cases")
+                        .help("Directory receiving one <edge case>.toml per edge case"),
+                )
+                .arg(
+                    Arg::with_name("manifest")
+                        .long("manifest")
+                        .takes_value(true)
+                        .help("Record the files written, their SHA-256, the options and the seed in this manifest"),
+                ),
+        )
+        .subcommand(
+            clap::SubCommand::with_name("dkim-corpus")
+                .about("Write DKIM-signed synthetic emails covering the canonicalization matrix, with their circuit inputs")
+                .arg(
+                    Arg::with_name("out_dir")
+                        .long("out-dir")
+                        .takes_value(true)
+                        .default_value("dkim-corpus")
+                        .help("Directory receiving the .eml files, their inputs, the key and manifest.toml"),
+                )
+                .arg(
+                    Arg::with_name("count")
+                        .long("count")
+                        .takes_value(true)
+                        .default_value("9")
+                        .help("Number of emails, cycling through the canonicalization cases"),
+                )
+                .arg(
+                    Arg::with_name("bits")
+                        .short("b")
+                        .long("bits")
+                        .takes_value(true)
+                        .default_value("2048")
+                        .help("Bit size of the signing key"),
+                )
+                .arg(
+                    Arg::with_name("seed")
+                        .long("seed")
+                        .takes_value(true)
+                        .default_value("0")
+                        .help("Seed of the signing key, making the whole corpus reproducible"),
+                )
+                .arg(
+                    Arg::with_name("domain")
+                        .long("domain")
+                        .takes_value(true)
+                        .default_value("example.com")
+                        .help("Signing domain, the d= tag"),
+                )
+                .arg(
+                    Arg::with_name("selector")
+                        .long("selector")
+                        .takes_value(true)
+                        .default_value("corpus")
+                        .help("Key selector, the s= tag"),
+                )
+                .arg(
+                    Arg::with_name("max_header_len")
+                        .long("max-header-len")
+                        .takes_value(true)
+                        .default_value("1024")
+                        .help("Capacity of the signed header BoundedVec in the inputs"),
+                )
+                .arg(
+                    Arg::with_name("manifest")
+                        .long("manifest")
+                        .takes_value(true)
+                        .help("Record the files written, their SHA-256, the options and the seed in this manifest"),
+                ),
+        )
+        .subcommand(
+            clap::SubCommand::with_name("raw-rsa")
+                .about("Emit bare modular exponentiation vectors y = x^e mod n, without hashing or padding, for testing the BigNum layer alone")
+                .arg(
+                    Arg::with_name("bits")
+                        .short("b")
+                        .long("bits")
+                        .takes_value(true)
+                        .default_value("2048")
+                        .conflicts_with("pubkey_file")
+                        .help("Bit size of the generated modulus"),
+                )
+                .arg(
+                    Arg::with_name("exponent")
+                        .short("e")
+                        .long("exponent")
+                        .takes_value(true)
+                        .default_value("65537")
+                        .conflicts_with("pubkey_file")
+                        .help("Public exponent of the generated key"),
+                )
+                .arg(
+                    Arg::with_name("pubkey_file")
+                        .long("pubkey-file")
+                        .takes_value(true)
+                        .help("Take the modulus and exponent from this public or private key instead"),
+                )
+                .arg(
+                    Arg::with_name("bases")
+                        .long("bases")
+                        .takes_value(true)
+                        .default_value("random")
+                        .help("Comma-separated bases: zero, one, n-minus-1, max-carry (every limb all ones) or random"),
+                )
+                .arg(
+                    Arg::with_name("base")
+                        .long("base")
+                        .takes_value(true)
+                        .conflicts_with("bases")
+                        .help("A single base of your own, as hex"),
+                )
+                .arg(
+                    Arg::with_name("seed")
+                        .long("seed")
+                        .takes_value(true)
+                        .default_value("0")
+                        .help("Seed of the generated key and the random bases"),
+                )
+                .arg(
+                    Arg::with_name("noir_test")
+                        .long("noir-test")
+                        .help("Emit a Noir file with one test asserting pow(x, e) == y for each base instead of TOML"),
+                )
+                .arg(canonical_limbs_arg()),
+        )
+        .subcommand(
+            clap::SubCommand::with_name("regen")
+                .about("Regenerate in place the vectors of a directory whose metadata matches --filter, from their recorded seed and command")
+                .arg(Arg::with_name("dir").required(true).help("Directory to scan for .toml vectors, recursively"))
+                .arg(
+                    Arg::with_name("filter")
+                        .long("filter")
+                        .takes_value(true)
+                        .multiple(true)
+                        .number_of_values(1)
+                        .help("name=value a --tag or [meta] field must match, e.g. hash=sha512; can be repeated"),
+                )
+                .arg(
+                    Arg::with_name("dry_run")
+                        .long("dry-run")
+                        .help("Report what would change without writing anything"),
+                ),
+        )
+        .subcommand(
+            clap::SubCommand::with_name("verify-manifest")
+                .about("Check committed vector files against the SHA-256 recorded by --manifest")
+                .arg(
+                    Arg::with_name("manifest")
+                        .required(true)
+                        .help("Manifest written by --manifest"),
+                )
+                .arg(
+                    Arg::with_name("regenerate")
+                        .long("regenerate")
+                        .help("Also rerun every seeded command into a scratch directory and compare its output"),
+                ),
+        )
+        .subcommand(
+            clap::SubCommand::with_name("inspect")
+                .about("Sanity check a third-party key before it is used as a verifier key")
+                .arg(
+                    Arg::with_name("public_key")
+                        .long("public-key")
+                        .takes_value(true)
+                        .required_unless_one(&["private_key", "cert"])
+                        .conflicts_with_all(&["private_key", "cert"])
+                        .help("Public key file: PEM (SPKI or PKCS#1), DER, base64 DER or a JWK"),
+                )
+                .arg(
+                    Arg::with_name("private_key")
+                        .long("private-key")
+                        .takes_value(true)
+                        .conflicts_with("cert")
+                        .help("Private key file, also checking p, q, d and the CRT values"),
+                )
+                .arg(
+                    Arg::with_name("cert")
+                        .long("cert")
+                        .takes_value(true)
+                        .help("X.509 certificate, PEM or DER, whose subject key is checked"),
+                )
+                .arg(
+                    Arg::with_name("passphrase_env")
+                        .long("passphrase-env")
+                        .takes_value(true)
+                        .requires("private_key")
+                        .conflicts_with("passphrase_file")
+                        .help("Environment variable holding the passphrase of an encrypted --private-key"),
+                )
+                .arg(
+                    Arg::with_name("passphrase_file")
+                        .long("passphrase-file")
+                        .takes_value(true)
+                        .requires("private_key")
+                        .help("File holding the passphrase of an encrypted --private-key"),
+                )
+                .arg(
+                    Arg::with_name("bits")
+                        .short("b")
+                        .long("bits")
+                        .takes_value(true)
+                        .help("Modulus size the key is advertised with"),
+                )
+                .arg(
+                    Arg::with_name("blocklist")
+                        .long("blocklist")
+                        .takes_value(true)
+                        .help("File of known weak key fingerprints, one per line: SHA-256 of the modulus or openssl-vulnkeys"),
+                )
+                .arg(
+                    Arg::with_name("lenient")
+                        .long("lenient")
+                        .help("Exit successfully even if a check fails"),
+                ),
+        )
+        .subcommand(
+            clap::SubCommand::with_name("verify")
+                .about("Recombine the limbs of a Prover.toml or JSON output and verify them off-circuit")
+                .arg(
+                    Arg::with_name("inputs")
+                        .required(true)
+                        .help("TOML or JSON inputs as sign emits them, canonical or pre-0.4.0 layout"),
+                )
+                .arg(
+                    Arg::with_name("hash")
+                        .long("hash")
+                        .takes_value(true)
+                        .possible_values(&["sha1", "sha256", "sha384", "sha512"])
+                        .help("Hash of the inputs (defaults to the recorded one, or the hash of that digest length)"),
+                )
+                .arg(
+                    Arg::with_name("pss")
+                        .long("pss")
+                        .help("Verify as RSA PSS (defaults to the recorded scheme, or PKCS#1 v1.5)"),
+                )
+                .arg(
+                    Arg::with_name("salt_len")
+                        .long("salt-len")
+                        .takes_value(true)
+                        .help("PSS salt length in bytes (defaults to the recorded one, or the hash length)"),
+                )
+                .arg(
+                    Arg::with_name("exponent")
+                        .long("exponent")
+                        .takes_value(true)
+                        .help("Public exponent (defaults to the recorded one, or 65537)"),
+                ),
+        )
+        .arg(
+            Arg::with_name("error_format")
+                .long("error-format")
+                .global(true)
+                .takes_value(true)
+                .possible_values(&["text", "json"])
+                .default_value("text")
+                .help("Report a failure as its message, or as one JSON object with a stable code and its context"),
+        )
+}
+
+/// Parse the command line and run the subcommand, exiting through `errors::fail` on failure
+pub fn run() {
+    let matches = parse_args(std::env::args().collect());
+
+    let error_format = matches.subcommand().1.unwrap_or(&matches).value_of("error_format");
+    errors::set_format(if error_format == Some("json") { ErrorFormat::Json } else { ErrorFormat::Text });
+
+    match matches.subcommand() {
+        ("keygen", Some(sub_m)) => {
+            let bits: u32 = parse_value(sub_m, "bits").unwrap();
+            if let Err(err) = check_generated_bits(bits) {
+                errors::fail(err);
+            }
+            // The same draws as the first key of `sign --seed`, so either reproduces the other
+            let mut rng = match seed(sub_m) {
+                Some(seed) => ChaCha20Rng::seed_from_u64(seed),
+                None => ChaCha20Rng::from_rng(rand::thread_rng()).expect("failed to seed the RNG"),
+            };
+            let key = generate_key(&mut rng, bits as usize, exponent(sub_m)).unwrap_or_else(|err| errors::fail(err));
+            let path = sub_m.value_of("out").unwrap();
+            export_key(path, &key, sub_m);
+            let pub_key = key.to_public_key();
+            if let Some(public_path) = sub_m.value_of("public_out") {
+                let format = ExportFormat::from_name(sub_m.value_of("export_format").unwrap()).unwrap();
+                let written = keys::export_public_key(&pub_key, format).and_then(|encoded| {
+                    std::fs::write(public_path, encoded)
+                        .map_err(|err| format!("Failed to write {}: {}", public_path, err))
+                });
+                if let Err(err) = written {
+                    errors::fail(Error::new(Code::Failed, err).with("path", public_path));
+                }
+            }
+            println!("{} {}", meta::key_fingerprint(&pub_key), path);
+        }
+        ("sign", Some(sub_m)) => {
+            if sub_m.is_present("manifest") && !sub_m.is_present("output") && !sub_m.is_present("batch") {
+                let message = "--manifest records the file of --output or the files of --batch, give one of them";
+                errors::fail(Error::new(Code::InvalidArgument, message));
+            }
+            if let Some(path) = sub_m.value_of("batch") {
+                sign_batch(path, sub_m);
+                return;
+            }
+            let prefix = message_prefix(sub_m);
+            let msg_max_len: Option<usize> = parse_value(sub_m, "msg_max_len");
+            // A certificate, an email or a token supplies the message, hash, scheme, signature and key
+            let embedded = match (sub_m.value_of("x509"), sub_m.value_of("dkim"), sub_m.value_of("jwt")) {
+                (Some(path), _, _) => Some(certificate_signature(path, sub_m.value_of("issuer"))),
+                (None, Some(path), _) => Some(dkim_signature(path, sub_m)),
+                (None, None, Some(token)) => Some(jwt_signature(token, sub_m)),
+                (None, None, None) => None,
+            };
+            // `--msg-file -` reads stdin into memory, as it cannot be read twice
+            let streamed_file = sub_m.value_of("msg_file").filter(|&path| path != "-");
+            let (msg, emitted_message) = match (sub_m.value_of("msg_json"), &embedded) {
+                // Emitted for circuits that hash it themselves, as noir-jwt and zkemail do
+                (_, Some(embedded)) => {
+                    let emitted = msg_max_len.map(|max_len| emitted_bytes(embedded.message.clone(), max_len));
+                    (embedded.message.clone(), emitted)
+                }
+                (Some(path), None) => {
+                    let json = std::fs::read_to_string(path).unwrap_or_else(|err| {
+                        let message = format!("Failed to read {}: {}", path, err);
+                        errors::fail(Error::new(Code::Failed, message).with("path", path))
+                    });
+                    let bytes = if sub_m.value_of("canonicalize") == Some("jcs") {
+                        jcs::canonicalize(&json)
+                            .unwrap_or_else(|err| {
+                                let message = format!("{}: {}", path, err);
+                                errors::fail(Error::new(Code::InvalidArgument, message).with("path", path))
+                            })
+                            .into_bytes()
+                    } else {
+                        json.into_bytes()
+                    };
+                    // The circuit hashes the emitted bytes, so they carry the prefix too
+                    let emitted = prefixed(&prefix, &bytes);
+                    let max_len = msg_max_len.unwrap_or(emitted.len());
+                    (bytes, Some(emitted_bytes(emitted, max_len)))
+                }
+                (None, None) if streamed_file.is_none() && sub_m.is_present("msg_file") => {
+                    let mut stdin = Vec::new();
+                    std::io::stdin().read_to_end(&mut stdin).unwrap_or_else(|err| {
+                        let message = format!("Failed to read the message from stdin: {}", err);
+                        errors::fail(Error::new(Code::Failed, message))
+                    });
+                    let emitted = msg_max_len.map(|max_len| emitted_bytes(prefixed(&prefix, &stdin), max_len));
+                    (stdin, emitted)
+                }
+                // Streamed once the hashes are known, or not known at all beside its digest
+                (None, None) if streamed_file.is_some() || sub_m.is_present("prehashed") => (Vec::new(), None),
+                (None, None) => match (sub_m.value_of("msg_list"), sub_m.value_of("msg_hex")) {
+                    (Some(path), _) => {
+                        let list = std::fs::read(path).unwrap_or_else(|err| {
+                            let message = format!("Failed to read {}: {}", path, err);
+                            errors::fail(Error::new(Code::Failed, message).with("path", path))
+                        });
+                        (list, None)
+                    }
+                    (None, Some(msg_hex)) => {
+                        let msg = hex::decode(msg_hex.strip_prefix("0x").unwrap_or(msg_hex)).unwrap_or_else(|err| {
+                            errors::fail(Error::new(Code::InvalidArgument, format!("Invalid --msg-hex: {}", err)))
+                        });
+                        let emitted = msg_max_len.map(|max_len| emitted_bytes(prefixed(&prefix, &msg), max_len));
+                        (msg, emitted)
+                    }
+                    (None, None) => {
+                        let msg = sub_m.value_of("msg").unwrap().as_bytes().to_vec();
+                        let emitted = msg_max_len.map(|max_len| emitted_bytes(prefixed(&prefix, &msg), max_len));
+                        (msg, emitted)
+                    }
+                },
+            };
+            // One message per slot with --msg-list, otherwise every key signs `msg`,
+            // each with the prefix in front
+            let messages: Vec<Vec<u8>> = if sub_m.is_present("msg_list") {
+                msg.split(|&b| b == b'\n')
+                    .map(|line| line.strip_suffix(b"\r").unwrap_or(line))
+                    .filter(|line| !line.is_empty())
+                    .map(|line| prefixed(&prefix, line))
+                    .collect()
+            } else if streamed_file.is_some() || sub_m.is_present("prehashed") {
+                Vec::new()
+            } else {
+                vec![prefixed(&prefix, &msg)]
+            };
+            let (format, template) = output_format(sub_m);
+            let boundary = sub_m.value_of("edge_case").map(|name| Boundary::from_name(name).unwrap());
+            // e3-short-msg generates keys of exponent 3 and takes loaded keys of no other
+            let e = match boundary {
+                Some(Boundary::SmallExponent) if sub_m.occurrences_of("exponent") > 0 && exponent(sub_m) != 3 => {
+                    errors::fail(Error::new(Code::InvalidArgument, "--edge-case e3-short-msg signs with exponent 3"))
+                }
+                Some(Boundary::SmallExponent) => 3,
+                _ => exponent(sub_m),
+            };
+            let inferred = sub_m.value_of("infer_from").map(|path| {
+                let circuit = infer::infer(path.as_ref()).unwrap_or_else(|err| {
+                    errors::fail(Error::new(Code::InvalidArgument, err).with("path", path))
+                });
+                (path, circuit)
+            });
+            let b: u32 = match inferred {
+                Some((path, circuit)) => {
+                    let bits: u32 = parse_value(sub_m, "bits").unwrap();
+                    if sub_m.occurrences_of("bits") > 0 && bits as usize != circuit.bits {
+                        errors::fail(
+                            Error::new(
+                                Code::InvalidArgument,
+                                format!("--bits {} contradicts the {}-bit BigNum in {}", bits, circuit.bits, path),
+                            )
+                            .with("path", path)
+                            .with("expected_bits", circuit.bits)
+                            .with("actual_bits", bits),
+                        );
+                    }
+                    circuit.bits as u32
+                }
+                None => parse_value(sub_m, "bits").unwrap(),
+            };
+            // Only generated keys are limited in size
+            let loaded =
+                ["key", "private_key_env", "pubkey", "x509", "dkim", "jwt"].iter().any(|arg| sub_m.is_present(arg));
+            if !loaded {
+                if let Err(err) = check_generated_bits(b) {
+                    errors::fail(err);
+                }
+            }
+            let hashes = match (&embedded, sub_m.value_of("hash")) {
+                (Some(embedded), _) => Ok(vec![embedded.hash_alg]),
+                (None, Some(name)) => HashAlg::from_name(name).map(|hash_alg| vec![hash_alg]).ok_or_else(|| {
+                    format!("Unsupported hash '{}', expected one of sha1, sha256, sha384, sha512", name)
+                }),
+                (None, None) => parse_hash_list(sub_m.value_of("hashes").unwrap()),
+            }
+            .unwrap_or_else(|err| {
+                errors::fail(Error::new(Code::InvalidArgument, err))
+            });
+
+            let num_keys = sub_m.value_of("keys").unwrap();
+            let num_keys = match num_keys.parse::<usize>() {
+                Ok(0) | Err(_) => {
+                    let message = format!("Invalid --keys {}, expected a number of keys of at least 1", num_keys);
+                    errors::fail(Error::new(Code::InvalidArgument, message).with("keys", num_keys))
+                }
+                Ok(num_keys) => num_keys,
+            };
+
+            let seed: Option<u64> = seed(sub_m);
+            let invalid = sub_m.value_of("invalid").map(|name| Invalid::from_name(name).unwrap());
+            // The passphrase is never taken as an argument value, where process listings would show it
+            let passphrase = match (sub_m.value_of("passphrase_env"), sub_m.value_of("passphrase_file")) {
+                (Some(var), _) => Passphrase::Env(var.to_string()),
+                (_, Some(path)) => Passphrase::File(path.to_string()),
+                _ => Passphrase::Prompt,
+            };
+            let loaded_keys: Option<Vec<RsaPrivateKey>> = if let Some(var) = sub_m.value_of("private_key_env") {
+                let key = load_private_key_env(var).unwrap_or_else(|err| {
+                    errors::fail(Error::new(Code::BadKeyFile, err).with("variable", var))
+                });
+                check_loaded_bits(&key, var).unwrap_or_else(|err| errors::fail(err.with("variable", var)));
+                Some(vec![key])
+            } else {
+                sub_m.values_of("key").map(|paths| {
+                    paths
+                        .map(|path| {
+                            load_key_file(path, &passphrase).unwrap_or_else(|err| errors::fail(err))
+                        })
+                        .collect()
+                })
+            };
+
+            // A signature made elsewhere comes with its public key only, checked like the loaded keys
+            let external_key: Option<RsaPublicKey> = match (sub_m.value_of("pubkey"), &embedded) {
+                (Some(path), _) => Some((path, keys::load_public_key(path))),
+                (None, Some(embedded)) => Some((embedded.key_source.as_str(), Ok(embedded.key.clone()))),
+                (None, None) => None,
+            }
+            .map(|(path, pub_key)| {
+                let pub_key =
+                    pub_key.unwrap_or_else(|err| errors::fail(Error::new(Code::BadKeyFile, err).with("path", path)));
+                check_loaded_bits(&pub_key, path).unwrap_or_else(|err| errors::fail(err.with("path", path)));
+                pub_key
+            });
+            let loaded_public: Option<Vec<RsaPublicKey>> = match (&loaded_keys, &external_key) {
+                (Some(keys), _) => Some(keys.iter().map(RsaPrivateKey::to_public_key).collect()),
+                (None, Some(pub_key)) => Some(vec![pub_key.clone()]),
+                (None, None) => None,
+            };
+            if let Some(keys) = &loaded_public {
+                if let Some(key) = keys.iter().find(|k| key_bits(*k) != key_bits(&keys[0])) {
+                    let message = "All keys must have the same size to share one set of circuit inputs";
+                    errors::fail(
+                        Error::new(Code::KeyMismatch, message)
+                            .with("expected_bits", key_bits(&keys[0]))
+                            .with("actual_bits", key_bits(key)),
+                    );
+                }
+                let exponent_input = sub_m.value_of("emit_exponent_input");
+                let expected: u32 = match boundary {
+                    Some(Boundary::SmallExponent) => 3,
+                    _ => parse_value(sub_m, "expect_exponent").unwrap(),
+                };
+                if let (None, Err(err)) = (exponent_input, check_exponents(keys, expected)) {
+                    errors::fail(err);
+                }
+                if let Some((path, circuit)) = inferred {
+                    if let Err(err) = check_circuit_bits(&keys[0], path, &circuit) {
+                        errors::fail(err);
+                    }
+                }
+            }
+            let bits = loaded_public.as_ref().map_or(b as usize, |keys| key_bits(&keys[0]));
+            let scheme = if let Some(embedded) = &embedded {
+                embedded.scheme.clone()
+            } else if boundary == Some(Boundary::PssEmptySalt) {
+                Scheme::Pss(PssSalt::Random(0))
+            } else if !sub_m.is_present("pss") {
+                Scheme::Pkcs1v15
+            } else if let Some(salt_hex) = sub_m.value_of("salt_hex") {
+                if sub_m.is_present("salt_len") {
+                    let message = "--salt-len cannot be combined with --salt-hex, which implies the salt length";
+                    errors::fail(Error::new(Code::InvalidArgument, message));
+                }
+                let salt_hex = salt_hex.strip_prefix("0x").unwrap_or(salt_hex);
+                let salt = hex::decode(salt_hex).unwrap_or_else(|err| {
+                    errors::fail(Error::new(Code::InvalidArgument, format!("Invalid hex string for salt: {}", err)))
+                });
+                Scheme::Pss(PssSalt::Fixed(salt))
+            } else {
+                // As long as the hash by default, or as long as every hash leaves room for with `max`
+                let salt_len = match sub_m.value_of("salt_len") {
+                    None => hashes[0].output_len(),
+                    Some("max") => hashes.iter().map(|&hash_alg| pss::max_salt_len(hash_alg, bits)).min().unwrap(),
+                    Some(_) => parse_value(sub_m, "salt_len").unwrap(),
+                };
+                Scheme::Pss(PssSalt::Random(salt_len))
+            };
+            // Hash and salt combinations the PSS encoding has no room for are refused before any key is generated
+            if let Some(salt_len) = scheme.salt_len() {
+                for &hash_alg in &hashes {
+                    if let Err(err) = pss::check_salt_len(hash_alg, salt_len, bits) {
+                        errors::fail(
+                            Error::new(Code::InvalidArgument, err).with("hash", hash_alg.name()).with("bits", bits),
+                        );
+                    }
+                }
+            }
+
+            // The digests of each message under each hash; a --msg-file is read once in chunks
+            let mut file_sha256 = None;
+            let mut emitted_message = emitted_message;
+            let digests: Vec<Vec<Vec<u8>>> = match (streamed_file, sub_m.value_of("prehashed")) {
+                (Some(path), _) => {
+                    // Only files that fit the emitted capacity are read into memory, and hashed as read
+                    let file = match msg_max_len {
+                        Some(max_len) => {
+                            let (file, bytes) = stream::read_file(path.as_ref(), &prefix, &hashes, max_len)
+                                .unwrap_or_else(|err| errors::fail(err));
+                            emitted_message = Some(MessageBytes { bytes, max_len });
+                            file
+                        }
+                        None => stream::digest_file(path.as_ref(), &prefix, &hashes, sub_m.is_present("progress"))
+                            .unwrap_or_else(|err| errors::fail(Error::new(Code::Failed, err).with("path", path))),
+                    };
+                    file_sha256 = Some(file.file_sha256);
+                    vec![file.digests]
+                }
+                (None, Some(digest_hex)) => vec![vec![prehashed_digest(digest_hex, &hashes)]],
+                (None, None) => messages
+                    .iter()
+                    .map(|message| hashes.iter().map(|hash_alg| hash_alg.digest(message)).collect())
+                    .collect(),
+            };
+
+            let external = external_key.map(|pub_key| {
+                if hashes.len() > 1 {
+                    let message =
+                        format!("--pubkey takes one signature made with one hash, --hashes gives {}", hashes.len());
+                    errors::fail(Error::new(Code::InvalidArgument, message));
+                }
+                let signature = match (&embedded, sub_m.value_of("signature"), sub_m.value_of("signature_hex")) {
+                    (Some(embedded), _, _) => pad_signature(embedded.signature.clone(), pub_key.size()),
+                    (None, Some(path), _) => read_signature_file(path, pub_key.size()),
+                    (None, None, Some(signature_hex)) => {
+                        hex::decode(signature_hex.strip_prefix("0x").unwrap_or(signature_hex))
+                            .map_err(|err| format!("Invalid hex string for --signature-hex: {}", err))
+                            .and_then(|signature| pad_signature(signature, pub_key.size()))
+                    }
+                    (None, None, None) => Err(String::from(
+                        "--pubkey emits the inputs of a given signature, pass --signature or --signature-hex",
+                    )),
+                }
+                .unwrap_or_else(|err| errors::fail(Error::new(Code::InvalidArgument, err)));
+                let entry = signature_params_for_signature(&pub_key, hashes[0], &digests[0][0], &signature, &scheme)
+                    .unwrap_or_else(|err| errors::fail(err));
+                (pub_key, entry)
+            });
+
+            let key_count = loaded_keys.as_ref().map_or(num_keys, Vec::len);
+            if key_count > 1 && sub_m.is_present("export_key") {
+                let message = format!("--export-key exports a single key, but {} are used", key_count);
+                errors::fail(Error::new(Code::InvalidArgument, message));
+            }
+            if key_count > 1 && hashes.len() > 1 {
+                let message = "--hashes with more than one hash cannot be combined with several keys";
+                errors::fail(Error::new(Code::InvalidArgument, message));
+            }
+
+            // Keys and messages pair up slot by slot; a single one of either is shared by every slot
+            let slots = key_count.max(digests.len());
+            if key_count > 1 && digests.len() > 1 && key_count != digests.len() {
+                let message = format!("{} keys cannot be paired with {} messages", key_count, digests.len());
+                errors::fail(Error::new(Code::InvalidArgument, message));
+            }
+            if let Some(boundary) = boundary {
+                if let Err(err) = boundary.check(bits, &messages[0]) {
+                    errors::fail(Error::new(Code::InvalidArgument, err).with("edge_case", boundary.name()));
+                }
+                if boundary == Boundary::LeadingZeroSig && (slots > 1 || hashes.len() > 1) {
+                    let message = "--edge-case leading-zero-sig signs one message with one key and one hash";
+                    errors::fail(Error::new(Code::InvalidArgument, message));
+                }
+            }
+            let aggregate: Option<usize> = parse_value(sub_m, "aggregate");
+            if let Some(k) = aggregate {
+                if digests.is_empty() || slots > k {
+                    let message = format!("--aggregate {} needs between 1 and {} vectors, got {}", k, k, slots);
+                    errors::fail(Error::new(Code::InvalidArgument, message));
+                }
+                if hashes.len() > 1 {
+                    let message = format!(
+                        "--aggregate signs every slot with one hash, --hashes gives {}",
+                        hashes.len()
+                    );
+                    errors::fail(Error::new(Code::InvalidArgument, message));
+                }
+                if !format.renders_toml() {
+                    let message = "--aggregate is emitted as TOML or ABI JSON, pass --toml or --format abi-json";
+                    errors::fail(Error::new(Code::InvalidArgument, message));
+                }
+            }
+
+            let preset = sub_m.value_of("preset").map(|name| {
+                presets::Preset::from_name(name).unwrap_or_else(|err| {
+                    errors::fail(Error::new(Code::InvalidArgument, err).with("preset", name))
+                })
+            });
+            if let Some(preset) = preset {
+                if key_count > 1 || hashes.len() > 1 || sub_m.is_present("msg_list") {
+                    let message = format!(
+                        "--preset {} describes a circuit verifying one signature with one hash",
+                        preset.name
+                    );
+                    errors::fail(Error::new(Code::InvalidArgument, message));
+                }
+                if !format.renders_toml() {
+                    let message = "--preset shapes TOML and ABI JSON output, pass --toml or --format abi-json";
+                    errors::fail(Error::new(Code::InvalidArgument, message));
+                }
+            }
+            if sub_m.is_present("json") && (key_count > 1 || hashes.len() > 1 || sub_m.is_present("msg_list")) {
+                let message = "--json emits the inputs of one signature, made with one key and one hash";
+                errors::fail(Error::new(Code::InvalidArgument, message));
+            }
+            if sub_m.is_present("noir_test") && (key_count > 1 || hashes.len() > 1 || sub_m.is_present("msg_list")) {
+                let message = "--noir-test verifies one signature, made with one key and one hash";
+                errors::fail(Error::new(Code::InvalidArgument, message));
+            }
+            if sub_m.is_present("compat_pair") && key_count > 1 {
+                let message = format!("--compat-pair writes the vector of one key, --keys gives {}", key_count);
+                errors::fail(Error::new(Code::InvalidArgument, message));
+            }
+            // ABI JSON holds circuit inputs only
+            if sub_m.is_present("expected") && format != OutputFormat::Toml {
+                errors::fail(Error::new(Code::InvalidArgument, "--expected is emitted as TOML, pass --toml"));
+            }
+
+            let limb_style = limb_style(sub_m);
+            let names = match preset {
+                Some(preset) => schema::Names::Preset(preset),
+                None if sub_m.is_present("legacy_names") => schema::Names::Legacy,
+                None => schema::Names::Canonical,
+            };
+
+            let keygen_options = keygen::Options {
+                timeout: sub_m.value_of("keygen_timeout").map(|secs| match secs.parse::<f64>() {
+                    Ok(secs) if secs > 0.0 => Duration::from_secs_f64(secs),
+                    _ => {
+                        let message = "--keygen-timeout must be a positive number of seconds";
+                        errors::fail(Error::new(Code::InvalidArgument, message))
+                    }
+                }),
+                retries: parse_value(sub_m, "keygen_retries").unwrap(),
+                verbose: sub_m.is_present("verbose"),
+            };
+            let keygen_stats = RefCell::new(keygen::Stats::default());
+
+            let configure = |entry: &mut SignatureParams, exponent: &BigUint| {
+                entry.message = emitted_message.clone();
+                entry.key.limb_style = limb_style;
+                if let Some(hash) = pubkey_hash(sub_m) {
+                    entry.key.set_pubkey_hash(hash);
+                }
+                if sub_m.is_present("emit_pubkey_poseidon") {
+                    entry.key.set_pubkey_poseidon();
+                }
+                if let Some(preset) = preset {
+                    preset.configure(&mut entry.key);
+                }
+                // Only on request, whatever the key's exponent, so every format has the same inputs
+                if let Some(input) = sub_m.value_of("emit_exponent_input").and_then(ExponentInput::from_name) {
+                    entry.key.set_exponent_input(input, exponent);
+                }
+            };
+
+            // Key generation and signing draw from `rng` only, so a seed reproduces the whole run
+            // Returns the key of every entry alongside the entries
+            let generate = |rng: &mut ChaCha20Rng| -> (Vec<RsaPrivateKey>, Vec<SignatureParams>) {
+                let priv_keys: Vec<RsaPrivateKey> = match &loaded_keys {
+                    Some(keys) => keys.clone(),
+                    None => (0..num_keys)
+                        .map(|_| {
+                            let mut stats = keygen_stats.borrow_mut();
+                            keygen::generate(&mut *rng, b as usize, e, &keygen_options, &mut stats)
+                                .unwrap_or_else(|err| errors::fail(err))
+                        })
+                        .collect(),
+                };
+
+                // Keys are signed with and printed in the order they were given or generated
+                let mut entries = Vec::with_capacity(slots);
+                let mut entry_keys = Vec::with_capacity(slots);
+                for slot in 0..slots {
+                    let priv_key = &priv_keys[slot % priv_keys.len()];
+                    let digests = &digests[slot % digests.len()];
+                    let mut entry = signature_params_for_digests(&mut *rng, priv_key, &hashes, digests, &scheme)
+                        .unwrap_or_else(|err| errors::fail(err));
+                    configure(&mut entry, priv_key.e());
+                    entries.push(entry);
+                    entry_keys.push(priv_key.clone());
+                }
+                (entry_keys, entries)
+            };
+
+            let render = |entries: &[SignatureParams]| -> String {
+                if let Some(k) = aggregate {
+                    render_aggregate_toml(entries, k)
+                } else if let [signature_params] = entries {
+                    if format.renders_toml() {
+                        signature_params.render_toml(names)
+                    } else {
+                        signature_params.render_snippet(template)
+                    }
+                } else if format.renders_toml() {
+                    render_multi_key_toml(entries, names)
+                } else {
+                    render_multi_key_snippet(entries, template)
+                }
+            };
+
+            if let Some(iterations) = parse_value(sub_m, "iterations") {
+                let options = soak::Options {
+                    iterations,
+                    seed,
+                    failures_dir: sub_m.value_of("failures_dir").unwrap().into(),
+                    replay_args: soak_replay_args(),
+                    stop: Arc::default(),
+                };
+                let stop = Arc::clone(&options.stop);
+                if let Err(err) = ctrlc::set_handler(move || stop.store(true, Ordering::SeqCst)) {
+                    errors::fail(Error::new(Code::Failed, format!("Failed to handle Ctrl-C: {}", err)));
+                }
+                let summary = soak::soak(&options, &scheme, |rng| {
+                    let (priv_keys, entries) = generate(rng);
+                    soak::Generated {
+                        pub_keys: priv_keys.iter().map(RsaPrivateKey::to_public_key).collect(),
+                        output: render(&entries),
+                        entries,
+                    }
+                })
+                .unwrap_or_else(|err| {
+                    errors::fail(Error::new(Code::Failed, err))
+                });
+                summary.print();
+                if keygen_options.verbose {
+                    eprintln!("{}", keygen_stats.borrow().summary());
+                }
+                if summary.failures > 0 {
+                    let message = format!("{} of {} iterations failed", summary.failures, summary.iterations);
+                    errors::fail(Error::new(Code::VerificationFailed, message).with("failures", summary.failures));
+                }
+            } else {
+                let mut rng = match seed {
+                    Some(seed) => ChaCha20Rng::seed_from_u64(seed),
+                    None => ChaCha20Rng::from_rng(rand::thread_rng()).expect("failed to seed the RNG"),
+                };
+                let (pub_keys, priv_keys, mut entries) = match external {
+                    Some((pub_key, mut entry)) => {
+                        configure(&mut entry, pub_key.e());
+                        (vec![pub_key], Vec::new(), vec![entry])
+                    }
+                    None => {
+                        let (priv_keys, entries) = generate(&mut rng);
+                        if keygen_options.verbose {
+                            eprintln!("{}", keygen_stats.borrow().summary());
+                        }
+                        let pub_keys: Vec<RsaPublicKey> = priv_keys.iter().map(RsaPrivateKey::to_public_key).collect();
+                        (pub_keys, priv_keys, entries)
+                    }
+                };
+                // The message is varied until the signature starts with a zero byte, and the one signed is reported
+                let (msg, messages) = match boundary {
+                    Some(Boundary::LeadingZeroSig) => {
+                        let max_attempts =
+                            parse_value(sub_m, "max_attempts").unwrap();
+                        let (msg, mut entry) = boundary::leading_zero_signature(
+                            &mut rng,
+                            &priv_keys[0],
+                            hashes[0],
+                            &scheme,
+                            &prefix,
+                            &msg,
+                            max_attempts,
+                        )
+                        .unwrap_or_else(|err| errors::fail(err));
+                        configure(&mut entry, priv_keys[0].e());
+                        entries = vec![entry];
+                        let messages = vec![prefixed(&prefix, &msg)];
+                        (msg, messages)
+                    }
+                    _ => (msg, messages),
+                };
+                // A key signing several entries is warned about once
+                let mut warnings: Vec<&String> = Vec::new();
+                for warning in entries.iter().flat_map(|entry| &entry.warnings) {
+                    if !warnings.contains(&warning) {
+                        warnings.push(warning);
+                    }
+                }
+                for warning in warnings {
+                    eprintln!("Warning: {}", warning);
+                }
+                if let Some(kind) = invalid {
+                    for (entry, priv_key) in entries.iter_mut().zip(&priv_keys) {
+                        invalid::corrupt(&mut rng, kind, priv_key, entry, &scheme)
+                            .unwrap_or_else(|err| errors::fail(err));
+                    }
+                }
+
+                if sub_m.is_present("cross_check") {
+                    for (slot, (pub_key, entry)) in pub_keys.iter().zip(&entries).enumerate() {
+                        let msg = &messages[slot % messages.len()];
+                        for signature in &entry.signatures {
+                            let sig = signature.signature_bytes(pub_key.size());
+                            match cross_check::cross_check_openssl(pub_key, signature.hash_alg, msg, &sig, &scheme) {
+                                Ok(cross_check::CrossCheck::Verified) => {}
+                                Ok(cross_check::CrossCheck::Skipped(reason)) => {
+                                    eprintln!("Warning: skipping the openssl cross-check, {}", reason);
+                                }
+                                Err(err) => {
+                                    errors::fail(
+                                        Error::new(Code::VerificationFailed, err)
+                                            .with("check", "openssl")
+                                            .with("hash", signature.hash_alg.name()),
+                                    );
+                                }
+                            }
+                        }
+                    }
+                }
+
+                if let Some(path) = sub_m.value_of("emit_verifier_script") {
+                    // A streamed --msg-file is checked by its hash only
+                    let slot_messages: Vec<Option<&[u8]>> = (0..entries.len())
+                        .map(|slot| messages.get(slot % messages.len().max(1)).map(Vec::as_slice))
+                        .collect();
+                    let vectors = verifier_script::vectors(&pub_keys, &entries, &slot_messages, &scheme);
+                    verifier_script::write(path.as_ref(), &vectors).unwrap_or_else(|err| {
+                        errors::fail(Error::new(Code::Failed, err).with("path", path))
+                    });
+                }
+
+                if let Some(path) = sub_m.value_of("export_key") {
+                    export_key(path, &priv_keys[0], sub_m);
+                }
+
+                if let Some(artifact) = sub_m.value_of("execute_artifact") {
+                    let inputs = if let Some(k) = aggregate {
+                        render_aggregate_toml(&entries, k)
+                    } else if let [signature_params] = entries.as_slice() {
+                        signature_params.render_toml(names)
+                    } else {
+                        render_multi_key_toml(&entries, names)
+                    };
+                    execute_artifact(artifact, &inputs);
+                }
+
+                let mut output = render(&entries);
+                let meta = if sub_m.is_present("no_meta") {
+                    None
+                } else {
+                    let mut meta = meta::Meta::new(&pub_keys, &hashes, &scheme, &msg);
+                    meta.prefix = prefix.clone();
+                    meta.message_sha256 = file_sha256.clone();
+                    if sub_m.is_present("prehashed") {
+                        meta.prehashed = Some(digests[0][0].clone());
+                    }
+                    for tag in sub_m.values_of("tag").into_iter().flatten() {
+                        let tag = meta::parse_tag(tag)
+                            .unwrap_or_else(|err| errors::fail(Error::new(Code::InvalidArgument, err)));
+                        meta.tags.push(tag);
+                    }
+                    meta.seed = seed;
+                    meta.invalid = invalid.map(|kind| kind.name().to_string());
+                    meta.edge_case =
+                        boundary.map(|boundary| (boundary.name().to_string(), boundary.description().to_string()));
+                    meta.key_files = sub_m.values_of("key").into_iter().flatten().map(String::from).collect();
+                    // Files written alongside the output are not regenerated, nor is a pair written instead of it,
+                    // and a message read from stdin is not there to be read again
+                    if !sub_m.is_present("compat_pair") && sub_m.value_of("msg_file") != Some("-") {
+                        meta.command = vec!["sign".to_string()];
+                        meta.command.extend(args_without(SIGN_SIDE_OUTPUTS));
+                        // Rerun to stdout, the command must still ask for the format the file was written in
+                        let formatted = ["toml", "json"].iter().any(|flag| sub_m.is_present(flag));
+                        if sub_m.is_present("output") && !formatted && sub_m.occurrences_of("format") == 0 {
+                            meta.command.push("--toml".to_string());
+                        }
+                    }
+                    Some(meta)
+                };
+                if let Some(prefix) = sub_m.value_of("compat_pair") {
+                    write_compat_pair(prefix, &entries[0], format, meta.as_ref());
+                    return;
+                }
+                // Nothing but the one JSON document goes to stdout, so it can be piped into JSON.parse
+                if sub_m.is_present("json") {
+                    let json = render_json(&entries[0], pub_keys[0].e(), &scheme, invalid, boundary);
+                    write_sign_output(sub_m, &json, seed);
+                    return;
+                }
+                if sub_m.is_present("noir_test") {
+                    let (key, signature) = (&entries[0].key, &entries[0].signatures[0]);
+                    let name = match sub_m.value_of("noir_test") {
+                        Some(name) => name.to_string(),
+                        None => NoirTest::default_name(signature.hash_alg, &scheme, key.bits),
+                    };
+                    let test = NoirTest {
+                        name: &name,
+                        key,
+                        signature,
+                        exponent: pub_keys[0].e(),
+                        scheme: &scheme,
+                        should_fail: invalid.is_some(),
+                    };
+                    let version = BignumVersion::from_name(sub_m.value_of("bignum_version").unwrap()).unwrap();
+                    let code = version
+                        .render_test(&test)
+                        .unwrap_or_else(|err| errors::fail(Error::new(Code::InvalidArgument, err)));
+                    let header = meta.as_ref().map_or(String::new(), |meta| meta.render_comment());
+                    write_sign_output(sub_m, &format!("{}{}", header, code), seed);
+                    return;
+                }
+                if format.renders_toml() {
+                    // The labels `run-negative` reads, top-level so they come before any table
+                    if let Some(kind) = invalid {
+                        output = format!("expected_valid = false\ntamper = \"{}\"\n{}", kind.name(), output);
+                    }
+                    if let Some(meta) = &meta {
+                        output = format!("{}\n{}", output, meta.render_toml());
+                    }
+                    if sub_m.is_present("expected") {
+                        output = format!("{}\n{}", output, expected_table(&entries, invalid));
+                    }
+                    if sub_m.is_present("annotate") {
+                        output = annotate::Annotator::for_signature_params(&entries[0]).annotate_toml(&output);
+                    }
+                } else {
+                    if sub_m.is_present("annotate") {
+                        output = annotate::Annotator::for_signature_params(&entries[0]).annotate_snippet(&output);
+                    }
+                    if let Some(meta) = &meta {
+                        output = format!("{}{}", meta.render_comment(), output);
+                    }
+                }
+                if format == OutputFormat::AbiJson {
+                    output = params::toml_to_abi_json(&output);
+                }
+                write_sign_output(sub_m, &output, seed);
+            }
+        }
+        ("params", Some(sub_m)) => {
+            let bits: usize = parse_value(sub_m, "bits").unwrap();
+            let (format, template) = output_format(sub_m);
+
+            let modulus = if let Some(pubkey) = sub_m.value_of("pubkey") {
+                let pubkey_hex = pubkey.strip_prefix("0x").unwrap_or(pubkey);
+                let pubkey_bytes = hex::decode(pubkey_hex).unwrap_or_else(|err| {
+                    let message = format!("Invalid --pubkey {}: {}", pubkey, err);
+                    errors::fail(Error::new(Code::InvalidArgument, message).with("pubkey", pubkey))
+                });
+                BigUint::from_bytes_be(&pubkey_bytes)
+            } else if let Some(limbs) = sub_m.value_of("limbs") {
+                parse_limbs_to_biguint(limbs)
+            } else {
+                errors::fail(Error::new(Code::InvalidArgument, "Either --pubkey or --limbs must be provided"));
+            };
+
+            generate_bignum_params_from_modulus(
+                &modulus,
+                bits,
+                format,
+                template,
+                limb_style(sub_m),
+                pubkey_hash(sub_m),
+                sub_m.is_present("emit_pubkey_poseidon"),
+                sub_m.is_present("annotate"),
+            );
+        }
+        ("doctor", Some(sub_m)) => {
+            let version = sub_m.value_of("bignum_version").unwrap();
+            let template = BignumVersion::from_name(version).unwrap_or_else(|| {
+                let message = format!("Unsupported --bignum-version {}, expected 0.3 or current", version);
+                errors::fail(Error::new(Code::InvalidArgument, message).with("bignum_version", version))
+            });
+            let ok = doctor::doctor(
+                sub_m.value_of("manifest").unwrap(),
+                sub_m.value_of("example_manifest").unwrap(),
+                sub_m.value_of("nargo").unwrap(),
+                template,
+            );
+            if !ok {
+                errors::fail(Error::new(Code::Failed, "Some checks failed, see above").with("check", "doctor"));
+            }
+        }
+        ("convert", Some(sub_m)) => {
+            let from = convert::Layout::from_name(sub_m.value_of("from").unwrap()).unwrap();
+            let to = convert::Layout::from_name(sub_m.value_of("to").unwrap()).unwrap();
+            let path = sub_m.value_of("file").unwrap();
+            let doc = read_prover_toml(path);
+            let (converted, warnings) = convert::convert(&doc, from, to, limb_style(sub_m)).unwrap_or_else(|err| {
+                let message = format!("Cannot convert {}: {}", path, err);
+                errors::fail(Error::new(Code::InvalidArgument, message).with("path", path))
+            });
+            for warning in warnings {
+                eprintln!("Warning: {}", warning);
+            }
+            write_or_print(sub_m.value_of("output"), &params::to_toml_string(converted));
+        }
+        ("diff", Some(sub_m)) => {
+            let (a, b) = (sub_m.value_of("a").unwrap(), sub_m.value_of("b").unwrap());
+            let differences = convert::diff(&read_prover_toml(a), &read_prover_toml(b)).unwrap_or_else(|err| {
+                errors::fail(Error::new(Code::InvalidArgument, format!("Cannot compare {} and {}: {}", a, b, err)))
+            });
+            if !differences.is_empty() {
+                for difference in &differences {
+                    println!("{}", difference);
+                }
+                let message = format!("{} and {} differ in {} inputs", a, b, differences.len());
+                errors::fail(Error::new(Code::VerificationFailed, message).with("differences", differences.len()));
+            }
+            println!("{} and {} encode the same inputs", a, b);
+        }
+        ("reencode-signature", Some(sub_m)) => {
+            let input = match (sub_m.value_of("signature"), sub_m.value_of("signature_file")) {
+                (Some(signature), _) => signature.as_bytes().to_vec(),
+                (None, Some(path)) => std::fs::read(path).unwrap_or_else(|err| {
+                    let message = format!("Failed to read {}: {}", path, err);
+                    errors::fail(Error::new(Code::Failed, message).with("path", path))
+                }),
+                (None, None) => unreachable!("clap requires a signature"),
+            };
+            let modulus = match (sub_m.value_of("modulus"), sub_m.value_of("pubkey_file")) {
+                (Some(modulus), _) => {
+                    let modulus = modulus.strip_prefix("0x").unwrap_or(modulus);
+                    BigUint::parse_bytes(modulus.as_bytes(), 16).unwrap_or_else(|| {
+                        errors::fail(Error::new(Code::InvalidArgument, "--modulus must be hex"))
+                    })
+                }
+                (None, Some(path)) => inspect::read_public_key(path)
+                    .unwrap_or_else(|err| errors::fail(Error::new(Code::BadKeyFile, err).with("path", path)))
+                    .n,
+                (None, None) => unreachable!("clap requires a modulus"),
+            };
+            let in_format = sub_m.value_of("in_format").and_then(reencode::InFormat::from_name);
+            let out_format = reencode::OutFormat::from_name(sub_m.value_of("out_format").unwrap()).unwrap();
+            let (_, signature) = reencode::decode(&input, in_format, &modulus).unwrap_or_else(|err| {
+                errors::fail(Error::new(Code::InvalidArgument, err).with("check", "signature"))
+            });
+            let key_bytes = (modulus.bits() + 7) / 8;
+            print!("{}", reencode::encode(&signature, out_format, key_bytes, limb_style(sub_m)));
+        }
+        ("serve", Some(sub_m)) => {
+            let threads: usize = parse_value(sub_m, "threads").unwrap();
+            if let Err(err) = serve::serve(sub_m.value_of("listen").unwrap(), threads.max(1)) {
+                errors::fail(Error::new(Code::Failed, err));
+            }
+        }
+        ("difftest", Some(sub_m)) => {
+            let tamper_fraction: f64 = parse_value(sub_m, "tamper_fraction").unwrap();
+            if !(0.0..=1.0).contains(&tamper_fraction) {
+                errors::fail(Error::new(Code::InvalidArgument, "--tamper-fraction must be between 0 and 1"));
+            }
+            let options = difftest::Options {
+                iterations: parse_value(sub_m, "iterations").unwrap(),
+                seed: seed(sub_m).unwrap(),
+                tamper_fraction,
+                example_dir: sub_m.value_of("example_dir").unwrap().into(),
+                failures_dir: sub_m.value_of("failures_dir").unwrap().into(),
+                nargo: sub_m.value_of("nargo").unwrap().to_string(),
+            };
+            match difftest::difftest(&options) {
+                Ok(true) => {}
+                Ok(false) => {
+                    let message = "The circuit and the reference verifier disagree, see above";
+                    errors::fail(Error::new(Code::VerificationFailed, message).with("check", "difftest"));
+                }
+                Err(err) => {
+                    errors::fail(Error::new(Code::Failed, err));
+                }
+            }
+        }
+        ("run-negative", Some(sub_m)) => {
+            let control_every: usize = parse_value(sub_m, "control_every").unwrap();
+            if control_every == 0 {
+                errors::fail(Error::new(Code::InvalidArgument, "--control-every must be at least 1"));
+            }
+            let options = negative::Options {
+                vectors_dir: sub_m.value_of("vectors_dir").unwrap().into(),
+                example_dir: sub_m.value_of("example_dir").unwrap().into(),
+                nargo: sub_m.value_of("nargo").unwrap().to_string(),
+                artifact: sub_m.value_of("execute_artifact").map(Into::into),
+                exponent: exponent(sub_m),
+                pss: sub_m.is_present("pss"),
+                salt_len: parse_value(sub_m, "salt_len"),
+                control_every,
+                seed: seed(sub_m).unwrap(),
+            };
+            match negative::run_negative(&options) {
+                Ok(true) => {}
+                Ok(false) => {
+                    let message = "The circuit accepted an invalid vector or rejected a valid one, see above";
+                    errors::fail(Error::new(Code::VerificationFailed, message).with("check", "run-negative"));
+                }
+                Err(err) => {
+                    errors::fail(Error::new(Code::Failed, err));
+                }
+            }
+        }
+        ("iso9796", Some(sub_m)) => {
+            let hash_alg = HashAlg::from_name(sub_m.value_of("hash").unwrap()).unwrap_or_else(|| {
+                let message = format!("Unsupported hash {}", sub_m.value_of("hash").unwrap());
+                errors::fail(Error::new(Code::InvalidArgument, message).with("hash", sub_m.value_of("hash").unwrap()))
+            });
+            let mut rng = match seed(sub_m) {
+                Some(seed) => ChaCha20Rng::seed_from_u64(seed),
+                None => ChaCha20Rng::from_rng(rand::thread_rng()).expect("failed to seed the RNG"),
+            };
+            let priv_key = match sub_m.value_of("key") {
+                Some(path) => load_key_file(path, &Passphrase::Prompt).unwrap_or_else(|err| errors::fail(err)),
+                None => {
+                    let bits = parse_value(sub_m, "bits").unwrap();
+                    let e = exponent(sub_m);
+                    generate_key(&mut rng, bits, e).unwrap_or_else(|err| errors::fail(err))
+                }
+            };
+            let message = match (sub_m.value_of("msg_hex"), sub_m.value_of("challenge")) {
+                (Some(msg_hex), _) => hex::decode(msg_hex).unwrap_or_else(|err| {
+                    errors::fail(Error::new(Code::InvalidArgument, format!("Invalid --msg-hex: {}", err)))
+                }),
+                // Active Authentication: a fresh M1 filling the capacity, then the challenge as M2
+                (None, Some(challenge)) => {
+                    let challenge = hex::decode(challenge).unwrap_or_else(|err| {
+                        errors::fail(Error::new(Code::InvalidArgument, format!("Invalid --challenge: {}", err)))
+                    });
+                    let capacity = iso9796::capacity(key_bits(&priv_key), hash_alg).unwrap_or_else(|err| {
+                        errors::fail(Error::new(Code::InvalidArgument, err))
+                    });
+                    let mut message = vec![0u8; capacity];
+                    rng.fill_bytes(&mut message);
+                    message.extend_from_slice(&challenge);
+                    message
+                }
+                (None, None) => sub_m.value_of("msg").unwrap().as_bytes().to_vec(),
+            };
+            match iso9796::sign(&priv_key, hash_alg, &message) {
+                Ok(vector) => print!("{}", vector.render_toml()),
+                Err(err) => {
+                    errors::fail(Error::new(Code::Failed, err));
+                }
+            }
+        }
+        ("edge-cases", Some(sub_m)) => {
+            let hash_alg = HashAlg::from_name(sub_m.value_of("hash").unwrap()).unwrap_or_else(|| {
+                let message = format!("Unsupported hash {}", sub_m.value_of("hash").unwrap());
+                errors::fail(Error::new(Code::InvalidArgument, message).with("hash", sub_m.value_of("hash").unwrap()))
+            });
+            let scheme = if sub_m.is_present("pss") {
+                Scheme::Pss(PssSalt::Random(parse_value(sub_m, "salt_len").unwrap_or(hash_alg.output_len())))
+            } else {
+                Scheme::Pkcs1v15
+            };
+            let cases = edge_cases::parse_edge_cases(sub_m.value_of("cases").unwrap()).unwrap_or_else(|err| {
+                errors::fail(Error::new(Code::InvalidArgument, err))
+            });
+            let options = edge_cases::Options {
+                bits: parse_value(sub_m, "bits").unwrap(),
+                exponent: exponent(sub_m),
+                hash_alg,
+                scheme,
+                cases,
+                max_attempts: parse_value(sub_m, "max_attempts").unwrap(),
+                seed: seed(sub_m).unwrap(),
+                output_dir: sub_m.value_of("output_dir").unwrap().into(),
+            };
+            let files = edge_cases::edge_cases(&options).unwrap_or_else(|err| {
+                errors::fail(Error::new(Code::Failed, err))
+            });
+            record_in_manifest("edge-cases", sub_m, &files, Some(options.seed));
+        }
+        ("dkim-corpus", Some(sub_m)) => {
+            let bits: usize = parse_value(sub_m, "bits").unwrap();
+            let seed: u64 = seed(sub_m).unwrap();
+            let key = generate_key(&mut ChaCha20Rng::seed_from_u64(seed), bits, 65537)
+                .unwrap_or_else(|err| errors::fail(err));
+            let options = dkim::Options {
+                count: parse_value(sub_m, "count").unwrap(),
+                domain: sub_m.value_of("domain").unwrap().to_string(),
+                selector: sub_m.value_of("selector").unwrap().to_string(),
+                max_header_len: parse_value(sub_m, "max_header_len").unwrap(),
+                output_dir: sub_m.value_of("out_dir").unwrap().into(),
+            };
+            if let Some(path) = sub_m.value_of("manifest") {
+                if same_file(path.as_ref(), &options.output_dir.join(dkim::MANIFEST)) {
+                    let message = format!(
+                        "--manifest {} is the corpus's own {}, give another path for the manifest of hashes",
+                        path,
+                        dkim::MANIFEST
+                    );
+                    errors::fail(Error::new(Code::InvalidArgument, message).with("manifest", path));
+                }
+            }
+            let files = dkim::corpus(&key, &options).unwrap_or_else(|err| {
+                errors::fail(Error::new(Code::Failed, err))
+            });
+            record_in_manifest("dkim-corpus", sub_m, &files, Some(seed));
+        }
+        ("regen", Some(sub_m)) => {
+            let filters: Vec<(String, String)> = sub_m
+                .values_of("filter")
+                .into_iter()
+                .flatten()
+                .map(|filter| {
+                    meta::parse_tag(filter).unwrap_or_else(|err| errors::fail(Error::new(Code::InvalidArgument, err)))
+                })
+                .collect();
+            let dry_run = sub_m.is_present("dry_run");
+            let outcomes = regen::regen(sub_m.value_of("dir").unwrap().as_ref(), &filters, dry_run, run_self)
+                .unwrap_or_else(|err| {
+                    errors::fail(Error::new(Code::Failed, err))
+                });
+            for (path, outcome) in &outcomes {
+                match outcome {
+                    regen::Outcome::Updated if dry_run => println!("{}: would be updated", path.display()),
+                    _ => println!("{}: {}", path.display(), outcome),
+                }
+            }
+            let count = |f: fn(&regen::Outcome) -> bool| outcomes.iter().filter(|(_, outcome)| f(outcome)).count();
+            println!(
+                "{} updated, {} unchanged, {} skipped, {} failed",
+                count(|outcome| *outcome == regen::Outcome::Updated),
+                count(|outcome| *outcome == regen::Outcome::Unchanged),
+                count(|outcome| matches!(outcome, regen::Outcome::Skipped(_))),
+                count(|outcome| matches!(outcome, regen::Outcome::Failed(_)))
+            );
+            let failed = count(|outcome| matches!(outcome, regen::Outcome::Failed(_)));
+            if failed > 0 {
+                let message = format!("{} files failed to regenerate", failed);
+                errors::fail(Error::new(Code::Failed, message).with("failed", failed));
+            }
+        }
+        ("raw-rsa", Some(sub_m)) => {
+            let seed: u64 = seed(sub_m).unwrap();
+            let mut rng = ChaCha20Rng::seed_from_u64(seed);
+            let (n, e) = match sub_m.value_of("pubkey_file") {
+                Some(path) => {
+                    let key = inspect::read_public_key(path)
+                        .unwrap_or_else(|err| errors::fail(Error::new(Code::BadKeyFile, err).with("path", path)));
+                    (key.n, key.e)
+                }
+                None => {
+                    let bits: u32 = parse_value(sub_m, "bits").unwrap();
+                    check_generated_bits(bits).unwrap_or_else(|err| errors::fail(err));
+                    let e = exponent(sub_m);
+                    let key = generate_key(&mut rng, bits as usize, e).unwrap_or_else(|err| errors::fail(err));
+                    (key.n().clone(), key.e().clone())
+                }
+            };
+            let exponent = e.to_u64().unwrap_or_else(|| {
+                errors::fail(Error::new(Code::KeyMismatch, format!("The exponent {} does not fit in 64 bits", e)))
+            });
+            let bases = match sub_m.value_of("base") {
+                Some(base) => {
+                    let base = base.strip_prefix("0x").unwrap_or(base);
+                    match BigUint::parse_bytes(base.as_bytes(), 16) {
+                        Some(base) => vec![raw_rsa::Base::Given(base)],
+                        None => errors::fail(Error::new(Code::InvalidArgument, "--base must be hex")),
+                    }
+                }
+                None => raw_rsa::parse_bases(sub_m.value_of("bases").unwrap())
+                    .unwrap_or_else(|err| errors::fail(Error::new(Code::InvalidArgument, err))),
+            };
+            let vectors = raw_rsa::vectors(&mut rng, &n, &e, &bases)
+                .unwrap_or_else(|err| errors::fail(Error::new(Code::InvalidArgument, err)));
+
+            let mut key = KeyParams::from_modulus(&n, (n.bits() + 7) / 8 * 8);
+            key.limb_style = limb_style(sub_m);
+            if sub_m.is_present("noir_test") {
+                print!("{}", raw_rsa::render_noir_tests(&key, exponent, &vectors));
+            } else {
+                print!("{}", raw_rsa::render_toml(&key, exponent, &vectors));
+            }
+        }
+        ("verify-manifest", Some(sub_m)) => {
+            let path = sub_m.value_of("manifest").unwrap();
+            let mismatches = manifest::verify(path.as_ref(), sub_m.is_present("regenerate")).unwrap_or_else(|err| {
+                errors::fail(Error::new(Code::Failed, err).with("path", path))
+            });
+            for mismatch in &mismatches {
+                println!("{}", mismatch);
+            }
+            if !mismatches.is_empty() {
+                errors::fail(
+                    Error::new(Code::VerificationFailed, format!("{} files do not match {}", mismatches.len(), path))
+                        .with("path", path)
+                        .with("check", "manifest"),
+                );
+            }
+            println!("Every file matches {}", path);
+        }
+        ("inspect", Some(sub_m)) => {
+            let key = match (sub_m.value_of("public_key"), sub_m.value_of("private_key"), sub_m.value_of("cert")) {
+                (Some(path), _, _) => inspect::read_public_key(path),
+                (_, Some(path), _) => {
+                    let passphrase = match (sub_m.value_of("passphrase_env"), sub_m.value_of("passphrase_file")) {
+                        (Some(var), _) => Passphrase::Env(var.to_string()),
+                        (_, Some(path)) => Passphrase::File(path.to_string()),
+                        _ => Passphrase::Prompt,
+                    };
+                    inspect::read_private_key(path, &passphrase)
+                }
+                (_, _, cert) => inspect::read_certificate_key(cert.unwrap()),
+            }
+            .unwrap_or_else(|err| {
+                let path = sub_m.value_of("public_key").or(sub_m.value_of("private_key")).or(sub_m.value_of("cert"));
+                errors::fail(Error::new(Code::BadKeyFile, err).with("path", path.unwrap()))
+            });
+            let blocklist = sub_m.value_of("blocklist").map(|path| {
+                let contents = std::fs::read_to_string(path).unwrap_or_else(|err| {
+                    let message = format!("Failed to read {}: {}", path, err);
+                    errors::fail(Error::new(Code::Failed, message).with("path", path))
+                });
+                inspect::parse_blocklist(&contents)
+            });
+            let options = inspect::Options {
+                bits: parse_value(sub_m, "bits"),
+                blocklist,
+            };
+            let failed = inspect::inspect(&key, &options);
+            if !failed.is_empty() && !sub_m.is_present("lenient") {
+                errors::fail(
+                    Error::new(Code::VerificationFailed, format!("Failed checks: {}", failed.join(", ")))
+                        .with("checks", failed),
+                );
+            }
+        }
+        ("verify", Some(sub_m)) => {
+            let path = sub_m.value_of("inputs").unwrap();
+            let exponent = sub_m.value_of("exponent").map(|e| {
+                let e = keys::parse_exponent(e);
+                BigUint::from(e.unwrap_or_else(|err| errors::fail(Error::new(Code::InvalidArgument, err))))
+            });
+            let options = verify::Options {
+                hash_alg: sub_m.value_of("hash").and_then(HashAlg::from_name),
+                pss: sub_m.is_present("pss"),
+                salt_len: parse_value(sub_m, "salt_len"),
+                exponent,
+            };
+            let inputs = std::fs::read_to_string(path)
+                .map_err(|err| format!("Failed to read {}: {}", path, err))
+                .and_then(|contents| verify::parse(&contents, &options).map_err(|err| format!("{}: {}", path, err)))
+                .unwrap_or_else(|err| errors::fail(Error::new(Code::InvalidArgument, err).with("path", path)));
+            let failed = verify::verify(&inputs);
+            if !failed.is_empty() {
+                errors::fail(
+                    Error::new(Code::VerificationFailed, format!("Failed checks: {}", failed.join(", ")))
+                        .with("path", path)
+                        .with("checks", failed),
+                );
+            }
+        }
+        _ => {
+            let (last, rest) = SUBCOMMANDS.split_last().unwrap();
+            let message = format!(
+                "Please specify a subcommand: '{}' or '{}'\nRun with --help for usage information",
+                rest.join("', '"),
+                last
+            );
+            errors::fail(Error::new(Code::InvalidArgument, message));
+        }
+    }
+}
+
+/*fn test_signature_generation_impl() {
+    let mut rng = rand::thread_rng();
+    let bits = 2048;
+    let priv_key = RsaPrivateKey::new(&mut rng, bits).expect("failed to generate a key");
+    let pub_key: RsaPublicKey = priv_key.clone().into();
+    let text: &str = "hello world";
+    let signing_key = rsa::pkcs1v15::SigningKey::<Sha256>::new(priv_key);
+    let sig: Vec<u8> = signing_key.sign(text.as_bytes()).to_vec();
+    let verifying_key = VerifyingKey::<Sha256>::new(pub_key);
+
+    let result = verifying_key.verify(
+        text.as_bytes(),
+        &Signature::try_from(sig.as_slice()).unwrap(),
+    );
+    if b == 1024 {
+        generate_1024_bit_signature_parameters(msg, as_toml, e);
+    } else {
+        generate_2048_bit_signature_parameters(msg, as_toml, e, pss);
+    }
+}*/
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::thread_rng;
+    use rsa::pkcs1v15::Signature;
+    use rsa::pkcs8::DecodePrivateKey;
+    use rsa::signature::{Signer, Verifier};
+    use rsa::{pkcs1v15::VerifyingKey, RsaPrivateKey, RsaPublicKey};
+    use sha2::{Sha256, Sha384, Sha512};
+
+    use crate::signature_params_for_key;
+    use crate::signing::{sign_digest, verify_digest};
+
+    #[test]
+    fn test_signature_generation() {
+        let mut rng = thread_rng();
+        let bits = 2048;
+        let priv_key = RsaPrivateKey::new(&mut rng, bits).expect("failed to generate a key");
+        let pub_key: RsaPublicKey = priv_key.clone().into();
+        let text: &str = "hello world";
+        let signing_key = rsa::pkcs1v15::SigningKey::<Sha256>::new(priv_key);
+        let sig: Vec<u8> = signing_key.sign(text.as_bytes()).to_vec();
+        let verifying_key = VerifyingKey::<Sha256>::new(pub_key);
+
+        let result = verifying_key.verify(
+            text.as_bytes(),
+            &Signature::try_from(sig.as_slice()).unwrap(),
+        );
+        result.expect("failed to verify");
+    }
+
+    #[test]
+    fn test_signature_generation_sha384() {
+        let mut rng = thread_rng();
+        let bits = 2048;
+        let priv_key = RsaPrivateKey::new(&mut rng, bits).expect("failed to generate a key");
+        let pub_key: RsaPublicKey = priv_key.clone().into();
+        let text: &str = "hello world";
+        let signing_key = rsa::pkcs1v15::SigningKey::<Sha384>::new(priv_key);
+        let sig: Vec<u8> = signing_key.sign(text.as_bytes()).to_vec();
+        let verifying_key = VerifyingKey::<Sha384>::new(pub_key);
+
+        let result = verifying_key.verify(
+            text.as_bytes(),
+            &Signature::try_from(sig.as_slice()).unwrap(),
+        );
+        result.expect("failed to verify");
+    }
+
+    #[test]
+    fn test_signature_generation_sha512() {
+       let mut rng = thread_rng();
+        let bits = 2048;
+        let priv_key = RsaPrivateKey::new(&mut rng, bits).expect("failed to generate a key");
+        let pub_key: RsaPublicKey = priv_key.clone().into();
+        let text: &str = "hello world";
+        let signing_key = rsa::pkcs1v15::SigningKey::<Sha384>::new(priv_key);
+        let sig: Vec<u8> = signing_key.sign(text.as_bytes()).to_vec();
+        let verifying_key = VerifyingKey::<Sha384>::new(pub_key);
+
+        let result = verifying_key.verify(
+            text.as_bytes(),
+            &Signature::try_from(sig.as_slice()).unwrap(),
+        );
+        result.expect("failed to verify");
+    }
+
+    #[test]
+    fn test_dual_hash_signature_params() {
+        let msg = "hello world";
+        let hashes = parse_hash_list("sha256,sha512").unwrap();
+        let mut rng = thread_rng();
+        let priv_key = generate_key(&mut rng, 1024, 65537).unwrap();
+        let signature_params =
+            signature_params_for_key(&mut rng, &priv_key, msg.as_bytes(), &hashes, &Scheme::Pkcs1v15).unwrap();
+
+        assert_eq!(signature_params.key.modulus_limbs.len(), 9);
+        assert_eq!(signature_params.signatures.len(), 2);
+
+        let modulus = parse_limbs_to_biguint(&params::format_limbs_as_hex(
+            &signature_params.key.modulus_limbs,
+            LimbStyle::Minimal,
+        ));
+        let pub_key = RsaPublicKey::new(modulus, BigUint::from(65537u32)).unwrap();
+        for (signature, hash_alg) in signature_params.signatures.iter().zip(hashes) {
+            assert_eq!(signature.hash_alg, hash_alg);
+            assert_eq!(signature.hash, hash_alg.digest(msg.as_bytes()));
+            assert_eq!(signature.signature_limbs.len(), 9);
+
+            let sig = parse_limbs_to_biguint(&params::format_limbs_as_hex(
+                &signature.signature_limbs,
+                LimbStyle::Minimal,
+            ));
+            // Left-pad to the key size since the verifier expects exactly 128 bytes
+            let mut sig_bytes = vec![0u8; 128 - sig.to_bytes_be().len()];
+            sig_bytes.extend_from_slice(&sig.to_bytes_be());
+            verify_digest(&pub_key, hash_alg, &signature.hash, &sig_bytes, &Scheme::Pkcs1v15)
+                .expect("failed to verify");
+        }
+    }
+
+    #[test]
+    fn test_prefix_is_hashed_in_front_of_the_message() {
+        use sha2::Digest;
+
+        let mut rng = thread_rng();
+        let priv_key = generate_key(&mut rng, 1024, 65537).unwrap();
+        let prefix = b"\x19Noir-RSA Signed Message:\n";
+        let signed = prefixed(prefix, b"hello world");
+        let signature_params =
+            signature_params_for_key(&mut rng, &priv_key, &signed, &[HashAlg::Sha256], &Scheme::Pkcs1v15).unwrap();
+
+        let mut expected = Sha256::new();
+        expected.update(prefix);
+        expected.update(b"hello world");
+        assert_eq!(signature_params.signatures[0].hash, expected.finalize().to_vec());
+        assert_ne!(signature_params.signatures[0].hash, HashAlg::Sha256.digest(b"hello world"));
+    }
+
+    #[test]
+    fn test_parse_hash_list_rejects_duplicates() {
+        assert!(parse_hash_list("sha256,sha256").is_err());
+        assert!(parse_hash_list("sha256,md5").is_err());
+        assert!(parse_hash_list("").is_err());
+    }
+
+    #[test]
+    fn test_multi_key_signature_params() {
+        let msg = "hello world";
+        let mut rng = thread_rng();
+        let priv_keys: Vec<RsaPrivateKey> =
+            (0..3).map(|_| generate_key(&mut rng, 1024, 65537).unwrap()).collect();
+        let entries: Vec<SignatureParams> = priv_keys
+            .iter()
+            .map(|priv_key| {
+                signature_params_for_key(&mut rng, priv_key, msg.as_bytes(), &[HashAlg::Sha256], &Scheme::Pkcs1v15)
+                    .unwrap()
+            })
+            .collect();
+
+        assert_eq!(entries.len(), 3);
+        for (i, entry) in entries.iter().enumerate() {
+            assert_eq!(entry.signatures.len(), 1);
+            assert_eq!(entry.signatures[0].hash, entries[0].signatures[0].hash);
+            for other in &entries[i + 1..] {
+                assert_ne!(entry.key.modulus_limbs, other.key.modulus_limbs);
+            }
+        }
+    }
+
+    #[test]
+    fn test_fixed_salt_pss_is_deterministic() {
+        let mut rng = thread_rng();
+        let priv_key = generate_key(&mut rng, 2048, 65537).unwrap();
+        let salt = hex::decode("000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f").unwrap();
+        let scheme = Scheme::Pss(PssSalt::Fixed(salt.clone()));
+
+        let first = signature_params_for_key(&mut rng, &priv_key, b"hello world", &[HashAlg::Sha256], &scheme).unwrap();
+        let second =
+            signature_params_for_key(&mut rng, &priv_key, b"hello world", &[HashAlg::Sha256], &scheme).unwrap();
+
+        assert_eq!(first.signatures[0].signature_limbs, second.signatures[0].signature_limbs);
+        assert_eq!(first.signatures[0].salt.as_deref(), Some(salt.as_slice()));
+    }
+
+    #[test]
+    fn test_fixed_salt_too_long_is_rejected() {
+        let mut rng = thread_rng();
+        let priv_key = generate_key(&mut rng, 1024, 65537).unwrap();
+        let hash = HashAlg::Sha512.digest(b"hello world");
+        // 128 byte encoding - 64 byte hash - 2 leaves room for at most 62 bytes of salt
+        let scheme = Scheme::Pss(PssSalt::Fixed(vec![0u8; 63]));
+
+        assert!(sign_digest(&mut rng, &priv_key, HashAlg::Sha512, &hash, &scheme).is_err());
+    }
+
+    #[test]
+    fn test_empty_and_longest_salts_verify() {
+        let mut rng = thread_rng();
+        let priv_key = RsaPrivateKey::from_pkcs8_pem(include_str!("../tests/fixtures/rsa_1024.pem")).unwrap();
+        let pub_key = priv_key.to_public_key();
+        let max_len = pss::max_salt_len(HashAlg::Sha256, 1024);
+        assert_eq!(max_len, 128 - 32 - 2);
+        for salt_len in [0, max_len] {
+            let scheme = Scheme::Pss(PssSalt::Random(salt_len));
+            let params =
+                signature_params_for_key(&mut rng, &priv_key, b"hello world", &[HashAlg::Sha256], &scheme).unwrap();
+            let signature = &params.signatures[0];
+            let sig = signature.signature_bytes(pub_key.size());
+            pub_key.verify(HashAlg::Sha256.pss(salt_len), &signature.hash, &sig).unwrap();
+        }
+        assert!(pss::check_salt_len(HashAlg::Sha256, max_len + 1, 1024).is_err());
+    }
+
+    #[test]
+    fn test_every_hash_round_trips_through_the_rsa_verifier() {
+        let mut rng = thread_rng();
+        let priv_key = RsaPrivateKey::from_pkcs8_pem(include_str!("../tests/fixtures/rsa_1024.pem")).unwrap();
+        let pub_key = priv_key.to_public_key();
+        let lengths = [(HashAlg::Sha1, 20), (HashAlg::Sha256, 32), (HashAlg::Sha384, 48), (HashAlg::Sha512, 64)];
+        for (hash_alg, len) in lengths {
+            for scheme in [Scheme::Pkcs1v15, Scheme::Pss(PssSalt::Random(32))] {
+                let params =
+                    signature_params_for_key(&mut rng, &priv_key, b"hello world", &[hash_alg], &scheme).unwrap();
+                let signature = &params.signatures[0];
+                assert_eq!(signature.hash.len(), len);
+                let sig = signature.signature_bytes(pub_key.size());
+                let verified = match scheme {
+                    Scheme::Pkcs1v15 => pub_key.verify(hash_alg.pkcs1v15(), &signature.hash, &sig),
+                    Scheme::Pss(_) => pub_key.verify(hash_alg.pss(32), &signature.hash, &sig),
+                };
+                verified.unwrap_or_else(|err| panic!("{} {:?}: {}", hash_alg.name(), scheme, err));
+            }
+        }
+
+        // A 1024-bit PSS encoding holds a SHA-512 hash with at most 62 bytes of salt
+        assert!(pss::check_salt_len(HashAlg::Sha512, 62, 1024).is_ok());
+        let err = pss::check_salt_len(HashAlg::Sha512, 63, 1024).unwrap_err();
+        assert!(err.contains("at most 62 bytes fit"), "{}", err);
+    }
+
+    #[test]
+    fn test_prehashed_digests_and_empty_messages_are_signed_as_given() {
+        let priv_key = RsaPrivateKey::from_pkcs8_pem(include_str!("../tests/fixtures/rsa_1024.pem")).unwrap();
+        let pub_key = priv_key.to_public_key();
+        let empty = HashAlg::Sha384.digest(b"");
+        let digest = prehashed_digest(&format!("0x{}", hex::encode(&empty)), &[HashAlg::Sha384]);
+        assert_eq!(digest, empty);
+
+        let scheme = Scheme::Pss(PssSalt::Random(32));
+        let params =
+            signature_params_for_digests(&mut thread_rng(), &priv_key, &[HashAlg::Sha384], &[digest], &scheme).unwrap();
+        let signature = &params.signatures[0];
+        assert_eq!(signature.hash, empty);
+        let sig = signature.signature_bytes(pub_key.size());
+        pub_key.verify(HashAlg::Sha384.pss(32), &signature.hash, &sig).unwrap();
+    }
+
+    #[test]
+    fn test_imported_exponent_must_match_the_circuit() {
+        let mut rng = thread_rng();
+        let keys = vec![generate_key(&mut rng, 1024, 65537).unwrap(), generate_key(&mut rng, 1024, 3).unwrap()];
+        assert!(check_exponents(&keys[..1], 65537).is_ok());
+        let err = check_exponents(&keys, 65537).unwrap_err();
+        let expected = "Key 2 has public exponent 3 but the circuit expects 65537";
+        assert!(err.message.starts_with(expected), "{}", err.message);
+        assert!(check_exponents(&keys[1..], 3).is_ok());
+
+        // The adapted output carries the key's own exponent, and the signature verifies with it
+        let mut params =
+            signature_params_for_key(&mut rng, &keys[1], b"hello world", &[HashAlg::Sha256], &Scheme::Pkcs1v15)
+                .unwrap();
+        params.key.set_exponent_input(ExponentInput::Integer, keys[1].e());
+        let inputs: toml::Value = params.render_toml(schema::Names::Canonical).parse().unwrap();
+        assert_eq!(inputs[schema::EXPONENT].as_integer(), Some(3));
+    }
+
+    #[test]
+    fn test_external_signatures_must_verify_and_keep_their_width() {
+        let fixture = |name: &str| format!("{}/tests/fixtures/{}", env!("CARGO_MANIFEST_DIR"), name);
+        let pub_key = keys::load_public_key(&fixture("rsa_2048_pub.pem")).unwrap();
+        let signature = read_signature_file(&fixture("rsa_2048_sha256_pkcs1v15.sig"), pub_key.size()).unwrap();
+        let digest = HashAlg::Sha256.digest(b"Hello World! This is Noir-RSA");
+        let params =
+            signature_params_for_signature(&pub_key, HashAlg::Sha256, &digest, &signature, &Scheme::Pkcs1v15).unwrap();
+        assert_eq!(params.signatures[0].signature_limbs[0].to_str_radix(16), "dfd2ecd8d18e88f761b181a3c25b12");
+        let tampered = HashAlg::Sha256.digest(b"tampered");
+        let err = signature_params_for_signature(&pub_key, HashAlg::Sha256, &tampered, &signature, &Scheme::Pkcs1v15)
+            .err()
+            .unwrap();
+        assert_eq!(err.code, Code::VerificationFailed);
+
+        // A signature starting with a zero byte, given base64 encoded without it
+        let priv_key = RsaPrivateKey::from_pkcs8_pem(include_str!("../tests/fixtures/rsa_1024.pem")).unwrap();
+        let pub_key = priv_key.to_public_key();
+        let (digest, signature) = (0u32..)
+            .map(|i| {
+                let digest = HashAlg::Sha256.digest(&i.to_be_bytes());
+                let signature = sign_digest(&mut thread_rng(), &priv_key, HashAlg::Sha256, &digest, &Scheme::Pkcs1v15);
+                (digest, signature.unwrap())
+            })
+            .find(|(_, signature)| signature[0] == 0)
+            .unwrap();
+        let scratch = tempfile::tempdir().unwrap();
+        let path = scratch.path().join("signature.b64");
+        std::fs::write(&path, Base64::encode_string(&signature[1..])).unwrap();
+        let read = read_signature_file(path.to_str().unwrap(), pub_key.size()).unwrap();
+        assert_eq!(read, signature);
+        let params =
+            signature_params_for_signature(&pub_key, HashAlg::Sha256, &digest, &read, &Scheme::Pkcs1v15).unwrap();
+        assert_eq!(params.signatures[0].signature_limbs.len(), 9);
+        assert_eq!(params.signatures[0].signature_bytes(pub_key.size()), signature);
+        assert!(pad_signature(vec![1; 129], 128).is_err());
+    }
+
+    #[test]
+    fn test_output_file_is_a_complete_prover_toml() {
+        let abi: serde_json::Value = serde_json::from_str(include_str!("../tests/fixtures/example_abi.json")).unwrap();
+        let priv_key = RsaPrivateKey::from_pkcs8_pem(include_str!("../tests/fixtures/rsa_2048.pem")).unwrap();
+        let params =
+            signature_params_for_key(&mut thread_rng(), &priv_key, b"hello", &[HashAlg::Sha256], &Scheme::Pkcs1v15)
+                .unwrap();
+        let scratch = tempfile::tempdir().unwrap();
+        let path = scratch.path().join("Prover.toml");
+        write_or_print(path.to_str(), &params.render_toml(schema::Names::Canonical));
+
+        let written: toml::Value = std::fs::read_to_string(&path).unwrap().parse().unwrap();
+        let inputs = written.as_table().unwrap();
+        let parameters = abi["parameters"].as_array().unwrap();
+        assert_eq!(inputs.len(), parameters.len());
+        for parameter in parameters {
+            let name = parameter["name"].as_str().unwrap();
+            let length = inputs[name].as_array().map(Vec::len);
+            assert_eq!(length, parameter["type"]["length"].as_u64().map(|length| length as usize), "`{}`", name);
+        }
+    }
+
+    #[test]
+    fn test_json_output_holds_the_limbs_of_the_key_and_signature() {
+        let priv_key = RsaPrivateKey::from_pkcs8_pem(include_str!("../tests/fixtures/rsa_2048.pem")).unwrap();
+        let scheme = Scheme::Pss(PssSalt::Random(32));
+        let mut params =
+            signature_params_for_key(&mut thread_rng(), &priv_key, b"hello", &[HashAlg::Sha256], &scheme).unwrap();
+        let json: serde_json::Value =
+            serde_json::from_str(&render_json(&params, priv_key.e(), &scheme, None, None)).unwrap();
+
+        let limbs = |name: &str| -> Vec<BigUint> {
+            let limbs = json[name].as_array().unwrap_or_else(|| panic!("`{}` is missing", name));
+            let parse = |limb: &serde_json::Value| BigUint::parse_bytes(&limb.as_str().unwrap().as_bytes()[2..], 16);
+            limbs.iter().map(|limb| parse(limb).unwrap()).collect()
+        };
+        assert_eq!(limbs("modulus_limbs"), split_into_120_bit_limbs(priv_key.n(), 2048));
+        let signature = &params.signatures[0];
+        let signature_bytes = signature.signature_bytes(priv_key.size());
+        assert_eq!(limbs("signature_limbs"), split_into_120_bit_limbs(&BigUint::from_bytes_be(&signature_bytes), 2048));
+        assert_eq!(limbs("redc_limbs"), params.key.redc_limbs);
+        assert_eq!(json["hash"], serde_json::json!(HashAlg::Sha256.digest(b"hello")));
+        assert_eq!((json["key_size_bits"].as_u64(), json["scheme"].as_str()), (Some(2048), Some("pss")));
+        assert_eq!(json["salt_len"], 32);
+        assert_eq!(json["exponent"], 65537);
+
+        // The circuit inputs are what the library serializes, in the key's limb style
+        params.key.limb_style = LimbStyle::Canonical;
+        let json: serde_json::Value =
+            serde_json::from_str(&render_json(&params, priv_key.e(), &scheme, None, None)).unwrap();
+        let inputs = serde_json::to_value(CircuitInputs::from(&params)).unwrap();
+        for name in ["hash", "modulus_limbs", "redc_limbs", "signature_limbs"] {
+            assert_eq!(json[name], inputs[name], "{}", name);
+        }
+        assert_eq!(json["redc_limbs"][0].as_str().unwrap().len(), 32);
+    }
+
+    #[test]
+    fn test_canonical_limbs_reparse_to_the_same_integers() {
+        let mut rng = thread_rng();
+        let priv_key = generate_key(&mut rng, 1024, 65537).unwrap();
+        let key = KeyParams::from_modulus(priv_key.n(), 1024);
+
+        let minimal = params::format_limbs_as_hex(&key.modulus_limbs, LimbStyle::Minimal);
+        let canonical = params::format_limbs_as_hex(&key.modulus_limbs, LimbStyle::Canonical);
+        assert!(canonical.split(", ").all(|limb| limb.len() == 32));
+        assert_eq!(parse_limbs_to_biguint(&canonical), parse_limbs_to_biguint(&minimal));
+        assert_eq!(&parse_limbs_to_biguint(&canonical), priv_key.n());
+    }
+
+    #[test]
+    fn test_limb_counts_match_the_circuit_instantiations() {
+        // The limb counts of the Params and RBN types in src/types.nr
+        let fixtures = [
+            (include_str!("../tests/fixtures/rsa_1024.pem"), 1024, 9),
+            (include_str!("../tests/fixtures/rsa_2048.pem"), 2048, 18),
+            (include_str!("../tests/fixtures/rsa_4096.pem"), 4096, 35),
+        ];
+        for (pem, bits, limbs) in fixtures {
+            let priv_key = RsaPrivateKey::from_pkcs8_pem(pem).unwrap();
+            let mut rng = ChaCha20Rng::seed_from_u64(0);
+            let params =
+                signature_params_for_key(&mut rng, &priv_key, b"hello", &[HashAlg::Sha256], &Scheme::Pkcs1v15).unwrap();
+            assert_eq!(params.key.bits, bits);
+            assert_eq!(params.key.runtime_bignum_type(), format!("RuntimeBigNum<{}, {}>", limbs, bits));
+
+            let document: toml::Value = params.render_toml(schema::Names::Canonical).parse().unwrap();
+            for name in [schema::MODULUS_LIMBS, schema::REDC_LIMBS, schema::SIGNATURE_LIMBS] {
+                assert_eq!(document[name].as_array().unwrap().len(), limbs, "{} of a {}-bit key", name, bits);
+            }
+        }
+        assert_eq!(params::num_limbs(3072), 26);
+        assert!(GENERATED_BITS.iter().all(|bits| check_generated_bits(*bits).is_ok()));
+    }
+
+    #[test]
+    fn test_failures_carry_their_context_as_json() {
+        let err = load_key_file("tests/fixtures/missing.pem", &Passphrase::Prompt).unwrap_err();
+        let json = err.to_json();
+        assert_eq!(json["code"], "bad_key_file");
+        assert_eq!(json["path"], "tests/fixtures/missing.pem");
+        assert!(json["message"].as_str().unwrap().starts_with("Failed to read key file"));
+
+        let priv_key = load_key_file("tests/fixtures/rsa_1024.pem", &Passphrase::Prompt).unwrap();
+        let circuit = infer::Instantiation { num_limbs: 18, bits: 2048 };
+        let json = check_circuit_bits(&priv_key, "example/", &circuit).unwrap_err().to_json();
+        assert_eq!(json["code"], "key_mismatch");
+        assert_eq!((json["expected_bits"].as_u64(), json["actual_bits"].as_u64()), (Some(2048), Some(1024)));
+        let json = check_exponents(&[priv_key], 3).unwrap_err().to_json();
+        assert_eq!((json["code"].as_str(), json["expected_exponent"].as_u64()), (Some("key_mismatch"), Some(3)));
+        assert_eq!(json["actual_exponent"], "65537");
+
+        let err = check_generated_bits(1536).unwrap_err();
+        assert_eq!(err.code.exit_code(), 2);
+        assert_eq!(
+            err.to_json(),
+            serde_json::json!({
+                "code": "invalid_argument",
+                "message": "Number of bits of RSA signature can only be 1024, 2048, 3072 or 4096",
+                "actual_bits": 1536,
+            })
+        );
+    }
+
+    #[test]
+    fn test_args_are_routed_to_their_subcommand() {
+        for name in SUBCOMMANDS {
+            let err = cli().get_matches_from_safe(["signature_gen", name, "--help"]).unwrap_err();
+            assert_eq!(err.kind, clap::ErrorKind::HelpDisplayed, "{}", name);
+        }
+
+        let args = |line: &str| -> Vec<String> { line.split(' ').map(String::from).collect() };
+        for (given, parsed) in [
+            ("signature_gen -m hi --toml", "signature_gen sign -m hi --toml"),
+            ("signature_gen --error-format json -m hi", "signature_gen --error-format json sign -m hi"),
+            ("signature_gen from-cert cert.der --toml", "signature_gen sign --x509 cert.der --toml"),
+            ("signature_gen from-jwt a.b.c --jwk key.json", "signature_gen sign --jwt a.b.c --jwk key.json"),
+            ("signature_gen from-cert --help", "signature_gen from-cert --help"),
+            ("signature_gen params --bits 2048", "signature_gen params --bits 2048"),
+            ("signature_gen --help", "signature_gen --help"),
+        ] {
+            assert_eq!(subcommand_args(args(given)), args(parsed));
+        }
+
+        let owners = option_owners("--pss");
+        assert!(owners.contains(&"sign") && owners.contains(&"verify"));
+        assert!(!owners.contains(&"params") && !owners.contains(&"keygen"));
+        assert!(option_owners("--no-such-option").is_empty());
+    }
+}
@@ -0,0 +1,110 @@
+//! Commitments to an RSA public key, for circuits that expose a hash of the
+//! key as a public input instead of the modulus itself
+//!
+//! Every output mode computes commitments through the functions in this
+//! module, so single-key and `[[keys]]` output always agree. The encodings
+//! are part of the output format and must not change.
+
+use num_bigint::BigUint;
+
+use crate::hash::HashAlg;
+use crate::params::combine_limbs;
+use crate::poseidon2;
+
+/// Hash used by `--emit-pubkey-hash`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PubkeyHash {
+    Sha256,
+}
+
+impl PubkeyHash {
+    pub fn from_name(name: &str) -> Option<PubkeyHash> {
+        match name {
+            "sha256" => Some(PubkeyHash::Sha256),
+            _ => None,
+        }
+    }
+}
+
+/// The modulus as big-endian bytes, left-padded to the key size (`ceil(bits / 8)` bytes)
+pub fn modulus_bytes(modulus_limbs: &[BigUint], bits: usize) -> Vec<u8> {
+    let bytes = combine_limbs(modulus_limbs).to_bytes_be();
+    let mut padded = vec![0u8; ((bits + 7) / 8).saturating_sub(bytes.len())];
+    padded.extend_from_slice(&bytes);
+    padded
+}
+
+/// Commitment to a public key: the hash of the fixed-width big-endian modulus bytes.
+/// The exponent is not included, since the circuit passes it as a constant.
+pub fn pubkey_hash(hash: PubkeyHash, modulus_limbs: &[BigUint], bits: usize) -> Vec<u8> {
+    match hash {
+        PubkeyHash::Sha256 => HashAlg::Sha256.digest(&modulus_bytes(modulus_limbs, bits)),
+    }
+}
+
+/// Commitment to a public key as a BN254 field element: Poseidon2 over the
+/// 120-bit modulus limbs, least significant first as they are stored in a
+/// BigNum, i.e. `Poseidon2::hash(modulus.limbs.map(|l| l as Field), N)` in Noir
+pub fn pubkey_poseidon2(modulus_limbs: &[BigUint]) -> BigUint {
+    poseidon2::hash(modulus_limbs, modulus_limbs.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use noir_bignum_paramgen::split_into_120_bit_limbs;
+    use rsa::pkcs8::DecodePrivateKey;
+    use rsa::traits::PublicKeyParts;
+    use rsa::RsaPrivateKey;
+
+    #[test]
+    fn test_pubkey_sha256_is_pinned() {
+        // SHA-256 of the modulus bytes, computed independently; a change here breaks deployed circuits
+        for (pem, bits, expected) in [
+            (
+                include_str!("../tests/fixtures/rsa_1024.pem"),
+                1024,
+                "a0db64cb2de131ec91d7697c51f3d432de3695b31b20b0a43c3bc523fa2839c6",
+            ),
+            (
+                include_str!("../tests/fixtures/rsa_2048.pem"),
+                2048,
+                "51aa7f207b7a8aea9c4ce063020f04559e70854b58c5c3323ff29ae7512732e2",
+            ),
+        ] {
+            let priv_key = RsaPrivateKey::from_pkcs8_pem(pem).unwrap();
+            let limbs = split_into_120_bit_limbs(priv_key.n(), bits);
+            assert_eq!(hex::encode(pubkey_hash(PubkeyHash::Sha256, &limbs, bits)), expected);
+        }
+    }
+
+    #[test]
+    fn test_pubkey_poseidon2_matches_noir() {
+        // Also asserted in Noir by tests/fixtures/poseidon_commitment, which must be kept in sync
+        for (pem, bits, expected) in [
+            (
+                include_str!("../tests/fixtures/rsa_1024.pem"),
+                1024,
+                "345036171562542775794442460249789325834704118810627170367169002658202093890",
+            ),
+            (
+                include_str!("../tests/fixtures/rsa_2048.pem"),
+                2048,
+                "14467594942425149394500613103008460688588047884043712495527331072423402127364",
+            ),
+        ] {
+            let priv_key = RsaPrivateKey::from_pkcs8_pem(pem).unwrap();
+            let limbs = split_into_120_bit_limbs(priv_key.n(), bits);
+            assert_eq!(pubkey_poseidon2(&limbs).to_string(), expected);
+        }
+    }
+
+    #[test]
+    fn test_modulus_bytes_are_fixed_width() {
+        // A modulus with a short top limb still hashes all `bits / 8` bytes
+        let modulus = (BigUint::from(1u32) << 1000) + 1u32;
+        let bytes = modulus_bytes(&split_into_120_bit_limbs(&modulus, 1024), 1024);
+        assert_eq!(bytes.len(), 128);
+        assert_eq!(BigUint::from_bytes_be(&bytes), modulus);
+    }
+}
@@ -0,0 +1,318 @@
+//! `convert` subcommand: migrates Prover.toml files between input layouts,
+//! and `diff`, which compares two of them by the numbers they encode
+
+use num_bigint::BigUint;
+use toml::value::Table;
+use toml::Value;
+
+use crate::params::{combine_limbs, format_limbs_as_toml_value, KeyParams, LimbStyle};
+use crate::schema::{
+    self, HASH, LEGACY_BN, LEGACY_SIGNATURE, LEGACY_SIGNATURE_LIMBS, MODULUS_LIMBS, REDC_LIMBS,
+    SIGNATURE_LIMBS,
+};
+
+/// Prover.toml layouts produced by signature_gen over time
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Layout {
+    /// Pre-0.4.0 BigNum: `bn = [modulus, redc]` and a `[signature]` table with `limbs`
+    V03,
+    /// BigNum 0.4.0 and later: flat `modulus_limbs`, `redc_limbs` and `signature_limbs`
+    V04,
+}
+
+impl Layout {
+    pub fn from_name(name: &str) -> Option<Layout> {
+        match name {
+            "v03" => Some(Layout::V03),
+            "v04" => Some(Layout::V04),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Layout::V03 => "v03",
+            Layout::V04 => "v04",
+        }
+    }
+
+    /// Keys owned by the layout, everything else is carried over unchanged
+    fn keys(self) -> &'static [&'static str] {
+        match self {
+            Layout::V03 => &[LEGACY_BN, HASH, LEGACY_SIGNATURE],
+            Layout::V04 => &[MODULUS_LIMBS, REDC_LIMBS, SIGNATURE_LIMBS, HASH],
+        }
+    }
+}
+
+/// The numbers a Prover.toml encodes, independently of its layout
+struct ProverInputs {
+    modulus: BigUint,
+    redc: BigUint,
+    signature: BigUint,
+    hash: Value,
+}
+
+pub fn detect_layout(doc: &Table) -> Option<Layout> {
+    if doc.contains_key(LEGACY_BN) && matches!(doc.get(LEGACY_SIGNATURE), Some(Value::Table(_))) {
+        Some(Layout::V03)
+    } else if doc.contains_key(MODULUS_LIMBS) && doc.contains_key(SIGNATURE_LIMBS) {
+        Some(Layout::V04)
+    } else {
+        None
+    }
+}
+
+fn parse_limb(value: &Value) -> Result<BigUint, String> {
+    match value {
+        Value::String(s) => match s.strip_prefix("0x") {
+            Some(hex) => BigUint::parse_bytes(hex.as_bytes(), 16),
+            None => BigUint::parse_bytes(s.as_bytes(), 10),
+        }
+        .ok_or_else(|| format!("invalid limb {:?}", s)),
+        Value::Integer(i) if *i >= 0 => Ok(BigUint::from(*i as u64)),
+        other => Err(format!("invalid limb {}", other)),
+    }
+}
+
+/// The limbs of an input as they are written, least significant first
+pub fn parse_limb_list(value: Option<&Value>, name: &str) -> Result<Vec<BigUint>, String> {
+    value
+        .and_then(|value| value.as_array())
+        .ok_or_else(|| format!("missing limb array `{}`", name))?
+        .iter()
+        .map(parse_limb)
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|err| format!("`{}`: {}", name, err))
+}
+
+pub fn parse_limbs(value: Option<&Value>, name: &str) -> Result<BigUint, String> {
+    Ok(combine_limbs(&parse_limb_list(value, name)?))
+}
+
+fn read_inputs(doc: &Table, layout: Layout) -> Result<ProverInputs, String> {
+    let hash = doc.get(HASH).cloned().ok_or("missing `hash`")?;
+    match layout {
+        Layout::V03 => {
+            let bn = doc.get(LEGACY_BN).and_then(|bn| bn.as_array()).ok_or("`bn` must be an array")?;
+            if bn.len() != 2 {
+                return Err(format!("`bn` must hold the modulus and redc arrays, found {} entries", bn.len()));
+            }
+            let signature = doc
+                .get(LEGACY_SIGNATURE)
+                .and_then(|signature| signature.get(LEGACY_SIGNATURE_LIMBS));
+            Ok(ProverInputs {
+                modulus: parse_limbs(Some(&bn[0]), "bn[0]")?,
+                redc: parse_limbs(Some(&bn[1]), "bn[1]")?,
+                signature: parse_limbs(signature, "signature.limbs")?,
+                hash,
+            })
+        }
+        Layout::V04 => Ok(ProverInputs {
+            modulus: parse_limbs(doc.get(MODULUS_LIMBS), MODULUS_LIMBS)?,
+            redc: parse_limbs(doc.get(REDC_LIMBS), REDC_LIMBS)?,
+            signature: parse_limbs(doc.get(SIGNATURE_LIMBS), SIGNATURE_LIMBS)?,
+            hash,
+        }),
+    }
+}
+
+fn limbs_value(value: &BigUint, bits: usize, style: LimbStyle) -> Value {
+    Value::Array(format_limbs_as_toml_value(
+        &noir_bignum_paramgen::split_into_120_bit_limbs(value, bits),
+        style,
+    ))
+}
+
+/// Convert a parsed Prover.toml from one layout to another, returning the new
+/// document and any warnings about values that had to be regenerated
+pub fn convert(
+    doc: &Table,
+    from: Layout,
+    to: Layout,
+    limb_style: LimbStyle,
+) -> Result<(Table, Vec<String>), String> {
+    match detect_layout(doc) {
+        Some(detected) if detected == from => {}
+        Some(detected) => {
+            return Err(format!(
+                "input looks like the {} layout, not {}",
+                detected.name(),
+                from.name()
+            ))
+        }
+        None => {
+            return Err(format!(
+                "unrecognized layout, expected {} keys: {}",
+                from.name(),
+                from.keys().join(", ")
+            ))
+        }
+    }
+
+    let inputs = read_inputs(doc, from)?;
+    let bits = ((inputs.modulus.bits() + 7) / 8) * 8;
+
+    // The Barrett parameter is always derived from the modulus again, since
+    // its representation is what changes between BigNum layouts
+    let key = KeyParams::from_modulus(&inputs.modulus, bits);
+    let mut warnings = Vec::new();
+    if combine_limbs(&key.redc_limbs) != inputs.redc {
+        warnings.push("the Barrett reduction parameter did not match the modulus and was recomputed".to_string());
+    }
+
+    let mut out: Table = doc
+        .iter()
+        .filter(|(name, _)| !from.keys().contains(&name.as_str()))
+        .map(|(name, value)| (name.clone(), value.clone()))
+        .collect();
+    for name in to.keys() {
+        if out.contains_key(*name) {
+            return Err(format!("`{}` already exists in the input and would be overwritten", name));
+        }
+    }
+
+    out.insert(HASH.to_string(), inputs.hash);
+    out.insert(
+        MODULUS_LIMBS.to_string(),
+        Value::Array(format_limbs_as_toml_value(&key.modulus_limbs, limb_style)),
+    );
+    out.insert(
+        REDC_LIMBS.to_string(),
+        Value::Array(format_limbs_as_toml_value(&key.redc_limbs, limb_style)),
+    );
+    out.insert(SIGNATURE_LIMBS.to_string(), limbs_value(&inputs.signature, bits, limb_style));
+    if to == Layout::V03 {
+        out = schema::to_legacy(out);
+    }
+
+    Ok((out, warnings))
+}
+
+/// Differences between the numbers two Prover.toml files encode, whichever their layouts.
+/// Inputs outside the layouts are compared as they are, except the provenance in `[meta]`.
+pub fn diff(a: &Table, b: &Table) -> Result<Vec<String>, String> {
+    let layout_a = detect_layout(a).ok_or("the first file has an unrecognized layout")?;
+    let layout_b = detect_layout(b).ok_or("the second file has an unrecognized layout")?;
+    let (inputs_a, inputs_b) = (read_inputs(a, layout_a)?, read_inputs(b, layout_b)?);
+
+    let mut differences = Vec::new();
+    let numbers = [
+        ("modulus", &inputs_a.modulus, &inputs_b.modulus),
+        ("redc", &inputs_a.redc, &inputs_b.redc),
+        ("signature", &inputs_a.signature, &inputs_b.signature),
+    ];
+    for (name, x, y) in numbers {
+        if x != y {
+            differences.push(format!("{}: 0x{} != 0x{}", name, x.to_str_radix(16), y.to_str_radix(16)));
+        }
+    }
+    if inputs_a.hash != inputs_b.hash {
+        differences.push(format!("{}: {} != {}", HASH, inputs_a.hash, inputs_b.hash));
+    }
+
+    let others = |doc: &Table, layout: Layout| -> Vec<String> {
+        doc.keys()
+            .filter(|name| !layout.keys().contains(&name.as_str()) && name.as_str() != "meta")
+            .cloned()
+            .collect()
+    };
+    let mut names = others(a, layout_a);
+    names.extend(others(b, layout_b).into_iter().filter(|name| !a.contains_key(name)));
+    for name in names {
+        match (a.get(&name), b.get(&name)) {
+            (Some(x), Some(y)) if x == y => {}
+            (Some(_), Some(_)) => differences.push(format!("{}: differs", name)),
+            (Some(_), None) => differences.push(format!("{}: only in the first file", name)),
+            _ => differences.push(format!("{}: only in the second file", name)),
+        }
+    }
+    Ok(differences)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PROVER_V03: &str = include_str!("../tests/fixtures/prover_v03.toml");
+
+    fn parse(contents: &str) -> Table {
+        match contents.parse::<Value>().unwrap() {
+            Value::Table(table) => table,
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn test_convert_v03_to_v04() {
+        let doc = parse(PROVER_V03);
+        assert_eq!(detect_layout(&doc), Some(Layout::V03));
+
+        let (converted, warnings) = convert(&doc, Layout::V03, Layout::V04, LimbStyle::Minimal).unwrap();
+        assert!(warnings.is_empty());
+        assert_eq!(detect_layout(&converted), Some(Layout::V04));
+
+        let bn = doc["bn"].as_array().unwrap();
+        assert_eq!(&converted["modulus_limbs"], &bn[0]);
+        assert_eq!(&converted["redc_limbs"], &bn[1]);
+        assert_eq!(&converted["signature_limbs"], &doc["signature"]["limbs"]);
+        assert_eq!(converted["modulus_limbs"].as_array().unwrap().len(), 18);
+
+        // Unrelated keys are preserved
+        assert_eq!(&converted["hash"], &doc["hash"]);
+        assert_eq!(&converted["nullifier"], &doc["nullifier"]);
+        assert_eq!(&converted["metadata"], &doc["metadata"]);
+        assert!(!converted.contains_key("bn"));
+        assert!(!converted.contains_key("signature"));
+    }
+
+    #[test]
+    fn test_convert_round_trip() {
+        let doc = parse(PROVER_V03);
+        let (v04, _) = convert(&doc, Layout::V03, Layout::V04, LimbStyle::Minimal).unwrap();
+        let (v03, _) = convert(&v04, Layout::V04, Layout::V03, LimbStyle::Minimal).unwrap();
+        assert_eq!(v03, doc);
+
+        // Zero-padded limbs read back as the same numbers
+        let (canonical, _) = convert(&doc, Layout::V03, Layout::V04, LimbStyle::Canonical).unwrap();
+        assert_eq!(canonical["modulus_limbs"][17].as_str(), Some("0x0000000000000000000000000000a1"));
+        let (v03, _) = convert(&canonical, Layout::V04, Layout::V03, LimbStyle::Minimal).unwrap();
+        assert_eq!(v03, doc);
+    }
+
+    #[test]
+    fn test_convert_recomputes_stale_redc() {
+        let mut doc = parse(PROVER_V03);
+        let bn = doc.get_mut("bn").unwrap().as_array_mut().unwrap();
+        bn[1].as_array_mut().unwrap()[0] = Value::String("0x1".to_string());
+
+        let (converted, warnings) = convert(&doc, Layout::V03, Layout::V04, LimbStyle::Minimal).unwrap();
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(&converted["redc_limbs"], &parse(PROVER_V03)["bn"][1]);
+    }
+
+    #[test]
+    fn test_diff_compares_numbers_across_layouts() {
+        let doc = parse(PROVER_V03);
+        let (canonical, _) = convert(&doc, Layout::V03, Layout::V04, LimbStyle::Canonical).unwrap();
+        assert_eq!(diff(&doc, &canonical), Ok(vec![]));
+
+        let mut tampered = canonical.clone();
+        tampered.get_mut("signature_limbs").unwrap().as_array_mut().unwrap()[0] = Value::String("0x1".to_string());
+        tampered.remove("nullifier");
+        let differences = diff(&doc, &tampered).unwrap();
+        assert_eq!(differences.len(), 2);
+        assert!(differences[0].starts_with("signature: 0x"));
+        assert_eq!(differences[1], "nullifier: only in the first file");
+    }
+
+    #[test]
+    fn test_convert_rejects_unknown_layouts() {
+        let doc = parse("modulus = [\"0x1\"]\nsig = [\"0x2\"]\n");
+        let err = convert(&doc, Layout::V03, Layout::V04, LimbStyle::Minimal).unwrap_err();
+        assert!(err.starts_with("unrecognized layout"));
+
+        let doc = parse(PROVER_V03);
+        assert!(convert(&doc, Layout::V04, Layout::V03, LimbStyle::Minimal).is_err());
+    }
+}
@@ -0,0 +1,166 @@
+//! Cross-validation of generated vectors against the openssl command line tool
+
+use std::fs;
+use std::io::ErrorKind;
+use std::path::PathBuf;
+use std::process::Command;
+
+use rsa::pkcs8::{EncodePublicKey, LineEnding};
+use rsa::RsaPublicKey;
+use tempfile::TempDir;
+
+use crate::hash::HashAlg;
+use crate::signing::{verify_digest, Scheme};
+
+/// Outcome of a cross-check that did not find a problem
+#[derive(Debug, PartialEq, Eq)]
+pub enum CrossCheck {
+    Verified,
+    /// The independent implementation is not available
+    Skipped(String),
+}
+
+/// Arguments to `openssl dgst` selecting the hash and padding of `scheme`.
+/// The PSS salt length and MGF1 hash are always given explicitly, since
+/// openssl otherwise auto-detects the salt length on verification.
+pub fn openssl_dgst_args(hash_alg: HashAlg, scheme: &Scheme) -> Vec<String> {
+    let mut args = vec!["dgst".to_string(), format!("-{}", hash_alg.name())];
+    if let Some(salt_len) = scheme.salt_len() {
+        for sigopt in [
+            "rsa_padding_mode:pss".to_string(),
+            format!("rsa_pss_saltlen:{}", salt_len),
+            format!("rsa_mgf1_md:{}", hash_alg.name()),
+        ] {
+            args.push("-sigopt".to_string());
+            args.push(sigopt);
+        }
+    }
+    args
+}
+
+/// Write one of the files handed to openssl into `dir`
+fn write(dir: &TempDir, name: &str, contents: &[u8]) -> Result<PathBuf, String> {
+    let path = dir.path().join(name);
+    fs::write(&path, contents).map_err(|err| format!("Failed to write {}: {}", path.display(), err))?;
+    Ok(path)
+}
+
+/// Check that `sig` verifies over `msg` with both the rsa crate and `openssl dgst -verify`.
+/// Any disagreement, or a rejection by either, is an error describing both results.
+pub fn cross_check_openssl(
+    pub_key: &RsaPublicKey,
+    hash_alg: HashAlg,
+    msg: &[u8],
+    sig: &[u8],
+    scheme: &Scheme,
+) -> Result<CrossCheck, String> {
+    // Removed when dropped
+    let dir = tempfile::tempdir().map_err(|err| format!("Failed to create a scratch directory: {}", err))?;
+    let pem = pub_key
+        .to_public_key_pem(LineEnding::LF)
+        .map_err(|err| format!("Failed to encode the public key: {}", err))?;
+    let key_path = write(&dir, "pubkey.pem", pem.as_bytes())?;
+    let msg_path = write(&dir, "message.bin", msg)?;
+    let sig_path = write(&dir, "signature.bin", sig)?;
+
+    let output = match Command::new("openssl")
+        .args(openssl_dgst_args(hash_alg, scheme))
+        .arg("-verify")
+        .arg(&key_path)
+        .arg("-signature")
+        .arg(&sig_path)
+        .arg(&msg_path)
+        .output()
+    {
+        Ok(output) => output,
+        Err(err) if err.kind() == ErrorKind::NotFound => {
+            return Ok(CrossCheck::Skipped("openssl is not installed".to_string()))
+        }
+        Err(err) => return Err(format!("Failed to run openssl: {}", err)),
+    };
+
+    let rsa_result = verify_digest(pub_key, hash_alg, &hash_alg.digest(msg), sig, scheme);
+    if rsa_result.is_ok() && output.status.success() {
+        return Ok(CrossCheck::Verified);
+    }
+
+    Err(format!(
+        "{} signature cross-check failed\nrsa crate: {}\nopenssl ({}):\n{}{}",
+        hash_alg.name(),
+        match rsa_result {
+            Ok(()) => "verified".to_string(),
+            Err(err) => format!("rejected ({})", err),
+        },
+        output.status,
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr)
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::signing::{sign_digest, PssSalt};
+    use rand::SeedableRng;
+    use rsa::pkcs8::DecodePrivateKey;
+    use rsa::RsaPrivateKey;
+
+    const FIXTURE_KEY: &str = include_str!("../tests/fixtures/rsa_2048.pem");
+    const MESSAGE: &[u8] = b"Hello World! This is Noir-RSA";
+
+    #[test]
+    fn test_openssl_args() {
+        assert_eq!(openssl_dgst_args(HashAlg::Sha256, &Scheme::Pkcs1v15), ["dgst", "-sha256"]);
+        assert_eq!(
+            openssl_dgst_args(HashAlg::Sha512, &Scheme::Pss(PssSalt::Random(32))),
+            [
+                "dgst",
+                "-sha512",
+                "-sigopt",
+                "rsa_padding_mode:pss",
+                "-sigopt",
+                "rsa_pss_saltlen:32",
+                "-sigopt",
+                "rsa_mgf1_md:sha512",
+            ]
+        );
+        // Fixed and empty salts still pass their exact length rather than openssl's -1/-2 specials
+        assert!(openssl_dgst_args(HashAlg::Sha256, &Scheme::Pss(PssSalt::Fixed(vec![7; 20])))
+            .contains(&"rsa_pss_saltlen:20".to_string()));
+        assert!(openssl_dgst_args(HashAlg::Sha1, &Scheme::Pss(PssSalt::Random(0)))
+            .contains(&"rsa_pss_saltlen:0".to_string()));
+    }
+
+    #[test]
+    fn test_cross_check_pss_salt_lengths() {
+        let priv_key = RsaPrivateKey::from_pkcs8_pem(FIXTURE_KEY).unwrap();
+        let pub_key = priv_key.to_public_key();
+        let mut rng = rand_chacha::ChaCha20Rng::seed_from_u64(0);
+
+        for scheme in [
+            Scheme::Pkcs1v15,
+            Scheme::Pss(PssSalt::Random(0)),
+            Scheme::Pss(PssSalt::Random(32)),
+            Scheme::Pss(PssSalt::Fixed((0..64).collect())),
+        ] {
+            let hash = HashAlg::Sha256.digest(MESSAGE);
+            let mut sig = sign_digest(&mut rng, &priv_key, HashAlg::Sha256, &hash, &scheme).unwrap();
+            match cross_check_openssl(&pub_key, HashAlg::Sha256, MESSAGE, &sig, &scheme).unwrap() {
+                CrossCheck::Verified => {}
+                CrossCheck::Skipped(reason) => {
+                    eprintln!("Skipping openssl cross-check: {}", reason);
+                    return;
+                }
+            }
+
+            // A salt length that differs from the one used to sign must be caught
+            if let Some(salt_len) = scheme.salt_len() {
+                let other = Scheme::Pss(PssSalt::Random(salt_len + 1));
+                assert!(cross_check_openssl(&pub_key, HashAlg::Sha256, MESSAGE, &sig, &other).is_err());
+            }
+
+            sig[100] ^= 1;
+            assert!(cross_check_openssl(&pub_key, HashAlg::Sha256, MESSAGE, &sig, &scheme).is_err());
+        }
+    }
+}
@@ -0,0 +1,397 @@
+//! `difftest` subcommand: randomized differential testing against the Noir circuit
+//!
+//! Every iteration draws a key size, hash, scheme and message, signs it, and
+//! optionally tampers with the result. The Rust side (the rsa crate and the
+//! limb-level reference) decides whether the vector should verify, and a
+//! scratch copy of the example project runs it through `nargo execute`.
+//! Disagreements are saved for reproduction and the run carries on.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+use num_bigint::BigUint;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha20Rng;
+use rsa::traits::PublicKeyParts;
+use rsa::RsaPrivateKey;
+use serde_json::json;
+use toml::Value;
+
+use noir_bignum_paramgen::split_into_120_bit_limbs;
+
+use crate::hash::HashAlg;
+use crate::keys::key_bits;
+use crate::params::{num_limbs, to_toml_string, KeyParams, SignatureParams, SignatureVector};
+use crate::reference::{self, CircuitVector};
+use crate::schema::Names;
+use crate::signing::{sign_digest, verify_digest, PssSalt, Scheme};
+
+/// Longest PSS salt the Noir verifier accepts (`MAX_SALT_LEN` in `src/rsa.nr`)
+const MAX_SALT_LEN: usize = 64;
+
+const KEY_SIZES: [usize; 2] = [1024, 2048];
+const HASHES: [HashAlg; 4] = [HashAlg::Sha1, HashAlg::Sha256, HashAlg::Sha384, HashAlg::Sha512];
+const EXPONENTS: [u32; 3] = [3, 17, 65537];
+
+pub struct Options {
+    pub iterations: usize,
+    pub seed: u64,
+    /// Fraction of vectors that are deliberately tampered with and must fail
+    pub tamper_fraction: f64,
+    pub example_dir: PathBuf,
+    pub failures_dir: PathBuf,
+    pub nargo: String,
+}
+
+/// Everything that changes the compiled circuit; vectors of the same shape share a project
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Shape {
+    pub bits: usize,
+    pub hash_alg: HashAlg,
+    /// `None` for PKCS#1 v1.5
+    pub salt_len: Option<usize>,
+    pub exponent: u32,
+}
+
+impl Shape {
+    pub fn name(&self) -> String {
+        let scheme = match self.salt_len {
+            Some(salt_len) => format!("pss{}", salt_len),
+            None => "pkcs1v15".to_string(),
+        };
+        format!("{}-{}-{}-e{}", self.bits, self.hash_alg.name(), scheme, self.exponent)
+    }
+}
+
+/// `src/main.nr` of the scratch project, with the same inputs as the example circuit
+pub fn circuit_source(shape: &Shape) -> String {
+    let (scheme, salt_arg) = match shape.salt_len {
+        Some(salt_len) => ("pss", format!(", {}", salt_len)),
+        None => ("pkcs1v15", String::new()),
+    };
+    let verify = format!("verify_{}_{}", shape.hash_alg.name(), scheme);
+    format!(
+        "use bignum::{{params::BigNumParams, RuntimeBigNum}};
+use noir_rsa::rsa::{verify};
+
+fn main(
+    hash: [u8; {hash_len}],
+    modulus_limbs: [u128; {n}],
+    redc_limbs: [u128; {n}],
+    signature_limbs: [u128; {n}],
+) {{
+    let params: BigNumParams<{n}, {bits}> = BigNumParams::new(false, modulus_limbs, redc_limbs);
+    let signature: RuntimeBigNum<{n}, {bits}> = RuntimeBigNum {{ params, limbs: signature_limbs }};
+    assert({verify}(hash, signature, {exponent}{salt_arg}));
+}}
+",
+        verify = verify,
+        hash_len = shape.hash_alg.output_len(),
+        n = num_limbs(shape.bits),
+        bits = shape.bits,
+        exponent = shape.exponent,
+        salt_arg = salt_arg
+    )
+}
+
+/// One generated vector and what the Rust side expects of it
+pub struct Vector {
+    pub shape: Shape,
+    pub message: Vec<u8>,
+    pub params: SignatureParams,
+    /// What was changed after signing, if anything
+    pub tampered: Option<String>,
+    pub rust_accepts: bool,
+    /// Set when the rsa crate and the limb-level reference disagree
+    pub rust_disagreement: Option<String>,
+}
+
+fn random_shape<R: Rng>(rng: &mut R) -> Shape {
+    let bits = KEY_SIZES[rng.gen_range(0..KEY_SIZES.len())];
+    let hash_alg = HASHES[rng.gen_range(0..HASHES.len())];
+    let salt_len = if rng.gen_bool(0.5) {
+        let max = MAX_SALT_LEN.min(bits / 8 - hash_alg.output_len() - 2);
+        Some(rng.gen_range(0..=max))
+    } else {
+        None
+    };
+    Shape {
+        bits,
+        hash_alg,
+        salt_len,
+        exponent: EXPONENTS[rng.gen_range(0..EXPONENTS.len())],
+    }
+}
+
+/// Draw the next vector; the whole sequence is determined by the RNG seed
+pub fn random_vector(rng: &mut ChaCha20Rng, tamper_fraction: f64) -> Vector {
+    let shape = random_shape(rng);
+    let priv_key = RsaPrivateKey::new_with_exp(&mut *rng, shape.bits, &BigUint::from(shape.exponent))
+        .expect("failed to generate a key");
+    let message: Vec<u8> = (0..rng.gen_range(0..64)).map(|_| rng.gen()).collect();
+    let scheme = match shape.salt_len {
+        Some(salt_len) => Scheme::Pss(PssSalt::Fixed((0..salt_len).map(|_| rng.gen()).collect())),
+        None => Scheme::Pkcs1v15,
+    };
+
+    let mut hash = shape.hash_alg.digest(&message);
+    let mut sig = sign_digest(rng, &priv_key, shape.hash_alg, &hash, &scheme).expect("failed to sign");
+
+    let tampered = if rng.gen_bool(tamper_fraction) {
+        if rng.gen_bool(0.5) {
+            let byte = rng.gen_range(0..hash.len());
+            hash[byte] ^= 1 << rng.gen_range(0..8);
+            Some(format!("flipped a bit of hash byte {}", byte))
+        } else {
+            // Skip the leading byte so the signature stays below the modulus
+            let byte = rng.gen_range(1..sig.len());
+            sig[byte] ^= 1 << rng.gen_range(0..8);
+            Some(format!("flipped a bit of signature byte {}", byte))
+        }
+    } else {
+        None
+    };
+
+    let bits = key_bits(&priv_key);
+    let pub_key = priv_key.to_public_key();
+    let key = KeyParams::from_modulus(pub_key.n(), bits);
+    let signature_limbs = split_into_120_bit_limbs(&BigUint::from_bytes_be(&sig), bits);
+
+    let crate_accepts = verify_digest(&pub_key, shape.hash_alg, &hash, &sig, &scheme).is_ok();
+    let reference_result = reference::verify(&CircuitVector {
+        bits,
+        modulus_limbs: &key.modulus_limbs,
+        redc_limbs: &key.redc_limbs,
+        signature_limbs: &signature_limbs,
+        exponent: pub_key.e(),
+        hash_alg: shape.hash_alg,
+        msg_hash: &hash,
+        scheme: &scheme,
+    });
+    let rust_disagreement = match (&reference_result, crate_accepts) {
+        (Ok(()), false) => Some("the reference accepts but the rsa crate rejects".to_string()),
+        (Err(divergence), true) => Some(format!("the rsa crate accepts but the reference rejects: {}", divergence)),
+        _ => None,
+    };
+
+    Vector {
+        shape,
+        message,
+        params: SignatureParams {
+            key,
+            signatures: vec![SignatureVector {
+                hash_alg: shape.hash_alg,
+                hash,
+                signature_limbs,
+                salt: None,
+            }],
+            message: None,
+            warnings: Vec::new(),
+        },
+        tampered,
+        rust_accepts: crate_accepts,
+        rust_disagreement,
+    }
+}
+
+/// Scratch copy of the example project, with the dependency on this repository made absolute
+pub struct Project {
+    dir: PathBuf,
+}
+
+impl Project {
+    pub fn create(example_dir: &Path, dir: PathBuf, shape: &Shape) -> Result<Project, String> {
+        let manifest_path = example_dir.join("Nargo.toml");
+        let manifest = fs::read_to_string(&manifest_path)
+            .map_err(|err| format!("Failed to read {}: {}", manifest_path.display(), err))?;
+        let mut manifest = match manifest.parse::<Value>() {
+            Ok(Value::Table(manifest)) => manifest,
+            _ => return Err(format!("Invalid manifest {}", manifest_path.display())),
+        };
+        let repo = example_dir
+            .join("..")
+            .canonicalize()
+            .map_err(|err| format!("Failed to resolve the repository root: {}", err))?;
+        let mut dependency = toml::value::Table::new();
+        dependency.insert("path".to_string(), Value::String(repo.display().to_string()));
+        if let Some(Value::Table(dependencies)) = manifest.get_mut("dependencies") {
+            dependencies.insert("noir_rsa".to_string(), Value::Table(dependency));
+        }
+
+        let write = |path: PathBuf, contents: String| {
+            fs::write(&path, contents).map_err(|err| format!("Failed to write {}: {}", path.display(), err))
+        };
+        fs::create_dir_all(dir.join("src")).map_err(|err| format!("Failed to create {}: {}", dir.display(), err))?;
+        write(dir.join("Nargo.toml"), to_toml_string(manifest))?;
+        write(dir.join("src").join("main.nr"), circuit_source(shape))?;
+        Ok(Project { dir })
+    }
+
+    pub fn execute(&self, nargo: &str, prover_toml: &str) -> Result<Output, String> {
+        fs::write(self.dir.join("Prover.toml"), prover_toml)
+            .map_err(|err| format!("Failed to write Prover.toml: {}", err))?;
+        Command::new(nargo)
+            .arg("execute")
+            .current_dir(&self.dir)
+            .output()
+            .map_err(|err| format!("Failed to run {}: {}", nargo, err))
+    }
+}
+
+fn save_failure(
+    options: &Options,
+    iteration: usize,
+    vector: &Vector,
+    project: &Project,
+    output: &Output,
+    reason: &str,
+) -> Result<PathBuf, String> {
+    let case_dir = options.failures_dir.join(format!("case-{}", iteration));
+    fs::create_dir_all(case_dir.join("src")).map_err(|err| format!("Failed to create {}: {}", case_dir.display(), err))?;
+    for file in ["Nargo.toml", "Prover.toml", "src/main.nr"] {
+        fs::copy(project.dir.join(file), case_dir.join(file)).map_err(|err| format!("Failed to copy {}: {}", file, err))?;
+    }
+
+    let details = json!({
+        "seed": options.seed,
+        "iteration": iteration,
+        "reason": reason,
+        "bits": vector.shape.bits,
+        "hash": vector.shape.hash_alg.name(),
+        "salt_len": vector.shape.salt_len,
+        "exponent": vector.shape.exponent,
+        "message_hex": hex::encode(&vector.message),
+        "tampered": vector.tampered,
+        "rust_accepts": vector.rust_accepts,
+        "nargo_status": output.status.code(),
+        "nargo_stdout": String::from_utf8_lossy(&output.stdout),
+        "nargo_stderr": String::from_utf8_lossy(&output.stderr),
+    });
+    fs::write(case_dir.join("vector.json"), serde_json::to_string_pretty(&details).unwrap())
+        .map_err(|err| format!("Failed to write vector.json: {}", err))?;
+    Ok(case_dir)
+}
+
+/// Run the differential test, returning whether every vector agreed
+pub fn difftest(options: &Options) -> Result<bool, String> {
+    match Command::new(&options.nargo).arg("--version").output() {
+        Ok(output) if output.status.success() => {}
+        _ => return Err(format!("difftest needs nargo, but `{} --version` failed", options.nargo)),
+    }
+
+    let scratch = tempfile::tempdir().map_err(|err| format!("Failed to create a scratch directory: {}", err))?;
+    let mut rng = ChaCha20Rng::seed_from_u64(options.seed);
+    let mut projects: HashMap<Shape, Project> = HashMap::new();
+    let (mut tampered, mut disagreements) = (0, 0);
+
+    for iteration in 0..options.iterations {
+        let vector = random_vector(&mut rng, options.tamper_fraction);
+        if !projects.contains_key(&vector.shape) {
+            let dir = scratch.path().join(vector.shape.name());
+            let project = Project::create(&options.example_dir, dir, &vector.shape)?;
+            projects.insert(vector.shape, project);
+        }
+        let project = &projects[&vector.shape];
+
+        let output = project.execute(&options.nargo, &vector.params.render_toml(Names::Canonical))?;
+        let nargo_accepts = output.status.success();
+        tampered += vector.tampered.is_some() as usize;
+
+        let reason = if let Some(reason) = &vector.rust_disagreement {
+            Some(reason.clone())
+        } else if nargo_accepts != vector.rust_accepts {
+            Some(format!(
+                "Rust {} the vector but nargo {} it",
+                if vector.rust_accepts { "accepts" } else { "rejects" },
+                if nargo_accepts { "accepts" } else { "rejects" }
+            ))
+        } else if vector.tampered.is_some() && vector.rust_accepts {
+            Some("a tampered vector was accepted".to_string())
+        } else {
+            None
+        };
+
+        match reason {
+            Some(reason) => {
+                disagreements += 1;
+                let case_dir = save_failure(options, iteration, &vector, project, &output, &reason)?;
+                println!(
+                    "[{}] {}: {}, saved to {}",
+                    iteration,
+                    vector.shape.name(),
+                    reason,
+                    case_dir.display()
+                );
+            }
+            None => println!(
+                "[{}] {}{}: ok",
+                iteration,
+                vector.shape.name(),
+                if vector.tampered.is_some() { " (tampered)" } else { "" }
+            ),
+        }
+    }
+
+    println!(
+        "{} vectors ({} tampered), {} shapes, {} disagreements",
+        options.iterations,
+        tampered,
+        projects.len(),
+        disagreements
+    );
+    Ok(disagreements == 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_circuit_source() {
+        let pkcs1 = circuit_source(&Shape {
+            bits: 2048,
+            hash_alg: HashAlg::Sha256,
+            salt_len: None,
+            exponent: 65537,
+        });
+        assert_eq!(
+            pkcs1,
+            include_str!("../../example/src/main.nr")
+                .lines()
+                .filter(|line| !line.starts_with("//"))
+                .map(|line| format!("{}\n", line))
+                .collect::<String>()
+        );
+
+        let pss = circuit_source(&Shape {
+            bits: 1024,
+            hash_alg: HashAlg::Sha512,
+            salt_len: Some(20),
+            exponent: 3,
+        });
+        assert!(pss.contains("use noir_rsa::rsa::verify_sha512_pss;"));
+        assert!(pss.contains("hash: [u8; 64],"));
+        assert!(pss.contains("modulus_limbs: [u128; 9],"));
+        assert!(pss.contains("BigNumParams<9, 1024>"));
+        assert!(pss.contains("assert(verify_sha512_pss(hash, signature, 3, 20));"));
+    }
+
+    #[test]
+    fn test_random_vectors_are_reproducible() {
+        let draw = |seed| {
+            let mut rng = ChaCha20Rng::seed_from_u64(seed);
+            (0..3)
+                .map(|_| {
+                    let vector = random_vector(&mut rng, 0.5);
+                    assert!(vector.rust_disagreement.is_none());
+                    assert_eq!(vector.rust_accepts, vector.tampered.is_none());
+                    (vector.shape, vector.params.render_toml(Names::Canonical), vector.tampered)
+                })
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(draw(7), draw(7));
+        assert_ne!(draw(7), draw(8));
+    }
+}
@@ -0,0 +1,794 @@
+//! `dkim-corpus`: synthetic emails covering the DKIM canonicalization matrix
+//!
+//! Each email is signed rsa-sha256 (RFC 6376) with one key and written as an
+//! `.eml` next to the inputs of a DKIM circuit: the signed header data as a
+//! BoundedVec in the zkemail shape, with a `[dkim]` table naming the case and
+//! the body hash. Content and timestamps are derived from the email index, so
+//! a corpus depends only on the key and `--count`. Every email is verified
+//! from its written bytes before the next one is generated. The same reader
+//! takes apart real emails for `sign --dkim`, with the key of a DNS record.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use base64ct::{Base64, Encoding};
+use rand::SeedableRng;
+use rand_chacha::ChaCha20Rng;
+use rsa::pkcs1::DecodeRsaPublicKey;
+use rsa::pkcs8::{DecodePublicKey, EncodePublicKey, LineEnding};
+use rsa::traits::PublicKeyParts;
+use rsa::{RsaPrivateKey, RsaPublicKey};
+use toml::value::Table;
+use toml::Value;
+
+use crate::hash::HashAlg;
+use crate::keys::{export_private_key, ExportFormat};
+use crate::params::{to_toml_string, MessageBytes};
+use crate::presets::Preset;
+use crate::schema::Names;
+use crate::signature_params_for_key;
+use crate::signing::{verify_digest, Scheme};
+
+/// Headers signed by every email, in `h=` order
+const SIGNED_HEADERS: &[&str] = &["from", "to", "subject", "date", "message-id"];
+
+/// First `t=` of a corpus, in seconds since the epoch
+const FIRST_TIMESTAMP: u64 = 1_700_000_000;
+
+/// The corpus's own listing of its emails and key, written next to them
+pub const MANIFEST: &str = "manifest.toml";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Canonicalization {
+    Simple,
+    Relaxed,
+}
+
+impl Canonicalization {
+    pub fn name(self) -> &'static str {
+        match self {
+            Canonicalization::Simple => "simple",
+            Canonicalization::Relaxed => "relaxed",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Canonicalization> {
+        match name {
+            "simple" => Some(Canonicalization::Simple),
+            "relaxed" => Some(Canonicalization::Relaxed),
+            _ => None,
+        }
+    }
+}
+
+/// One cell of the matrix: the `c=` pair and what the email does to stress it
+#[derive(Debug)]
+pub struct Case {
+    pub name: &'static str,
+    pub header: Canonicalization,
+    pub body: Canonicalization,
+    /// The subject and the DKIM-Signature itself are folded over several lines
+    pub folded: bool,
+    /// Header values and body lines end in spaces and tabs, and the body in empty lines
+    pub trailing_whitespace: bool,
+    /// Only the first half of the body is signed, with `l=`, and a footer is appended after it
+    pub body_length: bool,
+    /// The body is UTF-8 sent as 8bit
+    pub eight_bit: bool,
+}
+
+pub const CASES: &[Case] = &[
+    Case {
+        name: "simple-simple",
+        header: Canonicalization::Simple,
+        body: Canonicalization::Simple,
+        folded: false,
+        trailing_whitespace: false,
+        body_length: false,
+        eight_bit: false,
+    },
+    Case {
+        name: "relaxed-relaxed",
+        header: Canonicalization::Relaxed,
+        body: Canonicalization::Relaxed,
+        folded: false,
+        trailing_whitespace: false,
+        body_length: false,
+        eight_bit: false,
+    },
+    Case {
+        name: "relaxed-simple",
+        header: Canonicalization::Relaxed,
+        body: Canonicalization::Simple,
+        folded: false,
+        trailing_whitespace: false,
+        body_length: false,
+        eight_bit: false,
+    },
+    Case {
+        name: "simple-relaxed",
+        header: Canonicalization::Simple,
+        body: Canonicalization::Relaxed,
+        folded: false,
+        trailing_whitespace: false,
+        body_length: false,
+        eight_bit: false,
+    },
+    Case {
+        name: "folded-headers",
+        header: Canonicalization::Relaxed,
+        body: Canonicalization::Relaxed,
+        folded: true,
+        trailing_whitespace: false,
+        body_length: false,
+        eight_bit: false,
+    },
+    Case {
+        name: "folded-headers-simple",
+        header: Canonicalization::Simple,
+        body: Canonicalization::Simple,
+        folded: true,
+        trailing_whitespace: false,
+        body_length: false,
+        eight_bit: false,
+    },
+    Case {
+        name: "trailing-whitespace",
+        header: Canonicalization::Relaxed,
+        body: Canonicalization::Relaxed,
+        folded: false,
+        trailing_whitespace: true,
+        body_length: false,
+        eight_bit: false,
+    },
+    Case {
+        name: "body-length",
+        header: Canonicalization::Relaxed,
+        body: Canonicalization::Simple,
+        folded: false,
+        trailing_whitespace: false,
+        body_length: true,
+        eight_bit: false,
+    },
+    Case {
+        name: "eight-bit",
+        header: Canonicalization::Simple,
+        body: Canonicalization::Simple,
+        folded: false,
+        trailing_whitespace: false,
+        body_length: false,
+        eight_bit: true,
+    },
+];
+
+pub struct Options {
+    pub count: usize,
+    pub domain: String,
+    pub selector: String,
+    /// Capacity of the emitted header BoundedVec
+    pub max_header_len: usize,
+    pub output_dir: PathBuf,
+}
+
+fn is_wsp(c: u8) -> bool {
+    c == b' ' || c == b'\t'
+}
+
+/// Collapse runs of spaces and tabs into one space
+fn collapse_whitespace(bytes: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(bytes.len());
+    for &c in bytes {
+        if !is_wsp(c) {
+            out.push(c);
+        } else if out.last() != Some(&b' ') {
+            out.push(b' ');
+        }
+    }
+    out
+}
+
+fn trim_trailing_whitespace(bytes: &[u8]) -> &[u8] {
+    let end = bytes.iter().rposition(|&c| !is_wsp(c)).map_or(0, |i| i + 1);
+    &bytes[..end]
+}
+
+/// One header field as it is signed, CRLF included. `value` is everything after the colon,
+/// folding and all
+pub fn canonicalize_header(canonicalization: Canonicalization, name: &str, value: &[u8]) -> Vec<u8> {
+    let mut out = Vec::new();
+    match canonicalization {
+        Canonicalization::Simple => {
+            out.extend_from_slice(name.as_bytes());
+            out.push(b':');
+            out.extend_from_slice(value);
+        }
+        Canonicalization::Relaxed => {
+            let unfolded: Vec<u8> = value.iter().copied().filter(|&c| c != b'\r' && c != b'\n').collect();
+            let collapsed = collapse_whitespace(&unfolded);
+            let start = collapsed.iter().position(|&c| c != b' ').unwrap_or(collapsed.len());
+            out.extend_from_slice(name.trim_end().to_ascii_lowercase().as_bytes());
+            out.push(b':');
+            out.extend_from_slice(trim_trailing_whitespace(&collapsed[start..]));
+        }
+    }
+    out.extend_from_slice(b"\r\n");
+    out
+}
+
+/// The body as it is hashed, before any `l=` truncation
+pub fn canonicalize_body(canonicalization: Canonicalization, body: &[u8]) -> Vec<u8> {
+    let mut lines: Vec<Vec<u8>> = Vec::new();
+    let mut rest = body;
+    while !rest.is_empty() {
+        let (line, next) = match rest.windows(2).position(|pair| pair == b"\r\n") {
+            Some(i) => (&rest[..i], &rest[i + 2..]),
+            None => (rest, &[][..]),
+        };
+        lines.push(match canonicalization {
+            Canonicalization::Simple => line.to_vec(),
+            Canonicalization::Relaxed => trim_trailing_whitespace(&collapse_whitespace(line)).to_vec(),
+        });
+        rest = next;
+    }
+    while lines.last().map_or(false, Vec::is_empty) {
+        lines.pop();
+    }
+    if lines.is_empty() {
+        // An empty simple body is a single CRLF, an empty relaxed body is empty
+        return match canonicalization {
+            Canonicalization::Simple => b"\r\n".to_vec(),
+            Canonicalization::Relaxed => Vec::new(),
+        };
+    }
+    let mut out = Vec::new();
+    for line in lines {
+        out.extend_from_slice(&line);
+        out.extend_from_slice(b"\r\n");
+    }
+    out
+}
+
+/// Header fields (name and raw value) and body of an email with CRLF line endings
+struct Email {
+    headers: Vec<(String, Vec<u8>)>,
+    body: Vec<u8>,
+}
+
+impl Email {
+    fn parse(bytes: &[u8]) -> Result<Email, String> {
+        let mut headers: Vec<(String, Vec<u8>)> = Vec::new();
+        let mut rest = bytes;
+        loop {
+            let end = rest.windows(2).position(|pair| pair == b"\r\n").ok_or("the header has no CRLF end")?;
+            let (line, next) = (&rest[..end], &rest[end + 2..]);
+            rest = next;
+            if line.is_empty() {
+                break;
+            }
+            if is_wsp(line[0]) {
+                let (_, value) = headers.last_mut().ok_or("a continuation line before the first header")?;
+                value.extend_from_slice(b"\r\n");
+                value.extend_from_slice(line);
+                continue;
+            }
+            let colon = line.iter().position(|&c| c == b':').ok_or("a header line without a colon")?;
+            let name = String::from_utf8(line[..colon].to_vec()).map_err(|_| "a header name is not UTF-8")?;
+            headers.push((name, line[colon + 1..].to_vec()));
+        }
+        Ok(Email { headers, body: rest.to_vec() })
+    }
+
+    /// Name and value of the field named `name` that is `skip` instances from
+    /// the bottom, as DKIM signs header instances bottom up
+    fn header(&self, name: &str, skip: usize) -> Option<(&str, &[u8])> {
+        self.headers
+            .iter()
+            .rev()
+            .filter(|(header, _)| header.trim_end().eq_ignore_ascii_case(name))
+            .nth(skip)
+            .map(|(header, value)| (header.as_str(), value.as_slice()))
+    }
+}
+
+/// `tag=value` pairs of a DKIM-Signature, with whitespace removed from the values
+fn parse_tags(value: &[u8]) -> Result<Vec<(String, String)>, String> {
+    let value = String::from_utf8(value.to_vec()).map_err(|_| "the DKIM-Signature is not UTF-8")?;
+    value
+        .split(';')
+        .filter(|tag| !tag.trim().is_empty())
+        .map(|tag| match tag.split_once('=') {
+            Some((name, value)) => Ok((name.trim().to_string(), value.split_whitespace().collect())),
+            None => Err(format!("malformed DKIM-Signature tag {:?}", tag.trim())),
+        })
+        .collect()
+}
+
+/// The DKIM-Signature value with the value of its `b=` tag removed, as it is signed
+fn without_signature(value: &[u8]) -> Vec<u8> {
+    let tags: Vec<&[u8]> = value
+        .split(|&c| c == b';')
+        .map(|tag| match tag.iter().position(|&c| c == b'=') {
+            Some(eq) if tag[..eq].iter().filter(|c| !c.is_ascii_whitespace()).eq(b"b") => &tag[..=eq],
+            _ => tag,
+        })
+        .collect();
+    tags.join(&b';')
+}
+
+/// The header data an email's DKIM-Signature is made over, without the final CRLF.
+/// A name listed again in `h=` signs the next instance up, and nothing once
+/// there is none left, which is how signers guard against added headers.
+fn signed_data(email: &Email, names: &[&str], header: Canonicalization, dkim_signature: &[u8]) -> Vec<u8> {
+    let mut data = Vec::new();
+    for (i, name) in names.iter().enumerate() {
+        let skip = names[..i].iter().filter(|earlier| earlier.eq_ignore_ascii_case(name)).count();
+        if let Some((name, value)) = email.header(name, skip) {
+            data.extend_from_slice(&canonicalize_header(header, name, value));
+        }
+    }
+    let mut own = canonicalize_header(header, "DKIM-Signature", &without_signature(dkim_signature));
+    own.truncate(own.len() - 2);
+    data.extend_from_slice(&own);
+    data
+}
+
+/// `bytes` with every bare LF made a CRLF, as an email saved on Unix has lost the CRs it was signed with
+fn with_crlf(bytes: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(bytes.len());
+    for (i, &c) in bytes.iter().enumerate() {
+        if c == b'\n' && (i == 0 || bytes[i - 1] != b'\r') {
+            out.push(b'\r');
+        }
+        out.push(c);
+    }
+    out
+}
+
+/// What the DKIM-Signature of an email is made over, and the signature
+pub struct SignedHeaders {
+    /// The canonicalized header data, whose SHA-256 is signed
+    pub data: Vec<u8>,
+    /// The decoded `b=` value
+    pub signature: Vec<u8>,
+}
+
+/// The signed header data and signature of an email, once its body has been
+/// checked against `bh=`, so that a corrupted email is caught here rather than in-circuit
+pub fn signed_headers(eml: &[u8]) -> Result<SignedHeaders, String> {
+    let email = Email::parse(&with_crlf(eml))?;
+    let (_, dkim_signature) = email.header("DKIM-Signature", 0).ok_or("no DKIM-Signature header")?;
+    let tags = parse_tags(dkim_signature)?;
+    let tag = |name: &str| tags.iter().find(|(tag, _)| tag == name).map(|(_, value)| value.as_str());
+    if tag("a") != Some("rsa-sha256") {
+        return Err(format!("unsupported algorithm {:?}", tag("a")));
+    }
+    let c = tag("c").unwrap_or("simple/simple");
+    let (header, body) = c.split_once('/').unwrap_or((c, "simple"));
+    let (header, body) = match (Canonicalization::from_name(header), Canonicalization::from_name(body)) {
+        (Some(header), Some(body)) => (header, body),
+        _ => return Err(format!("unsupported canonicalization {}", c)),
+    };
+
+    let mut canonical_body = canonicalize_body(body, &email.body);
+    if let Some(length) = tag("l") {
+        let length: usize = length.parse().map_err(|_| format!("invalid l={}", length))?;
+        if length > canonical_body.len() {
+            return Err(format!("l={} is longer than the {} byte body", length, canonical_body.len()));
+        }
+        canonical_body.truncate(length);
+    }
+    let body_hash = Base64::encode_string(&HashAlg::Sha256.digest(&canonical_body));
+    if tag("bh") != Some(body_hash.as_str()) {
+        return Err(format!("body hash mismatch: bh={} but the body hashes to {}", tag("bh").unwrap_or(""), body_hash));
+    }
+
+    let names: Vec<&str> = tag("h").ok_or("no h= tag")?.split(':').collect();
+    let data = signed_data(&email, &names, header, dkim_signature);
+    let signature = Base64::decode_vec(tag("b").ok_or("no b= tag")?).map_err(|err| format!("invalid b=: {}", err))?;
+    Ok(SignedHeaders { data, signature })
+}
+
+/// Check the DKIM-Signature of an email, body hash included
+pub fn verify(eml: &[u8], pub_key: &RsaPublicKey) -> Result<(), String> {
+    let signed = signed_headers(eml)?;
+    let digest = HashAlg::Sha256.digest(&signed.data);
+    verify_digest(pub_key, HashAlg::Sha256, &digest, &signed.signature, &Scheme::Pkcs1v15)
+        .map_err(|err| format!("signature does not verify: {}", err))
+}
+
+/// The key of a DKIM DNS record, given as the record, as a line of `dig` output
+/// or a zone file holding it, or as the bare base64 of its `p=` tag
+pub fn parse_dns_key(record: &str) -> Result<RsaPublicKey, String> {
+    // A TXT record split into quoted strings is their concatenation
+    let text: String = if record.contains('"') {
+        record.split('"').skip(1).step_by(2).collect()
+    } else {
+        record.trim().to_string()
+    };
+    let p = if text.contains(';') || text.starts_with("p=") {
+        let tags = parse_tags(text.as_bytes())?;
+        let tag = |name: &str| tags.iter().find(|(tag, _)| tag == name).map(|(_, value)| value.as_str());
+        if let Some(k) = tag("k").filter(|&k| k != "rsa") {
+            return Err(format!("the DKIM key is k={}, not RSA", k));
+        }
+        tag("p").ok_or("the DKIM record has no p= tag")?.to_string()
+    } else {
+        text.split_whitespace().collect()
+    };
+    if p.is_empty() {
+        return Err("the DKIM key has been revoked, its p= tag is empty".to_string());
+    }
+    let der = Base64::decode_vec(&p).map_err(|err| format!("invalid p=: {}", err))?;
+    RsaPublicKey::from_public_key_der(&der)
+        .or_else(|_| RsaPublicKey::from_pkcs1_der(&der))
+        .map_err(|err| format!("p= is not an RSA public key: {}", err))
+}
+
+/// The p= value of a DKIM DNS record: the base64 SubjectPublicKeyInfo
+pub fn dns_record(pub_key: &RsaPublicKey) -> Result<String, String> {
+    let der = pub_key.to_public_key_der().map_err(|err| format!("Failed to encode the public key: {}", err))?;
+    Ok(format!("v=DKIM1; k=rsa; p={}", Base64::encode_string(der.as_bytes())))
+}
+
+/// One generated email, its circuit inputs and the tags describing it
+pub struct Generated {
+    pub eml: Vec<u8>,
+    pub inputs: String,
+    pub body_hash: String,
+    pub body_length: Option<usize>,
+}
+
+fn line_end(case: &Case) -> &'static str {
+    if case.trailing_whitespace {
+        " \t\r\n"
+    } else {
+        "\r\n"
+    }
+}
+
+fn headers(case: &Case, options: &Options, index: usize) -> Vec<(String, Vec<u8>)> {
+    let trailing = if case.trailing_whitespace { " \t" } else { "" };
+    let subject = if case.folded {
+        format!(" Corpus message {}\r\n\tabout the canonicalization\r\n  of folded headers{}", index, trailing)
+    } else {
+        format!(" Corpus message {}{}", index, trailing)
+    };
+    let date = format!(" Tue, 14 Nov 2023 22:{:02}:{:02} +0000{}", index / 60 % 60, index % 60, trailing);
+    let mut headers = vec![
+        ("From".to_string(), format!(" Alice <alice@{}>{}", options.domain, trailing)),
+        ("To".to_string(), format!(" Bob <bob@example.org>{}", trailing)),
+        ("Subject".to_string(), subject),
+        ("Date".to_string(), date),
+        ("Message-ID".to_string(), format!(" <corpus-{}@{}>", index, options.domain)),
+        ("MIME-Version".to_string(), " 1.0".to_string()),
+    ];
+    let content_type = if case.eight_bit {
+        vec![
+            ("Content-Type".to_string(), " text/plain; charset=utf-8".to_string()),
+            ("Content-Transfer-Encoding".to_string(), " 8bit".to_string()),
+        ]
+    } else {
+        vec![("Content-Type".to_string(), " text/plain; charset=us-ascii".to_string())]
+    };
+    headers.extend(content_type);
+    headers.into_iter().map(|(name, value)| (name, value.into_bytes())).collect()
+}
+
+fn body(case: &Case, index: usize) -> Vec<u8> {
+    let end = line_end(case);
+    let mut body = String::new();
+    for line in 0..3 + index % 4 {
+        if case.eight_bit {
+            body.push_str(&format!("Grüße, ligne {} — 署名 №{}{}", line, index, end));
+        } else {
+            body.push_str(&format!("Line {} of message {},  with  doubled   spaces{}", line, index, end));
+        }
+    }
+    if case.trailing_whitespace {
+        body.push_str("\r\n \r\n\t\r\n\r\n");
+    }
+    body.into_bytes()
+}
+
+/// Sign email `index` of the corpus with the case at that index
+pub fn generate(key: &RsaPrivateKey, options: &Options, index: usize) -> Result<Generated, String> {
+    let case = &CASES[index % CASES.len()];
+    let headers = headers(case, options, index);
+    let mut body = body(case, index);
+
+    let canonical_body = canonicalize_body(case.body, &body);
+    let body_length = if case.body_length { Some(canonical_body.len() / 2) } else { None };
+    let signed_body = &canonical_body[..body_length.unwrap_or(canonical_body.len())];
+    let body_hash = Base64::encode_string(&HashAlg::Sha256.digest(signed_body));
+    if case.body_length {
+        // Unsigned content after l= must not break the signature
+        body.extend_from_slice(b"-- \r\nUnsigned footer added in transit\r\n");
+    }
+
+    let separator = if case.folded { ";\r\n\t" } else { "; " };
+    let mut tags = vec![
+        "v=1".to_string(),
+        "a=rsa-sha256".to_string(),
+        format!("c={}/{}", case.header.name(), case.body.name()),
+        format!("d={}", options.domain),
+        format!("s={}", options.selector),
+        format!("t={}", FIRST_TIMESTAMP + index as u64),
+    ];
+    if let Some(length) = body_length {
+        tags.push(format!("l={}", length));
+    }
+    tags.push(format!("h={}", SIGNED_HEADERS.join(":")));
+    tags.push(format!("bh={}", body_hash));
+    tags.push("b=".to_string());
+    let unsigned = format!(" {}", tags.join(separator)).into_bytes();
+
+    let email = Email { headers: headers.clone(), body: Vec::new() };
+    let data = signed_data(&email, SIGNED_HEADERS, case.header, &unsigned);
+    if data.len() > options.max_header_len {
+        return Err(format!(
+            "{}: the signed header data is {} bytes, raise --max-header-len above {}",
+            case.name,
+            data.len(),
+            options.max_header_len
+        ));
+    }
+    // PKCS#1 v1.5 is deterministic, so the RNG is never drawn from
+    let mut rng = ChaCha20Rng::seed_from_u64(0);
+    let mut params = signature_params_for_key(&mut rng, key, &data, &[HashAlg::Sha256], &Scheme::Pkcs1v15)
+        .map_err(|err| err.message)?;
+    let signature = params.signatures[0].signature_bytes(key.size());
+    params.message = Some(MessageBytes { bytes: data, max_len: options.max_header_len });
+    let zkemail = Preset::from_name("zkemail")?;
+    zkemail.configure(&mut params.key);
+
+    let mut eml = Vec::new();
+    eml.extend_from_slice(b"DKIM-Signature:");
+    eml.extend_from_slice(&unsigned);
+    eml.extend_from_slice(Base64::encode_string(&signature).as_bytes());
+    eml.extend_from_slice(b"\r\n");
+    for (name, value) in &headers {
+        eml.extend_from_slice(name.as_bytes());
+        eml.push(b':');
+        eml.extend_from_slice(value);
+        eml.extend_from_slice(b"\r\n");
+    }
+    eml.extend_from_slice(b"\r\n");
+    eml.extend_from_slice(&body);
+
+    let mut dkim = Table::new();
+    dkim.insert("case".to_string(), Value::String(case.name.to_string()));
+    dkim.insert("canonicalization".to_string(), Value::String(format!("{}/{}", case.header.name(), case.body.name())));
+    dkim.insert("body_hash".to_string(), Value::String(body_hash.clone()));
+    if let Some(length) = body_length {
+        dkim.insert("body_length".to_string(), Value::Integer(length as i64));
+    }
+    let mut document = Table::new();
+    document.insert("dkim".to_string(), Value::Table(dkim));
+    let inputs = format!("{}\n{}", params.render_toml(Names::Preset(zkemail)), to_toml_string(document));
+
+    Ok(Generated { eml, inputs, body_hash, body_length })
+}
+
+fn write(path: &Path, contents: &[u8]) -> Result<(), String> {
+    fs::write(path, contents).map_err(|err| format!("Failed to write {}: {}", path.display(), err))
+}
+
+/// Write `options.count` emails, their inputs, the key and a manifest to `options.output_dir`,
+/// returning the paths written
+pub fn corpus(key: &RsaPrivateKey, options: &Options) -> Result<Vec<PathBuf>, String> {
+    let dir = &options.output_dir;
+    fs::create_dir_all(dir).map_err(|err| format!("Failed to create {}: {}", dir.display(), err))?;
+    let pub_key = key.to_public_key();
+    write(&dir.join("key.pem"), &export_private_key(key, ExportFormat::Pkcs8Pem)?)?;
+    let public_pem =
+        pub_key.to_public_key_pem(LineEnding::LF).map_err(|err| format!("Failed to encode the public key: {}", err))?;
+    write(&dir.join("key.pub.pem"), public_pem.as_bytes())?;
+    let record = dns_record(&pub_key)?;
+    let zone = format!("{}._domainkey.{} TXT \"{}\"\n", options.selector, options.domain, record);
+    write(&dir.join("dns.txt"), zone.as_bytes())?;
+    let mut written = vec!["key.pem".to_string(), "key.pub.pem".to_string(), "dns.txt".to_string()];
+
+    let mut emails = Vec::new();
+    for index in 0..options.count {
+        let case = &CASES[index % CASES.len()];
+        let generated = generate(key, options, index)?;
+        let stem = format!("{:04}-{}", index, case.name);
+        let eml_path = dir.join(format!("{}.eml", stem));
+        write(&eml_path, &generated.eml)?;
+        // Read back, so that what is checked is the file a consumer gets
+        let saved = fs::read(&eml_path).map_err(|err| format!("Failed to read {}: {}", eml_path.display(), err))?;
+        verify(&saved, &pub_key)
+            .map_err(|err| format!("{}: {} does not verify: {}", case.name, eml_path.display(), err))?;
+        write(&dir.join(format!("{}.toml", stem)), generated.inputs.as_bytes())?;
+        written.extend([format!("{}.eml", stem), format!("{}.toml", stem)]);
+
+        let mut entry = Table::new();
+        entry.insert("case".to_string(), Value::String(case.name.to_string()));
+        entry.insert("eml".to_string(), Value::String(format!("{}.eml", stem)));
+        entry.insert("inputs".to_string(), Value::String(format!("{}.toml", stem)));
+        let canonicalization = format!("{}/{}", case.header.name(), case.body.name());
+        entry.insert("canonicalization".to_string(), Value::String(canonicalization));
+        entry.insert("body_hash".to_string(), Value::String(generated.body_hash));
+        if let Some(length) = generated.body_length {
+            entry.insert("body_length".to_string(), Value::Integer(length as i64));
+        }
+        emails.push(Value::Table(entry));
+    }
+
+    let mut manifest = Table::new();
+    manifest.insert("domain".to_string(), Value::String(options.domain.clone()));
+    manifest.insert("selector".to_string(), Value::String(options.selector.clone()));
+    manifest.insert("bits".to_string(), Value::Integer(key.size() as i64 * 8));
+    manifest.insert("key".to_string(), Value::String("key.pem".to_string()));
+    manifest.insert("public_key".to_string(), Value::String("key.pub.pem".to_string()));
+    manifest.insert("dns_record".to_string(), Value::String(record));
+    manifest.insert("emails".to_string(), Value::Array(emails));
+    write(&dir.join(MANIFEST), to_toml_string(manifest).as_bytes())?;
+    written.push(MANIFEST.to_string());
+    println!("Wrote {} emails to {}", options.count, dir.display());
+    Ok(written.into_iter().map(|name| dir.join(name)).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, Instant};
+
+    use mail_auth::common::parse::TxtRecordParser;
+    use mail_auth::dkim::DomainKey;
+    use mail_auth::{AuthenticatedMessage, DkimResult, Resolver};
+    use rsa::pkcs8::DecodePrivateKey;
+
+    fn options(count: usize) -> Options {
+        Options {
+            count,
+            domain: "example.com".to_string(),
+            selector: "corpus".to_string(),
+            max_header_len: 1024,
+            output_dir: PathBuf::new(),
+        }
+    }
+
+    #[test]
+    fn test_canonicalization_examples_of_rfc_6376() {
+        // Section 3.4.6
+        let mut relaxed = canonicalize_header(Canonicalization::Relaxed, "A", b" X");
+        relaxed.extend(canonicalize_header(Canonicalization::Relaxed, "B ", b" Y\t\r\n\tZ  "));
+        assert_eq!(relaxed, b"a:X\r\nb:Y Z\r\n");
+        assert_eq!(canonicalize_header(Canonicalization::Simple, "B ", b" Y\t\r\n\tZ  "), b"B : Y\t\r\n\tZ  \r\n");
+
+        let body = b" C \r\nD \t E\r\n\r\n\r\n";
+        assert_eq!(canonicalize_body(Canonicalization::Relaxed, body), b" C\r\nD E\r\n");
+        assert_eq!(canonicalize_body(Canonicalization::Simple, body), b" C \r\nD \t E\r\n");
+        assert_eq!(canonicalize_body(Canonicalization::Simple, b""), b"\r\n");
+        assert_eq!(canonicalize_body(Canonicalization::Relaxed, b"\r\n"), b"");
+    }
+
+    #[test]
+    fn test_the_rsa_signature_of_rfc_8463_verifies() {
+        // Appendix A.3: made by another implementation, it oversigns From, Subject and Date
+        let eml = include_bytes!("../tests/fixtures/dkim_rfc8463.eml");
+        let pub_key = parse_dns_key(include_str!("../tests/fixtures/dkim_rfc8463_dns.txt")).unwrap();
+        assert_eq!(verify(eml, &pub_key), Ok(()));
+        let signed = signed_headers(eml).unwrap();
+        let data = String::from_utf8(signed.data).unwrap();
+        assert_eq!(data.matches("from:").count(), 1);
+        assert!(data.starts_with("from:Joe SixPack <joe@football.example.com>\r\nto:Suzie Q"), "{}", data);
+
+        let tampered = String::from_utf8_lossy(eml).replacen("Is dinner ready?", "Is dinner ready?!", 1);
+        assert!(verify(tampered.as_bytes(), &pub_key).is_err());
+    }
+
+    #[test]
+    fn test_every_case_verifies_and_tampering_is_caught() {
+        let key = RsaPrivateKey::from_pkcs8_pem(include_str!("../tests/fixtures/rsa_1024.pem")).unwrap();
+        let pub_key = key.to_public_key();
+        let options = options(CASES.len());
+        for index in 0..CASES.len() {
+            let generated = generate(&key, &options, index).unwrap();
+            assert_eq!(verify(&generated.eml, &pub_key), Ok(()), "{}", CASES[index].name);
+            let inputs: Value = generated.inputs.parse().unwrap();
+            assert_eq!(inputs["dkim"]["case"].as_str(), Some(CASES[index].name));
+            assert_eq!(inputs["header"]["storage"].as_array().unwrap().len(), 1024);
+
+            let tampered = String::from_utf8_lossy(&generated.eml).replacen("Corpus message", "Corpus massage", 1);
+            assert!(verify(tampered.as_bytes(), &pub_key).is_err(), "{}", CASES[index].name);
+        }
+
+        // Relaxed canonicalization survives whitespace changes in transit, simple does not
+        let relaxed = generate(&key, &options, 1).unwrap().eml;
+        let rewrapped = String::from_utf8(relaxed).unwrap().replacen("Subject: Corpus", "Subject:   Corpus", 1);
+        assert_eq!(verify(rewrapped.as_bytes(), &pub_key), Ok(()));
+        let simple = generate(&key, &options, 0).unwrap().eml;
+        let rewrapped = String::from_utf8(simple).unwrap().replacen("Subject: Corpus", "Subject:   Corpus", 1);
+        assert!(verify(rewrapped.as_bytes(), &pub_key).is_err());
+
+        // Content past l= is not signed
+        let truncated = generate(&key, &options, 7).unwrap();
+        assert!(truncated.body_length.is_some());
+        let footer = String::from_utf8(truncated.eml).unwrap().replace("in transit", "by a list server");
+        assert_eq!(verify(footer.as_bytes(), &pub_key), Ok(()));
+    }
+
+    #[tokio::test]
+    async fn test_every_case_verifies_with_mail_auth() {
+        // mail-auth parses and canonicalizes the email itself, with the key given as the DNS record
+        let key = RsaPrivateKey::from_pkcs8_pem(include_str!("../tests/fixtures/rsa_1024.pem")).unwrap();
+        let options = options(CASES.len());
+        let record = DomainKey::parse(dns_record(&key.to_public_key()).unwrap().as_bytes()).unwrap();
+        let resolver = Resolver::new_cloudflare_tls().unwrap();
+        let name = format!("{}._domainkey.{}.", options.selector, options.domain);
+        resolver.txt_add(name, record, Instant::now() + Duration::from_secs(3600));
+
+        for (index, case) in CASES.iter().enumerate() {
+            let eml = generate(&key, &options, index).unwrap().eml;
+            let outputs = resolver.verify_dkim(&AuthenticatedMessage::parse(&eml).unwrap()).await;
+            assert_eq!(outputs.len(), 1, "{}", case.name);
+            assert_eq!(outputs[0].result(), &DkimResult::Pass, "{}", case.name);
+
+            let tampered = String::from_utf8_lossy(&eml).replacen("Corpus message", "Corpus massage", 1);
+            let outputs = resolver.verify_dkim(&AuthenticatedMessage::parse(tampered.as_bytes()).unwrap()).await;
+            assert_ne!(outputs[0].result(), &DkimResult::Pass, "{}", case.name);
+        }
+    }
+
+    #[test]
+    fn test_corpus_writes_a_manifest() {
+        let key = RsaPrivateKey::from_pkcs8_pem(include_str!("../tests/fixtures/rsa_1024.pem")).unwrap();
+        let scratch = tempfile::tempdir().unwrap();
+        let options = Options { output_dir: scratch.path().to_path_buf(), ..options(3) };
+        corpus(&key, &options).unwrap();
+        let manifest: Value = fs::read_to_string(options.output_dir.join(MANIFEST)).unwrap().parse().unwrap();
+        let emails = manifest["emails"].as_array().unwrap();
+        assert_eq!(emails.len(), 3);
+        for email in emails {
+            let eml = fs::read(options.output_dir.join(email["eml"].as_str().unwrap())).unwrap();
+            assert_eq!(verify(&eml, &key.to_public_key()), Ok(()));
+            assert!(options.output_dir.join(email["inputs"].as_str().unwrap()).exists());
+        }
+    }
+
+    #[test]
+    fn test_signed_headers_and_dns_keys_of_real_world_shapes() {
+        let fixtures = [include_str!("../tests/fixtures/rsa_1024.pem"), include_str!("../tests/fixtures/rsa_2048.pem")];
+        for fixture in fixtures {
+            let key = RsaPrivateKey::from_pkcs8_pem(fixture).unwrap();
+            let pub_key = key.to_public_key();
+            let record = dns_record(&pub_key).unwrap();
+            let (head, p) = record.split_at(record.find("p=").unwrap() + 2);
+            let dig = format!(
+                "corpus._domainkey.example.com. 300 IN TXT \"{}\" \"{}\" \"{}\"",
+                head,
+                &p[..100],
+                &p[100..]
+            );
+            for given in [record.as_str(), p, dig.as_str()] {
+                assert_eq!(parse_dns_key(given), Ok(pub_key.clone()), "{}", given);
+            }
+
+            // Saved with Unix line endings, the email still verifies
+            let eml = generate(&key, &options(1), 1).unwrap().eml;
+            let unix = String::from_utf8(eml.clone()).unwrap().replace("\r\n", "\n");
+            let signed = signed_headers(unix.as_bytes()).unwrap();
+            assert_eq!(signed.signature.len(), pub_key.size());
+            assert_eq!(verify(unix.as_bytes(), &pub_key), Ok(()));
+
+            let corrupted = String::from_utf8(eml).unwrap().replacen("Line 0 of", "Line 9 of", 1);
+            let err = signed_headers(corrupted.as_bytes()).err().unwrap();
+            assert!(err.starts_with("body hash mismatch"), "{}", err);
+        }
+
+        // A message signed elsewhere, with its key as the zone file of RFC 8463 holds it
+        let eml = include_str!("../tests/fixtures/dkim_rfc8463.eml");
+        let pub_key = parse_dns_key(include_str!("../tests/fixtures/dkim_rfc8463_dns.txt")).unwrap();
+        assert_eq!(pub_key.size(), 128);
+        let crlf = eml.replace('\n', "\r\n");
+        assert_eq!(verify(crlf.as_bytes(), &pub_key), Ok(()));
+        assert_eq!(signed_headers(crlf.as_bytes()).unwrap().data, signed_headers(eml.as_bytes()).unwrap().data);
+        let err = signed_headers(eml.replacen("lost the game", "won the game", 1).as_bytes()).err().unwrap();
+        assert!(err.starts_with("body hash mismatch"), "{}", err);
+
+        assert!(parse_dns_key("v=DKIM1; k=rsa; p=").unwrap_err().contains("revoked"));
+        assert!(parse_dns_key("v=DKIM1; k=ed25519; p=AAAA").unwrap_err().contains("not RSA"));
+    }
+}
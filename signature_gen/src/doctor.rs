@@ -0,0 +1,324 @@
+//! `doctor` subcommand: checks that the installed nargo, the BigNum version the
+//! Noir library depends on and the code signature_gen emits are compatible
+
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+use semver::{Version, VersionReq};
+use toml::Value;
+
+use crate::templates::BignumVersion;
+
+/// Known BigNum release lines, the oldest nargo each one builds with and the
+/// template signature_gen must use to emit code for it
+struct BignumCompat {
+    /// Minor version of the `0.x` release line
+    minor: u64,
+    min_nargo: &'static str,
+    template: BignumVersion,
+}
+
+const COMPAT_TABLE: &[BignumCompat] = &[
+    BignumCompat { minor: 3, min_nargo: "0.34.0", template: BignumVersion::V03 },
+    BignumCompat { minor: 4, min_nargo: "0.36.0", template: BignumVersion::Current },
+    BignumCompat { minor: 5, min_nargo: "1.0.0-beta.0", template: BignumVersion::Current },
+    BignumCompat { minor: 6, min_nargo: "1.0.0-beta.1", template: BignumVersion::Current },
+    BignumCompat { minor: 7, min_nargo: "1.0.0-beta.3", template: BignumVersion::Current },
+    BignumCompat { minor: 8, min_nargo: "1.0.0-beta.3", template: BignumVersion::Current },
+    BignumCompat { minor: 9, min_nargo: "1.0.0-beta.3", template: BignumVersion::Current },
+    BignumCompat { minor: 10, min_nargo: "1.0.0-beta.3", template: BignumVersion::Current },
+];
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum Status {
+    Ok,
+    Warn,
+    Fail,
+}
+
+pub struct Check {
+    pub name: String,
+    pub status: Status,
+    pub detail: String,
+}
+
+impl Check {
+    pub fn new(name: &str, status: Status, detail: String) -> Check {
+        Check { name: name.to_string(), status, detail }
+    }
+}
+
+/// Dependencies and compiler requirement read from a Nargo.toml
+pub struct Manifest {
+    pub compiler_version: Option<String>,
+    pub bignum_tag: Option<String>,
+}
+
+pub fn parse_manifest(contents: &str) -> Result<Manifest, String> {
+    let value: Value = contents.parse().map_err(|err| format!("invalid TOML: {}", err))?;
+    let compiler_version = value
+        .get("package")
+        .and_then(|package| package.get("compiler_version"))
+        .and_then(|version| version.as_str())
+        .map(|version| version.to_string());
+    let bignum_tag = value
+        .get("dependencies")
+        .and_then(|dependencies| dependencies.get("bignum"))
+        .and_then(|bignum| bignum.get("tag"))
+        .and_then(|tag| tag.as_str())
+        .map(|tag| tag.to_string());
+    Ok(Manifest { compiler_version, bignum_tag })
+}
+
+/// Extract the version from the output of `nargo --version`, e.g. `nargo version = 1.0.0-beta.3`
+pub fn parse_nargo_version(output: &str) -> Option<Version> {
+    let line = output.lines().find(|line| line.trim_start().starts_with("nargo version"))?;
+    let version = line.split('=').nth(1)?.trim();
+    Version::parse(version).ok()
+}
+
+fn parse_tag(tag: &str) -> Option<Version> {
+    Version::parse(tag.trim_start_matches('v')).ok()
+}
+
+fn installed_nargo(nargo: &str) -> Option<Version> {
+    let output = Command::new(nargo).arg("--version").output().ok()?;
+    parse_nargo_version(&String::from_utf8_lossy(&output.stdout))
+}
+
+fn suggest_nargo(req: &str) -> String {
+    let version = req.trim_start_matches(|c: char| !c.is_ascii_digit());
+    format!("install a matching toolchain with `noirup -v {}`", version)
+}
+
+/// Run every check against a library manifest, an optional example manifest and the nargo version
+pub fn run_checks(
+    lib: &Manifest,
+    example: Option<&Manifest>,
+    nargo: Option<&Version>,
+    template: BignumVersion,
+) -> Vec<Check> {
+    let mut checks = Vec::new();
+
+    match nargo {
+        Some(version) => checks.push(Check::new("nargo", Status::Ok, format!("found nargo {}", version))),
+        None => checks.push(Check::new(
+            "nargo",
+            Status::Warn,
+            "nargo is not installed, skipping toolchain checks".to_string(),
+        )),
+    }
+
+    if let (Some(req), Some(version)) = (&lib.compiler_version, nargo) {
+        match VersionReq::parse(req) {
+            Ok(parsed) if parsed.matches(version) => checks.push(Check::new(
+                "compiler_version",
+                Status::Ok,
+                format!("nargo {} satisfies {}", version, req),
+            )),
+            Ok(_) => checks.push(Check::new(
+                "compiler_version",
+                Status::Fail,
+                format!("nargo {} does not satisfy {}, {}", version, req, suggest_nargo(req)),
+            )),
+            Err(err) => checks.push(Check::new(
+                "compiler_version",
+                Status::Warn,
+                format!("could not parse compiler_version {}: {}", req, err),
+            )),
+        }
+    }
+
+    let bignum = match lib.bignum_tag.as_deref().and_then(parse_tag) {
+        Some(bignum) => bignum,
+        None => {
+            checks.push(Check::new(
+                "bignum",
+                Status::Warn,
+                "no tagged bignum dependency found in the library manifest".to_string(),
+            ));
+            return checks;
+        }
+    };
+
+    match COMPAT_TABLE.iter().find(|compat| bignum.major == 0 && compat.minor == bignum.minor) {
+        Some(compat) => {
+            if let Some(version) = nargo {
+                let min_nargo = Version::parse(compat.min_nargo).unwrap();
+                if *version < min_nargo {
+                    checks.push(Check::new(
+                        "bignum",
+                        Status::Fail,
+                        format!(
+                            "bignum {} requires nargo >= {} but found {}, {}",
+                            bignum,
+                            min_nargo,
+                            version,
+                            suggest_nargo(compat.min_nargo)
+                        ),
+                    ));
+                } else {
+                    checks.push(Check::new(
+                        "bignum",
+                        Status::Ok,
+                        format!("bignum {} builds with nargo {}", bignum, version),
+                    ));
+                }
+            }
+
+            if compat.template == template {
+                checks.push(Check::new(
+                    "signature_gen",
+                    Status::Ok,
+                    format!("emitted code targets bignum {}", bignum),
+                ));
+            } else {
+                let suggested = match compat.template {
+                    BignumVersion::V03 => "0.3",
+                    BignumVersion::Current => "current",
+                };
+                checks.push(Check::new(
+                    "signature_gen",
+                    Status::Fail,
+                    format!(
+                        "emitted code does not match the bignum {} API, use --bignum-version {}",
+                        bignum, suggested
+                    ),
+                ));
+            }
+        }
+        None => checks.push(Check::new(
+            "bignum",
+            Status::Warn,
+            format!("bignum {} is not in the compatibility table of this signature_gen", bignum),
+        )),
+    }
+
+    if let Some(example_bignum) = example.and_then(|example| example.bignum_tag.as_deref()) {
+        if parse_tag(example_bignum).as_ref() == Some(&bignum) {
+            checks.push(Check::new(
+                "example",
+                Status::Ok,
+                format!("example uses the same bignum {}", bignum),
+            ));
+        } else {
+            checks.push(Check::new(
+                "example",
+                Status::Fail,
+                format!(
+                    "example depends on bignum {} but the library uses {}, bump the example to {}",
+                    example_bignum,
+                    bignum,
+                    lib.bignum_tag.as_deref().unwrap()
+                ),
+            ));
+        }
+    }
+
+    checks
+}
+
+fn read_manifest(path: &str) -> Result<Manifest, String> {
+    let contents = fs::read_to_string(path).map_err(|err| format!("{}: {}", path, err))?;
+    parse_manifest(&contents).map_err(|err| format!("{}: {}", path, err))
+}
+
+/// Print the doctor report and return whether every check passed
+pub fn doctor(lib_manifest: &str, example_manifest: &str, nargo: &str, template: BignumVersion) -> bool {
+    let lib = match read_manifest(lib_manifest) {
+        Ok(lib) => lib,
+        Err(err) => {
+            eprintln!("Failed to read the library manifest {}", err);
+            return false;
+        }
+    };
+    let example = if Path::new(example_manifest).exists() {
+        match read_manifest(example_manifest) {
+            Ok(example) => Some(example),
+            Err(err) => {
+                eprintln!("Failed to read the example manifest {}", err);
+                return false;
+            }
+        }
+    } else {
+        None
+    };
+    let nargo_version = installed_nargo(nargo);
+
+    let checks = run_checks(&lib, example.as_ref(), nargo_version.as_ref(), template);
+    for check in &checks {
+        let status = match check.status {
+            Status::Ok => "ok",
+            Status::Warn => "warn",
+            Status::Fail => "FAIL",
+        };
+        println!("[{:>4}] {}: {}", status, check.name, check.detail);
+    }
+
+    checks.iter().all(|check| check.status != Status::Fail)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn manifest(compiler_version: &str, bignum_tag: &str) -> Manifest {
+        Manifest {
+            compiler_version: Some(compiler_version.to_string()),
+            bignum_tag: Some(bignum_tag.to_string()),
+        }
+    }
+
+    fn failures(checks: &[Check]) -> Vec<&str> {
+        checks
+            .iter()
+            .filter(|check| check.status == Status::Fail)
+            .map(|check| check.name.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_repo_manifest() {
+        let lib = parse_manifest(include_str!("../../Nargo.toml")).unwrap();
+        assert_eq!(lib.compiler_version.as_deref(), Some(">=1.0.0"));
+        assert!(lib.bignum_tag.unwrap().starts_with('v'));
+    }
+
+    #[test]
+    fn test_parse_nargo_version() {
+        let output = "nargo version = 1.0.0-beta.3\nnoirc version = 1.0.0-beta.3+ceaa1986628197bd1170147f6a07f0f98d21030a\n";
+        assert_eq!(parse_nargo_version(output), Some(Version::parse("1.0.0-beta.3").unwrap()));
+        assert_eq!(parse_nargo_version("command not found"), None);
+    }
+
+    #[test]
+    fn test_compatible_toolchain_passes() {
+        let nargo = Version::parse("1.0.0").unwrap();
+        let checks = run_checks(&manifest(">=1.0.0", "v0.10.0"), None, Some(&nargo), BignumVersion::Current);
+        assert!(failures(&checks).is_empty());
+    }
+
+    #[test]
+    fn test_known_bad_combinations_fail() {
+        let beta = Version::parse("1.0.0-beta.3").unwrap();
+        let checks = run_checks(&manifest(">=1.0.0", "v0.10.0"), None, Some(&beta), BignumVersion::Current);
+        assert_eq!(failures(&checks), vec!["compiler_version"]);
+
+        let old = Version::parse("0.35.0").unwrap();
+        let checks = run_checks(&manifest(">=0.34.0", "v0.4.2"), None, Some(&old), BignumVersion::V03);
+        assert_eq!(failures(&checks), vec!["bignum", "signature_gen"]);
+
+        let example = manifest(">=1.0.0", "v0.7.0");
+        let nargo = Version::parse("1.0.0").unwrap();
+        let checks = run_checks(&manifest(">=1.0.0", "v0.10.0"), Some(&example), Some(&nargo), BignumVersion::Current);
+        assert_eq!(failures(&checks), vec!["example"]);
+    }
+
+    #[test]
+    fn test_missing_nargo_only_warns() {
+        let checks = run_checks(&manifest(">=1.0.0", "v0.10.0"), None, None, BignumVersion::Current);
+        assert!(failures(&checks).is_empty());
+        assert_eq!(checks[0].status, Status::Warn);
+    }
+}
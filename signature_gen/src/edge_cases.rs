@@ -0,0 +1,388 @@
+//! `edge-cases`: vectors near the representation boundaries of the BigNum arithmetic
+//!
+//! Keys are constructed prime by prime until the modulus has the required
+//! shape, and messages are varied until the signature integer has the required
+//! property. Every search is bounded by `max_attempts`. Each vector is labelled
+//! with the edge it exercises and whether the circuit must accept it.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use num_bigint::{BigUint, RandBigInt};
+use num_integer::Roots;
+use num_traits::ToPrimitive;
+use rand::SeedableRng;
+use rand_chacha::ChaCha20Rng;
+use rsa::traits::PublicKeyParts;
+use rsa::RsaPrivateKey;
+use toml::value::Table;
+use toml::Value;
+
+use noir_bignum_paramgen::split_into_120_bit_limbs;
+
+use crate::hash::HashAlg;
+use crate::params::{combine_limbs, num_limbs, to_toml_string, KeyParams, SignatureParams, SignatureVector};
+use crate::reference::exponentiate_counting;
+use crate::schema::Names;
+use crate::signing::{sign_digest, verify_digest, Scheme};
+
+/// Attempts between two progress lines
+const PROGRESS_INTERVAL: usize = 100;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EdgeCase {
+    /// Every bit of the modulus' most significant limb is set
+    TopLimbAllOnes,
+    /// The modulus is 2^(bits - 1) plus less than 2^(bits / 2 + 16)
+    ModulusAbovePowerOfTwo,
+    /// The signature is n - 1, which is never a valid signature
+    SignatureNMinusOne,
+    /// Some product in s^e mod n has a Barrett quotient estimate one too small,
+    /// so its remainder lands at or above n before the final subtraction
+    BarrettCorrection,
+}
+
+impl EdgeCase {
+    pub const ALL: [EdgeCase; 4] = [
+        EdgeCase::TopLimbAllOnes,
+        EdgeCase::ModulusAbovePowerOfTwo,
+        EdgeCase::SignatureNMinusOne,
+        EdgeCase::BarrettCorrection,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            EdgeCase::TopLimbAllOnes => "top-limb-all-ones",
+            EdgeCase::ModulusAbovePowerOfTwo => "modulus-above-power-of-two",
+            EdgeCase::SignatureNMinusOne => "signature-n-minus-1",
+            EdgeCase::BarrettCorrection => "barrett-correction",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<EdgeCase> {
+        EdgeCase::ALL.into_iter().find(|case| case.name() == name.trim())
+    }
+
+    pub fn description(self) -> &'static str {
+        match self {
+            EdgeCase::TopLimbAllOnes => "every bit of the most significant modulus limb is set",
+            EdgeCase::ModulusAbovePowerOfTwo => "the modulus is 2^(bits - 1) plus less than 2^(bits / 2 + 16)",
+            EdgeCase::SignatureNMinusOne => "the signature is n - 1, so s^e mod n = n - 1 for odd e",
+            EdgeCase::BarrettCorrection => {
+                "a product in s^e mod n has a Barrett quotient estimate floor(x * redc / 2^(2 * bits + 6)) \
+                 one too small, leaving a remainder of at least n"
+            }
+        }
+    }
+
+    /// Whether the vector holds a valid signature the circuit must accept
+    pub fn valid(self) -> bool {
+        self != EdgeCase::SignatureNMinusOne
+    }
+}
+
+/// Parse a comma-separated list of edge case names
+pub fn parse_edge_cases(list: &str) -> Result<Vec<EdgeCase>, String> {
+    list.split(',')
+        .map(|name| {
+            EdgeCase::from_name(name).ok_or_else(|| {
+                let names: Vec<&str> = EdgeCase::ALL.iter().map(|case| case.name()).collect();
+                format!("Unknown edge case `{}`, expected one of {}", name.trim(), names.join(", "))
+            })
+        })
+        .collect()
+}
+
+pub struct Options {
+    pub bits: usize,
+    pub exponent: u32,
+    pub hash_alg: HashAlg,
+    pub scheme: Scheme,
+    pub cases: Vec<EdgeCase>,
+    pub max_attempts: usize,
+    pub seed: u64,
+    pub output_dir: PathBuf,
+}
+
+pub struct Vector {
+    pub case: EdgeCase,
+    pub params: SignatureParams,
+    pub attempts: usize,
+}
+
+impl Vector {
+    /// Prover.toml inputs followed by an `[edge_case]` table describing the vector
+    pub fn render_toml(&self) -> String {
+        let mut label = Table::new();
+        label.insert("name".to_string(), Value::String(self.case.name().to_string()));
+        label.insert("description".to_string(), Value::String(self.case.description().to_string()));
+        let expect = if self.case.valid() { "accept" } else { "reject" };
+        label.insert("expect".to_string(), Value::String(expect.to_string()));
+        label.insert("attempts".to_string(), Value::Integer(self.attempts as i64));
+        let mut table = Table::new();
+        table.insert("edge_case".to_string(), Value::Table(label));
+        format!("{}\n{}", self.params.render_toml(Names::Canonical), to_toml_string(table))
+    }
+}
+
+/// Counts attempts against the bound and reports progress on stderr
+struct Search {
+    case: EdgeCase,
+    attempts: usize,
+    max_attempts: usize,
+}
+
+impl Search {
+    fn next(&mut self) -> Result<usize, String> {
+        if self.attempts == self.max_attempts {
+            return Err(format!(
+                "{}: no match after {} attempts, raise --max-attempts",
+                self.case.name(),
+                self.max_attempts
+            ));
+        }
+        self.attempts += 1;
+        if self.attempts % PROGRESS_INTERVAL == 0 {
+            eprintln!("{}: {} attempts", self.case.name(), self.attempts);
+        }
+        Ok(self.attempts)
+    }
+}
+
+/// Build a key whose modulus lies in `[low, high)` by choosing p just above
+/// sqrt(low) and q as the next prime above low / p
+fn key_in_range(
+    rng: &mut ChaCha20Rng,
+    search: &mut Search,
+    bits: usize,
+    exponent: u32,
+    low: &BigUint,
+    high: &BigUint,
+) -> Result<RsaPrivateKey, String> {
+    let e = BigUint::from(exponent);
+    let root = low.sqrt();
+    let spread = std::cmp::max((high.sqrt() - &root) / 4u32, BigUint::from(1u32));
+    loop {
+        search.next()?;
+        let p = num_bigint::prime::next_prime(&(&root + rng.gen_biguint_below(&spread)));
+        let q = num_bigint::prime::next_prime(&((low + &p - 1u32) / &p));
+        let n = &p * &q;
+        if p == q || n < *low || n >= *high || n.bits() != bits {
+            continue;
+        }
+        // Primes with gcd(e, p - 1) != 1 have no private exponent
+        if let Ok(key) = RsaPrivateKey::from_p_q(p, q, e.clone()) {
+            return Ok(key);
+        }
+    }
+}
+
+/// Whether some product of the circuit's computation of s^e mod n, reduced
+/// as `reference::exponentiate` does, needs a corrective subtraction after
+/// the Barrett quotient estimate
+pub fn needs_barrett_correction(signature: &BigUint, exponent: &BigUint, key: &KeyParams) -> bool {
+    let modulus = combine_limbs(&key.modulus_limbs);
+    let redc = combine_limbs(&key.redc_limbs);
+    match exponent.to_u32().map(|exponent| exponentiate_counting(signature, exponent, &modulus, &redc)) {
+        Some(Ok((_, corrections))) => corrections > 0,
+        _ => false,
+    }
+}
+
+fn signature_params(key: &RsaPrivateKey, hash_alg: HashAlg, hash: Vec<u8>, signature: &BigUint) -> SignatureParams {
+    let bits = key.size() * 8;
+    SignatureParams {
+        key: KeyParams::from_modulus(key.n(), bits),
+        signatures: vec![SignatureVector {
+            hash_alg,
+            hash,
+            signature_limbs: split_into_120_bit_limbs(signature, bits),
+            salt: None,
+        }],
+        message: None,
+        warnings: Vec::new(),
+    }
+}
+
+fn sign(rng: &mut ChaCha20Rng, options: &Options, key: &RsaPrivateKey, attempt: usize) -> (Vec<u8>, BigUint) {
+    let message = format!("noir_rsa edge case {}", attempt);
+    let hash = options.hash_alg.digest(message.as_bytes());
+    let signature = sign_digest(rng, key, options.hash_alg, &hash, &options.scheme).expect("failed to sign");
+    (hash, BigUint::from_bytes_be(&signature))
+}
+
+/// Construct or search for a vector hitting `case`
+pub fn find(rng: &mut ChaCha20Rng, options: &Options, case: EdgeCase) -> Result<Vector, String> {
+    let bits = options.bits;
+    let mut search = Search {
+        case,
+        attempts: 0,
+        max_attempts: options.max_attempts,
+    };
+    let generate = |rng: &mut ChaCha20Rng| {
+        RsaPrivateKey::new_with_exp(rng, bits, &BigUint::from(options.exponent)).expect("failed to generate a key")
+    };
+
+    let params = match case {
+        EdgeCase::TopLimbAllOnes | EdgeCase::ModulusAbovePowerOfTwo => {
+            let (low, high) = if case == EdgeCase::TopLimbAllOnes {
+                let top_limb_bits = bits - 120 * (num_limbs(bits) - 1);
+                let high = BigUint::from(1u32) << bits;
+                (&high - (BigUint::from(1u32) << (bits - top_limb_bits)), high)
+            } else {
+                let low = BigUint::from(1u32) << (bits - 1);
+                let high = &low + (BigUint::from(1u32) << (bits / 2 + 16));
+                (low, high)
+            };
+            let key = key_in_range(rng, &mut search, bits, options.exponent, &low, &high)?;
+            let (hash, signature) = sign(rng, options, &key, 0);
+            signature_params(&key, options.hash_alg, hash, &signature)
+        }
+        EdgeCase::SignatureNMinusOne => {
+            search.next()?;
+            let key = generate(rng);
+            let (hash, _) = sign(rng, options, &key, 0);
+            signature_params(&key, options.hash_alg, hash, &(key.n() - 1u32))
+        }
+        EdgeCase::BarrettCorrection => {
+            // The estimate falls short with a probability proportional to how far
+            // redc was rounded down, so take a key where that is at least 1/2
+            let shift = 2 * bits + 6;
+            let key = loop {
+                search.next()?;
+                let key = generate(rng);
+                let key_params = KeyParams::from_modulus(key.n(), bits);
+                let rounding = (BigUint::from(1u32) << shift) - combine_limbs(&key_params.redc_limbs) * key.n();
+                if rounding * 2u32 >= *key.n() {
+                    break key;
+                }
+            };
+            let key_params = KeyParams::from_modulus(key.n(), bits);
+            loop {
+                let attempt = search.next()?;
+                let (hash, signature) = sign(rng, options, &key, attempt);
+                if needs_barrett_correction(&signature, key.e(), &key_params) {
+                    break signature_params(&key, options.hash_alg, hash, &signature);
+                }
+            }
+        }
+    };
+
+    // Constructed vectors must still verify exactly when they are meant to
+    let key = &params.key;
+    let signature = &params.signatures[0];
+    let pub_key = rsa::RsaPublicKey::new(combine_limbs(&key.modulus_limbs), BigUint::from(options.exponent))
+        .map_err(|err| format!("{}: invalid key: {}", case.name(), err))?;
+    let verifies = verify_digest(
+        &pub_key,
+        options.hash_alg,
+        &signature.hash,
+        &signature.signature_bytes(pub_key.size()),
+        &options.scheme,
+    )
+    .is_ok();
+    if verifies != case.valid() {
+        return Err(format!("{}: constructed vector does not verify as expected", case.name()));
+    }
+
+    Ok(Vector {
+        case,
+        params,
+        attempts: search.attempts,
+    })
+}
+
+fn write(dir: &Path, vector: &Vector) -> Result<PathBuf, String> {
+    let path = dir.join(format!("{}.toml", vector.case.name()));
+    fs::write(&path, vector.render_toml()).map_err(|err| format!("Failed to write {}: {}", path.display(), err))?;
+    Ok(path)
+}
+
+/// Find every requested edge case and write one TOML file per case, returning their paths
+pub fn edge_cases(options: &Options) -> Result<Vec<PathBuf>, String> {
+    fs::create_dir_all(&options.output_dir)
+        .map_err(|err| format!("Failed to create {}: {}", options.output_dir.display(), err))?;
+    let mut rng = ChaCha20Rng::seed_from_u64(options.seed);
+    let mut paths = Vec::new();
+    for &case in &options.cases {
+        let vector = find(&mut rng, options, case)?;
+        let path = write(&options.output_dir, &vector)?;
+        println!(
+            "{}: found after {} attempts, expect {}, saved to {}",
+            case.name(),
+            vector.attempts,
+            if case.valid() { "accept" } else { "reject" },
+            path.display()
+        );
+        paths.push(path);
+    }
+    Ok(paths)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::signing::PssSalt;
+
+    fn options(bits: usize, scheme: Scheme) -> Options {
+        Options {
+            bits,
+            exponent: 65537,
+            hash_alg: HashAlg::Sha256,
+            scheme,
+            cases: EdgeCase::ALL.to_vec(),
+            max_attempts: 10_000,
+            seed: 3,
+            output_dir: PathBuf::new(),
+        }
+    }
+
+    #[test]
+    fn test_edge_cases_have_their_shape() {
+        let options = options(1024, Scheme::Pkcs1v15);
+        let mut rng = ChaCha20Rng::seed_from_u64(options.seed);
+
+        let vector = find(&mut rng, &options, EdgeCase::TopLimbAllOnes).unwrap();
+        let top_limb = vector.params.key.modulus_limbs.last().unwrap();
+        assert_eq!(*top_limb, (BigUint::from(1u32) << 64) - 1u32);
+
+        let vector = find(&mut rng, &options, EdgeCase::ModulusAbovePowerOfTwo).unwrap();
+        let excess = combine_limbs(&vector.params.key.modulus_limbs) - (BigUint::from(1u32) << 1023);
+        assert!(excess.bits() <= 1024 / 2 + 16);
+
+        let vector = find(&mut rng, &options, EdgeCase::SignatureNMinusOne).unwrap();
+        let key = &vector.params.key;
+        assert_eq!(combine_limbs(&vector.params.signatures[0].signature_limbs) + 1u32, combine_limbs(&key.modulus_limbs));
+        assert!(vector.render_toml().contains("expect = \"reject\""));
+    }
+
+    #[test]
+    fn test_barrett_correction_search() {
+        let options = options(1024, Scheme::Pss(PssSalt::Random(32)));
+        let mut rng = ChaCha20Rng::seed_from_u64(options.seed);
+        let vector = find(&mut rng, &options, EdgeCase::BarrettCorrection).unwrap();
+        let signature = combine_limbs(&vector.params.signatures[0].signature_limbs);
+        assert!(needs_barrett_correction(&signature, &BigUint::from(65537u32), &vector.params.key));
+        // The circuit's chain needs the correction and still reaches s^e mod n
+        let modulus = combine_limbs(&vector.params.key.modulus_limbs);
+        let redc = combine_limbs(&vector.params.key.redc_limbs);
+        let (result, corrections) = exponentiate_counting(&signature, 65537, &modulus, &redc).unwrap();
+        assert!(corrections >= 1);
+        assert_eq!(result, signature.modpow(&BigUint::from(65537u32), &modulus));
+
+        let parsed: Value = vector.render_toml().parse().unwrap();
+        assert_eq!(parsed["edge_case"]["name"].as_str(), Some("barrett-correction"));
+        assert_eq!(parsed["edge_case"]["expect"].as_str(), Some("accept"));
+    }
+
+    #[test]
+    fn test_search_is_bounded() {
+        let mut options = options(1024, Scheme::Pkcs1v15);
+        options.max_attempts = 0;
+        let mut rng = ChaCha20Rng::seed_from_u64(options.seed);
+        let err = find(&mut rng, &options, EdgeCase::BarrettCorrection).err().unwrap();
+        assert!(err.contains("--max-attempts"), "{}", err);
+        assert!(parse_edge_cases("top-limb-all-ones, bogus").is_err());
+        assert_eq!(parse_edge_cases("signature-n-minus-1").unwrap(), [EdgeCase::SignatureNMinusOne]);
+    }
+}
@@ -0,0 +1,128 @@
+//! Failures of the CLI, carried with their context until they are reported
+//!
+//! With `--error-format json` a failure is printed to stderr as one JSON
+//! object, `{"code": ..., "message": ..., <context>...}`, instead of the
+//! message alone. Codes are stable, and each has its own exit status so that
+//! callers can tell the classes apart without reading stderr at all.
+
+use std::sync::OnceLock;
+
+use serde_json::{Map, Value};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Code {
+    /// Anything not classified below
+    Failed,
+    /// An argument is invalid or contradicts another one
+    InvalidArgument,
+    /// A key file or variable cannot be read or parsed
+    BadKeyFile,
+    /// A key does not fit the circuit: its size or exponent differ
+    KeyMismatch,
+    /// A signature or a consistency check of the output failed
+    VerificationFailed,
+}
+
+impl Code {
+    pub const ALL: [Code; 5] =
+        [Code::Failed, Code::InvalidArgument, Code::BadKeyFile, Code::KeyMismatch, Code::VerificationFailed];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Code::Failed => "failed",
+            Code::InvalidArgument => "invalid_argument",
+            Code::BadKeyFile => "bad_key_file",
+            Code::KeyMismatch => "key_mismatch",
+            Code::VerificationFailed => "verification_failed",
+        }
+    }
+
+    pub fn exit_code(self) -> i32 {
+        match self {
+            Code::Failed => 1,
+            Code::InvalidArgument => 2,
+            Code::BadKeyFile => 3,
+            Code::KeyMismatch => 4,
+            Code::VerificationFailed => 5,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Error {
+    pub code: Code,
+    pub message: String,
+    /// Fields reported next to the message, such as `path` or `expected_bits`
+    pub context: Vec<(&'static str, Value)>,
+}
+
+impl Error {
+    pub fn new(code: Code, message: impl Into<String>) -> Error {
+        Error { code, message: message.into(), context: Vec::new() }
+    }
+
+    pub fn with(mut self, name: &'static str, value: impl Into<Value>) -> Error {
+        self.context.push((name, value.into()));
+        self
+    }
+
+    pub fn to_json(&self) -> Value {
+        let mut object = Map::new();
+        object.insert("code".to_string(), Value::String(self.code.name().to_string()));
+        object.insert("message".to_string(), Value::String(self.message.clone()));
+        for (name, value) in &self.context {
+            object.insert(name.to_string(), value.clone());
+        }
+        Value::Object(object)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ErrorFormat {
+    Text,
+    Json,
+}
+
+static FORMAT: OnceLock<ErrorFormat> = OnceLock::new();
+
+/// Choose how `fail` reports errors, once at startup
+pub fn set_format(format: ErrorFormat) {
+    FORMAT.set(format).expect("the error format is set twice");
+}
+
+/// Report `error` on stderr and exit with the status of its code
+pub fn fail(error: Error) -> ! {
+    match FORMAT.get().copied().unwrap_or(ErrorFormat::Text) {
+        ErrorFormat::Text => eprintln!("{}", error.message),
+        ErrorFormat::Json => eprintln!("{}", error.to_json()),
+    }
+    std::process::exit(error.code.exit_code())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeSet;
+
+    #[test]
+    fn test_json_carries_code_message_and_context() {
+        let error = Error::new(Code::KeyMismatch, "The key is 1024-bit")
+            .with("expected_bits", 2048)
+            .with("actual_bits", 1024);
+        assert_eq!(
+            error.to_json(),
+            serde_json::json!({
+                "code": "key_mismatch",
+                "message": "The key is 1024-bit",
+                "expected_bits": 2048,
+                "actual_bits": 1024,
+            })
+        );
+
+        // Every code exits with a status of its own
+        let exit_codes: BTreeSet<i32> = Code::ALL.iter().map(|code| code.exit_code()).collect();
+        assert_eq!(exit_codes.len(), Code::ALL.len());
+        let names: BTreeSet<&str> = Code::ALL.iter().map(|code| code.name()).collect();
+        assert_eq!(names.len(), Code::ALL.len());
+    }
+}
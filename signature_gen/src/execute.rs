@@ -0,0 +1,106 @@
+//! `--execute-artifact`: run generated inputs through a compiled circuit in-process
+//!
+//! The ACIR program and ABI are read from the JSON artifact `nargo compile`
+//! writes to `target/`, the Prover.toml inputs are encoded into the initial
+//! witness with the ABI, and the program is solved with the linked ACVM. This
+//! answers "does this input satisfy the circuit" without a nargo binary.
+//! Only built with the `acvm` feature.
+
+use std::fs;
+use std::path::Path;
+
+use acvm::FieldElement;
+use bn254_blackbox_solver::Bn254BlackBoxSolver;
+use nargo::errors::{ExecutionError, NargoError};
+use nargo::foreign_calls::DefaultForeignCallBuilder;
+use noirc_abi::input_parser::Format;
+use noirc_artifacts::program::ProgramArtifact;
+use semver::Version;
+
+/// Noir release the acvm, nargo and noirc crates are pinned to in Cargo.toml
+pub const LINKED_NOIR_VERSION: &str = "1.0.0-beta.3";
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum Execution {
+    Satisfied,
+    Failed {
+        /// `<function>:<opcode>` of the opcode that could not be solved, when known
+        opcode: Option<String>,
+        message: String,
+    },
+}
+
+/// Fail unless the artifact was compiled by the Noir release whose ACVM is linked;
+/// build metadata such as the commit hash is ignored
+pub fn check_version(artifact_version: &str, linked_version: &str) -> Result<(), String> {
+    let release = |version: &str| Version::parse(version.split('+').next().unwrap_or(version)).ok();
+    match (release(artifact_version), release(linked_version)) {
+        (Some(artifact), Some(linked)) if artifact == linked => Ok(()),
+        _ => Err(format!(
+            "Artifact was compiled by Noir {} but signature_gen links the ACVM of Noir {}; \
+             recompile the circuit with nargo {} or rebuild signature_gen against Noir {}",
+            artifact_version, linked_version, linked_version, artifact_version
+        )),
+    }
+}
+
+fn load_artifact(path: &Path) -> Result<ProgramArtifact, String> {
+    let json = fs::read_to_string(path).map_err(|err| format!("Failed to read {}: {}", path.display(), err))?;
+    // Read the version on its own first, the bytecode encoding changes between releases
+    let raw: serde_json::Value =
+        serde_json::from_str(&json).map_err(|err| format!("{} is not a JSON artifact: {}", path.display(), err))?;
+    let noir_version = raw["noir_version"]
+        .as_str()
+        .ok_or_else(|| format!("{} has no noir_version, is it a nargo compile artifact?", path.display()))?;
+    check_version(noir_version, LINKED_NOIR_VERSION)?;
+    serde_json::from_value(raw).map_err(|err| format!("Failed to decode the circuit in {}: {}", path.display(), err))
+}
+
+fn failing_opcode(err: &NargoError<FieldElement>) -> Option<String> {
+    let call_stack = match err {
+        NargoError::ExecutionError(ExecutionError::AssertionFailed(_, call_stack, _)) => call_stack,
+        NargoError::ExecutionError(ExecutionError::SolvingError(_, Some(call_stack))) => call_stack,
+        _ => return None,
+    };
+    call_stack
+        .last()
+        .map(|location| format!("{}:{}", location.acir_function_index, location.opcode_location))
+}
+
+/// Execute the circuit of the artifact at `path` on the Prover.toml `inputs`
+pub fn execute(path: &Path, inputs: &str) -> Result<Execution, String> {
+    let artifact = load_artifact(path)?;
+    let input_map = Format::Toml
+        .parse(inputs, &artifact.abi)
+        .map_err(|err| format!("Inputs do not match the ABI of {}: {}", path.display(), err))?;
+    let initial_witness = artifact
+        .abi
+        .encode(&input_map, None)
+        .map_err(|err| format!("Failed to encode the inputs for {}: {}", path.display(), err))?;
+
+    let mut foreign_calls = DefaultForeignCallBuilder::default().build();
+    match nargo::ops::execute_program(
+        &artifact.bytecode,
+        initial_witness,
+        &Bn254BlackBoxSolver(false),
+        &mut foreign_calls,
+    ) {
+        Ok(_) => Ok(Execution::Satisfied),
+        Err(err) => Ok(Execution::Failed { opcode: failing_opcode(&err), message: err.to_string() }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_version_skew_names_both_versions() {
+        assert!(check_version("1.0.0-beta.3+ceaa1986628197bd1170147f6a07f0f98d21030a", "1.0.0-beta.3").is_ok());
+
+        let err = check_version("1.0.0-beta.5+0123abcd", "1.0.0-beta.3").unwrap_err();
+        assert!(err.contains("Noir 1.0.0-beta.5+0123abcd"), "{}", err);
+        assert!(err.contains("Noir 1.0.0-beta.3"), "{}", err);
+        assert!(check_version("not a version", "1.0.0-beta.3").is_err());
+    }
+}
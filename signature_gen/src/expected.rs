@@ -0,0 +1,167 @@
+//! The `[expected]` table: values a circuit with public outputs should produce
+//!
+//! A prover harness asserts its public outputs against this table instead of
+//! re-deriving them: `verified`, whether the circuit must accept the inputs,
+//! and the pubkey commitments that were requested. With `[[keys]]` output the
+//! commitments are arrays in key order. `check` recomputes every value from
+//! the inputs of the same document, the commitments only of a vector the
+//! circuit must accept, since a rejected one has no public outputs.
+
+use toml::value::Table;
+use toml::Value;
+
+use noir_bignum_paramgen::split_into_120_bit_limbs;
+
+use crate::commitment::{pubkey_hash, pubkey_poseidon2, PubkeyHash};
+use crate::convert::parse_limbs;
+use crate::params::{bytes_value, to_toml_string, SignatureParams};
+use crate::schema::{MODULUS_LIMBS, PUBKEY_HASH, PUBKEY_POSEIDON};
+
+/// Name of the table in TOML output
+pub const EXPECTED: &str = "expected";
+pub const VERIFIED: &str = "verified";
+
+/// Top-level label of invalid vectors, as read by `run-negative`
+const EXPECTED_VALID: &str = "expected_valid";
+
+pub fn toml_table(entries: &[SignatureParams], verified: bool) -> Table {
+    let mut table = Table::new();
+    table.insert(VERIFIED.to_string(), Value::Boolean(verified));
+    let hashes: Vec<Value> =
+        entries.iter().filter_map(|entry| entry.key.pubkey_hash.as_deref().map(bytes_value)).collect();
+    let poseidons: Vec<Value> = entries
+        .iter()
+        .filter_map(|entry| entry.key.pubkey_poseidon.as_ref().map(|commitment| Value::String(commitment.to_string())))
+        .collect();
+    for (name, mut values) in [(PUBKEY_HASH, hashes), (PUBKEY_POSEIDON, poseidons)] {
+        match values.len() {
+            0 => {}
+            1 if entries.len() == 1 => {
+                table.insert(name.to_string(), values.pop().unwrap());
+            }
+            _ => {
+                table.insert(name.to_string(), Value::Array(values));
+            }
+        }
+    }
+    table
+}
+
+/// The `[expected]` table, to be appended to a TOML document
+pub fn render_toml(entries: &[SignatureParams], verified: bool) -> String {
+    let mut document = Table::new();
+    document.insert(EXPECTED.to_string(), Value::Table(toml_table(entries, verified)));
+    to_toml_string(document)
+}
+
+/// The value of one commitment, recomputed from the modulus limbs of `key`
+fn commitment(name: &str, key: &Table) -> Result<Value, String> {
+    let modulus = parse_limbs(key.get(MODULUS_LIMBS), MODULUS_LIMBS)?;
+    // Keys are a whole number of bytes, as their commitments are computed
+    let bits = (modulus.bits() + 7) / 8 * 8;
+    let limbs = split_into_120_bit_limbs(&modulus, bits);
+    Ok(match name {
+        PUBKEY_HASH => bytes_value(&pubkey_hash(PubkeyHash::Sha256, &limbs, bits)),
+        _ => Value::String(pubkey_poseidon2(&limbs).to_string()),
+    })
+}
+
+/// Check the `[expected]` table of a canonical document against its inputs
+pub fn check(document: &Table) -> Result<(), String> {
+    let expected = document
+        .get(EXPECTED)
+        .and_then(Value::as_table)
+        .ok_or("no [expected] table")?;
+    let verified = expected
+        .get(VERIFIED)
+        .and_then(Value::as_bool)
+        .ok_or("`expected.verified` is missing or not a boolean")?;
+    if let Some(expected_valid) = document.get(EXPECTED_VALID).and_then(Value::as_bool) {
+        if expected_valid != verified {
+            return Err(format!("`expected.verified` is {} but `expected_valid` is {}", verified, expected_valid));
+        }
+    }
+
+    let keys: Vec<&Table> = match document.get("keys").and_then(Value::as_array) {
+        Some(keys) => keys.iter().filter_map(Value::as_table).collect(),
+        None => vec![document],
+    };
+    for name in [PUBKEY_HASH, PUBKEY_POSEIDON] {
+        let values = match expected.get(name) {
+            Some(Value::Array(values)) if name == PUBKEY_POSEIDON || values.first().map_or(false, Value::is_array) => {
+                values.clone()
+            }
+            Some(value) => vec![value.clone()],
+            None => continue,
+        };
+        if values.len() != keys.len() {
+            return Err(format!("`expected.{}` has {} values for {} keys", name, values.len(), keys.len()));
+        }
+        for (i, (value, key)) in values.iter().zip(&keys).enumerate() {
+            if key.get(name) != Some(value) {
+                return Err(format!("`expected.{}` of key {} differs from the emitted `{}`", name, i + 1, name));
+            }
+            // The circuit rejects a vector that is not verified, so its outputs are never compared
+            if verified && &commitment(name, key)? != value {
+                return Err(format!("`expected.{}` of key {} is not the commitment to its modulus", name, i + 1));
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha20Rng;
+    use rsa::pkcs8::DecodePrivateKey;
+    use rsa::RsaPrivateKey;
+
+    use crate::hash::HashAlg;
+    use crate::params::render_multi_key_toml;
+    use crate::schema::Names;
+    use crate::signature_params_for_key;
+    use crate::signing::Scheme;
+
+    fn entry() -> SignatureParams {
+        let priv_key = RsaPrivateKey::from_pkcs8_pem(include_str!("../tests/fixtures/rsa_1024.pem")).unwrap();
+        let mut rng = ChaCha20Rng::seed_from_u64(0);
+        let mut entry =
+            signature_params_for_key(&mut rng, &priv_key, b"hello", &[HashAlg::Sha256], &Scheme::Pkcs1v15).unwrap();
+        entry.key.set_pubkey_hash(PubkeyHash::Sha256);
+        entry.key.set_pubkey_poseidon();
+        entry
+    }
+
+    fn parse(document: &str) -> Table {
+        document.parse::<Value>().unwrap().as_table().unwrap().clone()
+    }
+
+    #[test]
+    fn test_expected_values_check_against_the_inputs() {
+        let entries = [entry()];
+        let single = format!("{}\n{}", entries[0].render_toml(Names::Canonical), render_toml(&entries, true));
+        let document = parse(&single);
+        assert_eq!(document[EXPECTED][VERIFIED].as_bool(), Some(true));
+        assert!(document[EXPECTED][PUBKEY_POSEIDON].is_str());
+        assert_eq!(check(&document), Ok(()));
+
+        let entries = [entry(), entry()];
+        let multi = format!("{}\n{}", render_multi_key_toml(&entries, Names::Canonical), render_toml(&entries, true));
+        assert_eq!(check(&parse(&multi)), Ok(()));
+
+        // A commitment that does not match, or a verdict contradicting the label, is caught
+        let tampered = single.replacen("pubkey_poseidon = \"", "pubkey_poseidon = \"1", 2);
+        assert!(check(&parse(&tampered)).is_err());
+        assert!(check(&parse(&format!("expected_valid = false\n{}", single))).is_err());
+
+        // An invalid vector keeps the commitments of its signing key whatever its modulus
+        let mut invalid = entry();
+        invalid.key.modulus_limbs[0] += 1u32;
+        let invalid = [invalid];
+        let rendered = format!("{}\n{}", invalid[0].render_toml(Names::Canonical), render_toml(&invalid, false));
+        assert_eq!(check(&parse(&format!("expected_valid = false\n{}", rendered))), Ok(()));
+        assert!(check(&parse(&rendered.replace("verified = false", "verified = true"))).is_err());
+    }
+}
@@ -0,0 +1,155 @@
+use rsa::{Pkcs1v15Sign, Pss};
+use sha1::Sha1;
+use sha2::{Digest, Sha256, Sha384, Sha512};
+
+/// Hash functions supported by the Noir RSA verifiers
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HashAlg {
+    Sha1,
+    Sha256,
+    Sha384,
+    Sha512,
+}
+
+impl HashAlg {
+    pub fn from_name(name: &str) -> Option<HashAlg> {
+        match name.trim().to_ascii_lowercase().replace('-', "").as_str() {
+            "sha1" => Some(HashAlg::Sha1),
+            "sha256" => Some(HashAlg::Sha256),
+            "sha384" => Some(HashAlg::Sha384),
+            "sha512" => Some(HashAlg::Sha512),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            HashAlg::Sha1 => "sha1",
+            HashAlg::Sha256 => "sha256",
+            HashAlg::Sha384 => "sha384",
+            HashAlg::Sha512 => "sha512",
+        }
+    }
+
+    /// Length of the digest in bytes, i.e. the length of the `msg_hash` array in Noir
+    pub fn output_len(self) -> usize {
+        match self {
+            HashAlg::Sha1 => 20,
+            HashAlg::Sha256 => 32,
+            HashAlg::Sha384 => 48,
+            HashAlg::Sha512 => 64,
+        }
+    }
+
+    pub fn digest(self, msg: &[u8]) -> Vec<u8> {
+        match self {
+            HashAlg::Sha1 => Sha1::digest(msg).to_vec(),
+            HashAlg::Sha256 => Sha256::digest(msg).to_vec(),
+            HashAlg::Sha384 => Sha384::digest(msg).to_vec(),
+            HashAlg::Sha512 => Sha512::digest(msg).to_vec(),
+        }
+    }
+
+    /// DER encoded DigestInfo prefix that precedes the hash in a PKCS#1 v1.5 encoding
+    pub fn digest_info_prefix(self) -> &'static [u8] {
+        match self {
+            HashAlg::Sha1 => &[0x30, 0x21, 0x30, 0x09, 0x06, 0x05, 0x2b, 0x0e, 0x03, 0x02, 0x1a, 0x05, 0x00, 0x04, 0x14],
+            HashAlg::Sha256 => &[
+                0x30, 0x31, 0x30, 0x0d, 0x06, 0x09, 0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x01, 0x05, 0x00,
+                0x04, 0x20,
+            ],
+            HashAlg::Sha384 => &[
+                0x30, 0x41, 0x30, 0x0d, 0x06, 0x09, 0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x02, 0x05, 0x00,
+                0x04, 0x30,
+            ],
+            HashAlg::Sha512 => &[
+                0x30, 0x51, 0x30, 0x0d, 0x06, 0x09, 0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x03, 0x05, 0x00,
+                0x04, 0x40,
+            ],
+        }
+    }
+
+    pub fn pkcs1v15(self) -> Pkcs1v15Sign {
+        match self {
+            HashAlg::Sha1 => Pkcs1v15Sign::new::<Sha1>(),
+            HashAlg::Sha256 => Pkcs1v15Sign::new::<Sha256>(),
+            HashAlg::Sha384 => Pkcs1v15Sign::new::<Sha384>(),
+            HashAlg::Sha512 => Pkcs1v15Sign::new::<Sha512>(),
+        }
+    }
+
+    /// PSS scheme used for verification with an exact salt length
+    pub fn pss(self, salt_len: usize) -> Pss {
+        match self {
+            HashAlg::Sha1 => Pss::new_with_salt::<Sha1>(salt_len),
+            HashAlg::Sha256 => Pss::new_with_salt::<Sha256>(salt_len),
+            HashAlg::Sha384 => Pss::new_with_salt::<Sha384>(salt_len),
+            HashAlg::Sha512 => Pss::new_with_salt::<Sha512>(salt_len),
+        }
+    }
+
+    /// PSS scheme used for signing, with blinding of the private key operation
+    pub fn blinded_pss(self, salt_len: usize) -> Pss {
+        match self {
+            HashAlg::Sha1 => Pss::new_blinded_with_salt::<Sha1>(salt_len),
+            HashAlg::Sha256 => Pss::new_blinded_with_salt::<Sha256>(salt_len),
+            HashAlg::Sha384 => Pss::new_blinded_with_salt::<Sha384>(salt_len),
+            HashAlg::Sha512 => Pss::new_blinded_with_salt::<Sha512>(salt_len),
+        }
+    }
+}
+
+/// Incremental hashing, for messages that are not held in memory
+pub enum Hasher {
+    Sha1(Sha1),
+    Sha256(Sha256),
+    Sha384(Sha384),
+    Sha512(Sha512),
+}
+
+impl Hasher {
+    pub fn new(hash_alg: HashAlg) -> Hasher {
+        match hash_alg {
+            HashAlg::Sha1 => Hasher::Sha1(Sha1::new()),
+            HashAlg::Sha256 => Hasher::Sha256(Sha256::new()),
+            HashAlg::Sha384 => Hasher::Sha384(Sha384::new()),
+            HashAlg::Sha512 => Hasher::Sha512(Sha512::new()),
+        }
+    }
+
+    pub fn update(&mut self, data: &[u8]) {
+        match self {
+            Hasher::Sha1(hasher) => hasher.update(data),
+            Hasher::Sha256(hasher) => hasher.update(data),
+            Hasher::Sha384(hasher) => hasher.update(data),
+            Hasher::Sha512(hasher) => hasher.update(data),
+        }
+    }
+
+    pub fn finalize(self) -> Vec<u8> {
+        match self {
+            Hasher::Sha1(hasher) => hasher.finalize().to_vec(),
+            Hasher::Sha256(hasher) => hasher.finalize().to_vec(),
+            Hasher::Sha384(hasher) => hasher.finalize().to_vec(),
+            Hasher::Sha512(hasher) => hasher.finalize().to_vec(),
+        }
+    }
+}
+
+/// Parse a comma-separated list of hash names such as `sha256,sha512`
+pub fn parse_hash_list(list: &str) -> Result<Vec<HashAlg>, String> {
+    let mut hashes = Vec::new();
+    for name in list.split(',').map(|s| s.trim()).filter(|s| !s.is_empty()) {
+        let hash = HashAlg::from_name(name).ok_or_else(|| {
+            format!("Unsupported hash '{}', expected one of sha1, sha256, sha384, sha512", name)
+        })?;
+        if hashes.contains(&hash) {
+            return Err(format!("Hash '{}' is listed more than once", hash.name()));
+        }
+        hashes.push(hash);
+    }
+    if hashes.is_empty() {
+        return Err("At least one hash must be provided".to_string());
+    }
+    Ok(hashes)
+}
@@ -0,0 +1,216 @@
+//! `--infer-from`: read the BigNum instantiation off an existing Noir circuit
+//!
+//! The scan is deliberately tolerant rather than a Noir parser: comments are
+//! dropped, then every `BigNum`, `RuntimeBigNum` or `BigNumParams` followed by
+//! generics (with or without a turbofish) whose first two arguments are
+//! integer literals counts as an instantiation. Generics given by name, as in
+//! library code, are skipped. All instantiations must agree.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::params::num_limbs;
+
+const TYPE_NAMES: &[&str] = &["RuntimeBigNum", "BigNumParams", "BigNum"];
+
+/// Limb count and modulus width of a BigNum type
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Instantiation {
+    pub num_limbs: usize,
+    pub bits: usize,
+}
+
+/// An instantiation and where it was found
+#[derive(Debug)]
+pub struct Found {
+    pub instantiation: Instantiation,
+    pub type_name: String,
+    /// `<file>:<line>`
+    pub location: String,
+}
+
+/// `source` with `//` and `/* */` comments blanked out, keeping line numbers
+fn strip_comments(source: &str) -> String {
+    let mut out = String::with_capacity(source.len());
+    let mut chars = source.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (c, chars.peek()) {
+            ('/', Some('/')) => {
+                while chars.peek().map_or(false, |&c| c != '\n') {
+                    chars.next();
+                }
+            }
+            ('/', Some('*')) => {
+                chars.next();
+                let mut last = ' ';
+                for c in chars.by_ref() {
+                    if c == '\n' {
+                        out.push('\n');
+                    }
+                    if last == '*' && c == '/' {
+                        break;
+                    }
+                    last = c;
+                }
+            }
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+/// The comma-separated generic arguments of the `<...>` at the start of `rest`, if there is one
+fn generic_args(rest: &str) -> Option<Vec<&str>> {
+    let rest = rest.trim_start();
+    let rest = rest.strip_prefix("::").unwrap_or(rest).trim_start().strip_prefix('<')?;
+    let mut depth = 0;
+    for (i, c) in rest.char_indices() {
+        match c {
+            '<' => depth += 1,
+            '>' if depth == 0 => return Some(rest[..i].split(',').map(str::trim).collect()),
+            '>' => depth -= 1,
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Every instantiation with literal generics in `source`, labelled with `file`
+pub fn scan(source: &str, file: &str) -> Vec<Found> {
+    let source = strip_comments(source);
+    let mut found = Vec::new();
+    for (line_number, line) in source.lines().enumerate() {
+        let mut i = 0;
+        while i < line.len() {
+            let rest = &line[i..];
+            let at_word_start = line[..i].chars().last().map_or(true, |c| !c.is_alphanumeric() && c != '_');
+            let type_name = TYPE_NAMES.iter().find(|&&name| {
+                at_word_start
+                    && rest.starts_with(name)
+                    && !rest[name.len()..].starts_with(|c: char| c.is_alphanumeric() || c == '_')
+            });
+            let type_name = match type_name {
+                Some(&type_name) => type_name,
+                None => {
+                    i += rest.chars().next().unwrap().len_utf8();
+                    continue;
+                }
+            };
+            i += type_name.len();
+            let literals = generic_args(&line[i..]).and_then(|args| match args.as_slice() {
+                [num_limbs, bits, ..] => Some((num_limbs.parse().ok()?, bits.parse().ok()?)),
+                _ => None,
+            });
+            if let Some((num_limbs, bits)) = literals {
+                found.push(Found {
+                    instantiation: Instantiation { num_limbs, bits },
+                    type_name: type_name.to_string(),
+                    location: format!("{}:{}", file, line_number + 1),
+                });
+            }
+        }
+    }
+    found
+}
+
+fn noir_sources(dir: &Path, sources: &mut Vec<PathBuf>) -> Result<(), String> {
+    let entries = fs::read_dir(dir).map_err(|err| format!("Failed to read {}: {}", dir.display(), err))?;
+    for entry in entries {
+        let path = entry.map_err(|err| format!("Failed to read {}: {}", dir.display(), err))?.path();
+        if path.is_dir() {
+            noir_sources(&path, sources)?;
+        } else if path.extension().map_or(false, |extension| extension == "nr") {
+            sources.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// The one instantiation used by a Noir source file, or by the `src/` of a Nargo project
+pub fn infer(path: &Path) -> Result<Instantiation, String> {
+    let mut sources = Vec::new();
+    if path.is_dir() {
+        let src = path.join("src");
+        noir_sources(if src.is_dir() { &src } else { path }, &mut sources)?;
+        sources.sort();
+    } else {
+        sources.push(path.to_path_buf());
+    }
+
+    let mut found = Vec::new();
+    for source in &sources {
+        let contents =
+            fs::read_to_string(source).map_err(|err| format!("Failed to read {}: {}", source.display(), err))?;
+        found.extend(scan(&contents, &source.display().to_string()));
+    }
+    check(&found, &path.display().to_string())
+}
+
+/// Reduce the instantiations to one, failing on none, several or an inconsistent one
+pub fn check(found: &[Found], path: &str) -> Result<Instantiation, String> {
+    let first = found
+        .first()
+        .ok_or_else(|| format!("No BigNum instantiation with literal limb count and width found in {}", path))?;
+    if let Some(other) = found.iter().find(|other| other.instantiation != first.instantiation) {
+        return Err(format!(
+            "Conflicting BigNum instantiations in {}: {}<{}, {}> at {} and {}<{}, {}> at {}",
+            path,
+            first.type_name,
+            first.instantiation.num_limbs,
+            first.instantiation.bits,
+            first.location,
+            other.type_name,
+            other.instantiation.num_limbs,
+            other.instantiation.bits,
+            other.location
+        ));
+    }
+    let Instantiation { num_limbs: limbs, bits } = first.instantiation;
+    if limbs != num_limbs(bits) {
+        return Err(format!(
+            "{}<{}, {}> at {} has {} limbs, but {} bits take {} limbs of 120 bits",
+            first.type_name,
+            limbs,
+            bits,
+            first.location,
+            limbs,
+            bits,
+            num_limbs(bits)
+        ));
+    }
+    Ok(first.instantiation)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_example_project_is_2048_bits() {
+        let found = scan(include_str!("../../example/src/main.nr"), "main.nr");
+        assert_eq!(found.len(), 2);
+        let expected = Instantiation { num_limbs: 18, bits: 2048 };
+        assert_eq!(check(&found, "main.nr"), Ok(expected));
+        assert_eq!(infer(Path::new("../example")), Ok(expected));
+    }
+
+    #[test]
+    fn test_conflicting_widths_are_reported() {
+        let source = "// RuntimeBigNum<35, 4096> in a comment does not count\n\
+                      fn verify<let N: u32, let MOD_BITS: u32>(s: RuntimeBigNum<N, MOD_BITS>) {}\n\
+                      fn main() {\n\
+                          let a: BigNumParams<18, 2048> = BigNumParams::new(false, m, r);\n\
+                          let b = RuntimeBigNum::< 35 , 4096 >::from_array(p, l);\n\
+                      }\n";
+        let found = scan(source, "main.nr");
+        assert_eq!(found.len(), 2);
+        assert_eq!(found[1].location, "main.nr:5");
+        let err = check(&found, "main.nr").unwrap_err();
+        assert!(err.contains("BigNumParams<18, 2048> at main.nr:4"), "{}", err);
+        assert!(err.contains("RuntimeBigNum<35, 4096> at main.nr:5"), "{}", err);
+
+        let wrong_limbs = scan("let s: RuntimeBigNum<17, 2048> = x;", "main.nr");
+        assert!(check(&wrong_limbs, "main.nr").unwrap_err().contains("take 18 limbs"));
+        assert!(check(&[], "main.nr").is_err());
+    }
+}
@@ -0,0 +1,211 @@
+//! `sign --invalid`: inputs the circuit must reject, for `#[test(should_fail)]` tests
+//!
+//! Each kind breaks one part of an otherwise valid vector, so a test failing
+//! on it shows which check of the circuit caught it. `bad-padding` cannot be
+//! made with the rsa crate, which only signs well-formed encodings: it builds
+//! the encoded message by hand, corrupts one byte of its padding and applies
+//! the raw private key operation, so the signature exponentiates to exactly
+//! that encoding and only the padding check rejects it.
+
+use num_bigint::BigUint;
+use num_traits::{One, ToPrimitive};
+use rand::Rng;
+use rand_chacha::ChaCha20Rng;
+use rsa::traits::PublicKeyParts;
+use rsa::RsaPrivateKey;
+
+use noir_bignum_paramgen::split_into_120_bit_limbs;
+
+use crate::errors::{Code, Error};
+use crate::generate_key;
+use crate::hash::HashAlg;
+use crate::params::{combine_limbs, KeyParams, SignatureParams};
+use crate::pss::emsa_pss_encode;
+use crate::signing::{raw_sign, PssSalt, Scheme};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Invalid {
+    /// One bit of the signature flipped
+    FlippedSig,
+    /// The hash of another message, the signed digest itself, next to the signature
+    WrongHash,
+    /// The limbs of another key of the same size in place of the signing key's
+    WrongModulus,
+    /// A signature of an encoding with one corrupted padding byte
+    BadPadding,
+    /// The Barrett parameter without its top bit, which halves every quotient estimate
+    BadRedc,
+}
+
+impl Invalid {
+    pub const ALL: [Invalid; 5] =
+        [Invalid::FlippedSig, Invalid::WrongHash, Invalid::WrongModulus, Invalid::BadPadding, Invalid::BadRedc];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Invalid::FlippedSig => "flipped-sig",
+            Invalid::WrongHash => "wrong-hash",
+            Invalid::WrongModulus => "wrong-modulus",
+            Invalid::BadPadding => "bad-padding",
+            Invalid::BadRedc => "bad-redc",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Invalid> {
+        Invalid::ALL.into_iter().find(|kind| kind.name() == name)
+    }
+}
+
+/// EMSA-PKCS1-v1_5 (RFC 8017 9.2) of `digest` for a key of `key_size` bytes,
+/// with one bit of a random 0xff padding byte flipped
+fn bad_pkcs1v15_encoding(rng: &mut ChaCha20Rng, hash_alg: HashAlg, digest: &[u8], key_size: usize) -> Vec<u8> {
+    let prefix = hash_alg.digest_info_prefix();
+    let padding_len = key_size - 3 - prefix.len() - digest.len();
+    let mut em = vec![0x00, 0x01];
+    em.extend(std::iter::repeat(0xff).take(padding_len));
+    em.push(0x00);
+    em.extend_from_slice(prefix);
+    em.extend_from_slice(digest);
+    em[rng.gen_range(2..2 + padding_len)] ^= 1 << rng.gen_range(0..8);
+    em
+}
+
+/// EMSA-PSS (RFC 8017 9.1.1) of `digest` with one bit of the 0xbc trailer flipped
+fn bad_pss_encoding(
+    rng: &mut ChaCha20Rng,
+    hash_alg: HashAlg,
+    digest: &[u8],
+    salt: &PssSalt,
+    em_bits: usize,
+) -> Result<Vec<u8>, String> {
+    let salt = match salt {
+        PssSalt::Random(salt_len) => (0..*salt_len).map(|_| rng.gen()).collect(),
+        PssSalt::Fixed(salt) => salt.clone(),
+    };
+    let mut em = emsa_pss_encode(hash_alg, digest, &salt, em_bits)?;
+    *em.last_mut().unwrap() ^= 1 << rng.gen_range(0..8);
+    Ok(em)
+}
+
+/// Break `entry`, signed by `priv_key` with `scheme`, as `kind` says
+pub fn corrupt(
+    rng: &mut ChaCha20Rng,
+    kind: Invalid,
+    priv_key: &RsaPrivateKey,
+    entry: &mut SignatureParams,
+    scheme: &Scheme,
+) -> Result<(), Error> {
+    let (n, bits) = (priv_key.n(), entry.key.bits);
+    match kind {
+        Invalid::FlippedSig => {
+            for signature in &mut entry.signatures {
+                let s = combine_limbs(&signature.signature_limbs);
+                // A bit below the top one of n, and again until the result stays below n
+                let flipped = loop {
+                    let flipped = &s ^ &(BigUint::one() << rng.gen_range(0..n.bits() - 1));
+                    if &flipped < n {
+                        break flipped;
+                    }
+                };
+                signature.signature_limbs = split_into_120_bit_limbs(&flipped, bits);
+            }
+        }
+        Invalid::WrongHash => {
+            for signature in &mut entry.signatures {
+                signature.hash = signature.hash_alg.digest(&signature.hash);
+            }
+        }
+        Invalid::WrongModulus => {
+            let exponent = priv_key
+                .e()
+                .to_u32()
+                .ok_or_else(|| Error::new(Code::InvalidArgument, "wrong-modulus needs an exponent below 2^32"))?;
+            let other = generate_key(rng, n.bits(), exponent)?;
+            let key = KeyParams::from_modulus(other.n(), bits);
+            // Commitments, if emitted, stay those of the signing key
+            entry.key.modulus_limbs = key.modulus_limbs;
+            entry.key.redc_limbs = key.redc_limbs;
+        }
+        Invalid::BadPadding => {
+            for signature in &mut entry.signatures {
+                let (hash_alg, digest) = (signature.hash_alg, &signature.hash);
+                let em = match scheme {
+                    Scheme::Pkcs1v15 => bad_pkcs1v15_encoding(rng, hash_alg, digest, priv_key.size()),
+                    Scheme::Pss(salt) => bad_pss_encoding(rng, hash_alg, digest, salt, n.bits() - 1)
+                        .map_err(|err| Error::new(Code::InvalidArgument, err))?,
+                };
+                let s = BigUint::from_bytes_be(&raw_sign(priv_key, &em));
+                signature.signature_limbs = split_into_120_bit_limbs(&s, bits);
+            }
+        }
+        Invalid::BadRedc => {
+            let redc = combine_limbs(&entry.key.redc_limbs);
+            let perturbed = &redc - (BigUint::one() << (redc.bits() - 1));
+            entry.key.redc_limbs = split_into_120_bit_limbs(&perturbed, bits);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+    use rsa::pkcs8::DecodePrivateKey;
+
+    use crate::reference::{self, CircuitVector, Divergence};
+    use crate::signature_params_for_key;
+    use crate::signing::verify_digest;
+
+    fn circuit_verify(entry: &SignatureParams, scheme: &Scheme) -> Result<(), Divergence> {
+        reference::verify(&CircuitVector::of_entry(entry, &BigUint::from(65537u32), scheme))
+    }
+
+    #[test]
+    fn test_every_kind_is_rejected_by_the_circuit() {
+        let priv_key = RsaPrivateKey::from_pkcs8_pem(include_str!("../tests/fixtures/rsa_1024.pem")).unwrap();
+        let pub_key = priv_key.to_public_key();
+        let (mut rng, mut signing_rng) = (ChaCha20Rng::seed_from_u64(0), ChaCha20Rng::seed_from_u64(1));
+        for scheme in [Scheme::Pkcs1v15, Scheme::Pss(PssSalt::Random(32))] {
+            let mut sign = || {
+                signature_params_for_key(&mut signing_rng, &priv_key, b"hello", &[HashAlg::Sha256], &scheme).unwrap()
+            };
+            assert_eq!(circuit_verify(&sign(), &scheme), Ok(()));
+            for kind in Invalid::ALL {
+                let mut entry = sign();
+                corrupt(&mut rng, kind, &priv_key, &mut entry, &scheme).unwrap();
+                let divergence = circuit_verify(&entry, &scheme).unwrap_err();
+                let expected = match kind {
+                    // The other key's limbs agree with each other, so the signature decodes to noise or exceeds them
+                    Invalid::WrongModulus => {
+                        matches!(divergence, Divergence::Encoding { .. } | Divergence::SignatureNotReduced)
+                    }
+                    Invalid::BadRedc => matches!(divergence, Divergence::Redc { .. } | Divergence::BarrettBound { .. }),
+                    Invalid::FlippedSig | Invalid::WrongHash | Invalid::BadPadding => {
+                        matches!(divergence, Divergence::Encoding { .. })
+                    }
+                };
+                assert!(expected, "{} {:?}: {:?}", kind.name(), scheme, divergence);
+                assert_eq!(Invalid::from_name(kind.name()), Some(kind));
+            }
+
+            // A bad padding is a correct RSA signature of its encoding, which only the padding check rejects
+            let mut entry = sign();
+            corrupt(&mut rng, Invalid::BadPadding, &priv_key, &mut entry, &scheme).unwrap();
+            let signature = &entry.signatures[0];
+            let s = combine_limbs(&signature.signature_limbs);
+            let em = s.modpow(pub_key.e(), pub_key.n()).to_bytes_be();
+            let sig = signature.signature_bytes(pub_key.size());
+            assert!(verify_digest(&pub_key, HashAlg::Sha256, &signature.hash, &sig, &scheme).is_err());
+            match scheme {
+                // The leading zero byte is dropped by to_bytes_be
+                Scheme::Pkcs1v15 => {
+                    assert_eq!(em[0], 0x01);
+                    let padding: Vec<u8> = em[1..].iter().copied().take_while(|&byte| byte != 0).collect();
+                    assert_eq!(padding.iter().filter(|&&byte| byte != 0xff).count(), 1);
+                }
+                Scheme::Pss(_) => assert_ne!(*em.last().unwrap(), 0xbc),
+            }
+        }
+    }
+}
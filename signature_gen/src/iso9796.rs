@@ -0,0 +1,248 @@
+//! ISO/IEC 9796-2 scheme 1 signatures, as made by passport chips for Active Authentication
+//!
+//! Unlike PKCS#1 v1.5 and PSS the signature carries part of the message: the
+//! encoded message F is `header || [padding] || M1 || H(M1 || M2) || trailer`,
+//! where M1 is the recoverable part and M2 the rest, which the verifier must
+//! already know (the challenge in Active Authentication). The header nibble
+//! `6` marks partial recovery (M2 is not empty) and `4` total recovery; `A`
+//! means M1 starts right after the header and `B` that `0xbb .. 0xba` padding
+//! comes first. SHA-1 uses the implicit trailer `0xbc`, other hashes `0xcc`
+//! preceded by their ISO/IEC 10118-3 hash identifier.
+
+use num_bigint::BigUint;
+use rsa::traits::PublicKeyParts;
+use rsa::{RsaPrivateKey, RsaPublicKey};
+
+use noir_bignum_paramgen::split_into_120_bit_limbs;
+
+use crate::hash::HashAlg;
+use crate::keys::key_bits;
+use crate::params::{bytes_value, format_limbs_as_toml_value, to_toml_string, KeyParams};
+use crate::schema::{HASH, NON_RECOVERABLE_MESSAGE, RECOVERED_MESSAGE, SIGNATURE_LIMBS};
+use crate::signing::raw_sign;
+
+const TRAILER_IMPLICIT: u8 = 0xbc;
+const TRAILER_EXPLICIT: u8 = 0xcc;
+
+/// Hash identifier written before the explicit trailer, ISO/IEC 10118-3
+fn hash_id(hash_alg: HashAlg) -> u8 {
+    match hash_alg {
+        HashAlg::Sha1 => 0x33,
+        HashAlg::Sha256 => 0x34,
+        HashAlg::Sha512 => 0x35,
+        HashAlg::Sha384 => 0x36,
+    }
+}
+
+fn trailer(hash_alg: HashAlg) -> Vec<u8> {
+    match hash_alg {
+        HashAlg::Sha1 => vec![TRAILER_IMPLICIT],
+        _ => vec![hash_id(hash_alg), TRAILER_EXPLICIT],
+    }
+}
+
+/// Length of F in bytes; keys whose size is not a whole number of bytes are rejected
+fn encoded_len(bits: usize) -> Result<usize, String> {
+    if bits % 8 != 0 {
+        return Err(format!("ISO 9796-2 encoding needs a key size that is a multiple of 8 bits, got {}", bits));
+    }
+    Ok(bits / 8)
+}
+
+/// Most message bytes a `bits`-bit signature can carry; longer messages are
+/// recovered partially, their first `capacity` bytes being M1
+pub fn capacity(bits: usize, hash_alg: HashAlg) -> Result<usize, String> {
+    let overhead = 1 + hash_alg.output_len() + trailer(hash_alg).len();
+    encoded_len(bits)?
+        .checked_sub(overhead)
+        .ok_or_else(|| format!("A {}-bit key is too small for ISO 9796-2 with {}", bits, hash_alg.name()))
+}
+
+/// Split `message` into its recoverable and non-recoverable parts
+pub fn split(message: &[u8], bits: usize, hash_alg: HashAlg) -> Result<(&[u8], &[u8]), String> {
+    Ok(message.split_at(message.len().min(capacity(bits, hash_alg)?)))
+}
+
+/// Encoded message F of `message` for a `bits`-bit key
+pub fn encode(hash_alg: HashAlg, message: &[u8], bits: usize) -> Result<Vec<u8>, String> {
+    let capacity = capacity(bits, hash_alg)?;
+    let (m1, m2) = split(message, bits, hash_alg)?;
+    let partial = !m2.is_empty();
+
+    let mut em = Vec::with_capacity(encoded_len(bits)?);
+    if m1.len() == capacity {
+        em.push(if partial { 0x6a } else { 0x4a });
+    } else {
+        em.push(0x4b);
+        em.resize(capacity - m1.len(), 0xbb);
+        em.push(0xba);
+    }
+    em.extend_from_slice(m1);
+    em.extend_from_slice(&hash_alg.digest(message));
+    em.extend_from_slice(&trailer(hash_alg));
+    Ok(em)
+}
+
+/// Check F against the non-recoverable part M2 and return the recovered M1
+pub fn recover<'a>(em: &'a [u8], hash_alg: HashAlg, non_recoverable: &[u8]) -> Result<&'a [u8], String> {
+    let trailer = trailer(hash_alg);
+    if !em.ends_with(&trailer) {
+        return Err(format!("Trailer is not {} for {}", hex::encode(&trailer), hash_alg.name()));
+    }
+    let h_start = em
+        .len()
+        .checked_sub(trailer.len() + hash_alg.output_len())
+        .filter(|&h_start| h_start > 0)
+        .ok_or("Encoded message too short")?;
+
+    let partial = match em[0] >> 4 {
+        0x6 => true,
+        0x4 => false,
+        _ => return Err(format!("Header 0x{:02x} is not an ISO 9796-2 scheme 1 header", em[0])),
+    };
+    let m1_start = match em[0] & 0x0f {
+        0xa => 1,
+        0xb if !partial => match em[1..h_start].iter().position(|&b| b != 0xbb) {
+            Some(i) if em[1 + i] == 0xba => 2 + i,
+            _ => return Err("Padding is not 0xbb .. 0xba".to_string()),
+        },
+        _ => return Err(format!("Header 0x{:02x} is not an ISO 9796-2 scheme 1 header", em[0])),
+    };
+    if partial == non_recoverable.is_empty() {
+        return Err(if partial {
+            "Signature recovers the message partially, the non-recoverable part is needed".to_string()
+        } else {
+            "Signature recovers the whole message, yet a non-recoverable part was given".to_string()
+        });
+    }
+
+    let m1 = &em[m1_start..h_start];
+    let h = &em[h_start..em.len() - trailer.len()];
+    if hash_alg.digest(&[m1, non_recoverable].concat()) != h {
+        return Err(format!("H is not the {} digest of M1 || M2", hash_alg.name()));
+    }
+    Ok(m1)
+}
+
+/// Circuit inputs for one ISO 9796-2 signature
+pub struct Vector {
+    pub key: KeyParams,
+    pub hash_alg: HashAlg,
+    /// Digest of the whole message, as found in F
+    pub hash: Vec<u8>,
+    pub signature_limbs: Vec<BigUint>,
+    /// M1, carried by the signature
+    pub recovered: Vec<u8>,
+    /// M2, empty on total recovery
+    pub non_recoverable: Vec<u8>,
+}
+
+/// Recover F from a signature with the public key operation and check it
+/// against M2, returning M1
+pub fn verify(
+    pub_key: &RsaPublicKey,
+    hash_alg: HashAlg,
+    sig: &[u8],
+    non_recoverable: &[u8],
+) -> Result<Vec<u8>, String> {
+    let s = BigUint::from_bytes_be(sig);
+    if &s >= pub_key.n() {
+        return Err("Signature representative out of range".to_string());
+    }
+    let f = s.modpow(pub_key.e(), pub_key.n()).to_bytes_be();
+    let mut em = vec![0u8; encoded_len(key_bits(pub_key))?.saturating_sub(f.len())];
+    em.extend_from_slice(&f);
+    let m1 = recover(&em, hash_alg, non_recoverable)?;
+
+    // Re-encoding the recovered message must give F back
+    if encode(hash_alg, &[m1, non_recoverable].concat(), key_bits(pub_key))? != em {
+        return Err("Recovered message does not re-encode to the signed representative".to_string());
+    }
+    Ok(m1.to_vec())
+}
+
+/// Sign `message` with the raw private key operation and verify the result
+pub fn sign(priv_key: &RsaPrivateKey, hash_alg: HashAlg, message: &[u8]) -> Result<Vector, String> {
+    let bits = key_bits(priv_key);
+    let em = encode(hash_alg, message, bits)?;
+    let sig = raw_sign(priv_key, &em);
+    let (m1, m2) = split(message, bits, hash_alg)?;
+    let recovered = verify(&priv_key.to_public_key(), hash_alg, &sig, m2)
+        .map_err(|err| format!("Generated ISO 9796-2 signature failed to verify: {}", err))?;
+    assert_eq!(recovered, m1);
+
+    Ok(Vector {
+        key: KeyParams::from_modulus(priv_key.n(), bits),
+        hash_alg,
+        hash: hash_alg.digest(message),
+        signature_limbs: split_into_120_bit_limbs(&BigUint::from_bytes_be(&sig), bits),
+        recovered,
+        non_recoverable: m2.to_vec(),
+    })
+}
+
+impl Vector {
+    pub fn render_toml(&self) -> String {
+        let mut table = self.key.toml_table();
+        table.insert(HASH.to_string(), bytes_value(&self.hash));
+        table.insert(
+            SIGNATURE_LIMBS.to_string(),
+            toml::Value::Array(format_limbs_as_toml_value(&self.signature_limbs, self.key.limb_style)),
+        );
+        table.insert(RECOVERED_MESSAGE.to_string(), bytes_value(&self.recovered));
+        table.insert(NON_RECOVERABLE_MESSAGE.to_string(), bytes_value(&self.non_recoverable));
+        to_toml_string(table)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rsa::pkcs8::DecodePrivateKey;
+
+    const FIXTURE_KEY: &str = include_str!("../tests/fixtures/rsa_1024.pem");
+
+    #[test]
+    fn test_encodings_follow_the_header_rules() {
+        // 128 bytes - header - 20 byte SHA-1 hash - 0xbc leaves 106 bytes for M1
+        assert_eq!(capacity(1024, HashAlg::Sha1), Ok(106));
+        assert_eq!(capacity(1024, HashAlg::Sha256), Ok(93));
+
+        let short = encode(HashAlg::Sha1, b"challenge", 1024).unwrap();
+        assert_eq!(short.len(), 128);
+        assert_eq!(&short[..2], &[0x4b, 0xbb]);
+        assert_eq!(short[106 - 9], 0xba);
+        assert_eq!(&short[107 - 9..107], b"challenge");
+        assert_eq!(short[127], 0xbc);
+
+        let full = encode(HashAlg::Sha1, &[7u8; 106], 1024).unwrap();
+        assert_eq!(full[0], 0x4a);
+
+        let partial = encode(HashAlg::Sha256, &[7u8; 101], 1024).unwrap();
+        assert_eq!(partial[0], 0x6a);
+        assert_eq!(&partial[126..], &[0x34, 0xcc]);
+    }
+
+    #[test]
+    fn test_signatures_recover_the_message() {
+        let priv_key = RsaPrivateKey::from_pkcs8_pem(FIXTURE_KEY).unwrap();
+        let pub_key = priv_key.to_public_key();
+        for hash_alg in [HashAlg::Sha1, HashAlg::Sha256] {
+            // Total recovery, and partial recovery with an 8 byte challenge as M2
+            for message in [b"hello world".to_vec(), [vec![0x5au8; 200], b"RND.IFD!".to_vec()].concat()] {
+                let vector = sign(&priv_key, hash_alg, &message).unwrap();
+                assert_eq!([vector.recovered.as_slice(), &vector.non_recoverable].concat(), message);
+                assert_eq!(vector.hash, hash_alg.digest(&message));
+
+                let sig = raw_sign(&priv_key, &encode(hash_alg, &message, 1024).unwrap());
+                assert!(verify(&pub_key, hash_alg, &sig, &vector.non_recoverable).is_ok());
+                if !vector.non_recoverable.is_empty() {
+                    assert!(verify(&pub_key, hash_alg, &sig, b"RND.IFD?").is_err());
+                    assert!(verify(&pub_key, hash_alg, &sig, b"").is_err());
+                }
+                let other = if hash_alg == HashAlg::Sha1 { HashAlg::Sha256 } else { HashAlg::Sha1 };
+                assert!(verify(&pub_key, other, &sig, &vector.non_recoverable).is_err());
+            }
+        }
+    }
+}
@@ -0,0 +1,281 @@
+//! JSON Canonicalization Scheme (RFC 8785)
+
+use std::fmt;
+
+use serde::de::{self, Deserialize, Deserializer, MapAccess, SeqAccess, Visitor};
+use serde_json::{Map, Number, Value};
+
+/// Largest integer that an IEEE 754 double represents exactly along with all smaller integers
+const MAX_SAFE_INTEGER: i128 = (1 << 53) - 1;
+
+/// Canonicalize a JSON document so that signer and circuit hash exactly the same bytes
+pub fn canonicalize(input: &str) -> Result<String, String> {
+    let Strict(value) = serde_json::from_str(input).map_err(|err| format!("Invalid JSON: {}", err))?;
+    let mut out = String::new();
+    write_value(&value, &mut out)?;
+    Ok(out)
+}
+
+/// A JSON value whose objects name each key once; which of two duplicates
+/// would be signed is not defined, so they are rejected
+struct Strict(Value);
+
+impl<'de> Deserialize<'de> for Strict {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Strict, D::Error> {
+        deserializer.deserialize_any(StrictVisitor)
+    }
+}
+
+struct StrictVisitor;
+
+impl<'de> Visitor<'de> for StrictVisitor {
+    type Value = Strict;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a JSON value")
+    }
+
+    fn visit_unit<E>(self) -> Result<Strict, E> {
+        Ok(Strict(Value::Null))
+    }
+
+    fn visit_bool<E>(self, b: bool) -> Result<Strict, E> {
+        Ok(Strict(Value::Bool(b)))
+    }
+
+    fn visit_i64<E>(self, n: i64) -> Result<Strict, E> {
+        Ok(Strict(Value::Number(n.into())))
+    }
+
+    fn visit_u64<E>(self, n: u64) -> Result<Strict, E> {
+        Ok(Strict(Value::Number(n.into())))
+    }
+
+    fn visit_f64<E: de::Error>(self, n: f64) -> Result<Strict, E> {
+        match Number::from_f64(n) {
+            Some(n) => Ok(Strict(Value::Number(n))),
+            None => Err(E::custom(format!("number {} is not finite", n))),
+        }
+    }
+
+    fn visit_str<E>(self, s: &str) -> Result<Strict, E> {
+        Ok(Strict(Value::String(s.to_string())))
+    }
+
+    fn visit_string<E>(self, s: String) -> Result<Strict, E> {
+        Ok(Strict(Value::String(s)))
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Strict, A::Error> {
+        let mut items = Vec::new();
+        while let Some(Strict(item)) = seq.next_element()? {
+            items.push(item);
+        }
+        Ok(Strict(Value::Array(items)))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Strict, A::Error> {
+        let mut object = Map::new();
+        while let Some(key) = map.next_key::<String>()? {
+            if object.contains_key(&key) {
+                return Err(de::Error::custom(format!("duplicate key {:?}", key)));
+            }
+            let Strict(value) = map.next_value()?;
+            object.insert(key, value);
+        }
+        Ok(Strict(Value::Object(object)))
+    }
+}
+
+fn write_value(value: &Value, out: &mut String) -> Result<(), String> {
+    match value {
+        Value::Null => out.push_str("null"),
+        Value::Bool(b) => out.push_str(if *b { "true" } else { "false" }),
+        Value::Number(n) => out.push_str(&format_number(n)?),
+        Value::String(s) => write_string(s, out),
+        Value::Array(items) => {
+            out.push('[');
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_value(item, out)?;
+            }
+            out.push(']');
+        }
+        Value::Object(map) => {
+            // Properties are sorted by the UTF-16 code units of their names
+            let mut entries: Vec<(&String, &Value)> = map.iter().collect();
+            entries.sort_by(|(a, _), (b, _)| a.encode_utf16().cmp(b.encode_utf16()));
+
+            out.push('{');
+            for (i, (key, item)) in entries.into_iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_string(key, out);
+                out.push(':');
+                write_value(item, out)?;
+            }
+            out.push('}');
+        }
+    }
+    Ok(())
+}
+
+fn write_string(s: &str, out: &mut String) {
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\u{8}' => out.push_str("\\b"),
+            '\u{c}' => out.push_str("\\f"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+fn format_number(n: &Number) -> Result<String, String> {
+    // JSON numbers are IEEE 754 doubles under JCS, so integers must survive the conversion
+    let integer = n.as_i64().map(i128::from).or_else(|| n.as_u64().map(i128::from));
+    if let Some(integer) = integer {
+        let value = integer as f64;
+        if integer.abs() > MAX_SAFE_INTEGER && value as i128 != integer {
+            return Err(format!(
+                "Integer {} cannot be represented exactly as an IEEE 754 double and would not survive JCS",
+                integer
+            ));
+        }
+        return Ok(format_f64(value));
+    }
+
+    match n.as_f64() {
+        Some(value) if value.is_finite() => Ok(format_f64(value)),
+        _ => Err(format!("Number {} is not a finite IEEE 754 double and cannot be canonicalized", n)),
+    }
+}
+
+/// Serialize a double the way ECMAScript's `Number.prototype.toString` does
+fn format_f64(value: f64) -> String {
+    if value == 0.0 {
+        return "0".to_string();
+    }
+    if value < 0.0 {
+        return format!("-{}", format_f64(-value));
+    }
+
+    // `{:e}` gives the shortest digits that round-trip, e.g. `1.2345e3`
+    let scientific = format!("{:e}", value);
+    let (mantissa, exponent) = scientific.split_once('e').unwrap();
+    let digits: String = mantissa.chars().filter(|c| *c != '.').collect();
+    let k = digits.len() as i32;
+    // The value is 0.<digits> * 10^n
+    let n = exponent.parse::<i32>().unwrap() + 1;
+
+    if k <= n && n <= 21 {
+        format!("{}{}", digits, "0".repeat((n - k) as usize))
+    } else if 0 < n && n <= 21 {
+        format!("{}.{}", &digits[..n as usize], &digits[n as usize..])
+    } else if -6 < n && n <= 0 {
+        format!("0.{}{}", "0".repeat((-n) as usize), digits)
+    } else {
+        let e = n - 1;
+        let sign = if e >= 0 { "+" } else { "-" };
+        let mantissa = if k == 1 {
+            digits
+        } else {
+            format!("{}.{}", &digits[..1], &digits[1..])
+        };
+        format!("{}e{}{}", mantissa, sign, e.abs())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_canonicalize_rfc8785_example() {
+        let input = r#"{
+            "numbers": [333333333.33333329, 1E30, 4.50, 2e-3, 0.000000000000000000000000001],
+            "string": "\u20ac$\u000F\u000aA'\u0042\u0022\u005c\\\"\/",
+            "literals": [null, true, false]
+        }"#;
+        let expected = r#"{"literals":[null,true,false],"numbers":[333333333.3333333,1e+30,4.5,0.002,1e-27],"string":"€$\u000f\nA'B\"\\\\\"/"}"#;
+
+        assert_eq!(canonicalize(input).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_canonicalize_sorts_keys_by_utf16() {
+        let input = r#"{
+            "\u20ac": "Euro Sign",
+            "\r": "Carriage Return",
+            "\ufb33": "Hebrew Letter Dalet With Dagesh",
+            "1": "One",
+            "\ud83d\ude00": "Emoji: Grinning Face",
+            "\u0080": "Control",
+            "\u00f6": "Latin Small Letter O With Diaeresis"
+        }"#;
+        let canonical = canonicalize(input).unwrap();
+        let values: Vec<&str> = canonical
+            .split(',')
+            .map(|entry| entry.split(':').nth(1).unwrap().trim_end_matches('}'))
+            .collect();
+
+        assert_eq!(
+            values,
+            vec![
+                "\"Carriage Return\"",
+                "\"One\"",
+                "\"Control\"",
+                "\"Latin Small Letter O With Diaeresis\"",
+                "\"Euro Sign\"",
+                "\"Emoji",
+                "\"Hebrew Letter Dalet With Dagesh\"",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_canonicalize_messy_input_is_stable() {
+        let messy = "{ \"b\" : [ 1.0 , 2E1 ] ,\n\t\"a\":{\"y\":\"\\u00e9\",\"x\":null} }";
+        let canonical = canonicalize(messy).unwrap();
+
+        assert_eq!(canonical, "{\"a\":{\"x\":null,\"y\":\"é\"},\"b\":[1,20]}");
+        assert_eq!(canonicalize(&canonical).unwrap(), canonical);
+    }
+
+    #[test]
+    fn test_canonicalize_rejects_invalid_numbers() {
+        assert!(canonicalize("[NaN]").unwrap_err().starts_with("Invalid JSON"));
+        assert!(canonicalize("[1e400]").is_err());
+        assert!(canonicalize("[9007199254740993]")
+            .unwrap_err()
+            .contains("cannot be represented exactly"));
+        assert!(canonicalize("{\"a\": 1,}").unwrap_err().starts_with("Invalid JSON"));
+    }
+
+    #[test]
+    fn test_canonicalize_rejects_duplicate_keys() {
+        let err = canonicalize(r#"{"a": 1, "b": {"c": 2, "c": 3}}"#).unwrap_err();
+        assert!(err.starts_with("Invalid JSON: duplicate key \"c\""), "{}", err);
+        assert!(canonicalize(r#"[{"c": 2}, {"c": 3}]"#).is_ok());
+    }
+
+    #[test]
+    fn test_format_f64_matches_ecmascript() {
+        assert_eq!(format_f64(1e21), "1e+21");
+        assert_eq!(format_f64(1e20), "100000000000000000000");
+        assert_eq!(format_f64(0.000001), "0.000001");
+        assert_eq!(format_f64(1e-7), "1e-7");
+        assert_eq!(format_f64(-123.456), "-123.456");
+        assert_eq!(format_f64(-0.0), "0");
+    }
+}
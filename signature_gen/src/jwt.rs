@@ -0,0 +1,151 @@
+//! `sign --jwt`: the signing input and signature of a compact JWS, with its JWK
+//!
+//! A token is `header.payload.signature`, each part base64url without padding
+//! (RFC 7515). What is signed is the text `header.payload` as it stands, not
+//! its decoding, and the signature is the big-endian RSA integer. RS256 is
+//! PKCS#1 v1.5 and PS256 is PSS with a salt as long as the SHA-256 digest
+//! (RFC 7518 3.3 and 3.5); other algorithms are refused. The key is a JWK, or
+//! the member of a JWKS with the given `kid`, that of the token by default.
+
+use base64ct::{Base64UrlUnpadded, Encoding};
+use rsa::RsaPublicKey;
+use serde_json::Value;
+
+use crate::hash::HashAlg;
+use crate::keys::parse_public_key;
+use crate::signing::{PssSalt, Scheme};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Token {
+    /// The ASCII of `header.payload`
+    pub signing_input: Vec<u8>,
+    pub hash_alg: HashAlg,
+    pub scheme: Scheme,
+    /// The `kid` of the header, naming the key in a JWKS
+    pub kid: Option<String>,
+    pub signature: Vec<u8>,
+}
+
+fn decode_part(part: &str, name: &str) -> Result<Vec<u8>, String> {
+    Base64UrlUnpadded::decode_vec(part)
+        .map_err(|err| format!("the JWT {} is not base64url without padding: {}", name, err))
+}
+
+/// Take apart a compact token
+pub fn parse(token: &str) -> Result<Token, String> {
+    let parts: Vec<&str> = token.trim().split('.').collect();
+    let (header, payload, signature) = match parts[..] {
+        [header, payload, signature] => (header, payload, signature),
+        _ => return Err(format!("a compact JWT has 3 parts separated by '.', this one has {}", parts.len())),
+    };
+    let header_json: Value = serde_json::from_slice(&decode_part(header, "header")?)
+        .map_err(|err| format!("the JWT header is not JSON: {}", err))?;
+    decode_part(payload, "payload")?;
+    let (hash_alg, scheme) = match header_json["alg"].as_str() {
+        Some("RS256") => (HashAlg::Sha256, Scheme::Pkcs1v15),
+        Some("PS256") => (HashAlg::Sha256, Scheme::Pss(PssSalt::Random(HashAlg::Sha256.output_len()))),
+        Some(alg) => return Err(format!("the JWT is signed {}, only RS256 and PS256 are supported", alg)),
+        None => return Err("the JWT header has no \"alg\"".to_string()),
+    };
+    Ok(Token {
+        signing_input: format!("{}.{}", header, payload).into_bytes(),
+        hash_alg,
+        scheme,
+        kid: header_json["kid"].as_str().map(String::from),
+        signature: decode_part(signature, "signature")?,
+    })
+}
+
+/// The key of a JWK, or of the member of a JWKS with `kid`, which may be
+/// left out when the set has one key
+pub fn select_key(jwk: &str, kid: Option<&str>) -> Result<RsaPublicKey, String> {
+    let value: Value = serde_json::from_str(jwk).map_err(|err| format!("Invalid JWK: {}", err))?;
+    let keys = match value["keys"].as_array() {
+        Some(keys) => keys,
+        None => match (kid, value["kid"].as_str()) {
+            (Some(kid), Some(own)) if kid != own => {
+                return Err(format!("the JWK has kid {:?}, not {:?}", own, kid));
+            }
+            _ => return parse_public_key(jwk),
+        },
+    };
+    let member = match (kid, &keys[..]) {
+        (Some(kid), _) => keys.iter().find(|key| key["kid"] == kid).ok_or_else(|| {
+            let kids: Vec<String> = keys.iter().map(|key| key["kid"].to_string()).collect();
+            format!("no key of the JWKS has kid {:?}, its kids are {}", kid, kids.join(", "))
+        })?,
+        (None, [key]) => key,
+        (None, _) => return Err(format!("the JWKS has {} keys and the JWT no kid, pass --kid", keys.len())),
+    };
+    parse_public_key(&member.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha20Rng;
+    use rsa::pkcs8::DecodePrivateKey;
+    use rsa::RsaPrivateKey;
+
+    use crate::errors::Code;
+    use crate::keys::{export_public_key, ExportFormat};
+    use crate::signature_params_for_signature;
+    use crate::signing::sign_digest;
+
+    fn token(key: &RsaPrivateKey, alg: &str, payload: &str) -> String {
+        let header = format!("{{\"alg\":\"{}\",\"typ\":\"JWT\",\"kid\":\"passport\"}}", alg);
+        let input = format!(
+            "{}.{}",
+            Base64UrlUnpadded::encode_string(header.as_bytes()),
+            Base64UrlUnpadded::encode_string(payload.as_bytes())
+        );
+        let scheme = if alg == "PS256" { Scheme::Pss(PssSalt::Random(32)) } else { Scheme::Pkcs1v15 };
+        let digest = HashAlg::Sha256.digest(input.as_bytes());
+        let signature = sign_digest(&mut ChaCha20Rng::seed_from_u64(0), key, HashAlg::Sha256, &digest, &scheme);
+        format!("{}.{}", input, Base64UrlUnpadded::encode_string(&signature.unwrap()))
+    }
+
+    fn jwks_member(key: &RsaPrivateKey, kid: &str) -> Value {
+        let jwk = export_public_key(&key.to_public_key(), ExportFormat::Jwk).unwrap();
+        let mut jwk: Value = serde_json::from_slice(&jwk).unwrap();
+        jwk["kid"] = Value::String(kid.to_string());
+        jwk
+    }
+
+    #[test]
+    fn test_rs256_and_ps256_tokens_verify_under_the_key_of_their_kid() {
+        let key = RsaPrivateKey::from_pkcs8_pem(include_str!("../tests/fixtures/rsa_4096.pem")).unwrap();
+        let other = RsaPrivateKey::from_pkcs8_pem(include_str!("../tests/fixtures/rsa_2048.pem")).unwrap();
+        let jwks = serde_json::json!({ "keys": [jwks_member(&other, "other"), jwks_member(&key, "passport")] });
+        for alg in ["RS256", "PS256"] {
+            // Neither the 2-byte payload nor the 512-byte signature is a multiple of 3 bytes, so padding would show
+            let token = token(&key, alg, "{}");
+            assert!(!token.contains('=') && !token.contains('+') && !token.contains('/'));
+            let parsed = parse(&token).unwrap();
+            assert_eq!(parsed.signature.len(), 512);
+            assert_eq!(parsed.scheme.salt_len(), if alg == "PS256" { Some(32) } else { None });
+            let pub_key = select_key(&jwks.to_string(), parsed.kid.as_deref()).unwrap();
+            assert_eq!(pub_key, key.to_public_key());
+            let digest = parsed.hash_alg.digest(&parsed.signing_input);
+            let params =
+                signature_params_for_signature(&pub_key, parsed.hash_alg, &digest, &parsed.signature, &parsed.scheme);
+            assert_eq!(params.unwrap().signatures[0].signature_bytes(512), parsed.signature);
+
+            // The key of another kid does not verify the token
+            let wrong = select_key(&jwks.to_string(), Some("other")).unwrap();
+            let params =
+                signature_params_for_signature(&wrong, parsed.hash_alg, &digest, &parsed.signature, &parsed.scheme);
+            assert_eq!(params.err().unwrap().code, Code::VerificationFailed);
+        }
+        assert!(select_key(&jwks.to_string(), Some("missing")).unwrap_err().contains("no key of the JWKS"));
+        assert!(select_key(&jwks.to_string(), None).unwrap_err().contains("pass --kid"));
+        let single = jwks_member(&key, "passport").to_string();
+        assert_eq!(select_key(&single, None), Ok(key.to_public_key()));
+        assert!(select_key(&single, Some("other")).is_err());
+
+        let es256 = format!("{}.e30.AA", Base64UrlUnpadded::encode_string(b"{\"alg\":\"ES256\"}"));
+        assert!(parse(&es256).unwrap_err().contains("only RS256 and PS256"));
+        assert!(parse("e30.e30").unwrap_err().contains("3 parts"));
+    }
+}
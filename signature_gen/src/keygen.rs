@@ -0,0 +1,223 @@
+//! `--keygen-timeout`: key generation on a worker thread, retried when it runs long
+//!
+//! The prime search of a large key occasionally takes many times its average.
+//! With a timeout, each attempt runs on its own thread with an RNG seeded from
+//! the caller's, so a seed still reproduces the run. An attempt that runs out
+//! of time is cancelled through that RNG, which unwinds the search the next
+//! time it draws randomness, and the next attempt starts from fresh randomness.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use rand::SeedableRng;
+use rand_chacha::ChaCha20Rng;
+use rand_core::{CryptoRng, CryptoRngCore, RngCore};
+use rsa::RsaPrivateKey;
+
+use crate::errors::{Code, Error};
+use crate::generate_key;
+
+/// How often a running attempt is reported in verbose mode
+const PROGRESS_INTERVAL: Duration = Duration::from_secs(5);
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Options {
+    /// Time allowed to each attempt, or none to generate on the calling thread
+    pub timeout: Option<Duration>,
+    /// Attempts made after the first one has timed out
+    pub retries: u32,
+    pub verbose: bool,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Stats {
+    pub keys: usize,
+    pub attempts: usize,
+    pub timeouts: usize,
+    pub elapsed: Duration,
+    /// The longest successful attempt
+    pub slowest: Duration,
+}
+
+impl Stats {
+    /// The line of the verbose summary
+    pub fn summary(&self) -> String {
+        format!(
+            "Key generation: {} key(s) in {} attempt(s), {} timed out, {:.2}s in total, slowest key {:.2}s",
+            self.keys,
+            self.attempts,
+            self.timeouts,
+            self.elapsed.as_secs_f64(),
+            self.slowest.as_secs_f64()
+        )
+    }
+
+    /// A key found by its last attempt, after `attempts` in `elapsed`
+    fn record(&mut self, attempts: usize, elapsed: Duration, last_attempt: Duration) {
+        self.keys += 1;
+        self.attempts += attempts;
+        self.timeouts += attempts - 1;
+        self.elapsed += elapsed;
+        self.slowest = self.slowest.max(last_attempt);
+    }
+}
+
+/// Payload of the unwind ending a cancelled attempt
+struct Cancelled;
+
+/// An RNG that stops the key search drawing from it once `cancelled` is set
+struct CancellableRng {
+    rng: ChaCha20Rng,
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancellableRng {
+    fn check(&self) {
+        if self.cancelled.load(Ordering::Relaxed) {
+            // Unlike `panic!`, this does not run the panic hook, so nothing is printed
+            std::panic::resume_unwind(Box::new(Cancelled));
+        }
+    }
+}
+
+impl RngCore for CancellableRng {
+    fn next_u32(&mut self) -> u32 {
+        self.check();
+        self.rng.next_u32()
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.check();
+        self.rng.next_u64()
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        self.check();
+        self.rng.fill_bytes(dest)
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand_core::Error> {
+        self.check();
+        self.rng.try_fill_bytes(dest)
+    }
+}
+
+impl CryptoRng for CancellableRng {}
+
+/// One attempt on a worker thread, or none if it ran out of time
+fn attempt(
+    seed: [u8; 32],
+    bits: usize,
+    exponent: u32,
+    timeout: Duration,
+    verbose: bool,
+) -> Option<Result<RsaPrivateKey, Error>> {
+    let cancelled = Arc::new(AtomicBool::new(false));
+    let (sender, receiver) = mpsc::channel();
+    let worker = {
+        let mut rng = CancellableRng { rng: ChaCha20Rng::from_seed(seed), cancelled: Arc::clone(&cancelled) };
+        thread::spawn(move || {
+            // The receiver is gone only if the attempt was given up, and then the key is not wanted
+            let _ = sender.send(generate_key(&mut rng, bits, exponent));
+        })
+    };
+
+    let start = Instant::now();
+    let key = loop {
+        let remaining = timeout.saturating_sub(start.elapsed());
+        match receiver.recv_timeout(remaining.min(PROGRESS_INTERVAL)) {
+            Ok(key) => break Some(key),
+            Err(mpsc::RecvTimeoutError::Timeout) if start.elapsed() < timeout => {
+                if verbose {
+                    eprintln!("Generating a {}-bit key: {:.0}s elapsed", bits, start.elapsed().as_secs_f64());
+                }
+            }
+            Err(_) => break None,
+        }
+    };
+    cancelled.store(true, Ordering::Relaxed);
+    // Either the key was sent or the search unwinds at its next draw of randomness
+    let _ = worker.join();
+    key
+}
+
+/// A new key, within `options.timeout` per attempt if there is one
+pub fn generate<R: CryptoRngCore>(
+    rng: &mut R,
+    bits: usize,
+    exponent: u32,
+    options: &Options,
+    stats: &mut Stats,
+) -> Result<RsaPrivateKey, Error> {
+    let start = Instant::now();
+    let timeout = match options.timeout {
+        Some(timeout) => timeout,
+        None => {
+            let key = generate_key(rng, bits, exponent)?;
+            stats.record(1, start.elapsed(), start.elapsed());
+            return Ok(key);
+        }
+    };
+    for attempts in 1..=options.retries as usize + 1 {
+        let mut seed = [0u8; 32];
+        rng.fill_bytes(&mut seed);
+        let attempt_start = Instant::now();
+        match attempt(seed, bits, exponent, timeout, options.verbose) {
+            Some(key) => {
+                stats.record(attempts, start.elapsed(), attempt_start.elapsed());
+                return key;
+            }
+            None if options.verbose => {
+                let timeout = timeout.as_secs_f64();
+                eprintln!("Generating a {}-bit key: attempt {} timed out after {:.1}s", bits, attempts, timeout);
+            }
+            None => {}
+        }
+    }
+
+    let attempts = options.retries as usize + 1;
+    stats.attempts += attempts;
+    stats.timeouts += attempts;
+    stats.elapsed += start.elapsed();
+    let waited = start.elapsed().as_secs_f64();
+    Err(Error::new(
+        Code::Failed,
+        format!(
+            "Generating a {}-bit key timed out: {} attempt(s) of {:.1}s each, {:.1}s in total",
+            bits,
+            attempts,
+            timeout.as_secs_f64(),
+            waited
+        ),
+    )
+    .with("check", "keygen")
+    .with("bits", bits)
+    .with("attempts", attempts)
+    .with("waited_secs", waited))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_attempts_past_the_timeout_are_cancelled_and_retried() {
+        let mut rng = ChaCha20Rng::seed_from_u64(0);
+        let options = Options { timeout: Some(Duration::from_millis(1)), retries: 2, verbose: false };
+        let mut stats = Stats::default();
+        let start = Instant::now();
+        let err = generate(&mut rng, 4096, 65537, &options, &mut stats).unwrap_err();
+        assert_eq!(err.code, Code::Failed);
+        assert!(err.message.contains("3 attempt(s)"), "{}", err.message);
+        assert_eq!((stats.keys, stats.attempts, stats.timeouts), (0, 3, 3));
+        // The workers were stopped rather than left to finish their keys
+        assert!(start.elapsed() < Duration::from_secs(5));
+
+        let options = Options { timeout: Some(Duration::from_secs(600)), ..options };
+        let key = generate(&mut rng, 1024, 65537, &options, &mut stats).unwrap();
+        assert_eq!(crate::keys::key_bits(&key), 1024);
+        assert_eq!((stats.keys, stats.attempts, stats.timeouts), (1, 4, 3));
+    }
+}
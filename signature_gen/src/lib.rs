@@ -0,0 +1,372 @@
+//! Circuit inputs for the Noir RSA verifiers: the 120-bit limbs of keys and
+//! signatures and the Barrett reduction parameter, in the formats the
+//! `signature_gen` CLI emits. Services that need the inputs without shelling
+//! out call `generate_circuit_inputs`; the CLI is built on the same functions.
+
+// Most of the crate-private modules have no caller but the CLI
+#![cfg_attr(not(feature = "native"), allow(dead_code))]
+
+pub(crate) mod annotate;
+pub(crate) mod batch;
+pub(crate) mod boundary;
+#[cfg(feature = "native")]
+#[doc(hidden)]
+pub mod cli;
+pub mod commitment;
+pub(crate) mod convert;
+pub(crate) mod cross_check;
+pub(crate) mod difftest;
+pub(crate) mod dkim;
+pub(crate) mod doctor;
+pub(crate) mod edge_cases;
+pub mod errors;
+pub(crate) mod expected;
+#[cfg(feature = "acvm")]
+pub(crate) mod execute;
+pub mod hash;
+pub(crate) mod infer;
+pub(crate) mod inspect;
+pub(crate) mod invalid;
+pub(crate) mod iso9796;
+pub(crate) mod jcs;
+pub(crate) mod jwt;
+pub(crate) mod keygen;
+pub mod keys;
+pub(crate) mod manifest;
+pub(crate) mod meta;
+pub(crate) mod negative;
+pub mod params;
+pub(crate) mod poseidon2;
+pub mod presets;
+pub(crate) mod pss;
+pub(crate) mod raw_rsa;
+pub(crate) mod reference;
+pub(crate) mod reencode;
+pub(crate) mod regen;
+pub mod schema;
+#[cfg(feature = "native")]
+pub(crate) mod serve;
+pub mod signing;
+#[cfg(test)]
+mod snapshot_tests;
+#[cfg(feature = "native")]
+pub(crate) mod soak;
+pub(crate) mod stream;
+pub mod templates;
+pub(crate) mod verifier_script;
+pub(crate) mod verify;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+pub(crate) mod x509;
+
+use num_bigint::BigUint;
+use rand_core::CryptoRngCore;
+use rsa::traits::PublicKeyParts;
+use rsa::{RsaPrivateKey, RsaPublicKey};
+use serde::ser::SerializeStruct;
+use serde::{Serialize, Serializer};
+
+use noir_bignum_paramgen::split_into_120_bit_limbs;
+
+use errors::{Code, Error};
+use hash::HashAlg;
+use keys::key_bits;
+use params::{KeyParams, LimbStyle, SignatureParams, SignatureVector};
+use signing::{sign_digest, verify_digest, PssSalt, Scheme};
+
+pub fn generate_key<R: CryptoRngCore>(rng: &mut R, bits: usize, exponent: u32) -> Result<RsaPrivateKey, Error> {
+    RsaPrivateKey::new_with_exp(rng, bits, &BigUint::from(exponent))
+        .map_err(|err| Error::new(Code::Failed, format!("Failed to generate a {}-bit key: {}", bits, err)))
+}
+
+pub fn signature_params_for_key<R: CryptoRngCore>(
+    rng: &mut R,
+    priv_key: &RsaPrivateKey,
+    msg: &[u8],
+    hashes: &[HashAlg],
+    scheme: &Scheme,
+) -> Result<SignatureParams, Error> {
+    let digests: Vec<Vec<u8>> = hashes.iter().map(|hash_alg| hash_alg.digest(msg)).collect();
+    signature_params_for_digests(rng, priv_key, hashes, &digests, scheme)
+}
+
+/// Sign each digest with `priv_key`, checking every signature against the
+/// public key and the circuit's own steps, and split the key and signatures into limbs
+pub fn signature_params_for_digests<R: CryptoRngCore>(
+    rng: &mut R,
+    priv_key: &RsaPrivateKey,
+    hashes: &[HashAlg],
+    digests: &[Vec<u8>],
+    scheme: &Scheme,
+) -> Result<SignatureParams, Error> {
+    let bits = key_bits(priv_key);
+    let pub_key: RsaPublicKey = priv_key.to_public_key();
+
+    let key = KeyParams::from_modulus(pub_key.n(), bits);
+
+    // The same key signs the message once per hash, and every signature is
+    // checked against the public key before anything is printed
+    let mut signatures = Vec::with_capacity(hashes.len());
+    let mut warnings = Vec::new();
+    for (&hash_alg, hash) in hashes.iter().zip(digests) {
+        let hash = hash.to_vec();
+        let sig_bytes = sign_digest(rng, priv_key, hash_alg, &hash, scheme)
+            .map_err(|err| Error::new(Code::Failed, format!("Failed to sign the message: {}", err)))?;
+        verify_digest(&pub_key, hash_alg, &hash, &sig_bytes, scheme).map_err(|err| {
+            Error::new(Code::VerificationFailed, format!("The generated signature does not verify: {}", err))
+                .with("check", "signature")
+                .with("hash", hash_alg.name())
+        })?;
+
+        let sig_uint = BigUint::from_bytes_be(&sig_bytes);
+        let signature_limbs = split_into_120_bit_limbs(&sig_uint, bits);
+
+        // Also run the circuit's own steps on the limbs that will be emitted
+        let vector = reference::CircuitVector {
+            bits,
+            modulus_limbs: &key.modulus_limbs,
+            redc_limbs: &key.redc_limbs,
+            signature_limbs: &signature_limbs,
+            exponent: pub_key.e(),
+            hash_alg,
+            msg_hash: &hash,
+            scheme,
+        };
+        match reference::verify(&vector) {
+            Ok(()) => {}
+            Err(reference::Divergence::Exponent(e)) => {
+                let warning = exponent_warning(&e);
+                if !warnings.contains(&warning) {
+                    warnings.push(warning);
+                }
+            }
+            Err(divergence) => {
+                let message = format!("The generated signature fails the circuit's checks: {}", divergence);
+                return Err(Error::new(Code::VerificationFailed, message).with("check", "reference"));
+            }
+        }
+
+        signatures.push(SignatureVector {
+            hash_alg,
+            hash,
+            signature_limbs,
+            salt: scheme.fixed_salt().map(|salt| salt.to_vec()),
+        });
+    }
+
+    Ok(SignatureParams { key, signatures, message: None, warnings })
+}
+
+fn exponent_warning(exponent: &BigUint) -> String {
+    format!("exponent {} is not supported by the Noir verifier", exponent)
+}
+
+/// Circuit inputs for a signature made elsewhere, refused unless it verifies under `pub_key`
+pub fn signature_params_for_signature(
+    pub_key: &RsaPublicKey,
+    hash_alg: HashAlg,
+    digest: &[u8],
+    sig_bytes: &[u8],
+    scheme: &Scheme,
+) -> Result<SignatureParams, Error> {
+    let bits = key_bits(pub_key);
+    verify_digest(pub_key, hash_alg, digest, sig_bytes, scheme).map_err(|err| {
+        Error::new(Code::VerificationFailed, format!("The signature does not verify under the public key: {}", err))
+            .with("check", "signature")
+            .with("hash", hash_alg.name())
+    })?;
+
+    let key = KeyParams::from_modulus(pub_key.n(), bits);
+    // Split at the key size, so a signature with leading zero bytes keeps every limb
+    let signature_limbs = split_into_120_bit_limbs(&BigUint::from_bytes_be(sig_bytes), bits);
+    let vector = reference::CircuitVector {
+        bits,
+        modulus_limbs: &key.modulus_limbs,
+        redc_limbs: &key.redc_limbs,
+        signature_limbs: &signature_limbs,
+        exponent: pub_key.e(),
+        hash_alg,
+        msg_hash: digest,
+        scheme,
+    };
+    let mut warnings = Vec::new();
+    match reference::verify(&vector) {
+        Ok(()) => {}
+        Err(reference::Divergence::Exponent(e)) => warnings.push(exponent_warning(&e)),
+        Err(divergence) => {
+            let message = format!("The signature verifies but fails the circuit's checks: {}", divergence);
+            return Err(Error::new(Code::VerificationFailed, message).with("check", "reference"));
+        }
+    }
+
+    let signature = SignatureVector {
+        hash_alg,
+        hash: digest.to_vec(),
+        signature_limbs,
+        salt: scheme.fixed_salt().map(|salt| salt.to_vec()),
+    };
+    Ok(SignatureParams { key, signatures: vec![signature], message: None, warnings })
+}
+
+/// The inputs of a circuit verifying one signature. It serializes with the
+/// limbs as `0x`-prefixed hex strings in `limb_style` and the hash as an array
+/// of bytes, the way noir_js takes them, leaving out `warnings`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CircuitInputs {
+    pub hash: Vec<u8>,
+    pub modulus_limbs: Vec<BigUint>,
+    pub redc_limbs: Vec<BigUint>,
+    pub signature_limbs: Vec<BigUint>,
+    pub limb_style: LimbStyle,
+    /// Why the circuit may still reject the signature, such as an unsupported exponent
+    pub warnings: Vec<String>,
+}
+
+impl Serialize for CircuitInputs {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut inputs = serializer.serialize_struct("CircuitInputs", 4)?;
+        inputs.serialize_field("hash", &self.hash)?;
+        inputs.serialize_field("modulus_limbs", &self.modulus_limbs_hex())?;
+        inputs.serialize_field("redc_limbs", &self.redc_limbs_hex())?;
+        inputs.serialize_field("signature_limbs", &self.signature_limbs_hex())?;
+        inputs.end()
+    }
+}
+
+impl CircuitInputs {
+    fn hex_limbs(&self, limbs: &[BigUint]) -> Vec<String> {
+        limbs.iter().map(|limb| self.limb_style.format(limb)).collect()
+    }
+
+    pub fn modulus_limbs_hex(&self) -> Vec<String> {
+        self.hex_limbs(&self.modulus_limbs)
+    }
+
+    pub fn redc_limbs_hex(&self) -> Vec<String> {
+        self.hex_limbs(&self.redc_limbs)
+    }
+
+    pub fn signature_limbs_hex(&self) -> Vec<String> {
+        self.hex_limbs(&self.signature_limbs)
+    }
+}
+
+impl From<&SignatureParams> for CircuitInputs {
+    /// The inputs of the first signature of `params`, in the limb style of its key
+    fn from(params: &SignatureParams) -> CircuitInputs {
+        CircuitInputs {
+            hash: params.signatures[0].hash.clone(),
+            modulus_limbs: params.key.modulus_limbs.clone(),
+            redc_limbs: params.key.redc_limbs.clone(),
+            signature_limbs: params.signatures[0].signature_limbs.clone(),
+            limb_style: params.key.limb_style,
+            warnings: params.warnings.clone(),
+        }
+    }
+}
+
+/// Hash `msg` with `hash`, sign it with `key` and return the inputs of a
+/// circuit verifying the signature. PSS salts are drawn from the thread RNG.
+#[cfg(feature = "native")]
+pub fn generate_circuit_inputs(
+    msg: &[u8],
+    key: &RsaPrivateKey,
+    scheme: &Scheme,
+    hash: HashAlg,
+) -> Result<CircuitInputs, Error> {
+    let digests = [hash.digest(msg)];
+    let params = signature_params_for_digests(&mut rand::thread_rng(), key, &[hash], &digests, scheme)?;
+    Ok(CircuitInputs::from(&params))
+}
+
+/// The inputs of a circuit verifying a signature made elsewhere, from the
+/// big-endian `modulus` of a key with exponent 65537. `hash` is a name taken
+/// by `--hash` and `scheme` is `pkcs1v15` or `pss`, with a salt as long as the
+/// hash. Needs no RNG, so it also builds for wasm32.
+pub fn circuit_inputs_for_signature(
+    msg: &[u8],
+    modulus: &[u8],
+    signature: &[u8],
+    hash: &str,
+    scheme: &str,
+) -> Result<CircuitInputs, Error> {
+    let hash_alg = HashAlg::from_name(hash)
+        .ok_or_else(|| Error::new(Code::InvalidArgument, format!("Unknown hash {}", hash)).with("hash", hash))?;
+    let scheme = match scheme {
+        "pkcs1v15" => Scheme::Pkcs1v15,
+        "pss" => Scheme::Pss(PssSalt::Random(hash_alg.output_len())),
+        _ => {
+            let message = format!("Unknown scheme {}, expected pkcs1v15 or pss", scheme);
+            return Err(Error::new(Code::InvalidArgument, message).with("scheme", scheme));
+        }
+    };
+    let pub_key = RsaPublicKey::new(BigUint::from_bytes_be(modulus), BigUint::from(65537u32))
+        .map_err(|err| Error::new(Code::InvalidArgument, format!("Invalid modulus: {}", err)))?;
+    let digest = hash_alg.digest(msg);
+    let params = signature_params_for_signature(&pub_key, hash_alg, &digest, signature, &scheme)?;
+    Ok(CircuitInputs::from(&params))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rsa::pkcs8::DecodePrivateKey;
+
+    use crate::params::combine_limbs;
+    use crate::signing::PssSalt;
+
+    #[test]
+    #[cfg(feature = "native")]
+    fn test_circuit_inputs_recombine_to_the_key_and_a_valid_signature() {
+        let priv_key = RsaPrivateKey::from_pkcs8_pem(include_str!("../tests/fixtures/rsa_2048.pem")).unwrap();
+        let pub_key = priv_key.to_public_key();
+        let scheme = Scheme::Pss(PssSalt::Random(32));
+        let inputs = generate_circuit_inputs(b"hello world", &priv_key, &scheme, HashAlg::Sha512).unwrap();
+
+        assert_eq!(inputs.hash, HashAlg::Sha512.digest(b"hello world"));
+        assert_eq!(inputs.modulus_limbs.len(), 18);
+        assert_eq!(&combine_limbs(&inputs.modulus_limbs), pub_key.n());
+        let bytes = combine_limbs(&inputs.signature_limbs).to_bytes_be();
+        let mut signature = vec![0u8; pub_key.size() - bytes.len()];
+        signature.extend(bytes);
+        assert!(verify_digest(&pub_key, HashAlg::Sha512, &inputs.hash, &signature, &scheme).is_ok());
+
+        let json = serde_json::to_value(&inputs).unwrap();
+        assert_eq!(json["redc_limbs"], serde_json::json!(inputs.redc_limbs_hex()));
+        assert_eq!(json["hash"][0], inputs.hash[0]);
+        // A salt that does not fit the key is an error
+        let scheme = Scheme::Pss(PssSalt::Fixed(vec![0; 200]));
+        let err = generate_circuit_inputs(b"hello world", &priv_key, &scheme, HashAlg::Sha512).unwrap_err();
+        assert_eq!(err.code, Code::Failed);
+        assert!(inputs.warnings.is_empty());
+
+        // An exponent of 2^17 or more signs, with a warning that the circuit rejects it
+        let large_exponent = generate_key(&mut rand::thread_rng(), 1024, 131073).unwrap();
+        let inputs =
+            generate_circuit_inputs(b"hello world", &large_exponent, &Scheme::Pkcs1v15, HashAlg::Sha256).unwrap();
+        assert_eq!(inputs.warnings, vec![exponent_warning(&BigUint::from(131073u32))]);
+    }
+
+    #[test]
+    fn test_circuit_inputs_for_a_signature_made_elsewhere() {
+        let pub_key = keys::parse_public_key(include_str!("../tests/fixtures/rsa_2048_pub.pem")).unwrap();
+        let modulus = pub_key.n().to_bytes_be();
+        let signature = include_bytes!("../tests/fixtures/rsa_2048_sha256_pkcs1v15.sig");
+        let msg = b"Hello World! This is Noir-RSA";
+        let inputs = circuit_inputs_for_signature(msg, &modulus, signature, "sha256", "pkcs1v15").unwrap();
+
+        assert_eq!(hex::encode(&inputs.hash), "5bcf2e3c1699d990027fe08fb52d20787a83a64fa6b72b9e746949cfc44d2105");
+        assert_eq!(inputs.modulus_limbs_hex()[0], "0xd9408f681f2744650b2ffb8b8ece1b");
+        assert_eq!(inputs.redc_limbs_hex()[17], "0x65a9");
+        assert_eq!(inputs.signature_limbs_hex()[0], "0xdfd2ecd8d18e88f761b181a3c25b12");
+
+        // Each failure keeps the code the CLI would exit with
+        let code = |msg: &[u8], hash: &str, scheme: &str| {
+            circuit_inputs_for_signature(msg, &modulus, signature, hash, scheme).unwrap_err().code
+        };
+        assert_eq!(code(b"tampered", "sha256", "pkcs1v15"), Code::VerificationFailed);
+        assert_eq!(code(msg, "sha256", "pss"), Code::VerificationFailed);
+        assert_eq!(code(msg, "md4", "pkcs1v15"), Code::InvalidArgument);
+        assert_eq!(code(msg, "sha256", "raw"), Code::InvalidArgument);
+    }
+}
@@ -1,423 +1,3 @@
-use num_bigint::BigUint;
-use rsa::pkcs1v15::Signature;
-use rsa::{RsaPrivateKey, RsaPublicKey};
-use signature::Keypair;
-use signature::RandomizedSignerMut;
-use std::env;
-use toml::Value;
-
-use rsa::signature::{SignatureEncoding, Signer};
-use rsa::traits::PublicKeyParts;
-use sha2::{Digest, Sha256, Sha384, Sha512};
-use sha1::Sha1;
-
-use clap::{App, Arg};
-
-use noir_bignum_paramgen::{
-    bn_limbs, compute_barrett_reduction_parameter, split_into_120_bit_limbs,
-};
-
-fn format_limbs_as_hex(limbs: &Vec<BigUint>) -> String {
-    limbs
-        .iter()
-        .map(|a| format!("0x{:x}", a))
-        .collect::<Vec<_>>()
-        .join(", ")
-}
-
-fn format_limbs_as_toml_value(limbs: &Vec<BigUint>) -> Vec<Value> {
-    limbs
-        .iter()
-        .map(|a| Value::String(format!("0x{:x}", a)))
-        .collect()
-}
-
-fn generate_2048_bit_signature_parameters(msg: &str, as_toml: bool, exponent: u32, pss: bool, salt_len: usize) {
-    let mut hasher = Sha256::new();
-    hasher.update(msg.as_bytes());
-    let hashed_message = hasher.finalize();
-
-    let hashed_as_bytes = hashed_message
-        .iter()
-        .map(|&b| b.to_string())
-        .collect::<Vec<String>>()
-        .join(", ");
-
-    let mut rng: rand::prelude::ThreadRng = rand::thread_rng();
-    let bits: usize = 2048;
-    let priv_key: RsaPrivateKey =
-        RsaPrivateKey::new_with_exp(&mut rng, bits, &BigUint::from(exponent))
-            .expect("failed to generate a key");
-    let pub_key: RsaPublicKey = priv_key.clone().into();
-
-    let sig_bytes = if pss {
-        let mut signing_key = rsa::pss::BlindedSigningKey::<Sha256>::new_with_salt_len(priv_key, salt_len);
-        let sig = signing_key.sign_with_rng(&mut rng, msg.as_bytes());
-        sig.to_vec()
-    } else {
-        let signing_key = rsa::pkcs1v15::SigningKey::<Sha256>::new(priv_key);
-        signing_key.sign(msg.as_bytes()).to_vec()
-    };
-
-    let sig_uint: BigUint = BigUint::from_bytes_be(&sig_bytes);
-
-    let sig_str = bn_limbs(sig_uint.clone(), 2048);
-
-    let modulus_limbs: Vec<BigUint> = split_into_120_bit_limbs(&pub_key.n().clone(), 2048);
-    let redc_limbs = split_into_120_bit_limbs(
-        &compute_barrett_reduction_parameter(&pub_key.n().clone()),
-        2048,
-    );
-
-    if as_toml {
-        let sig_limbs = split_into_120_bit_limbs(&sig_uint.clone(), 2048);
-
-        println!("hash = [{}]", hashed_as_bytes);
-        println!(
-            "modulus_limbs = {}",
-            Value::Array(format_limbs_as_toml_value(&modulus_limbs))
-        );
-        println!(
-            "redc_limbs = {}",
-            Value::Array(format_limbs_as_toml_value(&redc_limbs))
-        );
-        println!(
-            "signature_limbs = {}",
-            Value::Array(format_limbs_as_toml_value(&sig_limbs))
-        );
-    } else {
-        println!("let hash: [u8; 32] = [{}];", hashed_as_bytes);
-        println!(
-            "let params: BigNumParams<18, 2048> = BigNumParams::new(\n\tfalse,\n\t[{}],\n\t[{}]\n);",
-            format_limbs_as_hex(&modulus_limbs),
-            format_limbs_as_hex(&redc_limbs)
-        );
-        println!(
-            "let signature: RuntimeBigNum<18, 2048> = RuntimeBigNum::from_array(\n\tparams,\n\tlimbs: {}\n);",
-            sig_str.as_str()
-        );
-    }
-}
-
-fn generate_1024_bit_signature_parameters(msg: &str, as_toml: bool, exponent: u32) {
-    let mut hasher = Sha256::new();
-    hasher.update(msg.as_bytes());
-    let hashed_message = hasher.finalize();
-
-    let hashed_as_bytes = hashed_message
-        .iter()
-        .map(|&b| b.to_string())
-        .collect::<Vec<String>>()
-        .join(", ");
-
-    let mut rng: rand::prelude::ThreadRng = rand::thread_rng();
-    let bits: usize = 1024;
-    let priv_key: RsaPrivateKey =
-        RsaPrivateKey::new_with_exp(&mut rng, bits, &BigUint::from(exponent))
-            .expect("failed to generate a key");
-    let pub_key: RsaPublicKey = priv_key.clone().into();
-
-    let signing_key = rsa::pkcs1v15::SigningKey::<Sha256>::new(priv_key);
-    let sig: Vec<u8> = signing_key.sign(msg.as_bytes()).to_vec();
-
-    let sig_bytes = &Signature::try_from(sig.as_slice()).unwrap().to_bytes();
-
-    let sig_uint: BigUint = BigUint::from_bytes_be(sig_bytes);
-
-    let sig_str = bn_limbs(sig_uint.clone(), 1024);
-
-    let modulus_limbs: Vec<BigUint> = split_into_120_bit_limbs(&pub_key.n().clone(), 1024);
-    let redc_limbs = split_into_120_bit_limbs(
-        &compute_barrett_reduction_parameter(&pub_key.n().clone()),
-        1024,
-    );
-
-    if as_toml {
-        let sig_limbs = split_into_120_bit_limbs(&sig_uint.clone(), 1024);
-
-        println!("hash = [{}]", hashed_as_bytes);
-        println!(
-            "modulus_limbs = {}",
-            Value::Array(format_limbs_as_toml_value(&modulus_limbs))
-        );
-        println!(
-            "redc_limbs = {}",
-            Value::Array(format_limbs_as_toml_value(&redc_limbs))
-        );
-        println!(
-            "signature_limbs = {}",
-            Value::Array(format_limbs_as_toml_value(&sig_limbs))
-        );
-    } else {
-        println!(
-            "let params: BigNumParams<9, 1024> = BigNumParams::new(\n\tfalse,\n\t[{}],\n\t[{}]\n);",
-            format_limbs_as_hex(&modulus_limbs),
-            format_limbs_as_hex(&redc_limbs)
-        );
-        println!(
-            "let signature: RuntimeBigNum<9, 1024> = RuntimeBigNum::from_array(\n\tparams,\n\tlimbs: {}\n);",
-            sig_str.as_str()
-        );
-    }
-}
-
-fn parse_limbs_to_biguint(limbs_str: &str) -> BigUint {
-    let mut result = BigUint::from(0u32);
-    for (i, limb_str) in limbs_str
-        .split(',')
-        .map(|s| s.trim())
-        .filter(|s| !s.is_empty())
-        .enumerate()
-    {
-        let limb_str = limb_str.strip_prefix("0x").unwrap_or(limb_str);
-        let limb = BigUint::parse_bytes(limb_str.as_bytes(), 16)
-            .expect(&format!("Invalid hex limb: {}", limb_str));
-        result += limb << (120 * i);
-    }
-    result
-}
-
-fn generate_bignum_params_from_modulus(modulus: &BigUint, bit_size: usize, as_toml: bool) {
-    let modulus_limbs: Vec<BigUint> = split_into_120_bit_limbs(modulus, bit_size);
-    let redc_limbs = split_into_120_bit_limbs(
-        &compute_barrett_reduction_parameter(modulus),
-        bit_size,
-    );
-
-    let num_limbs = ((bit_size as f64) / 120.0).ceil() as usize;
-
-    if as_toml {
-        println!(
-            "modulus_limbs = {}",
-            Value::Array(format_limbs_as_toml_value(&modulus_limbs))
-        );
-        println!(
-            "redc_limbs = {}",
-            Value::Array(format_limbs_as_toml_value(&redc_limbs))
-        );
-    } else {
-        println!(
-            "let params: BigNumParams<{}, {}> = BigNumParams::new(\n\tfalse,\n\t[{}],\n\t[{}]\n);",
-            num_limbs,
-            bit_size,
-            format_limbs_as_hex(&modulus_limbs),
-            format_limbs_as_hex(&redc_limbs)
-        );
-    }
-}
-
 fn main() {
-    let matches = App::new("RSA Signature Generator")
-        .subcommand(
-            clap::SubCommand::with_name("sign")
-                .about("Generate RSA signature parameters")
-                .arg(
-                    Arg::with_name("msg")
-                        .short("m")
-                        .long("msg")
-                        .takes_value(true)
-                        .help("Message to sign")
-                        .required(true),
-                )
-                .arg(
-                    Arg::with_name("toml")
-                        .short("t")
-                        .long("toml")
-                        .help("Print output in TOML format"),
-                )
-                .arg(
-                    Arg::with_name("exponent")
-                        .short("e")
-                        .long("exponent")
-                        .takes_value(true)
-                        .help("Exponent to use for the key")
-                        .default_value("65537"),
-                )
-                .arg(
-                    Arg::with_name("bits")
-                        .short("b")
-                        .long("bits")
-                        .takes_value(true)
-                        .help("Number of bits of RSA signature (1024 or 2048)")
-                        .default_value("2048"),
-                )
-                .arg(
-                    Arg::with_name("pss")
-                        .short("p")
-                        .long("pss")
-                        .help("Use RSA PSS"),
-                )
-                .arg(
-                    Arg::with_name("salt_len")
-                        .short("s")
-                        .long("salt-len")
-                        .takes_value(true)
-                        .help("Salt length for RSA PSS (only used with --pss)")
-                        .default_value("32"),
-                ),
-        )
-        .subcommand(
-            clap::SubCommand::with_name("params")
-                .about("Generate BigNum parameters (modulus and Barrett reduction) from a public key")
-                .arg(
-                    Arg::with_name("pubkey")
-                        .short("k")
-                        .long("pubkey")
-                        .takes_value(true)
-                        .help("RSA public key modulus as a hex string (with or without 0x prefix)")
-                        .conflicts_with("limbs"),
-                )
-                .arg(
-                    Arg::with_name("limbs")
-                        .short("l")
-                        .long("limbs")
-                        .takes_value(true)
-                        .help("RSA public key modulus as comma-separated 120-bit hex limbs (e.g. 0xaabb,0xccdd,...)")
-                        .conflicts_with("pubkey"),
-                )
-                .arg(
-                    Arg::with_name("bits")
-                        .short("b")
-                        .long("bits")
-                        .takes_value(true)
-                        .help("Bit size of the RSA key (e.g. 1024, 2048, 4096)")
-                        .required(true),
-                )
-                .arg(
-                    Arg::with_name("toml")
-                        .short("t")
-                        .long("toml")
-                        .help("Print output in TOML format"),
-                ),
-        )
-        .get_matches();
-
-    match matches.subcommand() {
-        ("sign", Some(sub_m)) => {
-            let msg = sub_m.value_of("msg").unwrap();
-            let as_toml = sub_m.is_present("toml");
-            let e: u32 = sub_m.value_of("exponent").unwrap().parse().unwrap();
-            let b: u32 = sub_m.value_of("bits").unwrap().parse().unwrap();
-            assert!(
-                b == 1024 || b == 2048,
-                "Number of bits of RSA signature can only be 1024 or 2048"
-            );
-            let pss = sub_m.is_present("pss");
-            let salt_len: usize = sub_m.value_of("salt_len").unwrap().parse().unwrap();
-
-            if b == 1024 {
-                generate_1024_bit_signature_parameters(msg, as_toml, e);
-            } else {
-                generate_2048_bit_signature_parameters(msg, as_toml, e, pss, salt_len);
-            }
-        }
-        ("params", Some(sub_m)) => {
-            let bits: usize = sub_m.value_of("bits").unwrap().parse().unwrap();
-            let as_toml = sub_m.is_present("toml");
-
-            let modulus = if let Some(pubkey) = sub_m.value_of("pubkey") {
-                let pubkey_hex = pubkey.strip_prefix("0x").unwrap_or(pubkey);
-                let pubkey_bytes = hex::decode(pubkey_hex).expect("Invalid hex string for pubkey");
-                BigUint::from_bytes_be(&pubkey_bytes)
-            } else if let Some(limbs) = sub_m.value_of("limbs") {
-                parse_limbs_to_biguint(limbs)
-            } else {
-                eprintln!("Either --pubkey or --limbs must be provided");
-                std::process::exit(1);
-            };
-
-            generate_bignum_params_from_modulus(&modulus, bits, as_toml);
-        }
-        _ => {
-            eprintln!("Please specify a subcommand: 'sign' or 'params'");
-            eprintln!("Run with --help for usage information");
-            std::process::exit(1);
-        }
-    }
-}
-
-/*fn test_signature_generation_impl() {
-    let mut rng = rand::thread_rng();
-    let bits = 2048;
-    let priv_key = RsaPrivateKey::new(&mut rng, bits).expect("failed to generate a key");
-    let pub_key: RsaPublicKey = priv_key.clone().into();
-    let text: &str = "hello world";
-    let signing_key = rsa::pkcs1v15::SigningKey::<Sha256>::new(priv_key);
-    let sig: Vec<u8> = signing_key.sign(text.as_bytes()).to_vec();
-    let verifying_key = VerifyingKey::<Sha256>::new(pub_key);
-
-    let result = verifying_key.verify(
-        text.as_bytes(),
-        &Signature::try_from(sig.as_slice()).unwrap(),
-    );
-    if b == 1024 {
-        generate_1024_bit_signature_parameters(msg, as_toml, e);
-    } else {
-        generate_2048_bit_signature_parameters(msg, as_toml, e, pss);
-    }
-}*/
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use rand::thread_rng;
-    use rsa::pkcs1v15::Signature;
-    use rsa::signature::{Signer, Verifier};
-    use rsa::{pkcs1v15::VerifyingKey, RsaPrivateKey, RsaPublicKey};
-    use sha2::{Sha256, Sha384, Sha512};
-
-    #[test]
-    fn test_signature_generation() {
-        let mut rng = thread_rng();
-        let bits = 2048;
-        let priv_key = RsaPrivateKey::new(&mut rng, bits).expect("failed to generate a key");
-        let pub_key: RsaPublicKey = priv_key.clone().into();
-        let text: &str = "hello world";
-        let signing_key = rsa::pkcs1v15::SigningKey::<Sha256>::new(priv_key);
-        let sig: Vec<u8> = signing_key.sign(text.as_bytes()).to_vec();
-        let verifying_key = VerifyingKey::<Sha256>::new(pub_key);
-
-        let result = verifying_key.verify(
-            text.as_bytes(),
-            &Signature::try_from(sig.as_slice()).unwrap(),
-        );
-        result.expect("failed to verify");
-    }
-
-    #[test]
-    fn test_signature_generation_sha384() {
-        let mut rng = thread_rng();
-        let bits = 2048;
-        let priv_key = RsaPrivateKey::new(&mut rng, bits).expect("failed to generate a key");
-        let pub_key: RsaPublicKey = priv_key.clone().into();
-        let text: &str = "hello world";
-        let signing_key = rsa::pkcs1v15::SigningKey::<Sha384>::new(priv_key);
-        let sig: Vec<u8> = signing_key.sign(text.as_bytes()).to_vec();
-        let verifying_key = VerifyingKey::<Sha384>::new(pub_key);
-
-        let result = verifying_key.verify(
-            text.as_bytes(),
-            &Signature::try_from(sig.as_slice()).unwrap(),
-        );
-        result.expect("failed to verify");
-    }
-
-    #[test]
-    fn test_signature_generation_sha512() {
-       let mut rng = thread_rng();
-        let bits = 2048;
-        let priv_key = RsaPrivateKey::new(&mut rng, bits).expect("failed to generate a key");
-        let pub_key: RsaPublicKey = priv_key.clone().into();
-        let text: &str = "hello world";
-        let signing_key = rsa::pkcs1v15::SigningKey::<Sha384>::new(priv_key);
-        let sig: Vec<u8> = signing_key.sign(text.as_bytes()).to_vec();
-        let verifying_key = VerifyingKey::<Sha384>::new(pub_key);
-
-        let result = verifying_key.verify(
-            text.as_bytes(),
-            &Signature::try_from(sig.as_slice()).unwrap(),
-        );
-        result.expect("failed to verify");
-    }
+    signature_gen::cli::run()
 }
-
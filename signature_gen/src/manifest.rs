@@ -0,0 +1,295 @@
+//! `--manifest` and `verify-manifest`: content hashes of committed vector files
+//!
+//! The manifest is TOML with one `[[files]]` entry per file, sorted by path:
+//! the path relative to the manifest, its SHA-256, and the subcommand and
+//! options that produced it, seed included. Updating replaces the entries of
+//! the files just written and keeps the others, and the new manifest is
+//! renamed over the old one so that it is never left half written.
+//! Regeneration runs the recorded command into a scratch directory, or for a
+//! single `sign` file to a file of its name there, and compares the file of
+//! the same name.
+
+use std::collections::BTreeMap;
+use std::ffi::OsStr;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use toml::value::Table;
+use toml::Value;
+
+use crate::hash::HashAlg;
+use crate::meta::{parse_seed_value, seed_value};
+use crate::params::to_toml_string;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Entry {
+    /// Relative to the directory of the manifest
+    pub path: String,
+    pub sha256: String,
+    /// Subcommand and options, without where the files were written
+    pub command: Vec<String>,
+    pub seed: Option<u64>,
+}
+
+/// A file whose contents no longer match the manifest
+#[derive(Debug, PartialEq, Eq)]
+pub enum Mismatch {
+    Missing { path: String },
+    Modified { path: String, expected: String, actual: String },
+    Regenerated { path: String, expected: String, actual: String },
+}
+
+impl std::fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Mismatch::Missing { path } => write!(f, "{}: missing", path),
+            Mismatch::Modified { path, expected, actual } => {
+                write!(f, "{}: sha256 is {} but the manifest records {}", path, actual, expected)
+            }
+            Mismatch::Regenerated { path, expected, actual } => {
+                write!(f, "{}: regenerated file has sha256 {} but the manifest records {}", path, actual, expected)
+            }
+        }
+    }
+}
+
+pub fn sha256_hex(contents: &[u8]) -> String {
+    hex::encode(HashAlg::Sha256.digest(contents))
+}
+
+/// The flag giving the output directory of a command writing vector files, or `--output`
+/// for a single `sign`, which writes one file
+fn output_flag(command: &[String]) -> Option<(&'static str, bool)> {
+    let batch = command.iter().any(|arg| arg == "--batch" || arg.starts_with("--batch="));
+    match command.first().map(String::as_str) {
+        Some("edge-cases") => Some(("--output-dir", true)),
+        Some("dkim-corpus") => Some(("--out-dir", true)),
+        Some("sign") if batch => Some(("--out-dir", true)),
+        Some("sign") => Some(("--output", false)),
+        _ => None,
+    }
+}
+
+fn read_error(path: &Path, err: impl std::fmt::Display) -> String {
+    format!("Failed to read {}: {}", path.display(), err)
+}
+
+fn parse_entry(value: &Value) -> Option<Entry> {
+    let table = value.as_table()?;
+    let command: Option<Vec<String>> =
+        table.get("command")?.as_array()?.iter().map(|arg| arg.as_str().map(str::to_string)).collect();
+    Some(Entry {
+        path: table.get("path")?.as_str()?.to_string(),
+        sha256: table.get("sha256")?.as_str()?.to_string(),
+        command: command?,
+        seed: match table.get("seed") {
+            Some(seed) => Some(parse_seed_value(seed)?),
+            None => None,
+        },
+    })
+}
+
+/// The entries of the manifest at `path`, or none if it does not exist yet
+pub fn read(path: &Path) -> Result<Vec<Entry>, String> {
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let contents = fs::read_to_string(path).map_err(|err| read_error(path, err))?;
+    let document: Value = contents.parse().map_err(|err| read_error(path, err))?;
+    let files = document.get("files").and_then(Value::as_array).map_or(&[][..], Vec::as_slice);
+    files
+        .iter()
+        .map(|value| {
+            parse_entry(value).ok_or_else(|| format!("{}: malformed [[files]] entry {}", path.display(), value))
+        })
+        .collect()
+}
+
+pub fn render(entries: &[Entry]) -> String {
+    let files = entries
+        .iter()
+        .map(|entry| {
+            let mut table = Table::new();
+            table.insert("path".to_string(), Value::String(entry.path.clone()));
+            table.insert("sha256".to_string(), Value::String(entry.sha256.clone()));
+            if let Some(seed) = entry.seed {
+                table.insert("seed".to_string(), seed_value(seed));
+            }
+            let command = entry.command.iter().map(|arg| Value::String(arg.clone())).collect();
+            table.insert("command".to_string(), Value::Array(command));
+            Value::Table(table)
+        })
+        .collect();
+    let mut document = Table::new();
+    document.insert("files".to_string(), Value::Array(files));
+    to_toml_string(document)
+}
+
+/// `file` relative to `dir`, both as given or both canonical
+fn relative_path(dir: &Path, file: &Path) -> Result<String, String> {
+    let dir = dir.canonicalize().map_err(|err| read_error(dir, err))?;
+    let file = file.canonicalize().map_err(|err| read_error(file, err))?;
+    let relative = file.strip_prefix(&dir).unwrap_or(&file);
+    Ok(relative.to_string_lossy().replace('\\', "/"))
+}
+
+/// Record `files`, just written by `command`, in the manifest at `manifest`
+pub fn update(manifest: &Path, files: &[PathBuf], command: &[String], seed: Option<u64>) -> Result<(), String> {
+    let dir = match manifest.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+        _ => PathBuf::from("."),
+    };
+    fs::create_dir_all(&dir).map_err(|err| format!("Failed to create {}: {}", dir.display(), err))?;
+
+    let mut entries: BTreeMap<String, Entry> =
+        read(manifest)?.into_iter().map(|entry| (entry.path.clone(), entry)).collect();
+    for file in files {
+        let contents = fs::read(file).map_err(|err| read_error(file, err))?;
+        let path = relative_path(&dir, file)?;
+        let entry = Entry { path: path.clone(), sha256: sha256_hex(&contents), command: command.to_vec(), seed };
+        entries.insert(path, entry);
+    }
+    let entries: Vec<Entry> = entries.into_values().collect();
+
+    let temporary = manifest.with_extension("toml.tmp");
+    fs::write(&temporary, render(&entries))
+        .map_err(|err| format!("Failed to write {}: {}", temporary.display(), err))?;
+    fs::rename(&temporary, manifest).map_err(|err| format!("Failed to replace {}: {}", manifest.display(), err))
+}
+
+/// Regenerate the files `names` of one command into a scratch directory
+fn regenerate(command: &[String], scratch: &Path, names: &[&OsStr]) -> Result<(), String> {
+    let (flag, is_dir) = output_flag(command)
+        .ok_or_else(|| format!("Files written by `{}` cannot be regenerated", command.join(" ")))?;
+    // A directory is written once, a single file once for each name it was saved under
+    let targets: Vec<PathBuf> = if is_dir {
+        vec![scratch.to_path_buf()]
+    } else {
+        names.iter().map(|name| scratch.join(name)).collect()
+    };
+    let exe = std::env::current_exe().map_err(|err| format!("Failed to locate signature_gen: {}", err))?;
+    for target in targets {
+        let output = Command::new(&exe)
+            .args(command)
+            .arg(flag)
+            .arg(target)
+            .output()
+            .map_err(|err| format!("Failed to run `{}`: {}", command.join(" "), err))?;
+        if !output.status.success() {
+            return Err(format!(
+                "`{}` failed while regenerating: {}",
+                command.join(" "),
+                String::from_utf8_lossy(&output.stderr).trim()
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Recompute the hash of every file listed in the manifest and, with `regenerate_seeded`,
+/// rerun the seeded commands and compare their output too
+pub fn verify(manifest: &Path, regenerate_seeded: bool) -> Result<Vec<Mismatch>, String> {
+    if !manifest.exists() {
+        return Err(format!("Manifest {} does not exist", manifest.display()));
+    }
+    let entries = read(manifest)?;
+    let dir = manifest.parent().unwrap_or(Path::new("."));
+    let mut mismatches = Vec::new();
+    for entry in &entries {
+        match fs::read(dir.join(&entry.path)) {
+            Ok(contents) => {
+                let actual = sha256_hex(&contents);
+                if actual != entry.sha256 {
+                    mismatches.push(Mismatch::Modified {
+                        path: entry.path.clone(),
+                        expected: entry.sha256.clone(),
+                        actual,
+                    });
+                }
+            }
+            Err(_) => mismatches.push(Mismatch::Missing { path: entry.path.clone() }),
+        }
+    }
+    if !regenerate_seeded {
+        return Ok(mismatches);
+    }
+
+    // Each distinct seeded command is run once for all of its files
+    let mut commands: BTreeMap<&[String], Vec<&Entry>> = BTreeMap::new();
+    for entry in entries.iter().filter(|entry| entry.seed.is_some()) {
+        commands.entry(entry.command.as_slice()).or_default().push(entry);
+    }
+    for (command, entries) in commands {
+        let scratch = tempfile::tempdir().map_err(|err| format!("Failed to create a scratch directory: {}", err))?;
+        let scratch = scratch.path();
+        let names: Vec<&OsStr> =
+            entries.iter().map(|entry| Path::new(&entry.path).file_name().unwrap_or_default()).collect();
+        regenerate(command, scratch, &names)?;
+        for (entry, name) in entries.into_iter().zip(names) {
+            let actual = match fs::read(scratch.join(name)) {
+                Ok(contents) => sha256_hex(&contents),
+                Err(_) => "nothing".to_string(),
+            };
+            if actual != entry.sha256 {
+                mismatches.push(Mismatch::Regenerated {
+                    path: entry.path.clone(),
+                    expected: entry.sha256.clone(),
+                    actual,
+                });
+            }
+        }
+    }
+    Ok(mismatches)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_updates_are_sorted_and_edits_are_detected() {
+        let scratch = tempfile::tempdir().unwrap();
+        let dir = scratch.path();
+        let (b, a) = (dir.join("b.toml"), dir.join("a.toml"));
+        fs::write(&b, "b = 1\n").unwrap();
+        fs::write(&a, "a = 1\n").unwrap();
+        let manifest = dir.join("manifest.toml");
+        let command = vec!["edge-cases".to_string(), "--seed".to_string(), "3".to_string()];
+        update(&manifest, &[b.clone()], &command, Some(3)).unwrap();
+        update(&manifest, &[a.clone()], &command, Some(3)).unwrap();
+
+        let entries = read(&manifest).unwrap();
+        let paths: Vec<&str> = entries.iter().map(|entry| entry.path.as_str()).collect();
+        assert_eq!(paths, ["a.toml", "b.toml"]);
+        assert_eq!(entries[0].sha256, sha256_hex(b"a = 1\n"));
+        assert_eq!(entries[0].command, command);
+        assert_eq!(render(&entries), fs::read_to_string(&manifest).unwrap());
+        assert_eq!(verify(&manifest, false), Ok(vec![]));
+
+        fs::write(&b, "b = 2\n").unwrap();
+        fs::remove_file(&a).unwrap();
+        let mismatches = verify(&manifest, false).unwrap();
+        assert_eq!(mismatches[0], Mismatch::Missing { path: "a.toml".to_string() });
+        assert!(matches!(&mismatches[1], Mismatch::Modified { path, .. } if path == "b.toml"));
+
+        // A seed above the range of TOML integers is written as hex, as in the metadata
+        let entry = Entry { seed: Some(u64::MAX), ..entries[0].clone() };
+        let rendered = render(&[entry.clone()]);
+        assert!(rendered.contains("seed = \"0xffffffffffffffff\""), "{}", rendered);
+        fs::write(&manifest, rendered).unwrap();
+        assert_eq!(read(&manifest).unwrap(), [entry]);
+    }
+
+    #[test]
+    fn test_an_empty_command_cannot_be_regenerated() {
+        let scratch = tempfile::tempdir().unwrap();
+        let manifest = scratch.path().join("manifest.toml");
+        fs::write(scratch.path().join("a.toml"), "a = 1\n").unwrap();
+        let sha256 = sha256_hex(b"a = 1\n");
+        let entry = Entry { path: "a.toml".to_string(), sha256, command: vec![], seed: Some(1) };
+        fs::write(&manifest, render(&[entry])).unwrap();
+        let err = verify(&manifest, true).unwrap_err();
+        assert!(err.contains("cannot be regenerated"), "{}", err);
+    }
+}
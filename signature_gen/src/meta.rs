@@ -0,0 +1,349 @@
+//! Provenance metadata recorded alongside emitted circuit inputs
+//!
+//! Only public values go into the metadata: the tool version, when and with
+//! which options the file was generated, the SHA-256 fingerprint of each
+//! public key, its exponent and the message. Private key material never reaches this module.
+//! The seed, key file paths and arguments of the run are what `regen` needs to
+//! regenerate the file, and `--tag` labels are what it selects files by.
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use num_traits::ToPrimitive;
+use rsa::pkcs8::EncodePublicKey;
+use rsa::traits::PublicKeyParts;
+use rsa::RsaPublicKey;
+use toml::value::Table;
+use toml::Value;
+
+use crate::hash::HashAlg;
+use crate::params::to_toml_string;
+use crate::signing::Scheme;
+
+/// Name of the metadata table in TOML output
+pub const META: &str = "meta";
+
+/// Name of the subtable of `--tag` labels
+pub const TAGS: &str = "tags";
+
+/// Messages up to this length that are valid UTF-8 are recorded verbatim,
+/// anything else by its SHA-256 digest
+const MAX_VERBATIM_MESSAGE_LEN: usize = 256;
+
+pub struct Meta {
+    pub timestamp: String,
+    /// `sha256:<hex>` of the DER SubjectPublicKeyInfo of each key, in output order
+    pub key_fingerprints: Vec<String>,
+    /// Public exponent of each key, read from the key
+    pub exponents: Vec<u64>,
+    pub hashes: Vec<HashAlg>,
+    pub scheme: Scheme,
+    /// The message without `prefix`
+    pub message: Vec<u8>,
+    /// SHA-256 of a message that was streamed rather than read, recorded instead of `message`
+    /// and, like it, without `prefix`
+    pub message_sha256: Option<Vec<u8>>,
+    /// The `--prehashed` digest, recorded instead of the message it was made from
+    pub prehashed: Option<Vec<u8>>,
+    /// Domain-separation bytes signed in front of the message, empty if none
+    pub prefix: Vec<u8>,
+    /// `--tag key=value` labels, in the order given
+    pub tags: Vec<(String, String)>,
+    pub seed: Option<u64>,
+    /// The `--invalid` kind of a vector made to be rejected
+    pub invalid: Option<String>,
+    /// The `--edge-case` of a valid boundary vector, with what it exercises
+    pub edge_case: Option<(String, String)>,
+    /// Paths of the `--key` files signed with, as given
+    pub key_files: Vec<String>,
+    /// Subcommand and arguments reproducing the output, empty if it cannot be rerun
+    pub command: Vec<String>,
+}
+
+impl Meta {
+    pub fn new(pub_keys: &[RsaPublicKey], hashes: &[HashAlg], scheme: &Scheme, message: &[u8]) -> Meta {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |elapsed| elapsed.as_secs());
+        Meta {
+            timestamp: format_timestamp(now),
+            key_fingerprints: pub_keys.iter().map(key_fingerprint).collect(),
+            // The rsa crate caps public exponents at 2^33 - 1
+            exponents: pub_keys.iter().map(|key| key.e().to_u64().unwrap()).collect(),
+            hashes: hashes.to_vec(),
+            scheme: scheme.clone(),
+            message: message.to_vec(),
+            message_sha256: None,
+            prehashed: None,
+            prefix: Vec::new(),
+            tags: Vec::new(),
+            seed: None,
+            invalid: None,
+            edge_case: None,
+            key_files: Vec::new(),
+            command: Vec::new(),
+        }
+    }
+
+    pub fn toml_table(&self) -> Table {
+        let mut table = Table::new();
+        let string = |s: &str| Value::String(s.to_string());
+        table.insert("tool".to_string(), string(env!("CARGO_PKG_NAME")));
+        table.insert("tool_version".to_string(), string(env!("CARGO_PKG_VERSION")));
+        if let Some(git_hash) = option_env!("SIGNATURE_GEN_GIT_HASH") {
+            table.insert("git_hash".to_string(), string(git_hash));
+        }
+        table.insert("timestamp".to_string(), string(&self.timestamp));
+        table.insert(
+            "key_fingerprints".to_string(),
+            Value::Array(self.key_fingerprints.iter().map(|f| string(f.as_str())).collect()),
+        );
+        table.insert(
+            "exponents".to_string(),
+            Value::Array(self.exponents.iter().map(|&e| Value::Integer(e as i64)).collect()),
+        );
+        table.insert(
+            "hashes".to_string(),
+            Value::Array(self.hashes.iter().map(|hash_alg| string(hash_alg.name())).collect()),
+        );
+        match self.scheme.salt_len() {
+            Some(salt_len) => {
+                table.insert("scheme".to_string(), string("pss"));
+                table.insert("salt_len".to_string(), Value::Integer(salt_len as i64));
+            }
+            None => {
+                table.insert("scheme".to_string(), string("pkcs1v15"));
+            }
+        }
+        match (&self.prehashed, &self.message_sha256, std::str::from_utf8(&self.message)) {
+            (Some(digest), _, _) => {
+                table.insert("prehashed".to_string(), Value::String(hex::encode(digest)));
+            }
+            (None, Some(digest), _) => {
+                table.insert("message_sha256".to_string(), Value::String(hex::encode(digest)));
+            }
+            (None, None, Ok(message)) if message.len() <= MAX_VERBATIM_MESSAGE_LEN => {
+                table.insert("message".to_string(), string(message));
+            }
+            (None, None, _) => {
+                let digest = HashAlg::Sha256.digest(&self.message);
+                table.insert("message_sha256".to_string(), Value::String(hex::encode(digest)));
+            }
+        }
+        if !self.prefix.is_empty() {
+            table.insert("prefix_hex".to_string(), Value::String(hex::encode(&self.prefix)));
+        }
+        if let Some(seed) = self.seed {
+            table.insert("seed".to_string(), seed_value(seed));
+        }
+        if let Some(invalid) = &self.invalid {
+            table.insert("invalid".to_string(), string(invalid));
+        }
+        if let Some((name, description)) = &self.edge_case {
+            table.insert("edge_case".to_string(), string(name));
+            table.insert("edge_case_note".to_string(), string(description));
+        }
+        if !self.key_files.is_empty() {
+            table.insert("key_files".to_string(), Value::Array(self.key_files.iter().map(|f| string(f)).collect()));
+        }
+        if !self.command.is_empty() {
+            table.insert("command".to_string(), Value::Array(self.command.iter().map(|arg| string(arg)).collect()));
+        }
+        // Last, as a subtable is written after the values of its table
+        if !self.tags.is_empty() {
+            let tags = self.tags.iter().map(|(name, value)| (name.clone(), string(value))).collect();
+            table.insert(TAGS.to_string(), Value::Table(tags));
+        }
+        table
+    }
+
+    /// The `[meta]` table, to be appended to a TOML document
+    pub fn render_toml(&self) -> String {
+        let mut document = Table::new();
+        document.insert(META.to_string(), Value::Table(self.toml_table()));
+        to_toml_string(document)
+    }
+
+    /// The metadata as a header of `//` comments, for Noir snippets
+    pub fn render_comment(&self) -> String {
+        self.toml_table()
+            .into_iter()
+            .map(|(name, value)| format!("// {}: {}\n", name, value))
+            .collect()
+    }
+}
+
+/// A seed as TOML: TOML integers are signed, so a seed above their range is kept readable as hex
+pub fn seed_value(seed: u64) -> Value {
+    match i64::try_from(seed) {
+        Ok(seed) => Value::Integer(seed),
+        Err(_) => Value::String(format!("{:#x}", seed)),
+    }
+}
+
+/// The seed written by [`seed_value`]
+pub fn parse_seed_value(value: &Value) -> Option<u64> {
+    match value {
+        Value::Integer(seed) => u64::try_from(*seed).ok(),
+        Value::String(seed) => crate::keys::parse_seed(seed).ok(),
+        _ => None,
+    }
+}
+
+/// Parse a `--tag` or `--filter`, `name=value` with a name of letters, digits, `_` and `-`
+pub fn parse_tag(tag: &str) -> Result<(String, String), String> {
+    match tag.split_once('=') {
+        Some((name, value))
+            if !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-') =>
+        {
+            Ok((name.to_string(), value.to_string()))
+        }
+        _ => Err(format!("`{}` is not name=value with a name of letters, digits, `_` and `-`", tag)),
+    }
+}
+
+/// `sha256:<hex>` of the DER-encoded SubjectPublicKeyInfo, as printed by
+/// `openssl pkey -pubin -outform DER | sha256sum`
+pub fn key_fingerprint(pub_key: &RsaPublicKey) -> String {
+    let der = pub_key.to_public_key_der().expect("failed to encode the public key");
+    format!("sha256:{}", hex::encode(HashAlg::Sha256.digest(der.as_bytes())))
+}
+
+/// RFC 3339 UTC timestamp of a Unix time in seconds
+pub fn format_timestamp(secs: u64) -> String {
+    let days = (secs / 86400) as i64;
+    let rem = secs % 86400;
+
+    // Civil date from a day count (Howard Hinnant's days_from_civil inverse)
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        rem / 3600,
+        rem / 60 % 60,
+        rem % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use num_bigint::BigUint;
+    use rand::SeedableRng;
+    use rsa::pkcs8::DecodePrivateKey;
+    use rsa::traits::PrivateKeyParts;
+    use rsa::RsaPrivateKey;
+
+    use crate::signature_params_for_key;
+    use crate::signing::PssSalt;
+
+    const FIXTURE_KEY: &str = include_str!("../tests/fixtures/rsa_2048.pem");
+
+    #[test]
+    fn test_format_timestamp() {
+        assert_eq!(format_timestamp(0), "1970-01-01T00:00:00Z");
+        assert_eq!(format_timestamp(951782400), "2000-02-29T00:00:00Z");
+        assert_eq!(format_timestamp(1700000000), "2023-11-14T22:13:20Z");
+    }
+
+    #[test]
+    fn test_key_fingerprint_covers_the_spki() {
+        let priv_key = RsaPrivateKey::from_pkcs8_pem(FIXTURE_KEY).unwrap();
+        let fingerprint = key_fingerprint(&priv_key.to_public_key());
+        assert!(fingerprint.starts_with("sha256:"));
+        assert_eq!(fingerprint.len(), "sha256:".len() + 64);
+
+        let other = RsaPrivateKey::from_pkcs8_pem(include_str!("../tests/fixtures/rsa_1024.pem")).unwrap();
+        assert_ne!(key_fingerprint(&other.to_public_key()), fingerprint);
+    }
+
+    #[test]
+    fn test_meta_contains_no_private_key_material() {
+        let priv_key = RsaPrivateKey::from_pkcs8_pem(FIXTURE_KEY).unwrap();
+        let scheme = Scheme::Pss(PssSalt::Random(32));
+        let mut rng = rand_chacha::ChaCha20Rng::seed_from_u64(0);
+        let params = signature_params_for_key(&mut rng, &priv_key, b"hello", &[HashAlg::Sha256], &scheme).unwrap();
+        let mut meta = Meta::new(&[priv_key.to_public_key()], &[HashAlg::Sha256], &scheme, b"hello");
+        meta.tags = vec![("suite".to_string(), "pss".to_string())];
+        meta.seed = Some(7);
+        meta.key_files = vec!["tests/fixtures/rsa_2048.pem".to_string()];
+        meta.command = vec!["sign".to_string(), "--pss".to_string()];
+        let output = format!(
+            "{}\n{}{}",
+            params.render_toml(crate::schema::Names::Canonical),
+            meta.render_toml(),
+            meta.render_comment()
+        )
+        .to_lowercase();
+
+        let mut secrets: Vec<&BigUint> = vec![priv_key.d()];
+        secrets.extend(priv_key.primes());
+        for secret in secrets {
+            for encoding in [secret.to_str_radix(16), secret.to_str_radix(10)] {
+                // Any 32 digit window would be enough to leak a meaningful part of the value
+                for window in encoding.as_bytes().chunks(32).filter(|window| window.len() == 32) {
+                    assert!(!output.contains(std::str::from_utf8(window).unwrap()));
+                }
+            }
+        }
+        for line in FIXTURE_KEY.lines().filter(|line| !line.starts_with("-----")) {
+            assert!(!output.contains(&line.to_lowercase()));
+        }
+
+        // Only these fields may ever be recorded
+        let allowed = [
+            "tool",
+            "tool_version",
+            "git_hash",
+            "timestamp",
+            "key_fingerprints",
+            "exponents",
+            "hashes",
+            "scheme",
+            "salt_len",
+            "message",
+            "message_sha256",
+            "prehashed",
+            "prefix_hex",
+            "seed",
+            "invalid",
+            "edge_case",
+            "edge_case_note",
+            "key_files",
+            "command",
+            "tags",
+        ];
+        for name in meta.toml_table().keys() {
+            assert!(allowed.contains(&name.as_str()), "unexpected metadata field `{}`", name);
+        }
+    }
+
+    #[test]
+    fn test_long_or_binary_messages_are_recorded_by_digest() {
+        for message in [vec![0xffu8, 0xfe], vec![b'a'; MAX_VERBATIM_MESSAGE_LEN + 1]] {
+            let meta = Meta::new(&[], &[HashAlg::Sha256], &Scheme::Pkcs1v15, &message);
+            let table = meta.toml_table();
+            assert!(!table.contains_key("message"));
+            assert_eq!(table["message_sha256"].as_str(), Some(hex::encode(HashAlg::Sha256.digest(&message)).as_str()));
+        }
+    }
+
+    #[test]
+    fn test_prefix_is_recorded_apart_from_the_message() {
+        let mut meta = Meta::new(&[], &[HashAlg::Sha256], &Scheme::Pkcs1v15, b"hello");
+        assert!(!meta.toml_table().contains_key("prefix_hex"));
+
+        meta.prefix = b"\x19context:".to_vec();
+        let table = meta.toml_table();
+        assert_eq!(table["message"].as_str(), Some("hello"));
+        assert_eq!(table["prefix_hex"].as_str(), Some("19636f6e746578743a"));
+    }
+}
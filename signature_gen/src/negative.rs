@@ -0,0 +1,284 @@
+//! `run-negative` subcommand: check that the circuit rejects every invalid vector
+//!
+//! Each TOML file of a directory is a Prover.toml, labelled either with a
+//! top-level `expected_valid` (and optionally `tamper`, naming what was
+//! changed) or with the `[edge_case]` table written by `edge-cases`. Every
+//! file runs through a scratch copy of the example project for its shape,
+//! or through `--execute-artifact` in-process, and after every few of them a
+//! freshly signed control vector of the same shape must be accepted, so a
+//! harness that rejects everything cannot pass. Results are grouped by tamper
+//! kind.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use rand::SeedableRng;
+use rand_chacha::ChaCha20Rng;
+use tempfile::TempDir;
+use toml::value::Table;
+use toml::Value;
+
+use crate::convert::parse_limbs;
+use crate::difftest::{Project, Shape};
+use crate::hash::HashAlg;
+use crate::meta::META;
+use crate::schema::{Names, HASH, MODULUS_LIMBS};
+use crate::signing::{PssSalt, Scheme};
+use crate::{generate_key, signature_params_for_key};
+
+/// Tamper kind of invalid vectors that do not name one
+const UNLABELLED: &str = "unlabelled";
+
+pub struct Options {
+    pub vectors_dir: PathBuf,
+    pub example_dir: PathBuf,
+    pub nargo: String,
+    /// Compiled circuit every vector is executed against in-process, instead of nargo
+    pub artifact: Option<PathBuf>,
+    /// Exponent of every key, which the circuit takes as a constant
+    pub exponent: u32,
+    /// Whether vectors without a `[meta]` table are PSS
+    pub pss: bool,
+    /// Salt length of those PSS vectors, the hash length if `None`
+    pub salt_len: Option<usize>,
+    /// A control vector is run after every this many vectors
+    pub control_every: usize,
+    pub seed: u64,
+}
+
+/// A vector read from the directory
+pub struct LabelledVector {
+    pub path: PathBuf,
+    pub prover_toml: String,
+    pub shape: Shape,
+    pub expected_valid: bool,
+    /// What makes the vector invalid, `None` for valid ones
+    pub kind: Option<String>,
+}
+
+fn hash_alg_of_len(len: usize) -> Option<HashAlg> {
+    [HashAlg::Sha1, HashAlg::Sha256, HashAlg::Sha384, HashAlg::Sha512]
+        .into_iter()
+        .find(|hash_alg| hash_alg.output_len() == len)
+}
+
+/// Read the expectation, tamper kind and circuit shape of a labelled Prover.toml
+pub fn parse_vector(path: &Path, prover_toml: String, options: &Options) -> Result<LabelledVector, String> {
+    let name = path.display();
+    let doc: Table = toml::from_str(&prover_toml).map_err(|err| format!("{}: {}", name, err))?;
+
+    let edge_case = doc.get("edge_case").and_then(Value::as_table);
+    let (expected_valid, kind) = match (doc.get("expected_valid"), edge_case) {
+        (Some(expected_valid), _) => {
+            let expected_valid = expected_valid
+                .as_bool()
+                .ok_or_else(|| format!("{}: `expected_valid` is not a boolean", name))?;
+            (expected_valid, doc.get("tamper").and_then(Value::as_str).map(str::to_string))
+        }
+        (None, Some(edge_case)) => (
+            edge_case.get("expect").and_then(Value::as_str) == Some("accept"),
+            edge_case.get("name").and_then(Value::as_str).map(str::to_string),
+        ),
+        (None, None) => return Err(format!("{}: no `expected_valid` or `[edge_case]` label", name)),
+    };
+
+    let modulus = parse_limbs(doc.get(MODULUS_LIMBS), MODULUS_LIMBS).map_err(|err| format!("{}: {}", name, err))?;
+    let hash_len = doc.get(HASH).and_then(Value::as_array).map_or(0, Vec::len);
+    let hash_alg = hash_alg_of_len(hash_len)
+        .ok_or_else(|| format!("{}: a {} byte `hash` matches no supported hash", name, hash_len))?;
+    let meta = doc.get(META).and_then(Value::as_table);
+    let salt_len = match meta.and_then(|meta| meta.get("scheme")).and_then(Value::as_str) {
+        Some("pss") => Some(
+            meta.and_then(|meta| meta.get("salt_len"))
+                .and_then(Value::as_integer)
+                .map_or(hash_alg.output_len(), |len| len as usize),
+        ),
+        Some(_) => None,
+        None if options.pss => Some(options.salt_len.unwrap_or(hash_alg.output_len())),
+        None => None,
+    };
+
+    Ok(LabelledVector {
+        path: path.to_path_buf(),
+        prover_toml,
+        shape: Shape { bits: modulus.bits(), hash_alg, salt_len, exponent: options.exponent },
+        expected_valid,
+        kind: if expected_valid { None } else { Some(kind.unwrap_or_else(|| UNLABELLED.to_string())) },
+    })
+}
+
+fn read_vectors(options: &Options) -> Result<Vec<LabelledVector>, String> {
+    let dir = &options.vectors_dir;
+    let mut paths: Vec<PathBuf> = fs::read_dir(dir)
+        .map_err(|err| format!("Failed to read {}: {}", dir.display(), err))?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().map_or(false, |extension| extension == "toml"))
+        .collect();
+    paths.sort();
+    if paths.is_empty() {
+        return Err(format!("No .toml vectors in {}", dir.display()));
+    }
+    paths
+        .into_iter()
+        .map(|path| {
+            let prover_toml =
+                fs::read_to_string(&path).map_err(|err| format!("Failed to read {}: {}", path.display(), err))?;
+            parse_vector(&path, prover_toml, options)
+        })
+        .collect()
+}
+
+/// A freshly signed vector of `shape` that the circuit must accept
+fn control_vector(rng: &mut ChaCha20Rng, shape: &Shape) -> Result<String, String> {
+    let priv_key = generate_key(&mut *rng, shape.bits, shape.exponent).map_err(|err| err.message)?;
+    let scheme = match shape.salt_len {
+        Some(salt_len) => Scheme::Pss(PssSalt::Random(salt_len)),
+        None => Scheme::Pkcs1v15,
+    };
+    let params = signature_params_for_key(rng, &priv_key, b"run-negative control", &[shape.hash_alg], &scheme)
+        .map_err(|err| err.message)?;
+    Ok(params.render_toml(Names::Canonical))
+}
+
+/// Runs vectors through nargo, one scratch project per shape, or through an artifact
+struct Executor<'a> {
+    options: &'a Options,
+    scratch: TempDir,
+    projects: HashMap<Shape, Project>,
+}
+
+impl Executor<'_> {
+    /// Whether the circuit accepts `prover_toml`
+    fn accepts(&mut self, shape: &Shape, prover_toml: &str) -> Result<bool, String> {
+        if let Some(artifact) = &self.options.artifact {
+            return execute_artifact(artifact, prover_toml);
+        }
+        if !self.projects.contains_key(shape) {
+            let project = Project::create(&self.options.example_dir, self.scratch.path().join(shape.name()), shape)?;
+            self.projects.insert(*shape, project);
+        }
+        Ok(self.projects[shape].execute(&self.options.nargo, prover_toml)?.status.success())
+    }
+}
+
+#[cfg(feature = "acvm")]
+fn execute_artifact(artifact: &Path, prover_toml: &str) -> Result<bool, String> {
+    Ok(crate::execute::execute(artifact, prover_toml)? == crate::execute::Execution::Satisfied)
+}
+
+#[cfg(not(feature = "acvm"))]
+fn execute_artifact(_artifact: &Path, _prover_toml: &str) -> Result<bool, String> {
+    Err("--execute-artifact needs signature_gen built with `--features acvm`".to_string())
+}
+
+/// Accepted and rejected counts of one tamper kind
+#[derive(Default)]
+struct Tally {
+    accepted: Vec<PathBuf>,
+    rejected: usize,
+}
+
+/// Run every vector, returning whether each invalid one was rejected and every valid one accepted
+pub fn run_negative(options: &Options) -> Result<bool, String> {
+    let vectors = read_vectors(options)?;
+    if options.artifact.is_none() {
+        match Command::new(&options.nargo).arg("--version").output() {
+            Ok(output) if output.status.success() => {}
+            _ => return Err(format!("run-negative needs nargo, but `{} --version` failed", options.nargo)),
+        }
+    }
+
+    let mut executor = Executor {
+        options,
+        scratch: tempfile::tempdir().map_err(|err| format!("Failed to create a scratch directory: {}", err))?,
+        projects: HashMap::new(),
+    };
+    let mut rng = ChaCha20Rng::seed_from_u64(options.seed);
+    let mut kinds: BTreeMap<String, Tally> = BTreeMap::new();
+    let (mut valid_rejected, mut controls, mut controls_rejected) = (Vec::new(), 0, 0);
+
+    for (i, vector) in vectors.iter().enumerate() {
+        let accepted = executor.accepts(&vector.shape, &vector.prover_toml)?;
+        match &vector.kind {
+            Some(kind) => {
+                let tally = kinds.entry(kind.clone()).or_default();
+                if accepted {
+                    println!("FAIL {}: invalid ({}) but the circuit accepts it", vector.path.display(), kind);
+                    tally.accepted.push(vector.path.clone());
+                } else {
+                    tally.rejected += 1;
+                }
+            }
+            None if !accepted => {
+                println!("FAIL {}: valid but the circuit rejects it", vector.path.display());
+                valid_rejected.push(vector.path.clone());
+            }
+            None => {}
+        }
+
+        if (i + 1) % options.control_every == 0 || i + 1 == vectors.len() {
+            controls += 1;
+            if !executor.accepts(&vector.shape, &control_vector(&mut rng, &vector.shape)?)? {
+                println!("FAIL control vector for {}: the circuit rejects a valid signature", vector.shape.name());
+                controls_rejected += 1;
+            }
+        }
+    }
+
+    println!("{:<32} {:>8} {:>8}", "tamper kind", "rejected", "accepted");
+    for (kind, tally) in &kinds {
+        println!("{:<32} {:>8} {:>8}", kind, tally.rejected, tally.accepted.len());
+    }
+    let valid = vectors.iter().filter(|vector| vector.expected_valid).count();
+    println!("valid vectors: {} accepted, {} rejected", valid - valid_rejected.len(), valid_rejected.len());
+    println!("control vectors: {} accepted, {} rejected", controls - controls_rejected, controls_rejected);
+    if controls_rejected > 0 {
+        println!("Control vectors were rejected, the harness or circuit setup is broken and the results above prove nothing");
+    }
+
+    let accepted_invalid: usize = kinds.values().map(|tally| tally.accepted.len()).sum();
+    Ok(accepted_invalid == 0 && valid_rejected.is_empty() && controls_rejected == 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options() -> Options {
+        Options {
+            vectors_dir: PathBuf::new(),
+            example_dir: PathBuf::new(),
+            nargo: "nargo".to_string(),
+            artifact: None,
+            exponent: 65537,
+            pss: false,
+            salt_len: None,
+            control_every: 1,
+            seed: 0,
+        }
+    }
+
+    #[test]
+    fn test_vectors_are_labelled_from_either_annotation() {
+        let mut rng = ChaCha20Rng::seed_from_u64(0);
+        let shape = Shape { bits: 1024, hash_alg: HashAlg::Sha256, salt_len: None, exponent: 65537 };
+        let inputs = control_vector(&mut rng, &shape).unwrap();
+        let path = Path::new("vector.toml");
+
+        let tampered = format!("expected_valid = false\ntamper = \"oid\"\n{}", inputs);
+        let vector = parse_vector(path, tampered, &options()).unwrap();
+        assert!(!vector.expected_valid);
+        assert_eq!(vector.kind.as_deref(), Some("oid"));
+        assert_eq!(vector.shape, shape);
+
+        let edge_case = format!("{}\n[edge_case]\nname = \"signature-n-minus-1\"\nexpect = \"reject\"\n", inputs);
+        let vector = parse_vector(path, edge_case, &options()).unwrap();
+        assert_eq!(vector.kind.as_deref(), Some("signature-n-minus-1"));
+
+        let unnamed = format!("expected_valid = false\n{}", inputs);
+        assert_eq!(parse_vector(path, unnamed, &options()).unwrap().kind.as_deref(), Some(UNLABELLED));
+        assert!(parse_vector(path, inputs, &options()).is_err());
+    }
+}
@@ -0,0 +1,669 @@
+use std::fmt::Write;
+
+use num_bigint::BigUint;
+use num_traits::ToPrimitive;
+use toml::value::Table;
+use toml::Value;
+
+use noir_bignum_paramgen::{compute_barrett_reduction_parameter, split_into_120_bit_limbs};
+
+use crate::hash::HashAlg;
+use crate::commitment::{pubkey_hash, pubkey_poseidon2, PubkeyHash};
+use crate::schema::{
+    Names, ACTIVE_COUNT, EXPONENT, HASH, MESSAGE, MESSAGE_LEN, MODULUS_LIMBS, NON_INPUTS, PUBKEY_HASH, PUBKEY_HASH_HEX, PUBKEY_POSEIDON, REDC_LIMBS,
+    SALT, SIGNATURE_LIMBS,
+};
+use crate::templates::NoirTemplate;
+
+/// How circuit inputs are printed
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// Compact Noir snippet
+    Snippet,
+    /// Noir code constructing `BigNumParams` and `RuntimeBigNum` like the Noir tests do
+    NoirRuntime,
+    /// Prover.toml inputs
+    Toml,
+    /// The Prover.toml inputs as a JSON object keyed by ABI parameter name, as noir_js takes them
+    AbiJson,
+}
+
+impl OutputFormat {
+    pub fn from_name(name: &str) -> Option<OutputFormat> {
+        match name {
+            "snippet" => Some(OutputFormat::Snippet),
+            "noir-runtime" => Some(OutputFormat::NoirRuntime),
+            "toml" => Some(OutputFormat::Toml),
+            "abi-json" => Some(OutputFormat::AbiJson),
+            _ => None,
+        }
+    }
+
+    /// Whether the output is rendered as a TOML document, possibly converted to JSON afterwards
+    pub fn renders_toml(self) -> bool {
+        matches!(self, OutputFormat::Toml | OutputFormat::AbiJson)
+    }
+}
+
+/// Convert a TOML document rendered by this tool into pretty-printed JSON with
+/// the same structure, without the keys that are not circuit inputs; limbs
+/// stay hex strings and bytes stay numbers
+pub fn toml_to_abi_json(document: &str) -> String {
+    fn convert(value: Value) -> serde_json::Value {
+        match value {
+            Value::String(s) => serde_json::Value::String(s),
+            Value::Integer(i) => serde_json::Value::from(i),
+            Value::Boolean(b) => serde_json::Value::Bool(b),
+            Value::Array(values) => serde_json::Value::Array(values.into_iter().map(convert).collect()),
+            Value::Table(table) => serde_json::Value::Object(
+                table
+                    .into_iter()
+                    .filter(|(k, _)| !NON_INPUTS.contains(&k.as_str()))
+                    .map(|(k, v)| (k, convert(v)))
+                    .collect(),
+            ),
+            value => serde_json::Value::String(value.to_string()),
+        }
+    }
+    let value = document.parse::<Value>().expect("emitted TOML does not parse");
+    format!("{}\n", serde_json::to_string_pretty(&convert(value)).unwrap())
+}
+
+/// Number of 120-bit limbs used by BigNum to represent a `bits`-bit integer
+pub fn num_limbs(bits: usize) -> usize {
+    (bits + 119) / 120
+}
+
+/// Recombine 120-bit limbs (least significant first) into the integer they represent
+pub fn combine_limbs(limbs: &[BigUint]) -> BigUint {
+    limbs
+        .iter()
+        .rev()
+        .fold(BigUint::from(0u32), |acc, limb| (acc << 120) + limb)
+}
+
+/// How limbs are written in hex
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LimbStyle {
+    /// Without leading zeros
+    #[default]
+    Minimal,
+    /// Zero-padded to 30 hex characters (120 bits), so regenerated files diff limb by limb
+    Canonical,
+}
+
+impl LimbStyle {
+    pub fn format(self, limb: &BigUint) -> String {
+        match self {
+            LimbStyle::Minimal => format!("0x{:x}", limb),
+            LimbStyle::Canonical => format!("0x{:030x}", limb),
+        }
+    }
+}
+
+pub fn format_limbs_as_hex(limbs: &[BigUint], style: LimbStyle) -> String {
+    limbs
+        .iter()
+        .map(|a| style.format(a))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+pub fn format_limbs_as_toml_value(limbs: &[BigUint], style: LimbStyle) -> Vec<Value> {
+    limbs.iter().map(|a| Value::String(style.format(a))).collect()
+}
+
+pub fn bytes_value(bytes: &[u8]) -> Value {
+    Value::Array(bytes.iter().map(|b| Value::Integer(i64::from(*b))).collect())
+}
+
+/// Serialize a TOML document. All TOML output goes through the toml crate so
+/// that strings are always escaped correctly; tables keep their insertion order.
+pub fn to_toml_string(table: Table) -> String {
+    toml::to_string(&Value::Table(table)).expect("failed to serialize TOML")
+}
+
+pub fn format_bytes(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .map(|b| b.to_string())
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
+/// How `--emit-exponent-input` writes the public exponent
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExponentInput {
+    Integer,
+    /// 120-bit limbs as many as the modulus has, for circuits taking the exponent as a BigNum
+    Limbs,
+}
+
+impl ExponentInput {
+    pub fn from_name(name: &str) -> Option<ExponentInput> {
+        match name {
+            "integer" => Some(ExponentInput::Integer),
+            "limbs" => Some(ExponentInput::Limbs),
+            _ => None,
+        }
+    }
+}
+
+/// BigNum parameters (modulus and Barrett reduction parameter) of an RSA public key
+pub struct KeyParams {
+    pub bits: usize,
+    pub modulus_limbs: Vec<BigUint>,
+    pub redc_limbs: Vec<BigUint>,
+    /// Style of every limb printed alongside this key, signatures included
+    pub limb_style: LimbStyle,
+    /// Commitment to the modulus, emitted alongside it when requested
+    pub pubkey_hash: Option<Vec<u8>>,
+    /// Poseidon2 commitment to the modulus limbs, as a BN254 field element
+    pub pubkey_poseidon: Option<BigUint>,
+    /// Public exponent, emitted for circuits that take it as an input instead of hardwiring it
+    pub exponent: Option<(ExponentInput, BigUint)>,
+}
+
+impl KeyParams {
+    pub fn from_modulus(modulus: &BigUint, bits: usize) -> KeyParams {
+        KeyParams {
+            bits,
+            modulus_limbs: split_into_120_bit_limbs(modulus, bits),
+            redc_limbs: split_into_120_bit_limbs(&compute_barrett_reduction_parameter(modulus), bits),
+            limb_style: LimbStyle::Minimal,
+            pubkey_hash: None,
+            pubkey_poseidon: None,
+            exponent: None,
+        }
+    }
+
+    /// Emit a commitment to the modulus computed with `hash`
+    pub fn set_pubkey_hash(&mut self, hash: PubkeyHash) {
+        self.pubkey_hash = Some(pubkey_hash(hash, &self.modulus_limbs, self.bits));
+    }
+
+    /// Emit the Poseidon2 commitment to the modulus limbs
+    pub fn set_pubkey_poseidon(&mut self) {
+        self.pubkey_poseidon = Some(pubkey_poseidon2(&self.modulus_limbs));
+    }
+
+    /// Emit the public exponent `e` as an input
+    pub fn set_exponent_input(&mut self, input: ExponentInput, e: &BigUint) {
+        self.exponent = Some((input, e.clone()));
+    }
+
+    pub fn num_limbs(&self) -> usize {
+        num_limbs(self.bits)
+    }
+
+    /// Noir type of the BigNum instance, e.g. `RuntimeBigNum<18, 2048>`
+    pub fn runtime_bignum_type(&self) -> String {
+        format!("RuntimeBigNum<{}, {}>", self.num_limbs(), self.bits)
+    }
+
+    pub fn params_type(&self) -> String {
+        format!("BigNumParams<{}, {}>", self.num_limbs(), self.bits)
+    }
+
+    pub fn toml_table(&self) -> Table {
+        let mut table = Table::new();
+        table.insert(
+            MODULUS_LIMBS.to_string(),
+            Value::Array(format_limbs_as_toml_value(&self.modulus_limbs, self.limb_style)),
+        );
+        table.insert(
+            REDC_LIMBS.to_string(),
+            Value::Array(format_limbs_as_toml_value(&self.redc_limbs, self.limb_style)),
+        );
+        match &self.exponent {
+            Some((ExponentInput::Integer, e)) => {
+                // The rsa crate caps public exponents at 2^33 - 1
+                table.insert(EXPONENT.to_string(), Value::Integer(e.to_i64().unwrap()));
+            }
+            Some((ExponentInput::Limbs, e)) => {
+                let limbs = split_into_120_bit_limbs(e, self.bits);
+                table.insert(EXPONENT.to_string(), Value::Array(format_limbs_as_toml_value(&limbs, self.limb_style)));
+            }
+            None => {}
+        }
+        if let Some(hash) = &self.pubkey_hash {
+            table.insert(PUBKEY_HASH.to_string(), bytes_value(hash));
+            table.insert(PUBKEY_HASH_HEX.to_string(), Value::String(format!("0x{}", hex::encode(hash))));
+        }
+        if let Some(commitment) = &self.pubkey_poseidon {
+            table.insert(PUBKEY_POSEIDON.to_string(), Value::String(commitment.to_string()));
+        }
+        table
+    }
+
+    /// Bindings of the exponent input and pubkey commitments that were requested
+    pub fn render_snippet_commitments(&self, suffix: &str) -> String {
+        let mut out = String::new();
+        match &self.exponent {
+            Some((ExponentInput::Integer, e)) => writeln!(out, "let exponent{}: u32 = {};", suffix, e).unwrap(),
+            Some((ExponentInput::Limbs, e)) => writeln!(
+                out,
+                "let exponent{}: [u128; {}] = [{}];",
+                suffix,
+                self.num_limbs(),
+                format_limbs_as_hex(&split_into_120_bit_limbs(e, self.bits), self.limb_style)
+            )
+            .unwrap(),
+            None => {}
+        }
+        if let Some(hash) = &self.pubkey_hash {
+            writeln!(out, "// pubkey_hash{} = 0x{}", suffix, hex::encode(hash)).unwrap();
+            writeln!(out, "let pubkey_hash{}: [u8; {}] = [{}];", suffix, hash.len(), format_bytes(hash)).unwrap();
+        }
+        if let Some(commitment) = &self.pubkey_poseidon {
+            writeln!(out, "let pubkey_poseidon{}: Field = {};", suffix, commitment).unwrap();
+        }
+        out
+    }
+
+    pub fn render_toml(&self, names: Names) -> String {
+        to_toml_string(names.apply(self.toml_table()))
+    }
+}
+
+/// A single signature and the message hash it signs
+pub struct SignatureVector {
+    pub hash_alg: HashAlg,
+    pub hash: Vec<u8>,
+    pub signature_limbs: Vec<BigUint>,
+    /// The PSS salt, when it was supplied rather than drawn at random
+    pub salt: Option<Vec<u8>>,
+}
+
+impl SignatureVector {
+    /// The signature as big-endian bytes, left-padded to `len` bytes
+    pub fn signature_bytes(&self, len: usize) -> Vec<u8> {
+        let bytes = combine_limbs(&self.signature_limbs).to_bytes_be();
+        let mut padded = vec![0u8; len.saturating_sub(bytes.len())];
+        padded.extend_from_slice(&bytes);
+        padded
+    }
+
+    fn toml_table(&self, with_hash: bool, style: LimbStyle) -> Table {
+        let mut table = Table::new();
+        if with_hash {
+            table.insert(HASH.to_string(), bytes_value(&self.hash));
+        }
+        table.insert(
+            SIGNATURE_LIMBS.to_string(),
+            Value::Array(format_limbs_as_toml_value(&self.signature_limbs, style)),
+        );
+        if let Some(salt) = &self.salt {
+            table.insert(SALT.to_string(), bytes_value(salt));
+        }
+        table
+    }
+
+    fn render_snippet_hash(&self, suffix: &str) -> String {
+        let mut out = String::new();
+        writeln!(
+            out,
+            "let hash{}: [u8; {}] = [{}];",
+            suffix,
+            self.hash_alg.output_len(),
+            format_bytes(&self.hash)
+        )
+        .unwrap();
+        if let Some(salt) = &self.salt {
+            writeln!(out, "let salt{}: [u8; {}] = [{}];", suffix, salt.len(), format_bytes(salt)).unwrap();
+        }
+        out
+    }
+}
+
+/// Message bytes emitted for in-circuit hashing, zero-padded to a fixed capacity
+#[derive(Clone)]
+pub struct MessageBytes {
+    pub bytes: Vec<u8>,
+    pub max_len: usize,
+}
+
+impl MessageBytes {
+    pub fn padded(&self) -> Vec<u8> {
+        let mut padded = self.bytes.clone();
+        padded.resize(self.max_len, 0);
+        padded
+    }
+
+    fn toml_table(&self) -> Table {
+        let mut table = Table::new();
+        table.insert(MESSAGE.to_string(), bytes_value(&self.padded()));
+        table.insert(MESSAGE_LEN.to_string(), Value::Integer(self.bytes.len() as i64));
+        table
+    }
+
+    fn render_snippet(&self) -> String {
+        format!(
+            "let message: [u8; {}] = [{}];\nlet message_len: u32 = {};\n",
+            self.max_len,
+            format_bytes(&self.padded()),
+            self.bytes.len()
+        )
+    }
+}
+
+/// Circuit inputs for one key and one or more signatures made with it
+pub struct SignatureParams {
+    pub key: KeyParams,
+    pub signatures: Vec<SignatureVector>,
+    pub message: Option<MessageBytes>,
+    /// What the circuit will not accept about an otherwise valid signature, such as its exponent
+    pub warnings: Vec<String>,
+}
+
+impl SignatureParams {
+    fn toml_table(&self) -> Table {
+        let mut table = self.key.toml_table();
+        if let Some(message) = &self.message {
+            table.extend(message.toml_table());
+        }
+
+        if let [signature] = self.signatures.as_slice() {
+            table.extend(signature.toml_table(true, self.key.limb_style));
+            return table;
+        }
+
+        // Each signature gets its own table labelled by the hash so that the
+        // hash lengths and limb counts stay unambiguous
+        for signature in &self.signatures {
+            table.insert(
+                signature.hash_alg.name().to_string(),
+                Value::Table(signature.toml_table(true, self.key.limb_style)),
+            );
+        }
+        table
+    }
+
+    pub fn render_toml(&self, names: Names) -> String {
+        to_toml_string(names.apply(self.toml_table()))
+    }
+
+    pub fn render_snippet(&self, template: &dyn NoirTemplate) -> String {
+        let mut out = String::new();
+        if let Some(message) = &self.message {
+            out.push_str(&message.render_snippet());
+        }
+        writeln!(out, "{}", template.params(&self.key, "params")).unwrap();
+        out.push_str(&self.key.render_snippet_commitments(""));
+
+        let single = self.signatures.len() == 1;
+        for signature in &self.signatures {
+            // Bindings are suffixed with the hash name when several signatures share the same params
+            let suffix = if single {
+                String::new()
+            } else {
+                format!("_{}", signature.hash_alg.name())
+            };
+            out.push_str(&signature.render_snippet_hash(&suffix));
+            writeln!(
+                out,
+                "{}",
+                template.signature(
+                    &self.key,
+                    &format!("signature{}", suffix),
+                    "params",
+                    &signature.signature_limbs
+                )
+            )
+            .unwrap();
+        }
+        out
+    }
+}
+
+/// Render the circuit inputs for several keys signing the same message as TOML,
+/// with the shared hash at the top and one `[[keys]]` entry per key
+pub fn render_multi_key_toml(entries: &[SignatureParams], names: Names) -> String {
+    let mut table = Table::new();
+    table.insert(HASH.to_string(), bytes_value(&entries[0].signatures[0].hash));
+    if let Some(message) = &entries[0].message {
+        table.extend(message.toml_table());
+    }
+    let keys = entries
+        .iter()
+        .map(|entry| {
+            let mut key = entry.key.toml_table();
+            key.extend(entry.signatures[0].toml_table(false, entry.key.limb_style));
+            Value::Table(key)
+        })
+        .collect();
+    table.insert("keys".to_string(), Value::Array(keys));
+    to_toml_string(names.apply(table))
+}
+
+/// Inputs of a circuit verifying `k` signatures in one proof. Every input
+/// becomes an array of exactly `k` slots, in the order the vectors were given,
+/// and `active_count` holds the number of real vectors. Unused slots repeat
+/// slot 0: it is a valid vector, so the circuit may either skip the slots from
+/// `active_count` on or verify every slot, and the padding never feeds zero
+/// BigNum parameters into the reduction.
+pub fn aggregate_table(entries: &[SignatureParams], k: usize) -> Table {
+    assert!(!entries.is_empty() && entries.len() <= k, "between 1 and {} vectors are required", k);
+    let slots: Vec<Table> = (0..k).map(|i| entries.get(i).unwrap_or(&entries[0]).toml_table()).collect();
+
+    let mut table = Table::new();
+    table.insert(ACTIVE_COUNT.to_string(), Value::Integer(entries.len() as i64));
+    for name in slots[0].keys() {
+        let values = slots.iter().map(|slot| slot[name].clone()).collect();
+        table.insert(name.clone(), Value::Array(values));
+    }
+    table
+}
+
+pub fn render_aggregate_toml(entries: &[SignatureParams], k: usize) -> String {
+    to_toml_string(aggregate_table(entries, k))
+}
+
+/// Render the circuit inputs for several keys signing the same message as Noir
+/// bindings, numbered in the order the keys were given
+pub fn render_multi_key_snippet(entries: &[SignatureParams], template: &dyn NoirTemplate) -> String {
+    let mut out = entries[0].signatures[0].render_snippet_hash("");
+    if let Some(message) = &entries[0].message {
+        out.push_str(&message.render_snippet());
+    }
+    for (i, entry) in entries.iter().enumerate() {
+        let params_binding = format!("params_{}", i);
+        writeln!(out, "{}", template.params(&entry.key, &params_binding)).unwrap();
+        out.push_str(&entry.key.render_snippet_commitments(&format!("_{}", i)));
+        writeln!(
+            out,
+            "{}",
+            template.signature(
+                &entry.key,
+                &format!("signature_{}", i),
+                &params_binding,
+                &entry.signatures[0].signature_limbs
+            )
+        )
+        .unwrap();
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use num_bigint::RandBigInt;
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha20Rng;
+
+    fn tricky_messages() -> Vec<String> {
+        vec![
+            "say \"hello\"".to_string(),
+            "C:\\keys\\rsa.pem".to_string(),
+            "first line\nsecond line\r\n".to_string(),
+            "signed 🦀🔐 ünïcödé".to_string(),
+            "x".repeat(10_000),
+        ]
+    }
+
+    fn parse(document: &str) -> Table {
+        match document.parse::<Value>().expect("emitted TOML does not parse") {
+            Value::Table(table) => table,
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn test_toml_strings_are_escaped() {
+        for message in tricky_messages() {
+            let mut table = Table::new();
+            table.insert("message".to_string(), Value::String(message.clone()));
+            let parsed = parse(&to_toml_string(table));
+            assert_eq!(parsed["message"].as_str(), Some(message.as_str()));
+        }
+    }
+
+    #[test]
+    fn test_toml_message_bytes_round_trip() {
+        let modulus = (BigUint::from(1u32) << 1023) + 1u32;
+        let modulus_limbs = split_into_120_bit_limbs(&modulus, 1024);
+        for message in tricky_messages() {
+            let params = SignatureParams {
+                key: KeyParams::from_modulus(&modulus, 1024),
+                signatures: vec![SignatureVector {
+                    hash_alg: HashAlg::Sha256,
+                    hash: HashAlg::Sha256.digest(message.as_bytes()),
+                    signature_limbs: modulus_limbs.clone(),
+                    salt: None,
+                }],
+                message: Some(MessageBytes {
+                    bytes: message.as_bytes().to_vec(),
+                    max_len: message.len() + 4,
+                }),
+                warnings: Vec::new(),
+            };
+
+            let parsed = parse(&params.render_toml(Names::Canonical));
+            let bytes: Vec<u8> = parsed["message"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|b| b.as_integer().unwrap() as u8)
+                .collect();
+            assert_eq!(&bytes[..message.len()], message.as_bytes());
+            assert_eq!(&bytes[message.len()..], &[0, 0, 0, 0]);
+            assert_eq!(parsed["message_len"].as_integer(), Some(message.len() as i64));
+            assert_eq!(
+                parsed["modulus_limbs"],
+                Value::Array(format_limbs_as_toml_value(&modulus_limbs, LimbStyle::Minimal))
+            );
+        }
+    }
+
+    #[test]
+    fn test_pubkey_hash_agrees_across_output_modes() {
+        let modulus = (BigUint::from(1u32) << 1023) + 1u32;
+        let params = || {
+            let mut key = KeyParams::from_modulus(&modulus, 1024);
+            key.set_pubkey_hash(PubkeyHash::Sha256);
+            SignatureParams {
+                key,
+                signatures: vec![SignatureVector {
+                    hash_alg: HashAlg::Sha256,
+                    hash: HashAlg::Sha256.digest(b"hello world"),
+                    signature_limbs: split_into_120_bit_limbs(&modulus, 1024),
+                    salt: None,
+                }],
+                message: None,
+                warnings: Vec::new(),
+            }
+        };
+        let expected = pubkey_hash(PubkeyHash::Sha256, &split_into_120_bit_limbs(&modulus, 1024), 1024);
+
+        let single = parse(&params().render_toml(Names::Canonical));
+        let multi = parse(&render_multi_key_toml(&[params(), params()], Names::Canonical));
+        for table in [&single, multi["keys"][0].as_table().unwrap(), multi["keys"][1].as_table().unwrap()] {
+            assert_eq!(table[PUBKEY_HASH], bytes_value(&expected));
+            assert_eq!(table[PUBKEY_HASH_HEX].as_str(), Some(format!("0x{}", hex::encode(&expected)).as_str()));
+        }
+    }
+
+    #[test]
+    fn test_combined_limbs_invert_the_split() {
+        let mut rng = ChaCha20Rng::seed_from_u64(0);
+        for bits in [1024, 2048, 4096] {
+            let value = rng.gen_biguint(bits);
+            assert_eq!(combine_limbs(&split_into_120_bit_limbs(&value, bits)), value);
+        }
+        // The top limb of a 1024-bit value holds 64 bits, so below 2^960 it is zero and is kept as such
+        let value = (BigUint::from(1u32) << 959) + 7u32;
+        let limbs = split_into_120_bit_limbs(&value, 1024);
+        assert_eq!(limbs.len(), 9);
+        assert_eq!(limbs[8], BigUint::from(0u32));
+        assert_eq!(combine_limbs(&limbs), value);
+        assert_eq!(combine_limbs(&limbs[..8]), value);
+        assert_eq!(combine_limbs(&[]), BigUint::from(0u32));
+    }
+
+    #[test]
+    fn test_exponent_input_is_emitted_as_integer_or_limbs() {
+        let modulus = (BigUint::from(1u32) << 1023) + 1u32;
+        let mut key = KeyParams::from_modulus(&modulus, 1024);
+        assert!(!parse(&key.render_toml(Names::Canonical)).contains_key(EXPONENT));
+
+        key.set_exponent_input(ExponentInput::Integer, &BigUint::from(3u32));
+        assert_eq!(parse(&key.render_toml(Names::Canonical))[EXPONENT].as_integer(), Some(3));
+        assert!(key.render_snippet_commitments("").contains("let exponent: u32 = 3;"));
+
+        key.set_exponent_input(ExponentInput::Limbs, &BigUint::from(17u32));
+        let limbs = parse(&key.render_toml(Names::Canonical))[EXPONENT].as_array().unwrap().clone();
+        assert_eq!(limbs.len(), 9);
+        assert_eq!(limbs[0].as_str(), Some("0x11"));
+        assert!(limbs[1..].iter().all(|limb| limb.as_str() == Some("0x0")));
+    }
+
+    #[test]
+    fn test_aggregate_arrays_have_k_slots() {
+        let entry = |n: u32| {
+            let modulus = (BigUint::from(1u32) << 2047) + n;
+            let mut key = KeyParams::from_modulus(&modulus, 2048);
+            key.set_pubkey_hash(PubkeyHash::Sha256);
+            SignatureParams {
+                key,
+                signatures: vec![SignatureVector {
+                    hash_alg: HashAlg::Sha256,
+                    hash: HashAlg::Sha256.digest(&n.to_be_bytes()),
+                    signature_limbs: split_into_120_bit_limbs(&BigUint::from(n), 2048),
+                    salt: None,
+                }],
+                message: None,
+                warnings: Vec::new(),
+            }
+        };
+        let entries = [entry(1), entry(3), entry(5)];
+        let table = parse(&render_aggregate_toml(&entries, 5));
+
+        assert_eq!(table[ACTIVE_COUNT].as_integer(), Some(3));
+        for (name, inner_len) in [(HASH, 32), (MODULUS_LIMBS, 18), (REDC_LIMBS, 18), (SIGNATURE_LIMBS, 18), (PUBKEY_HASH, 32)] {
+            let slots = table[name].as_array().unwrap();
+            assert_eq!(slots.len(), 5, "slots of `{}`", name);
+            assert!(slots.iter().all(|slot| slot.as_array().unwrap().len() == inner_len), "length of `{}`", name);
+            // Real vectors in order, then copies of slot 0
+            assert_ne!(slots[0], slots[1]);
+            assert_eq!(slots[3], slots[0]);
+            assert_eq!(slots[4], slots[0]);
+        }
+
+        let json: serde_json::Value = serde_json::from_str(&toml_to_abi_json(&render_aggregate_toml(&entries, 5))).unwrap();
+        assert_eq!(json[ACTIVE_COUNT], 3);
+        assert_eq!(json[SIGNATURE_LIMBS].as_array().unwrap().len(), 5);
+        assert_eq!(json[SIGNATURE_LIMBS][1][0], "0x3");
+        assert_eq!(json[HASH][0].as_array().unwrap().len(), 32);
+    }
+
+    #[test]
+    fn test_abi_json_has_only_circuit_inputs() {
+        let document = "expected_valid = false\ntamper = \"bad-redc\"\nhash = [1, 2]\nmodulus_limbs = [\"0x1\"]\n\
+                        pubkey_hash_hex = \"0x0102\"\n\n[meta]\nedge_case = \"leading-zero-sig\"\n\n\
+                        [expected]\nverified = false\n\n[dkim]\ncase = \"relaxed\"\n\n\
+                        [edge_case]\nname = \"top-limb-all-ones\"\nexpect = \"accept\"\n";
+        let json: serde_json::Value = serde_json::from_str(&toml_to_abi_json(document)).unwrap();
+        assert_eq!(json, serde_json::json!({ "hash": [1, 2], "modulus_limbs": ["0x1"] }));
+    }
+}
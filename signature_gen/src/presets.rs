@@ -0,0 +1,119 @@
+//! `--preset`: the input names and nesting of known downstream circuits
+//!
+//! A preset is data, not an output path: it says where each canonical input
+//! is emitted, as a path of table names, how limbs are written and which
+//! pubkey commitments come along. Inputs without a place are left out, so the
+//! output holds exactly what the downstream circuit takes. Adding a preset is
+//! adding an entry to `PRESETS` and a golden file in `snapshot_tests`.
+
+use toml::value::Table;
+use toml::Value;
+
+use crate::commitment::PubkeyHash;
+use crate::params::{KeyParams, LimbStyle};
+use crate::schema::{HASH, MESSAGE, MESSAGE_LEN, MODULUS_LIMBS, PUBKEY_POSEIDON, REDC_LIMBS, SIGNATURE_LIMBS};
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct Preset {
+    pub name: &'static str,
+    /// Each canonical input the circuit takes and the path it is emitted at,
+    /// outer tables first, in output order
+    pub fields: &'static [(&'static str, &'static [&'static str])],
+    pub limb_style: LimbStyle,
+    pub pubkey_hash: Option<PubkeyHash>,
+    pub pubkey_poseidon: bool,
+}
+
+pub const PRESETS: &[Preset] = &[
+    // zkemail.nr: `pubkey: RSAPubkey { modulus, redc }`, `signature` and the
+    // header as a `BoundedVec`, which the circuit hashes itself
+    Preset {
+        name: "zkemail",
+        fields: &[
+            (SIGNATURE_LIMBS, &["signature"]),
+            (MODULUS_LIMBS, &["pubkey", "modulus"]),
+            (REDC_LIMBS, &["pubkey", "redc"]),
+            (MESSAGE, &["header", "storage"]),
+            (MESSAGE_LEN, &["header", "len"]),
+        ],
+        limb_style: LimbStyle::Minimal,
+        pubkey_hash: None,
+        pubkey_poseidon: false,
+    },
+    // DSC signature over the signed attributes, with the Poseidon2 key commitment
+    Preset {
+        name: "zkpassport",
+        fields: &[
+            (MODULUS_LIMBS, &["dsc_pubkey"]),
+            (REDC_LIMBS, &["dsc_pubkey_redc_param"]),
+            (SIGNATURE_LIMBS, &["dsc_signature"]),
+            (HASH, &["signed_attributes_hash"]),
+            (PUBKEY_POSEIDON, &["dsc_pubkey_commitment"]),
+        ],
+        limb_style: LimbStyle::Canonical,
+        pubkey_hash: None,
+        pubkey_poseidon: true,
+    },
+    // noir-jwt: the signed `header.payload` as a `BoundedVec` and the RSA limbs
+    Preset {
+        name: "jwt",
+        fields: &[
+            (MESSAGE, &["data", "storage"]),
+            (MESSAGE_LEN, &["data", "len"]),
+            (MODULUS_LIMBS, &["pubkey_modulus_limbs"]),
+            (REDC_LIMBS, &["redc_params_limbs"]),
+            (SIGNATURE_LIMBS, &["signature_limbs"]),
+        ],
+        limb_style: LimbStyle::Minimal,
+        pubkey_hash: None,
+        pubkey_poseidon: false,
+    },
+];
+
+impl Preset {
+    pub fn from_name(name: &str) -> Result<&'static Preset, String> {
+        PRESETS.iter().find(|preset| preset.name == name).ok_or_else(|| {
+            let names: Vec<&str> = PRESETS.iter().map(|preset| preset.name).collect();
+            format!("Unknown preset {}, expected one of {}", name, names.join(", "))
+        })
+    }
+
+    /// Set the limb style and commitments the preset emits
+    pub fn configure(&self, key: &mut KeyParams) {
+        key.limb_style = self.limb_style;
+        if let Some(hash) = self.pubkey_hash {
+            key.set_pubkey_hash(hash);
+        }
+        if self.pubkey_poseidon {
+            key.set_pubkey_poseidon();
+        }
+    }
+
+    /// Move the inputs of a canonical table to their places
+    pub fn apply(&self, table: Table) -> Table {
+        let mut placed = Table::new();
+        for (input, path) in self.fields {
+            if let Some(value) = table.get(*input) {
+                insert_at(&mut placed, path, value.clone());
+            }
+        }
+        placed
+    }
+}
+
+fn insert_at(table: &mut Table, path: &[&str], value: Value) {
+    match path {
+        [name] => {
+            table.insert(name.to_string(), value);
+        }
+        [outer, rest @ ..] => {
+            if !table.contains_key(*outer) {
+                table.insert(outer.to_string(), Value::Table(Table::new()));
+            }
+            if let Some(Value::Table(nested)) = table.get_mut(*outer) {
+                insert_at(nested, rest, value);
+            }
+        }
+        [] => unreachable!("a preset field has an empty path"),
+    }
+}
@@ -0,0 +1,81 @@
+use crate::hash::HashAlg;
+
+/// Mask generation function MGF1 (RFC 8017 B.2.1) using `hash_alg` as the hash function
+pub fn mgf1(hash_alg: HashAlg, seed: &[u8], mask_len: usize) -> Vec<u8> {
+    let mut mask = Vec::with_capacity(mask_len + hash_alg.output_len());
+    let mut counter: u32 = 0;
+    while mask.len() < mask_len {
+        let mut block = seed.to_vec();
+        block.extend_from_slice(&counter.to_be_bytes());
+        mask.extend_from_slice(&hash_alg.digest(&block));
+        counter += 1;
+    }
+    mask.truncate(mask_len);
+    mask
+}
+
+/// The longest salt the PSS encoding of a `bits`-bit key has room for with `hash_alg`
+pub fn max_salt_len(hash_alg: HashAlg, bits: usize) -> usize {
+    let em_len = (bits - 1 + 7) / 8;
+    em_len.saturating_sub(hash_alg.output_len() + 2)
+}
+
+/// Fail unless a salt of `salt_len` bytes fits the PSS encoding of a `bits`-bit key with `hash_alg`
+pub fn check_salt_len(hash_alg: HashAlg, salt_len: usize, bits: usize) -> Result<(), String> {
+    let max_len = max_salt_len(hash_alg, bits);
+    if salt_len > max_len {
+        return Err(format!(
+            "Salt of {} bytes is too long for a {}-bit key with {}: at most {} bytes fit",
+            salt_len,
+            bits,
+            hash_alg.name(),
+            max_len
+        ));
+    }
+    Ok(())
+}
+
+/// EMSA-PSS-ENCODE (RFC 8017 9.1.1) of a message hash with a caller-provided salt
+///
+/// `em_bits` is the bit length of the modulus minus one
+pub fn emsa_pss_encode(
+    hash_alg: HashAlg,
+    m_hash: &[u8],
+    salt: &[u8],
+    em_bits: usize,
+) -> Result<Vec<u8>, String> {
+    let h_len = hash_alg.output_len();
+    let em_len = (em_bits + 7) / 8;
+    if m_hash.len() != h_len {
+        return Err(format!(
+            "Message hash is {} bytes but {} produces {} bytes",
+            m_hash.len(),
+            hash_alg.name(),
+            h_len
+        ));
+    }
+    check_salt_len(hash_alg, salt.len(), em_bits + 1)?;
+
+    // M' = (0x)00 00 00 00 00 00 00 00 || mHash || salt
+    let mut m_prime = vec![0u8; 8];
+    m_prime.extend_from_slice(m_hash);
+    m_prime.extend_from_slice(salt);
+    let h = hash_alg.digest(&m_prime);
+
+    // DB = PS || 0x01 || salt
+    let db_len = em_len - h_len - 1;
+    let mut db = vec![0u8; db_len - salt.len() - 1];
+    db.push(0x01);
+    db.extend_from_slice(salt);
+
+    let db_mask = mgf1(hash_alg, &h, db_len);
+    let mut masked_db: Vec<u8> = db.iter().zip(db_mask.iter()).map(|(a, b)| a ^ b).collect();
+
+    // Clear the leftmost 8 * em_len - em_bits bits so the encoding is smaller than the modulus
+    masked_db[0] &= 0xff >> (8 * em_len - em_bits);
+
+    let mut em = masked_db;
+    em.extend_from_slice(&h);
+    em.push(0xbc);
+    Ok(em)
+}
@@ -0,0 +1,223 @@
+//! `raw-rsa`: bare modular exponentiation vectors, without hashing or padding
+//!
+//! Each vector is a base x below the modulus and y = x^e mod n computed in
+//! Rust. When the full verification circuit fails, running these through a
+//! circuit that only computes x^e isolates the BigNum multiplication and
+//! reduction from the padding and hash checks. Besides random bases, the
+//! adversarial ones sit on the edges of that arithmetic: 0, 1, n - 1, and a
+//! base whose limbs are all ones, maximizing the carries of every product.
+
+use num_bigint::{BigUint, RandBigInt};
+use num_traits::{One, Zero};
+use rand_chacha::ChaCha20Rng;
+use toml::value::Table;
+use toml::Value;
+
+use noir_bignum_paramgen::split_into_120_bit_limbs;
+
+use crate::params::{format_limbs_as_toml_value, num_limbs, to_toml_string, KeyParams};
+use crate::schema::{EXPONENT, MODULUS_LIMBS, REDC_LIMBS};
+use crate::templates::{NoirTemplate, RuntimeTemplate};
+
+pub const BASE_LIMBS: &str = "base_limbs";
+pub const RESULT_LIMBS: &str = "result_limbs";
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Base {
+    Zero,
+    One,
+    NMinusOne,
+    /// Every limb 2^120 - 1, except the top one which is one below that of n
+    MaxCarry,
+    Random,
+    Given(BigUint),
+}
+
+impl Base {
+    pub const NAMED: [Base; 5] = [Base::Zero, Base::One, Base::NMinusOne, Base::MaxCarry, Base::Random];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Base::Zero => "zero",
+            Base::One => "one",
+            Base::NMinusOne => "n-minus-1",
+            Base::MaxCarry => "max-carry",
+            Base::Random => "random",
+            Base::Given(_) => "given",
+        }
+    }
+
+    /// The value of this base below `modulus`
+    fn value(&self, rng: &mut ChaCha20Rng, modulus: &BigUint, bits: usize) -> Result<BigUint, String> {
+        Ok(match self {
+            Base::Zero => BigUint::zero(),
+            Base::One => BigUint::one(),
+            Base::NMinusOne => modulus - 1u32,
+            Base::MaxCarry => max_carry(modulus, bits),
+            Base::Random => rng.gen_biguint_below(modulus),
+            Base::Given(x) if x < modulus => x.clone(),
+            Base::Given(x) => {
+                return Err(format!("The base 0x{} is not below the modulus", x.to_str_radix(16)));
+            }
+        })
+    }
+}
+
+/// Parse a comma-separated list of base names
+pub fn parse_bases(list: &str) -> Result<Vec<Base>, String> {
+    list.split(',')
+        .map(|name| {
+            Base::NAMED.into_iter().find(|base| base.name() == name.trim()).ok_or_else(|| {
+                let names: Vec<&str> = Base::NAMED.iter().map(Base::name).collect();
+                format!("Unknown base `{}`, expected one of {}", name.trim(), names.join(", "))
+            })
+        })
+        .collect()
+}
+
+/// The largest base below `modulus` whose limbs below the top one are all ones
+fn max_carry(modulus: &BigUint, bits: usize) -> BigUint {
+    let top = num_limbs(bits) - 1;
+    let low = (BigUint::one() << (120 * top)) - 1u32;
+    let top_limb = modulus >> (120 * top);
+    // The top limb of a modulus is at least 2^(bits - 1 - 120 * top), so never zero
+    ((top_limb - 1u32) << (120 * top)) + low
+}
+
+pub struct Vector {
+    pub base: &'static str,
+    pub x: BigUint,
+    pub y: BigUint,
+}
+
+/// One vector for each of `bases`
+pub fn vectors(
+    rng: &mut ChaCha20Rng,
+    modulus: &BigUint,
+    exponent: &BigUint,
+    bases: &[Base],
+) -> Result<Vec<Vector>, String> {
+    let bits = (modulus.bits() + 7) / 8 * 8;
+    bases
+        .iter()
+        .map(|base| {
+            let x = base.value(rng, modulus, bits)?;
+            let y = x.modpow(exponent, modulus);
+            Ok(Vector { base: base.name(), x, y })
+        })
+        .collect()
+}
+
+fn vector_table(key: &KeyParams, vector: &Vector) -> Table {
+    let mut table = Table::new();
+    table.insert("base".to_string(), Value::String(vector.base.to_string()));
+    for (name, value) in [(BASE_LIMBS, &vector.x), (RESULT_LIMBS, &vector.y)] {
+        let limbs = split_into_120_bit_limbs(value, key.bits);
+        table.insert(name.to_string(), Value::Array(format_limbs_as_toml_value(&limbs, key.limb_style)));
+    }
+    table
+}
+
+/// Inputs of a bare-modexp circuit: the key, then the base and result of a
+/// single vector at the top level, or one `[[vectors]]` table for each
+pub fn render_toml(key: &KeyParams, exponent: u64, vectors: &[Vector]) -> String {
+    let mut document = Table::new();
+    for (name, limbs) in [(MODULUS_LIMBS, &key.modulus_limbs), (REDC_LIMBS, &key.redc_limbs)] {
+        document.insert(name.to_string(), Value::Array(format_limbs_as_toml_value(limbs, key.limb_style)));
+    }
+    document.insert(EXPONENT.to_string(), Value::Integer(exponent as i64));
+    match vectors {
+        [vector] => document.extend(vector_table(key, vector)),
+        _ => {
+            let tables = vectors.iter().map(|vector| Value::Table(vector_table(key, vector))).collect();
+            document.insert("vectors".to_string(), Value::Array(tables));
+        }
+    }
+    to_toml_string(document)
+}
+
+const POW: &str = "\
+fn pow<let N: u32, let MOD_BITS: u32>(
+    base: RuntimeBigNum<N, MOD_BITS>,
+    exponent: u64,
+) -> RuntimeBigNum<N, MOD_BITS> {
+    let mut result = RuntimeBigNum::one(base.params);
+    let mut base = base;
+    let mut exp = exponent;
+    for _ in 0..64 {
+        if exp > 0 {
+            if exp % 2 == 1 {
+                result = result * base;
+            }
+            base = base * base;
+            exp = exp / 2;
+        }
+    }
+    result
+}
+";
+
+fn indent(code: &str) -> String {
+    code.lines().map(|line| format!("    {}\n", line)).collect()
+}
+
+/// A Noir file with one test asserting `pow(x, e) == y` for each vector
+pub fn render_noir_tests(key: &KeyParams, exponent: u64, vectors: &[Vector]) -> String {
+    let mut code = String::from("use bignum::params::BigNumParams;\nuse bignum::RuntimeBigNum;\n\n");
+    code.push_str(POW);
+    for (i, vector) in vectors.iter().enumerate() {
+        let x = split_into_120_bit_limbs(&vector.x, key.bits);
+        let y = split_into_120_bit_limbs(&vector.y, key.bits);
+        code.push_str(&format!(
+            "\n#[test]\nfn test_raw_rsa_{}_{}_{}() {{\n",
+            key.bits,
+            i,
+            vector.base.replace('-', "_")
+        ));
+        code.push_str(&indent(&RuntimeTemplate.params(key, "params")));
+        code.push_str(&indent(&RuntimeTemplate.signature(key, "base", "params", &x)));
+        code.push_str(&indent(&RuntimeTemplate.signature(key, "expected", "params", &y)));
+        code.push_str(&format!("    assert(pow(base, {}) == expected);\n}}\n", exponent));
+    }
+    code
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+    use rsa::pkcs8::DecodePrivateKey;
+    use rsa::traits::{PrivateKeyParts, PublicKeyParts};
+    use rsa::RsaPrivateKey;
+
+    use crate::convert::parse_limbs;
+
+    #[test]
+    fn test_adversarial_bases_exponentiate_to_their_results() {
+        let priv_key = RsaPrivateKey::from_pkcs8_pem(include_str!("../tests/fixtures/rsa_1024.pem")).unwrap();
+        let (n, e) = (priv_key.n().clone(), priv_key.e().clone());
+        let mut rng = ChaCha20Rng::seed_from_u64(0);
+        let generated = vectors(&mut rng, &n, &e, &Base::NAMED).unwrap();
+        let results: Vec<BigUint> = generated.iter().map(|vector| vector.y.clone()).collect();
+        assert_eq!(results[..3], [BigUint::zero(), BigUint::one(), &n - 1u32]);
+
+        let max_carry = &generated[3].x;
+        assert!(max_carry < &n && max_carry.bits() == 1024);
+        let all_ones = (BigUint::one() << 120) - 1u32;
+        assert!(split_into_120_bit_limbs(max_carry, 1024)[..8].iter().all(|limb| limb == &all_ones));
+
+        // Raising the result to d gives the base back
+        for vector in &generated {
+            assert_eq!(&vector.y.modpow(priv_key.d(), &n), &vector.x);
+        }
+        assert!(vectors(&mut rng, &n, &e, &[Base::Given(n.clone())]).is_err());
+
+        let key = KeyParams::from_modulus(&n, 1024);
+        let document: Value = render_toml(&key, 65537, &generated[2..3]).parse().unwrap();
+        assert_eq!(parse_limbs(document.get(RESULT_LIMBS), RESULT_LIMBS), Ok(&n - 1u32));
+        let tests = render_noir_tests(&key, 65537, &generated);
+        assert_eq!(tests.matches("#[test]").count(), 5);
+        assert!(tests.contains("fn test_raw_rsa_1024_3_max_carry()"));
+        assert!(tests.contains("    assert(pow(base, 65537) == expected);\n"));
+    }
+}
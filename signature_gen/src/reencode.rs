@@ -0,0 +1,216 @@
+//! `reencode-signature`: one signature between its raw, DER and limb forms
+//!
+//! Whatever form it comes in, the signature is normalized to the integer and
+//! checked against the modulus first, so a value of at least n never reaches
+//! the output. Byte forms are exactly as long as the modulus, leading zeros
+//! included, as PKCS#1 requires of a signature.
+
+use num_bigint::BigUint;
+use toml::value::Table;
+use toml::Value;
+
+use noir_bignum_paramgen::split_into_120_bit_limbs;
+
+use crate::params::{
+    combine_limbs, format_bytes, format_limbs_as_hex, format_limbs_as_toml_value, num_limbs, to_toml_string, LimbStyle,
+};
+use crate::schema::SIGNATURE_LIMBS;
+use crate::x509::bit_string;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum InFormat {
+    /// The big-endian integer, as bytes or hex
+    Raw,
+    /// A DER BIT STRING, as from a certificate's signatureValue, as bytes or hex
+    Der,
+    /// 120-bit limbs, least significant first, as printed by `sign`
+    Limbs,
+}
+
+impl InFormat {
+    pub fn from_name(name: &str) -> Option<InFormat> {
+        match name {
+            "raw" => Some(InFormat::Raw),
+            "der" => Some(InFormat::Der),
+            "limbs" => Some(InFormat::Limbs),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OutFormat {
+    RawHex,
+    /// Decimal byte array
+    Bytes,
+    /// Limb array for Noir
+    Limbs,
+    /// `signature_limbs = [...]` for a Prover.toml
+    TomlFragment,
+}
+
+impl OutFormat {
+    pub fn from_name(name: &str) -> Option<OutFormat> {
+        match name {
+            "raw-hex" => Some(OutFormat::RawHex),
+            "bytes" => Some(OutFormat::Bytes),
+            "limbs" => Some(OutFormat::Limbs),
+            "toml-fragment" => Some(OutFormat::TomlFragment),
+            _ => None,
+        }
+    }
+}
+
+/// Hex with optional `0x`, whitespace and `:` separators, as openssl prints it
+fn decode_hex(text: &str) -> Option<Vec<u8>> {
+    let text = text.strip_prefix("0x").unwrap_or(text);
+    let compact: String = text.chars().filter(|c| !c.is_whitespace() && *c != ':').collect();
+    hex::decode(compact).ok()
+}
+
+fn parse_limb(limb: &str) -> Result<BigUint, String> {
+    let limb = limb.trim().trim_matches('"');
+    let value = match limb.strip_prefix("0x") {
+        Some(hex) => BigUint::parse_bytes(hex.as_bytes(), 16),
+        None => BigUint::parse_bytes(limb.as_bytes(), 10),
+    }
+    .ok_or_else(|| format!("invalid limb {:?}", limb))?;
+    if value.bits() > 120 {
+        return Err(format!("limb {} does not fit in 120 bits", limb));
+    }
+    Ok(value)
+}
+
+fn decode_limbs(text: &str, bits: usize) -> Result<BigUint, String> {
+    let text = text.trim().trim_start_matches('[').trim_end_matches(']');
+    let limbs = text
+        .split(',')
+        .map(str::trim)
+        .filter(|limb| !limb.is_empty())
+        .map(parse_limb)
+        .collect::<Result<Vec<_>, _>>()?;
+    if limbs.len() != num_limbs(bits) {
+        return Err(format!("{} limbs given, a {}-bit signature has {}", limbs.len(), bits, num_limbs(bits)));
+    }
+    Ok(combine_limbs(&limbs))
+}
+
+/// The form of `input`: limb lists are printable text with commas or brackets,
+/// and bytes that are not as long as the modulus but parse as a BIT STRING are DER
+fn detect(input: &[u8], key_bytes: usize) -> InFormat {
+    let printable = input.iter().all(|b| b.is_ascii_graphic() || b.is_ascii_whitespace());
+    let bytes = match std::str::from_utf8(input) {
+        Ok(text) if printable && (text.trim_start().starts_with('[') || text.contains(',')) => return InFormat::Limbs,
+        Ok(text) => match decode_hex(text.trim()) {
+            Some(bytes) => bytes,
+            None => input.to_vec(),
+        },
+        Err(_) => input.to_vec(),
+    };
+    if bytes.len() != key_bytes && bit_string(&bytes).is_ok() {
+        InFormat::Der
+    } else {
+        InFormat::Raw
+    }
+}
+
+/// The bytes of `input`, given as hex or as they are
+fn input_bytes(input: &[u8]) -> Vec<u8> {
+    match std::str::from_utf8(input).ok().and_then(|text| decode_hex(text.trim())) {
+        Some(bytes) => bytes,
+        None => input.to_vec(),
+    }
+}
+
+/// The signature integer `input` encodes, which must be below `modulus`
+pub fn decode(input: &[u8], format: Option<InFormat>, modulus: &BigUint) -> Result<(InFormat, BigUint), String> {
+    let key_bytes = (modulus.bits() + 7) / 8;
+    let format = format.unwrap_or_else(|| detect(input, key_bytes));
+    let value = match format {
+        InFormat::Raw => BigUint::from_bytes_be(&input_bytes(input)),
+        InFormat::Der => BigUint::from_bytes_be(bit_string(&input_bytes(input))?),
+        InFormat::Limbs => {
+            let text = std::str::from_utf8(input).map_err(|_| "limbs must be given as text")?;
+            decode_limbs(text, key_bytes * 8)?
+        }
+    };
+    if value >= *modulus {
+        return Err(format!(
+            "the signature 0x{} is not below the modulus, no RSA key accepts it",
+            value.to_str_radix(16)
+        ));
+    }
+    Ok((format, value))
+}
+
+/// `signature` in `format`, sized for a modulus of `key_bytes` bytes
+pub fn encode(signature: &BigUint, format: OutFormat, key_bytes: usize, style: LimbStyle) -> String {
+    let mut bytes = signature.to_bytes_be();
+    bytes.splice(0..0, std::iter::repeat(0).take(key_bytes - bytes.len()));
+    let limbs = split_into_120_bit_limbs(signature, key_bytes * 8);
+    match format {
+        OutFormat::RawHex => format!("{}\n", hex::encode(&bytes)),
+        OutFormat::Bytes => format!("[{}]\n", format_bytes(&bytes)),
+        OutFormat::Limbs => format!("[{}]\n", format_limbs_as_hex(&limbs, style)),
+        OutFormat::TomlFragment => {
+            let mut table = Table::new();
+            table.insert(SIGNATURE_LIMBS.to_string(), Value::Array(format_limbs_as_toml_value(&limbs, style)));
+            to_toml_string(table)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use num_traits::One;
+
+    fn modulus() -> BigUint {
+        // A 1024-bit modulus whose top byte is 0xc0
+        (BigUint::from(0xc0u32) << 1016) + 1u32
+    }
+
+    #[test]
+    fn test_every_form_decodes_to_the_same_integer() {
+        let modulus = modulus();
+        // A signature with a leading zero byte
+        let signature = BigUint::from(0x0123u32) << 1000;
+        let raw = encode(&signature, OutFormat::RawHex, 128, LimbStyle::Minimal);
+        assert!(raw.starts_with("0001230000"));
+        assert_eq!(raw.trim().len(), 256);
+
+        let mut der = vec![0x03, 0x81, 129, 0x00];
+        der.extend(hex::decode(raw.trim()).unwrap());
+        let der_hex = hex::encode(&der);
+        let limbs = encode(&signature, OutFormat::Limbs, 128, LimbStyle::Minimal);
+        let fragment = encode(&signature, OutFormat::TomlFragment, 128, LimbStyle::Canonical);
+        let fragment: Value = fragment.parse().unwrap();
+        let fragment_limbs: Vec<&str> =
+            fragment[SIGNATURE_LIMBS].as_array().unwrap().iter().map(|limb| limb.as_str().unwrap()).collect();
+
+        let inputs: [(&[u8], InFormat); 5] = [
+            (raw.as_bytes(), InFormat::Raw),
+            (&hex::decode(raw.trim()).unwrap(), InFormat::Raw),
+            (&der, InFormat::Der),
+            (der_hex.as_bytes(), InFormat::Der),
+            (limbs.as_bytes(), InFormat::Limbs),
+        ];
+        for (input, format) in inputs {
+            assert_eq!(decode(input, None, &modulus), Ok((format, signature.clone())));
+        }
+        assert_eq!(decode(fragment_limbs.join(", ").as_bytes(), None, &modulus), Ok((InFormat::Limbs, signature)));
+
+        let bytes = encode(&BigUint::one(), OutFormat::Bytes, 128, LimbStyle::Minimal);
+        assert!(bytes.starts_with("[0, 0, 0,") && bytes.ends_with(", 0, 1]\n"));
+    }
+
+    #[test]
+    fn test_values_of_at_least_the_modulus_are_rejected() {
+        let modulus = modulus();
+        let at_modulus = hex::encode(modulus.to_bytes_be());
+        assert!(decode(at_modulus.as_bytes(), None, &modulus).unwrap_err().contains("not below the modulus"));
+        let below = hex::encode((&modulus - 1u32).to_bytes_be());
+        assert!(decode(below.as_bytes(), Some(InFormat::Raw), &modulus).is_ok());
+        assert!(decode(b"[0x1, 0x2]", None, &modulus).unwrap_err().contains("a 1024-bit signature has 9"));
+    }
+}
@@ -0,0 +1,494 @@
+//! Rust reference of the verification the Noir circuit performs, at the limb level
+//!
+//! The circuit never sees integers, only 120-bit limbs: it exponentiates the
+//! signature by square-and-multiply with Barrett reduction against the emitted
+//! redc parameter, then checks the encoded message byte by byte. This module
+//! follows the same steps so that a failure can be described in those terms,
+//! e.g. "limb 7 of the redc parameter differs", rather than "signature invalid".
+
+use std::fmt;
+
+use num_bigint::BigUint;
+use num_traits::ToPrimitive;
+
+use noir_bignum_paramgen::{compute_barrett_reduction_parameter, split_into_120_bit_limbs};
+
+use crate::hash::HashAlg;
+use crate::params::{combine_limbs, num_limbs, SignatureParams};
+use crate::pss::mgf1;
+use crate::signing::Scheme;
+
+/// Exponents accepted by `exponentiate_signature` in `src/rsa.nr`: more than 1 and less than 2^17
+const MAX_EXPONENT_BITS: u32 = 17;
+
+/// The redc parameter is `floor(2^(2 * modulus_bits + BARRETT_EXTRA_BITS) / modulus)`
+const BARRETT_EXTRA_BITS: usize = 6;
+
+/// Corrective subtractions of the modulus after the Barrett quotient estimate.
+/// With a correct redc parameter the estimate is off by at most this much.
+const MAX_BARRETT_CORRECTIONS: usize = 2;
+
+/// The inputs of one circuit invocation, as they appear in Prover.toml
+pub struct CircuitVector<'a> {
+    pub bits: usize,
+    pub modulus_limbs: &'a [BigUint],
+    pub redc_limbs: &'a [BigUint],
+    pub signature_limbs: &'a [BigUint],
+    pub exponent: &'a BigUint,
+    pub hash_alg: HashAlg,
+    pub msg_hash: &'a [u8],
+    pub scheme: &'a Scheme,
+}
+
+impl<'a> CircuitVector<'a> {
+    /// The first signature of `entry` under its key
+    #[cfg(test)]
+    pub fn of_entry(entry: &'a SignatureParams, exponent: &'a BigUint, scheme: &'a Scheme) -> CircuitVector<'a> {
+        let signature = &entry.signatures[0];
+        CircuitVector {
+            bits: entry.key.bits,
+            modulus_limbs: &entry.key.modulus_limbs,
+            redc_limbs: &entry.key.redc_limbs,
+            signature_limbs: &signature.signature_limbs,
+            exponent,
+            hash_alg: signature.hash_alg,
+            msg_hash: &signature.hash,
+            scheme,
+        }
+    }
+}
+
+/// The first step at which the vector fails the circuit's checks
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Divergence {
+    LimbCount {
+        input: &'static str,
+        expected: usize,
+        actual: usize,
+    },
+    /// A limb does not fit in 120 bits
+    LimbOverflow { input: &'static str, limb: usize },
+    /// The exponent is outside the range the circuit supports
+    Exponent(BigUint),
+    /// The redc limbs are not the Barrett parameter of the modulus
+    Redc {
+        limb: usize,
+        expected: BigUint,
+        actual: BigUint,
+    },
+    /// The modulus is zero or wider than the instantiation, so it has no Barrett parameter to compare
+    ModulusBits { bits: usize, actual: usize },
+    /// The signature is not smaller than the modulus
+    SignatureNotReduced,
+    /// The Barrett quotient estimate at the given multiplication was too far off
+    BarrettBound { step: usize, corrections: usize },
+    /// A byte of the encoded message (big-endian, as in RFC 8017) is wrong
+    Encoding {
+        check: &'static str,
+        byte: usize,
+        expected: u8,
+        actual: u8,
+    },
+    MessageHashLength { expected: usize, actual: usize },
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Divergence::LimbCount {
+                input,
+                expected,
+                actual,
+            } => write!(f, "{} has {} limbs but the circuit expects {}", input, actual, expected),
+            Divergence::LimbOverflow { input, limb } => {
+                write!(f, "limb {} of {} does not fit in 120 bits", limb, input)
+            }
+            Divergence::Exponent(exponent) => write!(
+                f,
+                "exponent {} is outside the supported range (1, 2^{})",
+                exponent, MAX_EXPONENT_BITS
+            ),
+            Divergence::Redc {
+                limb,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "limb {} of the redc parameter differs: expected 0x{:x}, got 0x{:x}",
+                limb, expected, actual
+            ),
+            Divergence::ModulusBits { bits, actual } => {
+                write!(f, "the modulus has {} bits, a {}-bit instantiation takes 1 to {}", actual, bits, bits)
+            }
+            Divergence::SignatureNotReduced => write!(f, "the signature is not smaller than the modulus"),
+            Divergence::BarrettBound { step, corrections } => write!(
+                f,
+                "Barrett reduction of multiplication {} needed {} corrections, at most {} are allowed",
+                step, corrections, MAX_BARRETT_CORRECTIONS
+            ),
+            Divergence::Encoding {
+                check,
+                byte,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "{} check failed at byte {} of the encoded message: expected 0x{:02x}, got 0x{:02x}",
+                check, byte, expected, actual
+            ),
+            Divergence::MessageHashLength { expected, actual } => {
+                write!(f, "message hash is {} bytes but {} were expected", actual, expected)
+            }
+        }
+    }
+}
+
+fn check_limbs(input: &'static str, limbs: &[BigUint], bits: usize) -> Result<(), Divergence> {
+    if limbs.len() != num_limbs(bits) {
+        return Err(Divergence::LimbCount {
+            input,
+            expected: num_limbs(bits),
+            actual: limbs.len(),
+        });
+    }
+    match limbs.iter().position(|limb| limb.bits() > 120) {
+        Some(limb) => Err(Divergence::LimbOverflow { input, limb }),
+        None => Ok(()),
+    }
+}
+
+/// Whether `modulus` is nonzero and fits `bits`, as computing its Barrett parameter in the limbs of a
+/// `bits`-bit instantiation requires
+pub fn check_modulus(modulus: &BigUint, bits: usize) -> Result<(), Divergence> {
+    match modulus.bits() {
+        0 => Err(Divergence::ModulusBits { bits, actual: 0 }),
+        actual if actual > bits => Err(Divergence::ModulusBits { bits, actual }),
+        _ => Ok(()),
+    }
+}
+
+/// Barrett reduction of `x < modulus^2` as BigNum computes it, returning the
+/// remainder and the number of corrective subtractions that were needed
+pub fn barrett_reduce(x: &BigUint, modulus: &BigUint, redc: &BigUint) -> (BigUint, usize) {
+    let shift = 2 * modulus.bits() + BARRETT_EXTRA_BITS;
+    let quotient = (x * redc) >> shift;
+    let mut remainder = x - quotient * modulus;
+    let mut corrections = 0;
+    while &remainder >= modulus {
+        remainder -= modulus;
+        corrections += 1;
+    }
+    (remainder, corrections)
+}
+
+/// The square-and-multiply chain of `exponentiate_signature`, over the same
+/// 17 iterations, with every product reduced by `barrett_reduce`
+pub fn exponentiate(
+    signature: &BigUint,
+    exponent: u32,
+    modulus: &BigUint,
+    redc: &BigUint,
+) -> Result<BigUint, Divergence> {
+    exponentiate_counting(signature, exponent, modulus, redc).map(|(result, _)| result)
+}
+
+/// `exponentiate`, also returning the most corrective subtractions any of its products needed
+pub fn exponentiate_counting(
+    signature: &BigUint,
+    exponent: u32,
+    modulus: &BigUint,
+    redc: &BigUint,
+) -> Result<(BigUint, usize), Divergence> {
+    let mut step = 0;
+    let mut most_corrections = 0;
+    let mut mul = |a: &BigUint, b: &BigUint| -> Result<BigUint, Divergence> {
+        step += 1;
+        let (reduced, corrections) = barrett_reduce(&(a * b), modulus, redc);
+        if corrections > MAX_BARRETT_CORRECTIONS {
+            return Err(Divergence::BarrettBound { step, corrections });
+        }
+        most_corrections = most_corrections.max(corrections);
+        Ok(reduced)
+    };
+
+    let mut result = BigUint::from(1u32);
+    let mut base = signature.clone();
+    let mut exp = exponent;
+    for _ in 0..MAX_EXPONENT_BITS {
+        if exp > 0 {
+            if exp % 2 == 1 {
+                result = mul(&result, &base)?;
+            }
+            base = mul(&base, &base)?;
+            exp /= 2;
+        }
+    }
+    Ok((result, most_corrections))
+}
+
+fn compare(check: &'static str, offset: usize, expected: &[u8], actual: &[u8]) -> Result<(), Divergence> {
+    match expected.iter().zip(actual).position(|(e, a)| e != a) {
+        Some(i) => Err(Divergence::Encoding {
+            check,
+            byte: offset + i,
+            expected: expected[i],
+            actual: actual[i],
+        }),
+        None => Ok(()),
+    }
+}
+
+fn check_pkcs1v15(em: &[u8], hash_alg: HashAlg, msg_hash: &[u8]) -> Result<(), Divergence> {
+    // EM = 0x00 || 0x01 || PS (0xff...) || 0x00 || DigestInfo prefix || hash
+    let prefix = hash_alg.digest_info_prefix();
+    let t_len = prefix.len() + msg_hash.len();
+    let ps_len = em.len().checked_sub(t_len + 3).ok_or(Divergence::Encoding {
+        check: "pkcs1v15 length",
+        byte: 0,
+        expected: 0,
+        actual: em[0],
+    })?;
+
+    compare("pkcs1v15 header", 0, &[0x00, 0x01], &em[..2])?;
+    compare("pkcs1v15 padding", 2, &vec![0xff; ps_len], &em[2..2 + ps_len])?;
+    compare("pkcs1v15 separator", 2 + ps_len, &[0x00], &em[2 + ps_len..3 + ps_len])?;
+    let prefix_start = 3 + ps_len;
+    compare("digest info", prefix_start, prefix, &em[prefix_start..prefix_start + prefix.len()])?;
+    let hash_start = prefix_start + prefix.len();
+    compare("message hash", hash_start, msg_hash, &em[hash_start..])
+}
+
+fn check_pss(em: &[u8], bits: usize, hash_alg: HashAlg, msg_hash: &[u8], salt_len: usize) -> Result<(), Divergence> {
+    // EMSA-PSS-VERIFY (RFC 8017 9.1.2) with emBits = modBits - 1. When emBits
+    // is a multiple of 8 the encoding is one byte shorter than the modulus.
+    let em_bits = bits - 1;
+    let em_len = (em_bits + 7) / 8;
+    let (leading, em) = em.split_at(em.len() - em_len);
+    compare("pss leading zero", 0, &vec![0; leading.len()], leading)?;
+    let offset = leading.len();
+
+    let h_len = hash_alg.output_len();
+    if em_len < h_len + salt_len + 2 {
+        return Err(Divergence::Encoding {
+            check: "pss length",
+            byte: offset,
+            expected: 0,
+            actual: em[0],
+        });
+    }
+    compare("pss trailer", offset + em_len - 1, &[0xbc], &em[em_len - 1..])?;
+
+    let db_len = em_len - h_len - 1;
+    let (masked_db, h) = (&em[..db_len], &em[db_len..em_len - 1]);
+    let unused_bits = 8 * em_len - em_bits;
+    let top_mask = if unused_bits == 0 { 0 } else { 0xffu8 << (8 - unused_bits) };
+    if masked_db[0] & top_mask != 0 {
+        return Err(Divergence::Encoding {
+            check: "pss masked db top bits",
+            byte: offset,
+            expected: masked_db[0] & !top_mask,
+            actual: masked_db[0],
+        });
+    }
+
+    let mut db: Vec<u8> = masked_db.iter().zip(mgf1(hash_alg, h, db_len)).map(|(m, k)| m ^ k).collect();
+    db[0] &= !top_mask;
+    let ps_len = db_len - salt_len - 1;
+    compare("pss db padding", offset, &vec![0; ps_len], &db[..ps_len])?;
+    compare("pss db separator", offset + ps_len, &[0x01], &db[ps_len..ps_len + 1])?;
+
+    let salt = &db[ps_len + 1..];
+    let mut m_prime = vec![0u8; 8];
+    m_prime.extend_from_slice(msg_hash);
+    m_prime.extend_from_slice(salt);
+    compare("pss hash", offset + db_len, &hash_alg.digest(&m_prime), h)
+}
+
+/// Run the circuit's verification on a vector, returning the first step that diverges
+pub fn verify(vector: &CircuitVector) -> Result<(), Divergence> {
+    let bits = vector.bits;
+    check_limbs("modulus", vector.modulus_limbs, bits)?;
+    check_limbs("redc", vector.redc_limbs, bits)?;
+    check_limbs("signature", vector.signature_limbs, bits)?;
+    if vector.msg_hash.len() != vector.hash_alg.output_len() {
+        return Err(Divergence::MessageHashLength {
+            expected: vector.hash_alg.output_len(),
+            actual: vector.msg_hash.len(),
+        });
+    }
+
+    let exponent = vector
+        .exponent
+        .to_u32()
+        .filter(|e| *e > 1 && *e < (1 << MAX_EXPONENT_BITS))
+        .ok_or_else(|| Divergence::Exponent(vector.exponent.clone()))?;
+
+    let modulus = combine_limbs(vector.modulus_limbs);
+    check_modulus(&modulus, bits)?;
+    let expected_redc = split_into_120_bit_limbs(&compute_barrett_reduction_parameter(&modulus), bits);
+    if let Some(limb) = (0..expected_redc.len()).find(|&i| expected_redc[i] != vector.redc_limbs[i]) {
+        return Err(Divergence::Redc {
+            limb,
+            expected: expected_redc[limb].clone(),
+            actual: vector.redc_limbs[limb].clone(),
+        });
+    }
+
+    let signature = combine_limbs(vector.signature_limbs);
+    if signature >= modulus {
+        return Err(Divergence::SignatureNotReduced);
+    }
+
+    let exponentiated = exponentiate(&signature, exponent, &modulus, &combine_limbs(vector.redc_limbs))?;
+    let em_len = (bits + 7) / 8;
+    let bytes = exponentiated.to_bytes_be();
+    let padding = em_len.checked_sub(bytes.len()).ok_or(Divergence::ModulusBits { bits, actual: modulus.bits() })?;
+    let mut em = vec![0u8; padding];
+    em.extend_from_slice(&bytes);
+
+    match vector.scheme.salt_len() {
+        None => check_pkcs1v15(&em, vector.hash_alg, vector.msg_hash),
+        Some(salt_len) => check_pss(&em, modulus.bits(), vector.hash_alg, vector.msg_hash, salt_len),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use num_bigint::RandBigInt;
+    use num_traits::Zero;
+    use rand::{Rng, SeedableRng};
+    use rand_chacha::ChaCha20Rng;
+    use rsa::pkcs8::DecodePrivateKey;
+    use rsa::traits::PublicKeyParts;
+    use rsa::RsaPrivateKey;
+
+    use crate::signing::{sign_digest, PssSalt};
+
+    const FIXTURE_KEY: &str = include_str!("../tests/fixtures/rsa_2048.pem");
+    const MESSAGE: &[u8] = b"Hello World! This is Noir-RSA";
+
+    struct Fixture {
+        modulus_limbs: Vec<BigUint>,
+        redc_limbs: Vec<BigUint>,
+        signature_limbs: Vec<BigUint>,
+        exponent: BigUint,
+        msg_hash: Vec<u8>,
+    }
+
+    impl Fixture {
+        fn new(scheme: &Scheme) -> Fixture {
+            let priv_key = RsaPrivateKey::from_pkcs8_pem(FIXTURE_KEY).unwrap();
+            let msg_hash = HashAlg::Sha256.digest(MESSAGE);
+            let mut rng = ChaCha20Rng::seed_from_u64(0);
+            let sig = sign_digest(&mut rng, &priv_key, HashAlg::Sha256, &msg_hash, scheme).unwrap();
+            Fixture {
+                modulus_limbs: split_into_120_bit_limbs(priv_key.n(), 2048),
+                redc_limbs: split_into_120_bit_limbs(&compute_barrett_reduction_parameter(priv_key.n()), 2048),
+                signature_limbs: split_into_120_bit_limbs(&BigUint::from_bytes_be(&sig), 2048),
+                exponent: priv_key.e().clone(),
+                msg_hash,
+            }
+        }
+
+        fn verify(&self, scheme: &Scheme) -> Result<(), Divergence> {
+            verify(&CircuitVector {
+                bits: 2048,
+                modulus_limbs: &self.modulus_limbs,
+                redc_limbs: &self.redc_limbs,
+                signature_limbs: &self.signature_limbs,
+                exponent: &self.exponent,
+                hash_alg: HashAlg::Sha256,
+                msg_hash: &self.msg_hash,
+                scheme,
+            })
+        }
+    }
+
+    #[test]
+    fn test_exponentiate_matches_modpow() {
+        let mut rng = ChaCha20Rng::seed_from_u64(0);
+        for bits in [1024, 2048] {
+            for _ in 0..8 {
+                let modulus = rng.gen_biguint(bits) | (BigUint::from(1u32) << (bits - 1)) | BigUint::from(1u32);
+                let redc = compute_barrett_reduction_parameter(&modulus);
+                let signature = rng.gen_biguint_below(&modulus);
+                let exponent: u32 = rng.gen_range(2..1 << MAX_EXPONENT_BITS);
+
+                let result = exponentiate(&signature, exponent, &modulus, &redc).unwrap();
+                assert_eq!(result, signature.modpow(&BigUint::from(exponent), &modulus));
+            }
+        }
+    }
+
+    #[test]
+    fn test_barrett_reduce_matches_remainder() {
+        let mut rng = ChaCha20Rng::seed_from_u64(1);
+        let modulus = rng.gen_biguint(2048) | (BigUint::from(1u32) << 2047);
+        let redc = compute_barrett_reduction_parameter(&modulus);
+        for _ in 0..32 {
+            let x = rng.gen_biguint_below(&(&modulus * &modulus));
+            let (remainder, corrections) = barrett_reduce(&x, &modulus, &redc);
+            assert_eq!(remainder, &x % &modulus);
+            assert!(corrections <= MAX_BARRETT_CORRECTIONS);
+        }
+    }
+
+    #[test]
+    fn test_fixture_vectors_verify() {
+        for scheme in [
+            Scheme::Pkcs1v15,
+            Scheme::Pss(PssSalt::Random(32)),
+            Scheme::Pss(PssSalt::Random(0)),
+        ] {
+            assert_eq!(Fixture::new(&scheme).verify(&scheme), Ok(()));
+        }
+    }
+
+    #[test]
+    fn test_divergences_name_the_failing_step() {
+        let scheme = Scheme::Pkcs1v15;
+        let fixture = Fixture::new(&scheme);
+
+        let mut tampered = Fixture::new(&scheme);
+        tampered.redc_limbs[7] += 1u32;
+        assert!(matches!(tampered.verify(&scheme), Err(Divergence::Redc { limb: 7, .. })));
+
+        let mut tampered = Fixture::new(&scheme);
+        tampered.msg_hash[3] ^= 1;
+        assert_eq!(
+            tampered.verify(&scheme),
+            Err(Divergence::Encoding {
+                check: "message hash",
+                byte: 256 - 32 + 3,
+                expected: tampered.msg_hash[3],
+                actual: fixture.msg_hash[3],
+            })
+        );
+
+        let mut tampered = Fixture::new(&scheme);
+        tampered.signature_limbs[0] = BigUint::from(1u32) << 120;
+        assert_eq!(
+            tampered.verify(&scheme),
+            Err(Divergence::LimbOverflow { input: "signature", limb: 0 })
+        );
+
+        // A zero or too wide modulus is refused before its Barrett parameter is computed
+        let mut tampered = Fixture::new(&scheme);
+        tampered.modulus_limbs.iter_mut().for_each(|limb| *limb = BigUint::zero());
+        assert_eq!(tampered.verify(&scheme), Err(Divergence::ModulusBits { bits: 2048, actual: 0 }));
+        let mut tampered = Fixture::new(&scheme);
+        tampered.modulus_limbs[17] = (BigUint::from(1u32) << 120) - 1u32;
+        assert_eq!(tampered.verify(&scheme), Err(Divergence::ModulusBits { bits: 2048, actual: 2160 }));
+
+        let mut tampered = Fixture::new(&scheme);
+        tampered.exponent = BigUint::from(1u32 << 17) + 1u32;
+        assert!(matches!(tampered.verify(&scheme), Err(Divergence::Exponent(_))));
+
+        // A PKCS#1 v1.5 signature checked as PSS fails at the trailer byte
+        assert!(matches!(
+            fixture.verify(&Scheme::Pss(PssSalt::Random(32))),
+            Err(Divergence::Encoding { check: "pss trailer", byte: 255, .. })
+        ));
+    }
+}
@@ -0,0 +1,217 @@
+//! `regen`: regenerate the vectors of a directory whose metadata matches a filter
+//!
+//! A filter `name=value` matches a `--tag` of that name, or a `[meta]` field
+//! of that name or its plural holding the value, so `hash=sha512` selects the
+//! files whose `hashes` include SHA-512. A matching file is rerun with the
+//! command recorded in its metadata, which reproduces it only if the run was
+//! seeded or signed deterministically with `--key` files. Files that cannot
+//! be rerun are reported as skipped. Regenerated content that differs from the
+//! file in nothing but its timestamp is not written, so unchanged vectors keep
+//! their bytes.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use toml::Value;
+
+use crate::meta::{META, TAGS};
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum Outcome {
+    Updated,
+    Unchanged,
+    Skipped(String),
+    Failed(String),
+}
+
+impl std::fmt::Display for Outcome {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Outcome::Updated => write!(f, "updated"),
+            Outcome::Unchanged => write!(f, "unchanged"),
+            Outcome::Skipped(reason) => write!(f, "skipped, {}", reason),
+            Outcome::Failed(err) => write!(f, "failed, {}", err),
+        }
+    }
+}
+
+/// Every TOML file under `dir`, sorted
+fn toml_files(dir: &Path) -> Result<Vec<PathBuf>, String> {
+    let mut files = Vec::new();
+    let entries = fs::read_dir(dir).map_err(|err| format!("Failed to read {}: {}", dir.display(), err))?;
+    for entry in entries {
+        let path = entry.map_err(|err| format!("Failed to read {}: {}", dir.display(), err))?.path();
+        if path.is_dir() {
+            files.extend(toml_files(&path)?);
+        } else if path.extension().map_or(false, |extension| extension == "toml") {
+            files.push(path);
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+fn value_matches(value: &Value, expected: &str) -> bool {
+    match value {
+        Value::String(value) => value == expected,
+        Value::Integer(value) => value.to_string() == expected,
+        Value::Boolean(value) => value.to_string() == expected,
+        Value::Array(values) => values.iter().any(|value| value_matches(value, expected)),
+        _ => false,
+    }
+}
+
+/// Whether the `[meta]` table `meta` satisfies every filter
+pub fn matches(meta: &Value, filters: &[(String, String)]) -> bool {
+    filters.iter().all(|(name, expected)| {
+        let tag = meta.get(TAGS).and_then(|tags| tags.get(name));
+        let candidates = [tag, meta.get(name), meta.get(format!("{}s", name))];
+        candidates.into_iter().flatten().any(|value| value_matches(value, expected))
+    })
+}
+
+/// The recorded command, if rerunning it reproduces the file
+fn command(meta: &Value) -> Result<Vec<String>, String> {
+    let command: Vec<String> = meta
+        .get("command")
+        .and_then(Value::as_array)
+        .map_or(Vec::new(), |args| args.iter().filter_map(Value::as_str).map(String::from).collect());
+    if command.is_empty() {
+        return Err("no command is recorded in [meta]".to_string());
+    }
+    let seeded = meta.get("seed").is_some();
+    let key_files = meta.get("key_files").and_then(Value::as_array).map_or(false, |files| !files.is_empty());
+    let deterministic = meta.get("scheme").and_then(Value::as_str) == Some("pkcs1v15");
+    if !seeded && !(key_files && deterministic) {
+        return Err("neither a seed nor --key files signing deterministically are recorded".to_string());
+    }
+    Ok(command)
+}
+
+/// The document without its timestamp, for comparing two generations of it
+fn without_timestamp(contents: &str) -> Option<Value> {
+    let mut document: Value = contents.parse().ok()?;
+    if let Some(meta) = document.get_mut(META).and_then(Value::as_table_mut) {
+        meta.remove("timestamp");
+    }
+    Some(document)
+}
+
+/// Regenerate the files under `dir` matching `filters` with `run`, which
+/// returns the output of a recorded command. With `dry_run` nothing is written.
+pub fn regen<F>(
+    dir: &Path,
+    filters: &[(String, String)],
+    dry_run: bool,
+    run: F,
+) -> Result<Vec<(PathBuf, Outcome)>, String>
+where
+    F: Fn(&[String]) -> Result<String, String>,
+{
+    let mut outcomes = Vec::new();
+    for path in toml_files(dir)? {
+        let contents =
+            fs::read_to_string(&path).map_err(|err| format!("Failed to read {}: {}", path.display(), err))?;
+        let meta = match contents.parse::<Value>() {
+            Ok(document) => document.get(META).cloned(),
+            Err(_) => None,
+        };
+        let meta = match meta {
+            Some(meta) => meta,
+            None => {
+                outcomes.push((path, Outcome::Skipped("no [meta] table to filter on".to_string())));
+                continue;
+            }
+        };
+        if !matches(&meta, filters) {
+            continue;
+        }
+        let command = match command(&meta) {
+            Ok(command) => command,
+            Err(reason) => {
+                outcomes.push((path, Outcome::Skipped(reason)));
+                continue;
+            }
+        };
+        let outcome = match run(&command) {
+            Ok(output) if without_timestamp(&output) == without_timestamp(&contents) => Outcome::Unchanged,
+            Ok(output) => {
+                if !dry_run {
+                    fs::write(&path, output).map_err(|err| format!("Failed to write {}: {}", path.display(), err))?;
+                }
+                Outcome::Updated
+            }
+            Err(err) => Outcome::Failed(err),
+        };
+        outcomes.push((path, outcome));
+    }
+    Ok(outcomes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha20Rng;
+    use rsa::pkcs8::DecodePrivateKey;
+    use rsa::RsaPrivateKey;
+
+    use crate::hash::HashAlg;
+    use crate::meta::Meta;
+    use crate::schema::Names;
+    use crate::signature_params_for_key;
+    use crate::signing::{PssSalt, Scheme};
+
+    /// What `sign --toml --pss --hash <hash> --seed <seed>` prints for the fixture key
+    fn sign(command: &[String], timestamp: &str) -> String {
+        let (hash, seed) = (HashAlg::from_name(&command[2]).unwrap(), command[4].parse().unwrap());
+        let priv_key = RsaPrivateKey::from_pkcs8_pem(include_str!("../tests/fixtures/rsa_1024.pem")).unwrap();
+        let scheme = Scheme::Pss(PssSalt::Random(32));
+        let mut rng = ChaCha20Rng::seed_from_u64(seed);
+        let params = signature_params_for_key(&mut rng, &priv_key, b"hello", &[hash], &scheme).unwrap();
+        let mut meta = Meta::new(&[priv_key.to_public_key()], &[hash], &scheme, b"hello");
+        meta.timestamp = timestamp.to_string();
+        meta.seed = Some(seed);
+        meta.command = command.to_vec();
+        format!("{}\n{}", params.render_toml(Names::Canonical), meta.render_toml())
+    }
+
+    fn sign_command(hash: &str, seed: u64) -> Vec<String> {
+        ["sign", "--hash", hash, "--seed", &seed.to_string()].map(String::from).to_vec()
+    }
+
+    #[test]
+    fn test_only_matching_seeded_files_are_regenerated() {
+        let scratch = tempfile::tempdir().unwrap();
+        let dir = scratch.path();
+        fs::create_dir_all(dir.join("nested")).unwrap();
+        let (sha256, sha512) = (dir.join("a.toml"), dir.join("nested/b.toml"));
+        fs::write(&sha256, sign(&sign_command("sha256", 1), "2020-01-01T00:00:00Z")).unwrap();
+        fs::write(&sha512, sign(&sign_command("sha512", 2), "2020-01-01T00:00:00Z")).unwrap();
+        // Without its seed, the same file cannot be reproduced
+        let unseeded = sign(&sign_command("sha512", 3), "2020-01-01T00:00:00Z").replace("seed = 3\n", "");
+        fs::write(dir.join("c.toml"), unseeded).unwrap();
+        fs::write(dir.join("d.toml"), "modulus_limbs = []\n").unwrap();
+
+        let filters = [("hash".to_string(), "sha512".to_string())];
+        let run = |command: &[String]| Ok(sign(command, "2030-01-01T00:00:00Z"));
+        let outcomes = regen(dir, &filters, false, run).unwrap();
+        let names: Vec<(&str, &Outcome)> =
+            outcomes.iter().map(|(path, outcome)| (path.file_name().unwrap().to_str().unwrap(), outcome)).collect();
+        assert_eq!(names.len(), 3);
+        assert_eq!(names[0].0, "c.toml");
+        assert!(matches!(names[0].1, Outcome::Skipped(reason) if reason.contains("seed")));
+        assert!(matches!(names[1], ("d.toml", Outcome::Skipped(_))));
+        // A seeded run regenerates the same file, so only the timestamp would change and nothing is written
+        assert_eq!(names[2], ("b.toml", &Outcome::Unchanged));
+        assert!(fs::read_to_string(&sha512).unwrap().contains("2020-01-01"));
+
+        // A stale file is rewritten with what its command reproduces
+        fs::write(&sha512, sign(&sign_command("sha512", 2), "2020-01-01T00:00:00Z").replace("0x", "0x1")).unwrap();
+        let outcomes = regen(dir, &filters, false, run).unwrap();
+        assert_eq!(outcomes[2].1, Outcome::Updated);
+        assert_eq!(fs::read_to_string(&sha512).unwrap(), sign(&sign_command("sha512", 2), "2030-01-01T00:00:00Z"));
+        let outcomes = regen(dir, &[("tags".to_string(), "none".to_string())], false, run).unwrap();
+        assert!(outcomes.iter().all(|(_, outcome)| matches!(outcome, Outcome::Skipped(_))));
+    }
+}
@@ -0,0 +1,189 @@
+//! The Prover.toml schema, defined once for every subcommand that reads or writes it
+//!
+//! The canonical names match the inputs of `example/src/main.nr`. The legacy
+//! shape is the one emitted before BigNum 0.4.0: `bn = [modulus, redc]` with
+//! the signature in a `[signature]` table.
+
+use toml::value::Table;
+use toml::Value;
+
+use crate::expected::EXPECTED;
+use crate::meta::META;
+use crate::presets::Preset;
+
+pub const HASH: &str = "hash";
+pub const MODULUS_LIMBS: &str = "modulus_limbs";
+pub const REDC_LIMBS: &str = "redc_limbs";
+pub const SIGNATURE_LIMBS: &str = "signature_limbs";
+pub const SALT: &str = "salt";
+/// The public exponent, only emitted for circuits that take it as an input
+pub const EXPONENT: &str = "exponent";
+pub const MESSAGE: &str = "message";
+pub const MESSAGE_LEN: &str = "message_len";
+pub const PUBKEY_HASH: &str = "pubkey_hash";
+pub const PUBKEY_HASH_HEX: &str = "pubkey_hash_hex";
+pub const PUBKEY_POSEIDON: &str = "pubkey_poseidon";
+/// ISO 9796-2 message parts: M1, carried by the signature, and M2, known to the verifier
+pub const RECOVERED_MESSAGE: &str = "recovered_message";
+pub const NON_RECOVERABLE_MESSAGE: &str = "non_recoverable_message";
+/// Number of real vectors in `--aggregate` output, whose inputs are arrays of slots
+pub const ACTIVE_COUNT: &str = "active_count";
+
+/// Keys and tables that label or describe a vector rather than being inputs of a circuit, left out of ABI
+/// JSON: provenance, expected outputs, the labels of invalid, edge-case and DKIM vectors, and by-eye values
+pub const NON_INPUTS: [&str; 8] =
+    [META, EXPECTED, "expected_valid", "tamper", "edge_case", "edge_case_note", "dkim", PUBKEY_HASH_HEX];
+
+pub const LEGACY_BN: &str = "bn";
+pub const LEGACY_SIGNATURE: &str = "signature";
+pub const LEGACY_SIGNATURE_LIMBS: &str = "limbs";
+
+/// Input names emitted in TOML output
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Names {
+    #[default]
+    Canonical,
+    /// The pre-0.4.0 shape, kept for circuits that have not migrated yet
+    Legacy,
+    /// The names and nesting of a downstream circuit
+    Preset(&'static Preset),
+}
+
+impl Names {
+    /// Rename the inputs of a canonical table
+    pub fn apply(self, table: Table) -> Table {
+        match self {
+            Names::Canonical => table,
+            Names::Legacy => to_legacy(table),
+            Names::Preset(preset) => preset.apply(table),
+        }
+    }
+}
+
+/// Rewrite canonical inputs into the legacy shape, in this table and every
+/// nested table (per-hash tables and `[[keys]]` entries). Other keys are kept.
+pub fn to_legacy(table: Table) -> Table {
+    // The modulus and redc only move into `bn` as a pair
+    let mut bn = match (table.get(MODULUS_LIMBS), table.get(REDC_LIMBS)) {
+        (Some(modulus), Some(redc)) => Some(Value::Array(vec![modulus.clone(), redc.clone()])),
+        _ => None,
+    };
+    let paired = bn.is_some();
+
+    let mut legacy = Table::new();
+    let mut signature = None;
+    for (name, value) in table {
+        match name.as_str() {
+            MODULUS_LIMBS if paired => {
+                legacy.insert(LEGACY_BN.to_string(), bn.take().unwrap());
+            }
+            REDC_LIMBS if paired => {}
+            SIGNATURE_LIMBS => signature = Some(value),
+            _ => {
+                legacy.insert(name, nested_to_legacy(value));
+            }
+        }
+    }
+
+    if let Some(limbs) = signature {
+        let mut signature_table = Table::new();
+        signature_table.insert(LEGACY_SIGNATURE_LIMBS.to_string(), limbs);
+        legacy.insert(LEGACY_SIGNATURE.to_string(), Value::Table(signature_table));
+    }
+    legacy
+}
+
+fn nested_to_legacy(value: Value) -> Value {
+    match value {
+        Value::Table(table) => Value::Table(to_legacy(table)),
+        Value::Array(values) if values.iter().all(Value::is_table) => {
+            Value::Array(values.into_iter().map(nested_to_legacy).collect())
+        }
+        value => value,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand_chacha::rand_core::SeedableRng;
+    use rand_chacha::ChaCha20Rng;
+    use rsa::pkcs8::DecodePrivateKey;
+    use rsa::RsaPrivateKey;
+
+    use crate::hash::HashAlg;
+    use crate::signature_params_for_key;
+    use crate::signing::Scheme;
+
+    const FIXTURE_KEY: &str = include_str!("../tests/fixtures/rsa_2048.pem");
+    const EXAMPLE_ABI: &str = include_str!("../tests/fixtures/example_abi.json");
+    const PROVER_V03: &str = include_str!("../tests/fixtures/prover_v03.toml");
+
+    fn fixture_toml(names: Names) -> Table {
+        let priv_key = RsaPrivateKey::from_pkcs8_pem(FIXTURE_KEY).unwrap();
+        let mut rng = ChaCha20Rng::seed_from_u64(0);
+        let params = signature_params_for_key(
+            &mut rng,
+            &priv_key,
+            b"Hello World! This is Noir-RSA",
+            &[HashAlg::Sha256],
+            &Scheme::Pkcs1v15,
+        ).unwrap();
+        match params.render_toml(names).parse::<Value>().unwrap() {
+            Value::Table(table) => table,
+            _ => unreachable!(),
+        }
+    }
+
+    /// Check that a TOML value has the shape of a Noir ABI type
+    fn assert_matches_abi(name: &str, value: &Value, abi_type: &serde_json::Value) {
+        match abi_type["kind"].as_str().unwrap() {
+            "array" => {
+                let values = value.as_array().unwrap_or_else(|| panic!("`{}` is not an array", name));
+                assert_eq!(values.len() as u64, abi_type["length"].as_u64().unwrap(), "length of `{}`", name);
+                for element in values {
+                    assert_matches_abi(name, element, &abi_type["type"]);
+                }
+            }
+            "integer" if abi_type["width"] == 8 => {
+                let byte = value.as_integer().unwrap_or_else(|| panic!("`{}` has a non-integer byte", name));
+                assert!((0..256).contains(&byte), "`{}` has an out of range byte", name);
+            }
+            "integer" => {
+                let limb = value.as_str().unwrap_or_else(|| panic!("`{}` has a non-string limb", name));
+                let width = abi_type["width"].as_u64().unwrap() as usize;
+                let digits = limb.strip_prefix("0x").unwrap();
+                assert!(digits.len() * 4 <= width, "`{}` limb {} is wider than {} bits", name, limb, width);
+            }
+            kind => panic!("unexpected ABI type {} for `{}`", kind, name),
+        }
+    }
+
+    #[test]
+    fn test_canonical_names_match_example_abi() {
+        let abi: serde_json::Value = serde_json::from_str(EXAMPLE_ABI).unwrap();
+        let parameters = abi["parameters"].as_array().unwrap();
+        let document = fixture_toml(Names::Canonical);
+
+        assert_eq!(document.len(), parameters.len());
+        for parameter in parameters {
+            let name = parameter["name"].as_str().unwrap();
+            let value = document.get(name).unwrap_or_else(|| panic!("`{}` is missing", name));
+            assert_matches_abi(name, value, &parameter["type"]);
+        }
+    }
+
+    #[test]
+    fn test_legacy_names_match_v03_layout() {
+        let expected = match PROVER_V03.parse::<Value>().unwrap() {
+            Value::Table(table) => table,
+            _ => unreachable!(),
+        };
+        let document = fixture_toml(Names::Legacy);
+
+        assert_eq!(document.len(), 3);
+        for name in [LEGACY_BN, HASH, LEGACY_SIGNATURE] {
+            assert_eq!(document[name], expected[name], "`{}`", name);
+        }
+    }
+}
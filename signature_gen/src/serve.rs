@@ -0,0 +1,402 @@
+//! `serve` subcommand: a small JSON API computing circuit inputs for existing
+//! signatures. It only ever sees public data, so there is no key generation.
+
+use std::io::Read;
+use std::sync::Arc;
+
+use base64ct::{Base64, Encoding};
+use rsa::traits::PublicKeyParts;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tiny_http::{Header, Method, Request, Response, Server};
+
+use crate::errors::{Code, Error};
+use crate::hash::HashAlg;
+use crate::keys::{check_loaded_bits, parse_public_key};
+use crate::params::LimbStyle;
+use crate::signing::{PssSalt, Scheme};
+use crate::{signature_params_for_signature, CircuitInputs};
+
+/// Largest request body accepted, comfortably above a 4096-bit key, signature and message
+pub const MAX_BODY_BYTES: usize = 64 * 1024;
+
+/// Body of `POST /v1/params`
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ParamsRequest {
+    /// PEM, base64 encoded DER or JWK
+    public_key: String,
+    signature_hex: Option<String>,
+    signature_base64: Option<String>,
+    /// UTF-8 message, hashed with `hash`
+    message: Option<String>,
+    message_hex: Option<String>,
+    /// Message hash, when the message itself is not shared
+    digest_hex: Option<String>,
+    #[serde(default = "default_hash")]
+    hash: String,
+    #[serde(default = "default_scheme")]
+    scheme: String,
+    salt_len: Option<usize>,
+    /// Zero-pad every limb to 30 hex characters
+    #[serde(default)]
+    canonical_limbs: bool,
+}
+
+fn default_hash() -> String {
+    "sha256".to_string()
+}
+
+fn default_scheme() -> String {
+    "pkcs1v15".to_string()
+}
+
+/// Error returned to the client as `{"error": {"code": ..., "message": ...}}`
+#[derive(Debug)]
+pub struct ApiError {
+    pub status: u16,
+    pub code: &'static str,
+    pub message: String,
+}
+
+impl ApiError {
+    fn new(status: u16, code: &'static str, message: impl Into<String>) -> ApiError {
+        ApiError {
+            status,
+            code,
+            message: message.into(),
+        }
+    }
+
+    fn invalid(message: impl Into<String>) -> ApiError {
+        ApiError::new(400, "invalid_request", message)
+    }
+
+    /// A library failure, with the status of its code and the code's own name
+    fn from_error(err: Error) -> ApiError {
+        match err.code {
+            Code::InvalidArgument | Code::BadKeyFile => ApiError::invalid(err.message),
+            Code::KeyMismatch | Code::VerificationFailed => ApiError::new(422, err.code.name(), err.message),
+            Code::Failed => ApiError::new(500, err.code.name(), err.message),
+        }
+    }
+
+    fn to_json(&self) -> Value {
+        json!({ "error": { "code": self.code, "message": self.message } })
+    }
+}
+
+fn decode_hex(field: &str, value: &str) -> Result<Vec<u8>, ApiError> {
+    let value = value.trim();
+    let value = value.strip_prefix("0x").unwrap_or(value);
+    hex::decode(value).map_err(|err| ApiError::invalid(format!("`{}` is not valid hex: {}", field, err)))
+}
+
+fn exactly_one<'a>(
+    fields: &[(&'static str, &'a Option<String>)],
+) -> Result<(&'static str, &'a str), ApiError> {
+    let given: Vec<_> = fields
+        .iter()
+        .filter_map(|(name, value)| value.as_deref().map(|value| (*name, value)))
+        .collect();
+    match given.as_slice() {
+        [(name, value)] => Ok((*name, *value)),
+        _ => {
+            let names: Vec<_> = fields.iter().map(|(name, _)| format!("`{}`", name)).collect();
+            Err(ApiError::invalid(format!("exactly one of {} is required", names.join(", "))))
+        }
+    }
+}
+
+/// Compute the circuit inputs for a request, after checking the signature
+/// verifies, with the checks and warnings of `sign --signature`
+pub fn handle_params(request: &ParamsRequest) -> Result<Value, ApiError> {
+    let pub_key = parse_public_key(&request.public_key)
+        .map_err(|err| ApiError::invalid(format!("`public_key`: {}", err)))?;
+    check_loaded_bits(&pub_key, "`public_key`").map_err(ApiError::from_error)?;
+
+    let hash_alg = HashAlg::from_name(&request.hash).ok_or_else(|| {
+        ApiError::invalid(format!(
+            "unsupported hash '{}', expected one of sha1, sha256, sha384, sha512",
+            request.hash
+        ))
+    })?;
+    let scheme = match (request.scheme.as_str(), request.salt_len) {
+        ("pkcs1v15", None) => Scheme::Pkcs1v15,
+        ("pkcs1v15", Some(_)) => return Err(ApiError::invalid("`salt_len` only applies to the pss scheme")),
+        ("pss", salt_len) => Scheme::Pss(PssSalt::Random(salt_len.unwrap_or(hash_alg.output_len()))),
+        (other, _) => {
+            return Err(ApiError::invalid(format!(
+                "unsupported scheme '{}', expected pkcs1v15 or pss",
+                other
+            )))
+        }
+    };
+
+    let (field, signature) = exactly_one(&[
+        ("signature_hex", &request.signature_hex),
+        ("signature_base64", &request.signature_base64),
+    ])?;
+    let signature = if field == "signature_hex" {
+        decode_hex(field, signature)?
+    } else {
+        Base64::decode_vec(signature.trim())
+            .map_err(|err| ApiError::invalid(format!("`signature_base64` is not valid base64: {}", err)))?
+    };
+    if signature.len() != pub_key.size() {
+        return Err(ApiError::invalid(format!(
+            "signature is {} bytes but the key modulus is {} bytes",
+            signature.len(),
+            pub_key.size()
+        )));
+    }
+
+    let (field, message) = exactly_one(&[
+        ("message", &request.message),
+        ("message_hex", &request.message_hex),
+        ("digest_hex", &request.digest_hex),
+    ])?;
+    let digest = match field {
+        "message" => hash_alg.digest(message.as_bytes()),
+        "message_hex" => hash_alg.digest(&decode_hex(field, message)?),
+        _ => decode_hex(field, message)?,
+    };
+    if digest.len() != hash_alg.output_len() {
+        return Err(ApiError::invalid(format!(
+            "`digest_hex` is {} bytes but {} digests are {} bytes",
+            digest.len(),
+            hash_alg.name(),
+            hash_alg.output_len()
+        )));
+    }
+
+    let params = signature_params_for_signature(&pub_key, hash_alg, &digest, &signature, &scheme)
+        .map_err(ApiError::from_error)?;
+    let mut inputs = CircuitInputs::from(&params);
+    if request.canonical_limbs {
+        inputs.limb_style = LimbStyle::Canonical;
+    }
+
+    Ok(json!({
+        "bits": params.key.bits,
+        "num_limbs": params.key.num_limbs(),
+        "exponent": pub_key.e().to_string(),
+        "hash_alg": hash_alg.name(),
+        "scheme": request.scheme,
+        "salt_len": scheme.salt_len(),
+        "hash": inputs.hash,
+        "modulus_limbs": inputs.modulus_limbs_hex(),
+        "redc_limbs": inputs.redc_limbs_hex(),
+        "signature_limbs": inputs.signature_limbs_hex(),
+        "warnings": inputs.warnings,
+    }))
+}
+
+fn read_body(request: &mut Request) -> Result<Vec<u8>, ApiError> {
+    let too_large = || {
+        ApiError::new(
+            413,
+            "body_too_large",
+            format!("request body exceeds {} bytes", MAX_BODY_BYTES),
+        )
+    };
+    if matches!(request.body_length(), Some(len) if len > MAX_BODY_BYTES) {
+        return Err(too_large());
+    }
+    // Content-Length may be absent with chunked encoding, so the read is bounded too
+    let mut body = Vec::new();
+    request
+        .as_reader()
+        .take(MAX_BODY_BYTES as u64 + 1)
+        .read_to_end(&mut body)
+        .map_err(|err| ApiError::invalid(format!("failed to read body: {}", err)))?;
+    if body.len() > MAX_BODY_BYTES {
+        return Err(too_large());
+    }
+    Ok(body)
+}
+
+fn route(request: &mut Request) -> Result<Value, ApiError> {
+    let method = request.method().clone();
+    let url = request.url().to_string();
+    match (method, url.as_str()) {
+        (Method::Get, "/health") => Ok(json!({ "status": "ok" })),
+        (Method::Post, "/v1/params") => {
+            let body = read_body(request)?;
+            let params: ParamsRequest = serde_json::from_slice(&body)
+                .map_err(|err| ApiError::invalid(format!("invalid JSON body: {}", err)))?;
+            handle_params(&params)
+        }
+        (_, "/health") | (_, "/v1/params") => Err(ApiError::new(405, "method_not_allowed", "method not allowed")),
+        (_, url) => Err(ApiError::new(404, "not_found", format!("no such endpoint {}", url))),
+    }
+}
+
+fn respond(mut request: Request) {
+    let (status, body) = match route(&mut request) {
+        Ok(body) => (200, body),
+        Err(err) => (err.status, err.to_json()),
+    };
+    let content_type = Header::from_bytes(&b"Content-Type"[..], &b"application/json"[..]).unwrap();
+    let response = Response::from_string(body.to_string())
+        .with_status_code(status)
+        .with_header(content_type);
+    if let Err(err) = request.respond(response) {
+        eprintln!("Failed to send response: {}", err);
+    }
+}
+
+/// Serve the API on `listen` with `threads` worker threads, until the process is killed
+pub fn serve(listen: &str, threads: usize) -> Result<(), String> {
+    let server = Arc::new(Server::http(listen).map_err(|err| format!("Failed to listen on {}: {}", listen, err))?);
+    eprintln!("Listening on http://{}", listen);
+
+    let workers: Vec<_> = (0..threads)
+        .map(|_| {
+            let server = Arc::clone(&server);
+            std::thread::spawn(move || {
+                for request in server.incoming_requests() {
+                    respond(request);
+                }
+            })
+        })
+        .collect();
+    for worker in workers {
+        worker.join().map_err(|_| "Worker thread panicked".to_string())?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha20Rng;
+    use rsa::pkcs8::{DecodePrivateKey, EncodePublicKey, LineEnding};
+    use rsa::RsaPrivateKey;
+
+    use crate::generate_key;
+    use crate::signing::sign_digest;
+
+    const PUBLIC_KEY: &str = include_str!("../tests/fixtures/rsa_2048_pub.pem");
+    const SIGNATURE: &[u8] = include_bytes!("../tests/fixtures/rsa_2048_sha256_pkcs1v15.sig");
+    const MESSAGE: &str = "Hello World! This is Noir-RSA";
+
+    fn request(body: Value) -> Result<ParamsRequest, serde_json::Error> {
+        serde_json::from_value(body)
+    }
+
+    #[test]
+    fn test_params_for_known_vector() {
+        let response = handle_params(
+            &request(json!({
+                "public_key": PUBLIC_KEY,
+                "signature_hex": hex::encode(SIGNATURE),
+                "message": MESSAGE,
+            }))
+            .unwrap(),
+        )
+        .unwrap();
+
+        assert_eq!(response["bits"], 2048);
+        assert_eq!(response["num_limbs"], 18);
+        assert_eq!(response["exponent"], "65537");
+        assert_eq!(response["hash"], json!(HashAlg::Sha256.digest(MESSAGE.as_bytes())));
+        assert_eq!(response["modulus_limbs"][0], "0xd9408f681f2744650b2ffb8b8ece1b");
+        assert_eq!(response["redc_limbs"][17], "0x65a9");
+        assert_eq!(response["signature_limbs"][0], "0xdfd2ecd8d18e88f761b181a3c25b12");
+        assert_eq!(response["signature_limbs"][17], "0x11");
+        assert_eq!(response["warnings"], json!([]));
+
+        // The same vector given by digest gives the same inputs
+        let by_digest = handle_params(
+            &request(json!({
+                "public_key": PUBLIC_KEY,
+                "signature_hex": hex::encode(SIGNATURE),
+                "digest_hex": hex::encode(HashAlg::Sha256.digest(MESSAGE.as_bytes())),
+            }))
+            .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(by_digest, response);
+    }
+
+    #[test]
+    fn test_pss_salt_defaults_to_the_hash_length() {
+        let priv_key = RsaPrivateKey::from_pkcs8_pem(include_str!("../tests/fixtures/rsa_2048.pem")).unwrap();
+        let digest = HashAlg::Sha384.digest(MESSAGE.as_bytes());
+        let scheme = Scheme::Pss(PssSalt::Random(HashAlg::Sha384.output_len()));
+        let mut rng = ChaCha20Rng::seed_from_u64(0);
+        let signature = sign_digest(&mut rng, &priv_key, HashAlg::Sha384, &digest, &scheme).unwrap();
+        let response = handle_params(
+            &request(json!({
+                "public_key": PUBLIC_KEY,
+                "signature_hex": hex::encode(signature),
+                "message": MESSAGE,
+                "hash": "sha384",
+                "scheme": "pss",
+            }))
+            .unwrap(),
+        )
+        .unwrap();
+        assert_eq!((&response["hash_alg"], &response["salt_len"]), (&json!("sha384"), &json!(48)));
+    }
+
+    #[test]
+    fn test_params_rejects_bad_requests() {
+        let err = handle_params(
+            &request(json!({
+                "public_key": PUBLIC_KEY,
+                "signature_hex": hex::encode(SIGNATURE),
+                "message": "tampered",
+            }))
+            .unwrap(),
+        )
+        .unwrap_err();
+        assert_eq!((err.status, err.code), (422, "verification_failed"));
+
+        let err = handle_params(
+            &request(json!({
+                "public_key": PUBLIC_KEY,
+                "signature_hex": hex::encode(SIGNATURE),
+                "message": MESSAGE,
+                "message_hex": "00",
+            }))
+            .unwrap(),
+        )
+        .unwrap_err();
+        assert_eq!((err.status, err.code), (400, "invalid_request"));
+
+        let err = handle_params(
+            &request(json!({
+                "public_key": PUBLIC_KEY,
+                "signature_hex": "00ff",
+                "message": MESSAGE,
+            }))
+            .unwrap(),
+        )
+        .unwrap_err();
+        assert_eq!(err.status, 400);
+
+        assert!(request(json!({ "public_key": PUBLIC_KEY, "private_key": "..." })).is_err());
+    }
+
+    #[test]
+    fn test_params_rejects_keys_below_1024_bits() {
+        let mut rng = ChaCha20Rng::seed_from_u64(0);
+        let priv_key = generate_key(&mut rng, 768, 65537).unwrap();
+        let digest = HashAlg::Sha256.digest(MESSAGE.as_bytes());
+        let signature = sign_digest(&mut rng, &priv_key, HashAlg::Sha256, &digest, &Scheme::Pkcs1v15).unwrap();
+        let err = handle_params(
+            &request(json!({
+                "public_key": priv_key.to_public_key().to_public_key_pem(LineEnding::LF).unwrap(),
+                "signature_hex": hex::encode(signature),
+                "message": MESSAGE,
+            }))
+            .unwrap(),
+        )
+        .unwrap_err();
+        assert_eq!((err.status, err.code), (422, "key_mismatch"));
+        assert!(err.message.contains("768-bit"), "{}", err.message);
+    }
+}
@@ -0,0 +1,90 @@
+use num_bigint::BigUint;
+use rand_core::CryptoRngCore;
+use rsa::traits::{PrivateKeyParts, PublicKeyParts};
+use rsa::{RsaPrivateKey, RsaPublicKey};
+
+use crate::hash::HashAlg;
+use crate::pss::emsa_pss_encode;
+
+/// Salt used when signing with RSA PSS
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PssSalt {
+    /// A fresh random salt of the given length
+    Random(usize),
+    /// A fixed salt, which makes the signature deterministic
+    Fixed(Vec<u8>),
+}
+
+/// Signature scheme used to sign and verify a message hash
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Scheme {
+    Pkcs1v15,
+    Pss(PssSalt),
+}
+
+impl Scheme {
+    pub fn salt_len(&self) -> Option<usize> {
+        match self {
+            Scheme::Pkcs1v15 => None,
+            Scheme::Pss(PssSalt::Random(salt_len)) => Some(*salt_len),
+            Scheme::Pss(PssSalt::Fixed(salt)) => Some(salt.len()),
+        }
+    }
+
+    pub fn fixed_salt(&self) -> Option<&[u8]> {
+        match self {
+            Scheme::Pss(PssSalt::Fixed(salt)) => Some(salt),
+            _ => None,
+        }
+    }
+}
+
+/// Raw RSA private key operation on an encoded message, returning a signature
+/// of exactly the key size in bytes
+pub fn raw_sign(priv_key: &RsaPrivateKey, em: &[u8]) -> Vec<u8> {
+    let m = BigUint::from_bytes_be(em);
+    let s = m.modpow(priv_key.d(), priv_key.n());
+    let s_bytes = s.to_bytes_be();
+    let mut sig = vec![0u8; priv_key.size() - s_bytes.len()];
+    sig.extend_from_slice(&s_bytes);
+    sig
+}
+
+/// Sign a message hash with the given scheme
+pub fn sign_digest<R: CryptoRngCore>(
+    rng: &mut R,
+    priv_key: &RsaPrivateKey,
+    hash_alg: HashAlg,
+    digest: &[u8],
+    scheme: &Scheme,
+) -> Result<Vec<u8>, String> {
+    match scheme {
+        Scheme::Pkcs1v15 => priv_key
+            .sign(hash_alg.pkcs1v15(), digest)
+            .map_err(|err| err.to_string()),
+        Scheme::Pss(PssSalt::Random(salt_len)) => priv_key
+            .sign_with_rng(rng, hash_alg.blinded_pss(*salt_len), digest)
+            .map_err(|err| err.to_string()),
+        Scheme::Pss(PssSalt::Fixed(salt)) => {
+            // The rsa crate always draws the salt from the RNG, so the encoding
+            // is built here and signed with the raw private key operation
+            let em_bits = priv_key.n().bits() - 1;
+            let em = emsa_pss_encode(hash_alg, digest, salt, em_bits)?;
+            Ok(raw_sign(priv_key, &em))
+        }
+    }
+}
+
+/// Verify a signature over a message hash, mirroring `sign_digest`
+pub fn verify_digest(
+    pub_key: &RsaPublicKey,
+    hash_alg: HashAlg,
+    digest: &[u8],
+    sig: &[u8],
+    scheme: &Scheme,
+) -> rsa::Result<()> {
+    match scheme.salt_len() {
+        Some(salt_len) => pub_key.verify(hash_alg.pss(salt_len), digest, sig),
+        None => pub_key.verify(hash_alg.pkcs1v15(), digest, sig),
+    }
+}
@@ -0,0 +1,211 @@
+//! Snapshot tests of every output format
+//!
+//! The vectors are generated from a fixture key, a fixed message and, for PSS,
+//! a fixed salt, so the output is fully deterministic. An intentional change to
+//! the output shows up as a snapshot diff to review with `cargo insta review`.
+
+use insta::assert_snapshot;
+use num_bigint::BigUint;
+use rand_chacha::rand_core::SeedableRng;
+use rand_chacha::ChaCha20Rng;
+use rsa::pkcs8::DecodePrivateKey;
+use rsa::RsaPrivateKey;
+
+use crate::annotate::Annotator;
+use crate::hash::HashAlg;
+use crate::params::{
+    render_aggregate_toml, render_multi_key_snippet, render_multi_key_toml, toml_to_abi_json, LimbStyle, MessageBytes,
+    SignatureParams,
+};
+use crate::presets::Preset;
+use crate::schema::Names;
+use crate::signature_params_for_key;
+use crate::signing::{PssSalt, Scheme};
+use crate::templates::{BignumVersion, NoirTest, SnippetTemplate};
+
+const FIXTURE_KEY: &str = include_str!("../tests/fixtures/rsa_2048.pem");
+/// The second key of the multi-key vectors, of the same size
+const OTHER_KEY: &str = include_str!("../tests/fixtures/rsa_2048_other.pem");
+const MESSAGE: &[u8] = b"Hello World! This is Noir-RSA";
+
+fn fixture_params(msg: &[u8], hashes: &[HashAlg], scheme: &Scheme) -> SignatureParams {
+    key_params(FIXTURE_KEY, msg, hashes, scheme)
+}
+
+fn key_params(key: &str, msg: &[u8], hashes: &[HashAlg], scheme: &Scheme) -> SignatureParams {
+    let priv_key = RsaPrivateKey::from_pkcs8_pem(key).unwrap();
+    let mut rng = ChaCha20Rng::seed_from_u64(0);
+    signature_params_for_key(&mut rng, &priv_key, msg, hashes, scheme).unwrap()
+}
+
+fn fixed_salt() -> Scheme {
+    Scheme::Pss(PssSalt::Fixed((0u8..32).collect()))
+}
+
+#[test]
+fn snippet() {
+    let output = fixture_params(MESSAGE, &[HashAlg::Sha256], &Scheme::Pkcs1v15).render_snippet(&SnippetTemplate);
+    assert_snapshot!("snippet", output);
+}
+
+#[test]
+fn noir_runtime() {
+    let output = fixture_params(MESSAGE, &[HashAlg::Sha256], &Scheme::Pkcs1v15)
+        .render_snippet(BignumVersion::Current.template());
+    assert_snapshot!("noir_runtime", output);
+}
+
+#[test]
+fn noir_runtime_bignum_0_3() {
+    let output = fixture_params(MESSAGE, &[HashAlg::Sha256], &Scheme::Pkcs1v15)
+        .render_snippet(BignumVersion::V03.template());
+    assert_snapshot!("noir_runtime_bignum_0_3", output);
+}
+
+#[test]
+fn toml() {
+    let output = fixture_params(MESSAGE, &[HashAlg::Sha256], &Scheme::Pkcs1v15).render_toml(Names::Canonical);
+    assert_snapshot!("toml", output);
+}
+
+#[test]
+fn toml_legacy_names() {
+    let output = fixture_params(MESSAGE, &[HashAlg::Sha256], &Scheme::Pkcs1v15).render_toml(Names::Legacy);
+    assert_snapshot!("toml_legacy_names", output);
+}
+
+#[test]
+fn toml_pss_fixed_salt() {
+    let output = fixture_params(MESSAGE, &[HashAlg::Sha256], &fixed_salt()).render_toml(Names::Canonical);
+    assert_snapshot!("toml_pss_fixed_salt", output);
+}
+
+#[test]
+fn toml_dual_hash() {
+    let output = fixture_params(MESSAGE, &[HashAlg::Sha256, HashAlg::Sha512], &Scheme::Pkcs1v15)
+        .render_toml(Names::Canonical);
+    assert_snapshot!("toml_dual_hash", output);
+}
+
+#[test]
+fn snippet_dual_hash() {
+    let output = fixture_params(MESSAGE, &[HashAlg::Sha256, HashAlg::Sha512], &Scheme::Pkcs1v15)
+        .render_snippet(&SnippetTemplate);
+    assert_snapshot!("snippet_dual_hash", output);
+}
+
+#[test]
+fn toml_multi_key() {
+    let entries: Vec<SignatureParams> = [FIXTURE_KEY, OTHER_KEY]
+        .iter()
+        .map(|key| key_params(key, MESSAGE, &[HashAlg::Sha256], &Scheme::Pkcs1v15))
+        .collect();
+    assert_snapshot!("toml_multi_key", render_multi_key_toml(&entries, Names::Canonical));
+}
+
+#[test]
+fn snippet_multi_key() {
+    let entries: Vec<SignatureParams> = [FIXTURE_KEY, OTHER_KEY]
+        .iter()
+        .map(|key| key_params(key, MESSAGE, &[HashAlg::Sha256], &Scheme::Pkcs1v15))
+        .collect();
+    assert_snapshot!("snippet_multi_key", render_multi_key_snippet(&entries, &SnippetTemplate));
+}
+
+#[test]
+fn toml_json_message() {
+    let message = br#"{"a":"x","b":1}"#.to_vec();
+    let mut signature_params = fixture_params(&message, &[HashAlg::Sha256], &Scheme::Pkcs1v15);
+    signature_params.message = Some(MessageBytes { bytes: message, max_len: 32 });
+    assert_snapshot!("toml_json_message", signature_params.render_toml(Names::Canonical));
+}
+
+#[test]
+fn toml_canonical_limbs() {
+    let mut params = fixture_params(MESSAGE, &[HashAlg::Sha256], &Scheme::Pkcs1v15);
+    params.key.limb_style = LimbStyle::Canonical;
+    assert_snapshot!("toml_canonical_limbs", params.render_toml(Names::Canonical));
+}
+
+#[test]
+fn snippet_canonical_limbs() {
+    let mut params = fixture_params(MESSAGE, &[HashAlg::Sha256], &Scheme::Pkcs1v15);
+    params.key.limb_style = LimbStyle::Canonical;
+    assert_snapshot!("snippet_canonical_limbs", params.render_snippet(&SnippetTemplate));
+}
+
+#[cfg(feature = "native")]
+#[test]
+fn json() {
+    use rsa::traits::PublicKeyParts;
+
+    let priv_key = RsaPrivateKey::from_pkcs8_pem(FIXTURE_KEY).unwrap();
+    let params = fixture_params(MESSAGE, &[HashAlg::Sha256], &fixed_salt());
+    let output = crate::cli::render_json(&params, priv_key.e(), &fixed_salt(), None, None);
+    assert_snapshot!("json", output);
+}
+
+#[test]
+fn abi_json() {
+    let toml = fixture_params(MESSAGE, &[HashAlg::Sha256], &fixed_salt()).render_toml(Names::Canonical);
+    let output = toml_to_abi_json(&toml);
+    assert_snapshot!("abi_json", output);
+}
+
+#[test]
+fn toml_aggregate() {
+    let entries: Vec<SignatureParams> = [MESSAGE, b"A second message"]
+        .iter()
+        .map(|msg| fixture_params(msg, &[HashAlg::Sha256], &Scheme::Pkcs1v15))
+        .collect();
+    let output = render_aggregate_toml(&entries, 3);
+    assert_snapshot!("toml_aggregate", output);
+}
+
+#[test]
+fn noir_test() {
+    let scheme = fixed_salt();
+    let params = fixture_params(MESSAGE, &[HashAlg::Sha256], &scheme);
+    let name = NoirTest::default_name(HashAlg::Sha256, &scheme, params.key.bits);
+    let test = NoirTest {
+        name: &name,
+        key: &params.key,
+        signature: &params.signatures[0],
+        exponent: &BigUint::from(65537u32),
+        scheme: &scheme,
+        should_fail: false,
+    };
+    let output = BignumVersion::Current.render_test(&test).unwrap();
+    assert_snapshot!("noir_test", output);
+}
+
+#[test]
+fn toml_annotated() {
+    let params = fixture_params(MESSAGE, &[HashAlg::Sha256], &Scheme::Pkcs1v15);
+    let output = Annotator::for_signature_params(&params).annotate_toml(&params.render_toml(Names::Canonical));
+    assert_snapshot!("toml_annotated", output);
+}
+
+/// Golden files of the presets, which downstream circuits depend on
+fn preset_toml(name: &str, msg: &[u8]) -> String {
+    let preset = Preset::from_name(name).unwrap();
+    let mut params = fixture_params(msg, &[HashAlg::Sha256], &Scheme::Pkcs1v15);
+    params.message = Some(MessageBytes { bytes: msg.to_vec(), max_len: 32 });
+    preset.configure(&mut params.key);
+    params.render_toml(Names::Preset(preset))
+}
+
+#[test]
+fn preset_zkemail() {
+    assert_snapshot!("preset_zkemail", preset_toml("zkemail", br#"{"a":"x","b":1}"#));
+}
+
+#[test]
+fn preset_zkpassport() {
+    assert_snapshot!("preset_zkpassport", preset_toml("zkpassport", MESSAGE));
+}
+
+#[test]
+fn preset_jwt() {
+    assert_snapshot!("preset_jwt", preset_toml("jwt", br#"{"a":"x","b":1}"#));
+}
@@ -0,0 +1,308 @@
+//! Soak mode of `sign`: repeated generation with fresh randomness
+//!
+//! Each iteration draws its own seed, generates and self-verifies the circuit
+//! inputs exactly like a single `sign --seed <seed>` run, then parses the
+//! emitted TOML back and checks the limbs against the key and signature. Any
+//! failure is saved with the seed that reproduces it.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha20Rng;
+use rsa::pkcs8::{EncodePublicKey, LineEnding};
+use rsa::traits::PublicKeyParts;
+use rsa::RsaPublicKey;
+use toml::Value;
+
+use crate::convert::parse_limbs;
+use crate::keys::key_bits;
+use crate::params::SignatureParams;
+use crate::schema::{Names, HASH, MODULUS_LIMBS, SIGNATURE_LIMBS};
+use crate::signing::{verify_digest, Scheme};
+
+/// One generation run, as `sign` would print it
+pub struct Generated {
+    pub pub_keys: Vec<RsaPublicKey>,
+    pub entries: Vec<SignatureParams>,
+    pub output: String,
+}
+
+pub struct Options {
+    pub iterations: usize,
+    /// Seed of the first iteration; later iterations count up from it. Random when not given.
+    pub seed: Option<u64>,
+    pub failures_dir: PathBuf,
+    /// `sign` arguments that reproduce an iteration when followed by `--seed <seed>`
+    pub replay_args: Vec<String>,
+    /// Checked before every iteration; the caller sets it, e.g. from a Ctrl-C handler, to stop early
+    pub stop: Arc<AtomicBool>,
+}
+
+#[derive(Debug, Default)]
+pub struct Summary {
+    pub iterations: usize,
+    pub failures: usize,
+    pub interrupted: bool,
+    pub durations: Vec<Duration>,
+    pub key_sizes: BTreeMap<usize, usize>,
+}
+
+impl Summary {
+    pub fn print(&self) {
+        println!(
+            "{} iterations{}, {} failures",
+            self.iterations,
+            if self.interrupted { " (interrupted)" } else { "" },
+            self.failures
+        );
+        for (bits, count) in &self.key_sizes {
+            println!("  {}-bit keys: {}", bits, count);
+        }
+        if !self.durations.is_empty() {
+            let mut sorted = self.durations.clone();
+            sorted.sort();
+            println!(
+                "  duration p50 {:?}, p90 {:?}, p99 {:?}, max {:?}",
+                percentile(&sorted, 50.0),
+                percentile(&sorted, 90.0),
+                percentile(&sorted, 99.0),
+                sorted[sorted.len() - 1]
+            );
+        }
+    }
+}
+
+/// Nearest-rank percentile of sorted, non-empty durations
+pub fn percentile(sorted: &[Duration], p: f64) -> Duration {
+    let rank = ((p / 100.0) * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+/// Parse the emitted TOML of each entry back and check that the limbs still
+/// describe the key and a valid signature over the emitted hash
+pub fn round_trip(generated: &Generated, scheme: &Scheme) -> Result<(), String> {
+    for (entry, pub_key) in generated.entries.iter().zip(&generated.pub_keys) {
+        let doc = match entry.render_toml(Names::Canonical).parse::<Value>() {
+            Ok(Value::Table(doc)) => doc,
+            Ok(_) => unreachable!(),
+            Err(err) => return Err(format!("emitted TOML does not parse: {}", err)),
+        };
+        if &parse_limbs(doc.get(MODULUS_LIMBS), MODULUS_LIMBS)? != pub_key.n() {
+            return Err("modulus limbs do not recombine to the modulus".to_string());
+        }
+
+        for signature in &entry.signatures {
+            // Several signatures are emitted in per-hash tables
+            let table = match entry.signatures.len() {
+                1 => &doc,
+                _ => doc
+                    .get(signature.hash_alg.name())
+                    .and_then(Value::as_table)
+                    .ok_or_else(|| format!("missing `{}` table", signature.hash_alg.name()))?,
+            };
+            let hash: Vec<u8> = table
+                .get(HASH)
+                .and_then(Value::as_array)
+                .ok_or("missing `hash`")?
+                .iter()
+                .map(|byte| byte.as_integer().map(|byte| byte as u8).ok_or("invalid hash byte"))
+                .collect::<Result<_, _>>()?;
+            let sig = parse_limbs(table.get(SIGNATURE_LIMBS), SIGNATURE_LIMBS)?.to_bytes_be();
+            let mut sig_bytes = vec![0u8; pub_key.size().saturating_sub(sig.len())];
+            sig_bytes.extend_from_slice(&sig);
+            verify_digest(pub_key, signature.hash_alg, &hash, &sig_bytes, scheme).map_err(|err| {
+                format!("{} signature read back from the TOML does not verify: {}", signature.hash_alg.name(), err)
+            })?;
+        }
+    }
+    Ok(())
+}
+
+fn panic_message(payload: Box<dyn std::any::Any + Send>) -> String {
+    match payload.downcast::<String>() {
+        Ok(message) => *message,
+        Err(payload) => match payload.downcast::<&str>() {
+            Ok(message) => message.to_string(),
+            Err(_) => "panic".to_string(),
+        },
+    }
+}
+
+fn save_failure(
+    options: &Options,
+    iteration: usize,
+    seed: u64,
+    error: &str,
+    generated: Option<&Generated>,
+) -> Result<PathBuf, String> {
+    let dir = options.failures_dir.join(format!("seed-{}", seed));
+    let write = |name: &str, contents: &[u8]| {
+        let path = dir.join(name);
+        fs::write(&path, contents).map_err(|err| format!("Failed to write {}: {}", path.display(), err))
+    };
+    fs::create_dir_all(&dir).map_err(|err| format!("Failed to create {}: {}", dir.display(), err))?;
+
+    let replay = format!("cargo run -- sign {} --seed {}", options.replay_args.join(" "), seed);
+    write(
+        "failure.txt",
+        format!("iteration: {}\nseed: {}\nerror: {}\nreplay: {}\n", iteration, seed, error, replay).as_bytes(),
+    )?;
+    if let Some(generated) = generated {
+        write("output.txt", generated.output.as_bytes())?;
+        // Only public keys are saved; the seed regenerates the private ones
+        for (i, pub_key) in generated.pub_keys.iter().enumerate() {
+            let pem = pub_key
+                .to_public_key_pem(LineEnding::LF)
+                .map_err(|err| format!("Failed to encode the public key: {}", err))?;
+            write(&format!("pubkey-{}.pem", i), pem.as_bytes())?;
+        }
+    }
+    Ok(dir)
+}
+
+/// Run `generate` for every iteration until done or `options.stop` is set
+pub fn soak<F>(options: &Options, scheme: &Scheme, generate: F) -> Result<Summary, String>
+where
+    F: Fn(&mut ChaCha20Rng) -> Generated,
+{
+    let first_seed = options.seed.unwrap_or_else(|| rand::thread_rng().gen());
+    let mut summary = Summary::default();
+    for iteration in 0..options.iterations {
+        if options.stop.load(Ordering::SeqCst) {
+            summary.interrupted = true;
+            break;
+        }
+        let seed = first_seed.wrapping_add(iteration as u64);
+        let start = Instant::now();
+
+        let result = panic::catch_unwind(AssertUnwindSafe(|| generate(&mut ChaCha20Rng::seed_from_u64(seed))));
+        let (error, generated) = match result {
+            Ok(generated) => match round_trip(&generated, scheme) {
+                Ok(()) => (None, Some(generated)),
+                Err(err) => (Some(err), Some(generated)),
+            },
+            Err(payload) => (Some(panic_message(payload)), None),
+        };
+
+        summary.iterations += 1;
+        summary.durations.push(start.elapsed());
+        if let Some(generated) = &generated {
+            for pub_key in &generated.pub_keys {
+                *summary.key_sizes.entry(key_bits(pub_key)).or_default() += 1;
+            }
+        }
+        if let Some(error) = error {
+            summary.failures += 1;
+            let dir = save_failure(options, iteration, seed, &error, generated.as_ref())?;
+            eprintln!("Iteration {} (seed {}) failed: {}, saved to {}", iteration, seed, error, dir.display());
+        }
+    }
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    use crate::hash::HashAlg;
+    use crate::signature_params_for_key;
+    use rsa::pkcs8::DecodePrivateKey;
+    use rsa::RsaPrivateKey;
+
+    const FIXTURE_KEY: &str = include_str!("../tests/fixtures/rsa_1024.pem");
+
+    fn fixture_generate(rng: &mut ChaCha20Rng) -> Generated {
+        let priv_key = RsaPrivateKey::from_pkcs8_pem(FIXTURE_KEY).unwrap();
+        let hashes = [HashAlg::Sha256, HashAlg::Sha512];
+        let entry = signature_params_for_key(rng, &priv_key, b"soak", &hashes, &Scheme::Pkcs1v15).unwrap();
+        Generated {
+            output: entry.render_toml(Names::Canonical),
+            pub_keys: vec![priv_key.to_public_key()],
+            entries: vec![entry],
+        }
+    }
+
+    fn options(dir: &Path) -> Options {
+        Options {
+            iterations: 3,
+            seed: Some(7),
+            failures_dir: dir.to_path_buf(),
+            replay_args: vec!["--bits".to_string(), "1024".to_string()],
+            stop: Arc::default(),
+        }
+    }
+
+    fn saved_failures(dir: &Path) -> Vec<String> {
+        let mut names: Vec<String> = match fs::read_dir(dir) {
+            Ok(entries) => entries
+                .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+                .collect(),
+            Err(_) => Vec::new(),
+        };
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn test_percentile() {
+        let sorted: Vec<Duration> = (1..=100).map(Duration::from_millis).collect();
+        assert_eq!(percentile(&sorted, 50.0), Duration::from_millis(50));
+        assert_eq!(percentile(&sorted, 99.0), Duration::from_millis(99));
+        assert_eq!(percentile(&sorted[..1], 90.0), Duration::from_millis(1));
+    }
+
+    #[test]
+    fn test_round_trip_catches_corrupted_limbs() {
+        let mut generated = fixture_generate(&mut ChaCha20Rng::seed_from_u64(0));
+        round_trip(&generated, &Scheme::Pkcs1v15).unwrap();
+
+        generated.entries[0].signatures[1].signature_limbs[0] += 1u32;
+        let err = round_trip(&generated, &Scheme::Pkcs1v15).unwrap_err();
+        assert!(err.contains("sha512"), "{}", err);
+    }
+
+    #[test]
+    fn test_soak_saves_failures_with_their_seed() {
+        let scratch = tempfile::tempdir().unwrap();
+        let dir = scratch.path();
+
+        let summary = soak(&options(dir), &Scheme::Pkcs1v15, fixture_generate).unwrap();
+        assert_eq!((summary.iterations, summary.failures), (3, 0));
+        assert_eq!(summary.key_sizes[&1024], 3);
+        assert!(saved_failures(dir).is_empty());
+
+        // Fail only the iteration whose RNG was seeded with 8, the second one
+        let marker: u64 = ChaCha20Rng::seed_from_u64(8).gen();
+        let summary = soak(&options(dir), &Scheme::Pkcs1v15, |rng| {
+            if rng.clone().gen::<u64>() == marker {
+                panic!("injected failure");
+            }
+            fixture_generate(rng)
+        })
+        .unwrap();
+        assert_eq!(summary.failures, 1);
+        assert_eq!(saved_failures(dir), ["seed-8"]);
+        let failure = fs::read_to_string(dir.join("seed-8").join("failure.txt")).unwrap();
+        assert!(failure.contains("error: injected failure"));
+        assert!(failure.contains("replay: cargo run -- sign --bits 1024 --seed 8"));
+    }
+
+    #[test]
+    fn test_soak_stops_when_asked() {
+        let scratch = tempfile::tempdir().unwrap();
+        let options = options(scratch.path());
+        let summary = soak(&options, &Scheme::Pkcs1v15, |rng| {
+            options.stop.store(true, Ordering::SeqCst);
+            fixture_generate(rng)
+        })
+        .unwrap();
+        assert_eq!((summary.iterations, summary.interrupted), (1, true));
+    }
+}
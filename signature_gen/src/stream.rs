@@ -0,0 +1,207 @@
+//! `--msg-file`: hash a message file in fixed-size chunks
+//!
+//! The file is read once, `CHUNK_LEN` bytes at a time, and every chunk is fed
+//! to one hasher per requested hash, so peak memory does not depend on the
+//! size of the file. With `--progress`, files over `PROGRESS_THRESHOLD` show
+//! a progress line on stderr. A file emitted with `--msg-max-len` is small
+//! enough to be read whole instead, once.
+
+use std::fs::File;
+use std::io::{self, ErrorKind, Read, Write};
+use std::path::Path;
+
+use crate::errors::{Code, Error};
+use crate::hash::{HashAlg, Hasher};
+
+/// Bytes read per chunk
+pub const CHUNK_LEN: usize = 1 << 20;
+
+/// Files larger than this get a progress line when `--progress` is given
+pub const PROGRESS_THRESHOLD: u64 = 64 << 20;
+
+/// Feed everything `reader` yields to each of `hashers`, one chunk at a time
+fn feed<R: Read>(mut reader: R, hashers: &mut [Hasher], mut on_chunk: impl FnMut(u64)) -> io::Result<()> {
+    let mut chunk = vec![0u8; CHUNK_LEN];
+    let mut total = 0u64;
+    loop {
+        let len = match reader.read(&mut chunk) {
+            Ok(0) => break,
+            Ok(len) => len,
+            Err(err) if err.kind() == ErrorKind::Interrupted => continue,
+            Err(err) => return Err(err),
+        };
+        for hasher in hashers.iter_mut() {
+            hasher.update(&chunk[..len]);
+        }
+        total += len as u64;
+        on_chunk(total);
+    }
+    Ok(())
+}
+
+/// Digests of a streamed message file
+pub struct FileDigests {
+    /// Length of the file, without the prefix
+    pub len: u64,
+    /// One digest of `prefix || file` per requested hash, in order
+    pub digests: Vec<Vec<u8>>,
+    /// SHA-256 of the file alone, without the prefix
+    pub file_sha256: Vec<u8>,
+}
+
+/// Hash `prefix` followed by the file at `path`
+pub fn digest_file(path: &Path, prefix: &[u8], hashes: &[HashAlg], progress: bool) -> Result<FileDigests, String> {
+    let file = File::open(path).map_err(|err| format!("Failed to open {}: {}", path.display(), err))?;
+    let len = file
+        .metadata()
+        .map_err(|err| format!("Failed to read {}: {}", path.display(), err))?
+        .len();
+    let show_progress = progress && len > PROGRESS_THRESHOLD;
+
+    // The requested hashers see the prefix first, the last one only the file
+    let mut hashers: Vec<Hasher> = hashes
+        .iter()
+        .map(|&hash_alg| {
+            let mut hasher = Hasher::new(hash_alg);
+            hasher.update(prefix);
+            hasher
+        })
+        .collect();
+    hashers.push(Hasher::new(HashAlg::Sha256));
+    feed(file, &mut hashers, |done| {
+        if show_progress {
+            eprint!("\rHashing {}: {:>3}% of {} MiB", path.display(), done * 100 / len, len >> 20);
+            let _ = io::stderr().flush();
+        }
+    })
+    .map_err(|err| format!("Failed to read {}: {}", path.display(), err))?;
+    if show_progress {
+        eprintln!();
+    }
+    let file_sha256 = hashers.pop().unwrap().finalize();
+    Ok(FileDigests { len, digests: hashers.into_iter().map(Hasher::finalize).collect(), file_sha256 })
+}
+
+/// Read the file at `path` once, refused by its size unless `prefix || file`
+/// fits in `max_len` bytes, and return its digests with `prefix || file`,
+/// so that the bytes emitted are the bytes hashed
+pub fn read_file(
+    path: &Path,
+    prefix: &[u8],
+    hashes: &[HashAlg],
+    max_len: usize,
+) -> Result<(FileDigests, Vec<u8>), Error> {
+    let read_error = |err: io::Error| {
+        let message = format!("Failed to read {}: {}", path.display(), err);
+        Error::new(Code::Failed, message).with("path", path.display().to_string())
+    };
+    let too_large = |len: u64| {
+        let message = format!(
+            "{} is {} bytes with the prefix, larger than --msg-max-len {}; it cannot be emitted",
+            path.display(),
+            len,
+            max_len
+        );
+        Error::new(Code::InvalidArgument, message).with("path", path.display().to_string())
+    };
+    let file = File::open(path).map_err(read_error)?;
+    let len = file.metadata().map_err(read_error)?.len() + prefix.len() as u64;
+    if len > max_len as u64 {
+        return Err(too_large(len));
+    }
+    // The file may grow after its size was read, so no more than one byte past the limit is read
+    let mut message = prefix.to_vec();
+    file.take((max_len - prefix.len()) as u64 + 1).read_to_end(&mut message).map_err(read_error)?;
+    if message.len() > max_len {
+        return Err(too_large(message.len() as u64));
+    }
+    let digests = FileDigests {
+        len: (message.len() - prefix.len()) as u64,
+        digests: hashes.iter().map(|hash_alg| hash_alg.digest(&message)).collect(),
+        file_sha256: HashAlg::Sha256.digest(&message[prefix.len()..]),
+    };
+    Ok((digests, message))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{RngCore, SeedableRng};
+    use rand_chacha::ChaCha20Rng;
+    use sha2::{Digest, Sha256};
+
+    /// Records the largest buffer its reader is asked to fill
+    struct MaxRead<R> {
+        inner: R,
+        max_buf: usize,
+    }
+
+    impl<R: Read> Read for MaxRead<R> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            self.max_buf = self.max_buf.max(buf.len());
+            self.inner.read(buf)
+        }
+    }
+
+    /// Hash everything `reader` yields with each of `hashes`, calling `on_chunk`
+    /// with the running byte count after every chunk
+    fn digest_reader<R: Read>(reader: R, hashes: &[HashAlg], on_chunk: impl FnMut(u64)) -> io::Result<Vec<Vec<u8>>> {
+        let mut hashers: Vec<Hasher> = hashes.iter().map(|&hash_alg| Hasher::new(hash_alg)).collect();
+        feed(reader, &mut hashers, on_chunk)?;
+        Ok(hashers.into_iter().map(Hasher::finalize).collect())
+    }
+
+    #[test]
+    fn test_a_file_read_whole_hashes_the_bytes_it_returns() {
+        let scratch = tempfile::tempdir().unwrap();
+        let path = scratch.path().join("small.bin");
+        std::fs::write(&path, b"hello file").unwrap();
+        let hashes = [HashAlg::Sha256, HashAlg::Sha1];
+        let (read, message) = read_file(&path, b"p:", &hashes, 12).unwrap();
+        assert_eq!(message, b"p:hello file");
+        let streamed = digest_file(&path, b"p:", &hashes, false).unwrap();
+        assert_eq!((read.len, read.digests, read.file_sha256), (streamed.len, streamed.digests, streamed.file_sha256));
+        // One byte over the limit, prefix included, is refused
+        assert_eq!(read_file(&path, b"p:", &hashes, 11).unwrap_err().code, Code::InvalidArgument);
+    }
+
+    #[test]
+    #[ignore = "writes and hashes 100 MiB, run it with --ignored"]
+    fn test_large_file_digest_matches_a_reference_in_bounded_chunks() {
+        const FILE_LEN: usize = 100 << 20;
+        let scratch = tempfile::tempdir().unwrap();
+        let path = scratch.path().join("large.bin");
+
+        // Written and hashed for reference in odd-sized pieces, never held whole
+        let mut rng = ChaCha20Rng::seed_from_u64(0);
+        let mut file = File::create(&path).unwrap();
+        let mut reference = Sha256::new();
+        reference.update(b"prefix:");
+        let mut file_reference = Sha256::new();
+        let mut piece = vec![0u8; 999_983];
+        let mut written = 0;
+        while written < FILE_LEN {
+            let len = piece.len().min(FILE_LEN - written);
+            rng.fill_bytes(&mut piece[..len]);
+            file.write_all(&piece[..len]).unwrap();
+            reference.update(&piece[..len]);
+            file_reference.update(&piece[..len]);
+            written += len;
+        }
+        drop(file);
+
+        let mut reader = MaxRead { inner: File::open(&path).unwrap(), max_buf: 0 };
+        let mut chunks = 0;
+        let prefixed = (&b"prefix:"[..]).chain(&mut reader);
+        let digests = digest_reader(prefixed, &[HashAlg::Sha256, HashAlg::Sha1], |_| chunks += 1).unwrap();
+        assert_eq!(digests[0], reference.finalize().to_vec());
+        assert_eq!(digests[1].len(), 20);
+        assert_eq!(reader.max_buf, CHUNK_LEN);
+        assert!(chunks >= FILE_LEN / CHUNK_LEN);
+
+        let streamed = digest_file(&path, b"prefix:", &[HashAlg::Sha256], false).unwrap();
+        assert_eq!(streamed.len, FILE_LEN as u64);
+        assert_eq!(streamed.digests, [digests[0].clone()]);
+        assert_eq!(streamed.file_sha256, file_reference.finalize().to_vec());
+    }
+}
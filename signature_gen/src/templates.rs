@@ -0,0 +1,369 @@
+use num_bigint::BigUint;
+
+use crate::hash::HashAlg;
+use crate::params::{format_bytes, format_limbs_as_hex, KeyParams, LimbStyle, SignatureVector};
+use crate::signing::Scheme;
+
+/// Noir code generation for a given BigNum API
+///
+/// Each BigNum release that changed how params and BigNums are constructed gets
+/// its own implementation, so that the emitted code for one version never
+/// depends on conditionals added for another
+pub trait NoirTemplate {
+    /// Binding of the BigNum parameters of `key`
+    fn params(&self, key: &KeyParams, binding: &str) -> String;
+
+    /// Binding of a signature as a BigNum using the params bound to `params_binding`
+    fn signature(
+        &self,
+        key: &KeyParams,
+        binding: &str,
+        params_binding: &str,
+        limbs: &[BigUint],
+    ) -> String;
+}
+
+/// BigNum versions with a distinct construction syntax
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BignumVersion {
+    /// `BigNumInstance` and `BigNum::from_array` (bignum v0.3.x)
+    V03,
+    /// `BigNumParams` and `RuntimeBigNum` (bignum v0.4.0 and later)
+    Current,
+}
+
+impl BignumVersion {
+    pub fn from_name(name: &str) -> Option<BignumVersion> {
+        match name.trim_start_matches('v') {
+            "0.3" => Some(BignumVersion::V03),
+            "current" => Some(BignumVersion::Current),
+            version if version.starts_with("0.3.") => Some(BignumVersion::V03),
+            _ => None,
+        }
+    }
+
+    pub fn template(self) -> &'static dyn NoirTemplate {
+        match self {
+            BignumVersion::V03 => &V03Template,
+            BignumVersion::Current => &RuntimeTemplate,
+        }
+    }
+
+    /// `--noir-test`: a complete Noir test of one signature, with its imports
+    pub fn render_test(self, test: &NoirTest) -> Result<String, String> {
+        match self {
+            BignumVersion::V03 => v03_test(test),
+            BignumVersion::Current => Ok(runtime_test(test)),
+        }
+    }
+}
+
+/// One signature and the key it verifies under, as a `#[test]` asserts it
+#[derive(Clone, Copy)]
+pub struct NoirTest<'a> {
+    pub name: &'a str,
+    pub key: &'a KeyParams,
+    pub signature: &'a SignatureVector,
+    pub exponent: &'a BigUint,
+    pub scheme: &'a Scheme,
+    /// Whether the signature is invalid, for a `#[test(should_fail)]`
+    pub should_fail: bool,
+}
+
+impl NoirTest<'_> {
+    /// The name of the tests in `src/rsa.nr`, e.g. `test_verify_sha256_pss_2048`
+    pub fn default_name(hash_alg: HashAlg, scheme: &Scheme, bits: usize) -> String {
+        let scheme = if scheme.salt_len().is_some() { "pss" } else { "pkcs1v15" };
+        format!("test_verify_{}_{}_{}", hash_alg.name(), scheme, bits)
+    }
+
+    fn attribute(&self) -> &'static str {
+        if self.should_fail {
+            "#[test(should_fail)]"
+        } else {
+            "#[test]"
+        }
+    }
+
+    /// Binding of the hash, named as in the tests of `src/rsa.nr`
+    fn hash(&self) -> String {
+        let hash = &self.signature.hash;
+        format!("let {}_hash: [u8; {}] = [{}];", self.signature.hash_alg.name(), hash.len(), format_bytes(hash))
+    }
+}
+
+fn indent(code: &str) -> String {
+    code.lines().map(|line| format!("    {}\n", line)).collect()
+}
+
+/// `BigNumParams` and `RuntimeBigNum`, verified by the free functions of `noir_rsa::rsa`
+fn runtime_test(test: &NoirTest) -> String {
+    let hash_alg = test.signature.hash_alg.name();
+    let (verifier, salt_len) = match test.scheme.salt_len() {
+        Some(salt_len) => (format!("verify_{}_pss", hash_alg), format!(", {}", salt_len)),
+        None => (format!("verify_{}_pkcs1v15", hash_alg), String::new()),
+    };
+    let mut code = format!(
+        "use bignum::params::BigNumParams;\nuse bignum::RuntimeBigNum;\nuse noir_rsa::rsa::{};\n\n\
+         {}\nfn {}() {{\n",
+        verifier,
+        test.attribute(),
+        test.name
+    );
+    code.push_str(&indent(&test.hash()));
+    code.push_str(&indent(&RuntimeTemplate.params(test.key, "params")));
+    let signature = RuntimeTemplate.signature(test.key, "signature", "params", &test.signature.signature_limbs);
+    code.push_str(&indent(&signature));
+    code.push_str(&format!(
+        "    assert({}({}_hash, signature, {}{}));\n}}\n",
+        verifier, hash_alg, test.exponent, salt_len
+    ));
+    code
+}
+
+/// `BigNumInstance` and `BigNum`, verified by the methods of the `RSA` struct of
+/// noir_rsa releases on bignum 0.3, which only verify SHA-256 PKCS#1 v1.5
+fn v03_test(test: &NoirTest) -> Result<String, String> {
+    if test.signature.hash_alg != HashAlg::Sha256 || test.scheme.salt_len().is_some() {
+        let scheme = if test.scheme.salt_len().is_some() { "pss" } else { "pkcs1v15" };
+        return Err(format!(
+            "noir_rsa on bignum 0.3 only verifies sha256 pkcs1v15, not {} {}; use --bignum-version current",
+            test.signature.hash_alg.name(),
+            scheme
+        ));
+    }
+    let (limbs, bits) = (test.key.num_limbs(), test.key.bits);
+    let mut code = format!(
+        "use dep::bignum::BigNum;\nuse dep::bignum::runtime_bignum::BigNumInstance;\nuse dep::noir_rsa::rsa::RSA;\n\
+         use dep::noir_rsa::types::Params{};\n\n{}\nfn {}() {{\n",
+        bits,
+        test.attribute(),
+        test.name
+    );
+    code.push_str(&indent(&test.hash()));
+    code.push_str(&indent(&V03Template.params(test.key, "instance")));
+    code.push_str(&indent(&V03Template.signature(test.key, "signature", "instance", &test.signature.signature_limbs)));
+    let generics = format!("<{}, Params{}>", limbs, bits);
+    code.push_str(&format!(
+        "    let rsa: RSA<BigNum{}, BigNumInstance{}, {}> = RSA {{}};\n",
+        generics,
+        generics,
+        (bits + 7) / 8
+    ));
+    code.push_str(&format!(
+        "    assert(rsa.verify_sha256_pkcs1v15(instance, sha256_hash, signature, {}));\n}}\n",
+        test.exponent
+    ));
+    Ok(code)
+}
+
+fn limb_array(limbs: &[BigUint], indent: &str, style: LimbStyle) -> String {
+    let mut array = String::from("[\n");
+    for limb in limbs {
+        array.push_str(&format!("{}    {},\n", indent, style.format(limb)));
+    }
+    array.push_str(indent);
+    array.push(']');
+    array
+}
+
+/// Compact snippet printed by default, with every limb array on a single line
+pub struct SnippetTemplate;
+
+impl NoirTemplate for SnippetTemplate {
+    fn params(&self, key: &KeyParams, binding: &str) -> String {
+        format!(
+            "let {}: {} = BigNumParams::new(\n\tfalse,\n\t[{}],\n\t[{}]\n);",
+            binding,
+            key.params_type(),
+            format_limbs_as_hex(&key.modulus_limbs, key.limb_style),
+            format_limbs_as_hex(&key.redc_limbs, key.limb_style)
+        )
+    }
+
+    fn signature(
+        &self,
+        key: &KeyParams,
+        binding: &str,
+        params_binding: &str,
+        limbs: &[BigUint],
+    ) -> String {
+        format!(
+            "let {}: {} = RuntimeBigNum::from_array(\n\t{},\n\tlimbs: [{}]\n);",
+            binding,
+            key.runtime_bignum_type(),
+            params_binding,
+            format_limbs_as_hex(limbs, key.limb_style)
+        )
+    }
+}
+
+/// `BigNumParams::new` and a `RuntimeBigNum` struct literal, as used by the tests in `src/rsa.nr`
+pub struct RuntimeTemplate;
+
+impl NoirTemplate for RuntimeTemplate {
+    fn params(&self, key: &KeyParams, binding: &str) -> String {
+        format!(
+            "let {}: {} = BigNumParams::new(\n    false,\n    {},\n    {},\n);",
+            binding,
+            key.params_type(),
+            limb_array(&key.modulus_limbs, "    ", key.limb_style),
+            limb_array(&key.redc_limbs, "    ", key.limb_style)
+        )
+    }
+
+    fn signature(
+        &self,
+        key: &KeyParams,
+        binding: &str,
+        params_binding: &str,
+        limbs: &[BigUint],
+    ) -> String {
+        let params_field = if params_binding == "params" {
+            "params".to_string()
+        } else {
+            format!("params: {}", params_binding)
+        };
+        format!(
+            "let {}: {} = RuntimeBigNum {{\n    {},\n    limbs: {},\n}};",
+            binding,
+            key.runtime_bignum_type(),
+            params_field,
+            limb_array(limbs, "    ", key.limb_style)
+        )
+    }
+}
+
+/// `BigNumInstance::new` and `BigNum::from_array`, with the params as a type parameter
+pub struct V03Template;
+
+impl NoirTemplate for V03Template {
+    fn params(&self, key: &KeyParams, binding: &str) -> String {
+        format!(
+            "let {}: BigNumInstance<{}, Params{}> = BigNumInstance::new(\n    {},\n    {},\n);",
+            binding,
+            key.num_limbs(),
+            key.bits,
+            limb_array(&key.modulus_limbs, "    ", key.limb_style),
+            limb_array(&key.redc_limbs, "    ", key.limb_style)
+        )
+    }
+
+    fn signature(
+        &self,
+        key: &KeyParams,
+        binding: &str,
+        _params_binding: &str,
+        limbs: &[BigUint],
+    ) -> String {
+        // The instance is passed to the verifier separately in this API
+        format!(
+            "let {}: BigNum<{}, Params{}> = BigNum::from_array({});",
+            binding,
+            key.num_limbs(),
+            key.bits,
+            limb_array(limbs, "", key.limb_style)
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RSA_NR: &str = include_str!("../../src/rsa.nr");
+
+    fn normalize(code: &str) -> String {
+        let compact: String = code.chars().filter(|c| !c.is_whitespace()).collect();
+        compact.replace(",]", "]").replace(",)", ")").replace(",}", "}")
+    }
+
+    fn hex_limbs(code: &str) -> Vec<BigUint> {
+        code.split(|c: char| !c.is_ascii_alphanumeric())
+            .filter_map(|token| token.strip_prefix("0x"))
+            .map(|limb| BigUint::parse_bytes(limb.as_bytes(), 16).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_runtime_template_matches_noir_tests() {
+        // The inputs of the 1024-bit PKCS#1 v1.5 test in src/rsa.nr
+        let test_start = RSA_NR.find("fn test_verify_sha256_pkcs1v15_1024()").unwrap();
+        let test_body = &RSA_NR[test_start..];
+        let golden = &test_body[test_body.find("let params:").unwrap()..test_body.find("assert(").unwrap()];
+
+        let limbs = hex_limbs(golden);
+        assert_eq!(limbs.len(), 27);
+        let modulus = limbs[..9]
+            .iter()
+            .enumerate()
+            .fold(BigUint::from(0u32), |acc, (i, limb)| acc + (limb.clone() << (120 * i)));
+
+        let key = KeyParams::from_modulus(&modulus, 1024);
+        assert_eq!(key.redc_limbs, limbs[9..18].to_vec());
+
+        let generated = format!(
+            "{}\n{}",
+            RuntimeTemplate.params(&key, "params"),
+            RuntimeTemplate.signature(&key, "signature", "params", &limbs[18..])
+        );
+        assert_eq!(normalize(&generated), normalize(golden));
+    }
+
+    #[test]
+    fn test_noir_test_matches_the_tests_of_rsa_nr() {
+        let start = RSA_NR.find("#[test]\n    fn test_verify_sha256_pkcs1v15_1024()").unwrap();
+        let end = start + RSA_NR[start..].find("\n    }\n").unwrap() + 6;
+        // Without the comment on how the inputs were generated
+        let golden: String = RSA_NR[start..end].lines().filter(|line| !line.trim_start().starts_with("//")).collect();
+        let hash_start = golden.find("[u8; 32] = [").unwrap() + 12;
+        let hash_end = hash_start + golden[hash_start..].find(']').unwrap();
+        let hash: Vec<u8> = golden[hash_start..hash_end]
+            .split(',')
+            .map(str::trim)
+            .filter(|byte| !byte.is_empty())
+            .map(|byte| byte.parse().unwrap())
+            .collect();
+        assert_eq!(hash.len(), 32);
+
+        let limbs = hex_limbs(&golden);
+        let key = KeyParams::from_modulus(&crate::params::combine_limbs(&limbs[..9]), 1024);
+        let signature =
+            SignatureVector { hash_alg: HashAlg::Sha256, hash, signature_limbs: limbs[18..].to_vec(), salt: None };
+        let name = NoirTest::default_name(HashAlg::Sha256, &Scheme::Pkcs1v15, 1024);
+        let exponent = BigUint::from(65537u32);
+        let test = NoirTest {
+            name: &name,
+            key: &key,
+            signature: &signature,
+            exponent: &exponent,
+            scheme: &Scheme::Pkcs1v15,
+            should_fail: false,
+        };
+        let code = BignumVersion::Current.render_test(&test).unwrap();
+        assert!(code.starts_with("use bignum::params::BigNumParams;\nuse bignum::RuntimeBigNum;\n"));
+        assert!(code.contains("use noir_rsa::rsa::verify_sha256_pkcs1v15;\n"));
+        assert_eq!(normalize(&code[code.find("#[test]").unwrap()..]), normalize(&golden));
+
+        // PSS passes the salt length, which the 0.3 releases do not verify
+        let pss = Scheme::Pss(crate::signing::PssSalt::Random(32));
+        let code = BignumVersion::Current.render_test(&NoirTest { scheme: &pss, ..test }).unwrap();
+        assert!(code.contains("    assert(verify_sha256_pss(sha256_hash, signature, 65537, 32));\n"));
+        assert!(BignumVersion::V03.render_test(&NoirTest { scheme: &pss, ..test }).is_err());
+        let code = BignumVersion::V03.render_test(&test).unwrap();
+        assert!(code.contains("    let instance: BigNumInstance<9, Params1024> = BigNumInstance::new(\n"));
+        assert!(code.contains("    assert(rsa.verify_sha256_pkcs1v15(instance, sha256_hash, signature, 65537));\n"));
+        let code = BignumVersion::Current.render_test(&NoirTest { should_fail: true, ..test }).unwrap();
+        assert!(code.contains("\n#[test(should_fail)]\nfn test_verify_sha256_pkcs1v15_1024() {\n"));
+    }
+
+    #[test]
+    fn test_v03_template_uses_instance_syntax() {
+        let key = KeyParams::from_modulus(&((BigUint::from(1u32) << 2047) + 1u32), 2048);
+        let params = V03Template.params(&key, "instance");
+        let signature = V03Template.signature(&key, "signature", "instance", &key.modulus_limbs);
+
+        assert!(params.starts_with("let instance: BigNumInstance<18, Params2048> = BigNumInstance::new("));
+        assert!(signature.starts_with("let signature: BigNum<18, Params2048> = BigNum::from_array(["));
+    }
+}
@@ -0,0 +1,111 @@
+#!/usr/bin/env python3
+"""Independent check of RSA signature vectors emitted by signature_gen.
+
+Every vector is verified from scratch with the Python standard library only:
+s^e mod n is computed with pow() and compared against the EMSA-PKCS1-v1_5
+encoding, or checked with EMSA-PSS-VERIFY, of RFC 8017. Prints PASS or FAIL
+for each vector and exits with status 0 only if all of them pass.
+"""
+
+import hashlib
+import sys
+
+# DER encodings of the DigestInfo prefix, RFC 8017 section 9.2, note 1
+DIGEST_INFO_PREFIXES = {
+    "sha1": "3021300906052b0e03021a05000414",
+    "sha256": "3031300d060960864801650304020105000420",
+    "sha384": "3041300d060960864801650304020205000430",
+    "sha512": "3051300d060960864801650304020305000440",
+}
+
+VECTORS = []
+
+
+def mgf1(seed, length, hash_name):
+    output = b""
+    counter = 0
+    while len(output) < length:
+        output += hashlib.new(hash_name, seed + counter.to_bytes(4, "big")).digest()
+        counter += 1
+    return output[:length]
+
+
+def emsa_pkcs1_v15_encode(digest, hash_name, em_len):
+    t = bytes.fromhex(DIGEST_INFO_PREFIXES[hash_name]) + digest
+    if em_len < len(t) + 11:
+        raise ValueError("intended encoded message length too short")
+    return b"\x00\x01" + b"\xff" * (em_len - len(t) - 3) + b"\x00" + t
+
+
+def emsa_pss_verify(digest, em, em_bits, hash_name, salt_len):
+    """EMSA-PSS-VERIFY, RFC 8017 section 9.1.2, from step 3 on (mHash given)"""
+    h_len = len(digest)
+    em_len = (em_bits + 7) // 8
+    if em_len < h_len + salt_len + 2:
+        return "encoded message too short for the salt length"
+    if em[-1] != 0xBC:
+        return "trailer byte is not 0xbc"
+    masked_db, h = em[: em_len - h_len - 1], em[em_len - h_len - 1 : -1]
+    unused_bits = 8 * em_len - em_bits
+    if masked_db[0] >> (8 - unused_bits) != 0:
+        return "leftmost bits of maskedDB are not zero"
+    db = bytearray(a ^ b for a, b in zip(masked_db, mgf1(h, len(masked_db), hash_name)))
+    db[0] &= 0xFF >> unused_bits
+    ps_len = em_len - h_len - salt_len - 2
+    if any(db[:ps_len]) or db[ps_len] != 0x01:
+        return "padding of DB is not 0x00..00 0x01 for the salt length"
+    salt = bytes(db[ps_len + 1 :])
+    if hashlib.new(hash_name, bytes(8) + digest + salt).digest() != h:
+        return "H does not match Hash(0x00 * 8 || mHash || salt)"
+    return None
+
+
+def verify(vector):
+    """Returns None if the vector verifies, or the reason it does not"""
+    n, e = vector["modulus"], vector["exponent"]
+    hash_name = vector["hash_alg"]
+    k = (n.bit_length() + 7) // 8
+    signature = bytes.fromhex(vector["signature"])
+    digest = bytes.fromhex(vector["hash"])
+
+    if "message" in vector and hashlib.new(hash_name, bytes.fromhex(vector["message"])).digest() != digest:
+        return "hash is not the %s digest of the message" % hash_name
+    if len(digest) != hashlib.new(hash_name).digest_size:
+        return "hash has the wrong length for %s" % hash_name
+    if len(signature) != k:
+        return "signature is %d bytes, expected %d" % (len(signature), k)
+    s = int.from_bytes(signature, "big")
+    if s >= n:
+        return "signature representative out of range"
+    m = pow(s, e, n)
+
+    if vector["scheme"] == "pkcs1v15":
+        if m.to_bytes(k, "big") != emsa_pkcs1_v15_encode(digest, hash_name, k):
+            return "s^e mod n is not the EMSA-PKCS1-v1_5 encoding of the hash"
+        return None
+
+    em_bits = n.bit_length() - 1
+    em_len = (em_bits + 7) // 8
+    if m.bit_length() > 8 * em_len:
+        return "s^e mod n is longer than the encoded message"
+    return emsa_pss_verify(digest, m.to_bytes(em_len, "big"), em_bits, hash_name, vector["salt_len"])
+
+
+def main():
+    if not VECTORS:
+        print("FAIL: no vectors")
+        return 1
+    failures = 0
+    for vector in VECTORS:
+        reason = verify(vector)
+        if reason is None:
+            print("PASS: %s" % vector["label"])
+        else:
+            print("FAIL: %s: %s" % (vector["label"], reason))
+            failures += 1
+    print("PASS" if failures == 0 else "FAIL")
+    return 1 if failures else 0
+
+
+if __name__ == "__main__":
+    sys.exit(main())
@@ -0,0 +1,179 @@
+//! `--emit-verifier-script`: a standalone Python 3 script for auditors
+//!
+//! The script (`verifier_script.py`) verifies each vector with the standard
+//! library only, reconstructing the EMSA-PKCS1-v1_5 or EMSA-PSS encoding
+//! itself, so it does not trust this tool, the rsa crate or the circuit. The
+//! vectors are embedded in place of its empty `VECTORS` list.
+
+use std::fs;
+use std::path::Path;
+
+use num_bigint::BigUint;
+use rsa::traits::PublicKeyParts;
+use rsa::RsaPublicKey;
+
+use crate::hash::HashAlg;
+use crate::params::SignatureParams;
+use crate::signing::Scheme;
+
+const SCRIPT: &str = include_str!("verifier_script.py");
+const PLACEHOLDER: &str = "VECTORS = []\n";
+
+/// Everything the script needs to verify one signature
+pub struct Vector {
+    pub label: String,
+    pub modulus: BigUint,
+    pub exponent: BigUint,
+    pub hash_alg: HashAlg,
+    pub hash: Vec<u8>,
+    /// Big-endian, padded to the key size
+    pub signature: Vec<u8>,
+    /// `None` for PKCS#1 v1.5
+    pub salt_len: Option<usize>,
+    /// Checked against `hash` when given
+    pub message: Option<Vec<u8>>,
+}
+
+/// The vectors of every signature of every entry, in output order; `messages`
+/// holds the message each entry signed, if it is to be embedded
+pub fn vectors(
+    pub_keys: &[RsaPublicKey],
+    entries: &[SignatureParams],
+    messages: &[Option<&[u8]>],
+    scheme: &Scheme,
+) -> Vec<Vector> {
+    let mut vectors = Vec::new();
+    for (i, ((pub_key, entry), msg)) in pub_keys.iter().zip(entries).zip(messages).enumerate() {
+        for signature in &entry.signatures {
+            let label = match entries.len() {
+                1 => signature.hash_alg.name().to_string(),
+                _ => format!("key {} {}", i, signature.hash_alg.name()),
+            };
+            vectors.push(Vector {
+                label,
+                modulus: pub_key.n().clone(),
+                exponent: pub_key.e().clone(),
+                hash_alg: signature.hash_alg,
+                hash: signature.hash.clone(),
+                signature: signature.signature_bytes(pub_key.size()),
+                salt_len: scheme.salt_len(),
+                message: msg.map(<[u8]>::to_vec),
+            });
+        }
+    }
+    vectors
+}
+
+fn python_vector(vector: &Vector) -> String {
+    let mut fields = vec![
+        format!("\"label\": \"{}\"", vector.label),
+        format!("\"modulus\": 0x{}", vector.modulus.to_str_radix(16)),
+        format!("\"exponent\": {}", vector.exponent),
+        format!("\"hash_alg\": \"{}\"", vector.hash_alg.name()),
+        format!("\"hash\": \"{}\"", hex::encode(&vector.hash)),
+        format!("\"signature\": \"{}\"", hex::encode(&vector.signature)),
+    ];
+    match vector.salt_len {
+        Some(salt_len) => {
+            fields.push("\"scheme\": \"pss\"".to_string());
+            fields.push(format!("\"salt_len\": {}", salt_len));
+        }
+        None => fields.push("\"scheme\": \"pkcs1v15\"".to_string()),
+    }
+    if let Some(message) = &vector.message {
+        fields.push(format!("\"message\": \"{}\"", hex::encode(message)));
+    }
+    let body: String = fields.iter().map(|field| format!("        {},\n", field)).collect();
+    format!("    {{\n{}    }},\n", body)
+}
+
+/// The script with the vectors embedded
+pub fn render(vectors: &[Vector]) -> String {
+    let list: String = vectors.iter().map(python_vector).collect();
+    SCRIPT.replacen(PLACEHOLDER, &format!("VECTORS = [\n{}]\n", list), 1)
+}
+
+/// Write the script to `path`, executable where that applies
+pub fn write(path: &Path, vectors: &[Vector]) -> Result<(), String> {
+    fs::write(path, render(vectors)).map_err(|err| format!("Failed to write {}: {}", path.display(), err))?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(path, fs::Permissions::from_mode(0o755))
+            .map_err(|err| format!("Failed to make {} executable: {}", path.display(), err))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::ErrorKind;
+    use std::process::Command;
+
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha20Rng;
+    use rsa::pkcs8::DecodePrivateKey;
+    use rsa::RsaPrivateKey;
+
+    use crate::signature_params_for_key;
+    use crate::signing::PssSalt;
+
+    const FIXTURE_KEY: &str = include_str!("../tests/fixtures/rsa_1024.pem");
+    const MESSAGE: &[u8] = b"Hello World! This is Noir-RSA";
+
+    fn fixture_vectors(scheme: &Scheme) -> Vec<Vector> {
+        let priv_key = RsaPrivateKey::from_pkcs8_pem(FIXTURE_KEY).unwrap();
+        let mut rng = ChaCha20Rng::seed_from_u64(0);
+        let entry =
+            signature_params_for_key(&mut rng, &priv_key, MESSAGE, &[HashAlg::Sha256, HashAlg::Sha512], scheme)
+                .unwrap();
+        vectors(&[priv_key.to_public_key()], &[entry], &[Some(MESSAGE)], scheme)
+    }
+
+    /// Run the script with python3, or `None` if it is not installed
+    fn run(vectors: &[Vector], name: &str) -> Option<(bool, String)> {
+        let scratch = tempfile::tempdir().unwrap();
+        let path = scratch.path().join(format!("{}.py", name));
+        write(&path, vectors).unwrap();
+        let output = match Command::new("python3").arg(&path).output() {
+            Ok(output) => output,
+            Err(err) if err.kind() == ErrorKind::NotFound => return None,
+            Err(err) => panic!("Failed to run python3: {}", err),
+        };
+        Some((output.status.success(), String::from_utf8_lossy(&output.stdout).into_owned()))
+    }
+
+    #[test]
+    fn test_script_embeds_the_vectors() {
+        let script = render(&fixture_vectors(&Scheme::Pss(PssSalt::Random(32))));
+        assert!(!script.contains(PLACEHOLDER));
+        assert_eq!(script.matches("\"salt_len\": 32").count(), 2);
+        assert!(script.contains("\"label\": \"sha512\""));
+        assert!(script.starts_with("#!/usr/bin/env python3\n"));
+    }
+
+    #[test]
+    fn test_script_passes_valid_and_fails_tampered_vectors() {
+        for (name, scheme) in [("pkcs1v15", Scheme::Pkcs1v15), ("pss", Scheme::Pss(PssSalt::Random(32)))] {
+            let mut vectors = fixture_vectors(&scheme);
+            let Some((passed, stdout)) = run(&vectors, name) else {
+                eprintln!("Skipping the verifier script test, python3 is not installed");
+                return;
+            };
+            assert!(passed, "{}", stdout);
+            assert!(stdout.ends_with("PASS\n"), "{}", stdout);
+
+            vectors[1].signature[40] ^= 1;
+            let (passed, stdout) = run(&vectors, name).unwrap();
+            assert!(!passed);
+            assert!(stdout.contains("PASS: sha256\nFAIL: sha512: "), "{}", stdout);
+
+            // A salt length other than the signer's must be rejected too
+            if let Some(salt_len) = vectors[0].salt_len {
+                vectors[0].salt_len = Some(salt_len - 1);
+                assert!(!run(&vectors[..1], name).unwrap().0);
+            }
+        }
+    }
+}
@@ -0,0 +1,360 @@
+//! `verify`: the inputs of a Prover.toml or JSON output, checked off-circuit
+//!
+//! The limbs are recombined into the modulus, redc parameter and signature,
+//! the redc limbs are compared with the Barrett parameter of that modulus, and
+//! the signature is verified with the rsa crate under the recombined key and
+//! the `hash`. The hash function, scheme and exponent are read from the file
+//! where it records them, as JSON output and `[meta]` do, and otherwise default
+//! to the hash of that length, PKCS#1 v1.5 and 65537. Each field is checked on
+//! its own, so a stale file points at the limb that went wrong rather than
+//! failing as a whole. An `[expected]` table must agree with the inputs and
+//! with the verdict of the circuit.
+
+use num_bigint::BigUint;
+use rsa::RsaPublicKey;
+use toml::Value;
+
+use noir_bignum_paramgen::{compute_barrett_reduction_parameter, split_into_120_bit_limbs};
+
+use crate::convert::{detect_layout, parse_limb_list, parse_limbs, Layout};
+use crate::doctor::{Check, Status};
+use crate::expected::{self, EXPECTED, VERIFIED};
+use crate::hash::HashAlg;
+use crate::meta::META;
+use crate::params::{combine_limbs, num_limbs};
+use crate::reference::{self, CircuitVector};
+use crate::schema::{
+    EXPONENT, HASH, LEGACY_BN, LEGACY_SIGNATURE, LEGACY_SIGNATURE_LIMBS, MODULUS_LIMBS, REDC_LIMBS, SIGNATURE_LIMBS,
+};
+use crate::signing::{verify_digest, PssSalt, Scheme};
+
+/// What the file does not record, overriding what it does
+#[derive(Clone, Debug, Default)]
+pub struct Options {
+    pub hash_alg: Option<HashAlg>,
+    pub pss: bool,
+    pub salt_len: Option<usize>,
+    pub exponent: Option<BigUint>,
+}
+
+/// The inputs of one verification, with the limbs as they are written
+pub struct Inputs {
+    pub bits: usize,
+    pub modulus_limbs: Vec<BigUint>,
+    pub redc_limbs: Vec<BigUint>,
+    pub signature_limbs: Vec<BigUint>,
+    pub hash: Vec<u8>,
+    pub hash_alg: HashAlg,
+    pub scheme: Scheme,
+    pub exponent: BigUint,
+    /// `expected.verified` of an `[expected]` table, or why the table is inconsistent
+    pub expected: Option<Result<bool, String>>,
+}
+
+/// A JSON object as the TOML table it corresponds to, without its nulls
+fn json_document(contents: &str) -> Result<Value, String> {
+    let mut json: serde_json::Value =
+        serde_json::from_str(contents).map_err(|err| format!("invalid JSON: {}", err))?;
+    if let Some(object) = json.as_object_mut() {
+        object.retain(|_, value| !value.is_null());
+    }
+    serde_json::from_value(json).map_err(|err| format!("invalid JSON: {}", err))
+}
+
+/// The inputs of a TOML or JSON document in the canonical or pre-0.4.0 layout
+pub fn parse(contents: &str, options: &Options) -> Result<Inputs, String> {
+    let document = if contents.trim_start().starts_with('{') {
+        json_document(contents)?
+    } else {
+        contents.parse::<Value>().map_err(|err| format!("invalid TOML: {}", err))?
+    };
+    let table = document.as_table().ok_or("the inputs are not a table")?;
+    let meta = table.get(META);
+    // JSON output records at the top level what TOML output records in `[meta]`
+    let recorded = |name: &str| table.get(name).or_else(|| meta.and_then(|meta| meta.get(name)));
+
+    let (modulus_limbs, redc_limbs, signature_limbs) = match detect_layout(table) {
+        Some(Layout::V03) => {
+            let bn = table.get(LEGACY_BN).and_then(Value::as_array).map_or(&[][..], Vec::as_slice);
+            let signature = table.get(LEGACY_SIGNATURE).and_then(|signature| signature.get(LEGACY_SIGNATURE_LIMBS));
+            (
+                parse_limb_list(bn.first(), "bn[0]")?,
+                parse_limb_list(bn.get(1), "bn[1]")?,
+                parse_limb_list(signature, "signature.limbs")?,
+            )
+        }
+        _ => (
+            parse_limb_list(table.get(MODULUS_LIMBS), MODULUS_LIMBS)?,
+            parse_limb_list(table.get(REDC_LIMBS), REDC_LIMBS)?,
+            parse_limb_list(table.get(SIGNATURE_LIMBS), SIGNATURE_LIMBS)?,
+        ),
+    };
+    let hash = table
+        .get(HASH)
+        .and_then(Value::as_array)
+        .ok_or("missing byte array `hash`")?
+        .iter()
+        .map(|byte| byte.as_integer().and_then(|byte| u8::try_from(byte).ok()))
+        .collect::<Option<Vec<u8>>>()
+        .ok_or("`hash` is not an array of bytes")?;
+
+    let named = recorded("hash_alg").or_else(|| recorded("hashes").and_then(|hashes| hashes.get(0)));
+    let hash_alg = match (options.hash_alg, named.and_then(Value::as_str)) {
+        (Some(hash_alg), _) => hash_alg,
+        (None, Some(name)) => HashAlg::from_name(name).ok_or_else(|| format!("unknown hash {}", name))?,
+        (None, None) => [HashAlg::Sha1, HashAlg::Sha256, HashAlg::Sha384, HashAlg::Sha512]
+            .into_iter()
+            .find(|hash_alg| hash_alg.output_len() == hash.len())
+            .ok_or_else(|| format!("no hash has {}-byte digests, pass --hash", hash.len()))?,
+    };
+    let scheme = match recorded("scheme").and_then(Value::as_str) {
+        _ if options.pss => Scheme::Pss(PssSalt::Random(options.salt_len.unwrap_or(hash_alg.output_len()))),
+        Some("pss") => {
+            let salt_len = recorded("salt_len").and_then(Value::as_integer).map(|salt_len| salt_len as usize);
+            Scheme::Pss(PssSalt::Random(options.salt_len.or(salt_len).unwrap_or(hash_alg.output_len())))
+        }
+        Some("pkcs1v15") | None => Scheme::Pkcs1v15,
+        Some(scheme) => return Err(format!("unknown scheme {}", scheme)),
+    };
+    let recorded_exponent = table.get(EXPONENT).or_else(|| recorded("exponents").and_then(|e| e.get(0)));
+    let exponent = match (&options.exponent, recorded_exponent) {
+        (Some(exponent), _) => exponent.clone(),
+        (None, Some(Value::Array(_))) => parse_limbs(table.get(EXPONENT), EXPONENT)?,
+        (None, Some(value)) => value
+            .as_integer()
+            .filter(|&exponent| exponent > 0)
+            .map(|exponent| BigUint::from(exponent as u64))
+            .ok_or_else(|| format!("invalid exponent {}", value))?,
+        (None, None) => BigUint::from(65537u32),
+    };
+    let bits = match recorded("key_size_bits").and_then(Value::as_integer) {
+        Some(bits) => bits as usize,
+        None => (combine_limbs(&modulus_limbs).bits() + 7) / 8 * 8,
+    };
+    let expected = table.get(EXPECTED).map(|expected| {
+        expected::check(table)?;
+        Ok(expected.get(VERIFIED).and_then(Value::as_bool) == Some(true))
+    });
+    Ok(Inputs { bits, modulus_limbs, redc_limbs, signature_limbs, hash, hash_alg, scheme, exponent, expected })
+}
+
+/// Whether an input has the limbs of a `bits`-bit value, each within 120 bits
+fn limb_check(name: &str, limbs: &[BigUint], bits: usize) -> Result<(), Check> {
+    if limbs.len() != num_limbs(bits) {
+        let detail = format!("{} limbs, a {}-bit key has {}", limbs.len(), bits, num_limbs(bits));
+        return Err(Check::new(name, Status::Fail, detail));
+    }
+    match limbs.iter().position(|limb| limb.bits() > 120) {
+        Some(limb) => Err(Check::new(name, Status::Fail, format!("limb {} does not fit in 120 bits", limb))),
+        None => Ok(()),
+    }
+}
+
+fn modulus_check(inputs: &Inputs) -> Check {
+    if let Err(check) = limb_check("modulus", &inputs.modulus_limbs, inputs.bits) {
+        return check;
+    }
+    let modulus = combine_limbs(&inputs.modulus_limbs);
+    if let Err(divergence) = reference::check_modulus(&modulus, inputs.bits) {
+        return Check::new("modulus", Status::Fail, divergence.to_string());
+    }
+    Check::new("modulus", Status::Ok, format!("{}-bit modulus in {} limbs", modulus.bits(), inputs.modulus_limbs.len()))
+}
+
+fn redc_check(inputs: &Inputs) -> Check {
+    if let Err(check) = limb_check("redc", &inputs.redc_limbs, inputs.bits) {
+        return check;
+    }
+    let modulus = combine_limbs(&inputs.modulus_limbs);
+    if let Err(divergence) = reference::check_modulus(&modulus, inputs.bits) {
+        return Check::new("redc", Status::Fail, format!("no Barrett parameter to compare, {}", divergence));
+    }
+    let expected = split_into_120_bit_limbs(&compute_barrett_reduction_parameter(&modulus), inputs.bits);
+    let differing: Vec<usize> = (0..expected.len()).filter(|&i| expected[i] != inputs.redc_limbs[i]).collect();
+    match differing.first() {
+        Some(&limb) => Check::new(
+            "redc",
+            Status::Fail,
+            format!(
+                "redc mismatch at limb {}: expected 0x{:x}, got 0x{:x} ({} of {} limbs differ)",
+                limb,
+                expected[limb],
+                inputs.redc_limbs[limb],
+                differing.len(),
+                expected.len()
+            ),
+        ),
+        None => Check::new("redc", Status::Ok, "the Barrett parameter of the modulus".to_string()),
+    }
+}
+
+fn signature_check(inputs: &Inputs) -> Check {
+    if let Err(check) = limb_check("signature", &inputs.signature_limbs, inputs.bits) {
+        return check;
+    }
+    if combine_limbs(&inputs.signature_limbs) >= combine_limbs(&inputs.modulus_limbs) {
+        return Check::new("signature", Status::Fail, "not below the modulus".to_string());
+    }
+    Check::new("signature", Status::Ok, "below the modulus".to_string())
+}
+
+fn hash_check(inputs: &Inputs) -> Check {
+    let (len, expected) = (inputs.hash.len(), inputs.hash_alg.output_len());
+    let detail = format!("{} bytes, a {} digest has {}", len, inputs.hash_alg.name(), expected);
+    Check::new("hash", if len == expected { Status::Ok } else { Status::Fail }, detail)
+}
+
+/// The signature under the rsa crate's verifier, independent of the limbs
+fn rsa_check(inputs: &Inputs) -> Check {
+    let scheme = match inputs.scheme.salt_len() {
+        Some(salt_len) => format!("pss with a {}-byte salt", salt_len),
+        None => "pkcs1v15".to_string(),
+    };
+    let result = RsaPublicKey::new(combine_limbs(&inputs.modulus_limbs), inputs.exponent.clone())
+        .and_then(|pub_key| {
+            let signature = combine_limbs(&inputs.signature_limbs).to_bytes_be();
+            verify_digest(&pub_key, inputs.hash_alg, &inputs.hash, &signature, &inputs.scheme)
+        });
+    let detail = format!("{} {} under e = {}", inputs.hash_alg.name(), scheme, inputs.exponent);
+    match result {
+        Ok(()) => Check::new("rsa", Status::Ok, detail),
+        Err(err) => Check::new("rsa", Status::Fail, format!("{}: {}", detail, err)),
+    }
+}
+
+/// The limb-level steps of the circuit, which locate a failure of `rsa`
+fn circuit_check(inputs: &Inputs) -> Check {
+    let vector = CircuitVector {
+        bits: inputs.bits,
+        modulus_limbs: &inputs.modulus_limbs,
+        redc_limbs: &inputs.redc_limbs,
+        signature_limbs: &inputs.signature_limbs,
+        exponent: &inputs.exponent,
+        hash_alg: inputs.hash_alg,
+        msg_hash: &inputs.hash,
+        scheme: &inputs.scheme,
+    };
+    match reference::verify(&vector) {
+        Ok(()) => Check::new("circuit", Status::Ok, "the reference of the circuit accepts the inputs".to_string()),
+        Err(divergence) => Check::new("circuit", Status::Fail, divergence.to_string()),
+    }
+}
+
+/// Whether the `[expected]` table agrees with the rest of the file and with the verdict of the circuit
+fn expected_check(expected: &Result<bool, String>, circuit: &Check) -> Check {
+    let accepted = circuit.status == Status::Ok;
+    match expected {
+        Err(err) => Check::new("expected", Status::Fail, err.clone()),
+        Ok(verified) if *verified == accepted => {
+            Check::new("expected", Status::Ok, format!("expected.verified = {} agrees with the inputs", verified))
+        }
+        Ok(verified) => {
+            let verdict = if accepted { "accepts" } else { "rejects" };
+            let detail = format!("expected.verified = {} but the circuit {} the inputs", verified, verdict);
+            Check::new("expected", Status::Fail, detail)
+        }
+    }
+}
+
+pub fn run_checks(inputs: &Inputs) -> Vec<Check> {
+    let mut checks = vec![
+        modulus_check(inputs),
+        redc_check(inputs),
+        signature_check(inputs),
+        hash_check(inputs),
+        rsa_check(inputs),
+        circuit_check(inputs),
+    ];
+    if let Some(expected) = &inputs.expected {
+        checks.push(expected_check(expected, checks.last().unwrap()));
+    }
+    checks
+}
+
+/// Print every check and return the names of those that failed
+pub fn verify(inputs: &Inputs) -> Vec<String> {
+    let checks = run_checks(inputs);
+    for check in &checks {
+        let status = match check.status {
+            Status::Ok => "ok",
+            Status::Warn => "warn",
+            Status::Fail => "FAIL",
+        };
+        println!("[{:>4}] {}: {}", status, check.name, check.detail);
+    }
+    checks.into_iter().filter(|check| check.status == Status::Fail).map(|check| check.name).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha20Rng;
+    use rsa::pkcs8::DecodePrivateKey;
+    use rsa::RsaPrivateKey;
+
+    use crate::meta::Meta;
+    use crate::schema::Names;
+    use crate::signature_params_for_key;
+
+    fn failures(contents: &str, options: &Options) -> Vec<(String, String)> {
+        run_checks(&parse(contents, options).unwrap())
+            .into_iter()
+            .filter(|check| check.status == Status::Fail)
+            .map(|check| (check.name, check.detail))
+            .collect()
+    }
+
+    #[test]
+    fn test_emitted_inputs_pass_and_stale_limbs_are_located() {
+        let priv_key = RsaPrivateKey::from_pkcs8_pem(include_str!("../tests/fixtures/rsa_2048.pem")).unwrap();
+        let scheme = Scheme::Pss(PssSalt::Random(20));
+        let mut rng = ChaCha20Rng::seed_from_u64(0);
+        let params = signature_params_for_key(&mut rng, &priv_key, b"hello", &[HashAlg::Sha384], &scheme).unwrap();
+        let meta = Meta::new(&[priv_key.to_public_key()], &[HashAlg::Sha384], &scheme, b"hello");
+        let toml = format!("{}\n{}", params.render_toml(Names::Canonical), meta.render_toml());
+        assert_eq!(failures(&toml, &Options::default()), []);
+        // Without [meta], the scheme and salt length must be given
+        let bare = params.render_toml(Names::Canonical);
+        assert!(failures(&bare, &Options::default()).iter().any(|(name, _)| name == "rsa"));
+        assert_eq!(failures(&bare, &Options { pss: true, salt_len: Some(20), ..Options::default() }), []);
+
+        // A hand-edited top limb of the redc parameter
+        let mut document: Value = toml.parse().unwrap();
+        document[REDC_LIMBS].as_array_mut().unwrap()[17] = Value::String("0x1".to_string());
+        let failed = failures(&document.to_string(), &Options::default());
+        assert_eq!(failed[0].0, "redc");
+        assert!(failed[0].1.starts_with("redc mismatch at limb 17: expected 0x"), "{}", failed[0].1);
+        assert!(failed.iter().all(|(name, _)| name != "rsa"));
+
+        // An [expected] table with the verdict of the circuit, and one contradicting it
+        let expected = crate::expected::render_toml(std::slice::from_ref(&params), true);
+        assert_eq!(failures(&format!("{}\n{}", toml, expected), &Options::default()), []);
+        let contradicting = format!("{}\n{}", toml, expected.replace("verified = true", "verified = false"));
+        let failed = failures(&contradicting, &Options::default());
+        assert_eq!(failed.len(), 1);
+        assert_eq!(failed[0].0, "expected");
+        assert_eq!(failed[0].1, "expected.verified = false but the circuit accepts the inputs");
+
+        // JSON output, with the nulls it writes for PKCS#1 v1.5
+        let params =
+            signature_params_for_key(&mut rng, &priv_key, b"hello", &[HashAlg::Sha256], &Scheme::Pkcs1v15).unwrap();
+        let hex = |limbs: &[BigUint]| -> Vec<String> { limbs.iter().map(|limb| format!("0x{:x}", limb)).collect() };
+        let mut json = serde_json::json!({
+            "hash": params.signatures[0].hash,
+            "modulus_limbs": hex(&params.key.modulus_limbs),
+            "redc_limbs": hex(&params.key.redc_limbs),
+            "exponent": 65537,
+            "signature_limbs": hex(&params.signatures[0].signature_limbs),
+            "key_size_bits": 2048,
+            "hash_alg": "sha256",
+            "scheme": "pkcs1v15",
+            "salt_len": null,
+        });
+        assert_eq!(failures(&json.to_string(), &Options::default()), []);
+        json["hash"][0] = serde_json::json!(params.signatures[0].hash[0] ^ 1);
+        let failed: Vec<String> =
+            failures(&json.to_string(), &Options::default()).into_iter().map(|(name, _)| name).collect();
+        assert_eq!(failed, ["rsa", "circuit"]);
+    }
+}
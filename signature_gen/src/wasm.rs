@@ -0,0 +1,44 @@
+//! `wasm` feature: circuit inputs for noir_js, computed in the browser or node
+//!
+//! Only the public-key path is exposed, a signature made elsewhere and its
+//! key's modulus, so nothing needs a key generator or an RNG. Build it with
+//! `cargo rustc --lib --crate-type cdylib --target wasm32-unknown-unknown --no-default-features --features wasm`
+//! and run `wasm-bindgen --target web` on the result.
+
+use serde::Serialize;
+use wasm_bindgen::prelude::*;
+
+use crate::circuit_inputs_for_signature;
+
+/// What `generateCircuitInputs` resolves to, every value a `0x` hex string
+#[derive(Serialize)]
+struct JsCircuitInputs {
+    hash: String,
+    modulus_limbs: Vec<String>,
+    redc_limbs: Vec<String>,
+    signature_limbs: Vec<String>,
+}
+
+/// `generateCircuitInputs(msgBytes, modulusBytes, signatureBytes, hash, scheme)`:
+/// hash the message with `hash` (`sha1` to `sha512`) and return the hash and
+/// limbs of the circuit inputs for `signatureBytes` under a big-endian modulus
+/// with exponent 65537. `scheme` is `pkcs1v15` or `pss`. A signature that
+/// does not verify throws.
+#[wasm_bindgen(js_name = generateCircuitInputs)]
+pub fn generate_circuit_inputs(
+    msg_bytes: &[u8],
+    modulus_bytes: &[u8],
+    signature_bytes: &[u8],
+    hash: &str,
+    scheme: &str,
+) -> Result<JsValue, JsError> {
+    let inputs = circuit_inputs_for_signature(msg_bytes, modulus_bytes, signature_bytes, hash, scheme)
+        .map_err(|err| JsError::new(&err.message))?;
+    let inputs = JsCircuitInputs {
+        hash: format!("0x{}", hex::encode(&inputs.hash)),
+        modulus_limbs: inputs.modulus_limbs_hex(),
+        redc_limbs: inputs.redc_limbs_hex(),
+        signature_limbs: inputs.signature_limbs_hex(),
+    };
+    serde_wasm_bindgen::to_value(&inputs).map_err(|err| JsError::new(&err.to_string()))
+}
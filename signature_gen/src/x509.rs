@@ -0,0 +1,541 @@
+//! Reading the signed parts of an X.509 certificate
+//!
+//! Only what is needed to rebuild the issuer's signature is decoded: the raw
+//! `tbsCertificate` bytes, the signature algorithm and the signature, plus
+//! the certificate's own public key so that it can act as an issuer. For
+//! id-RSASSA-PSS the hash, MGF1 hash and salt length come from the
+//! RSASSA-PSS-params of RFC 4055 instead of being guessed. The same reader
+//! takes apart unencrypted private keys for `inspect`, which has to see the
+//! CRT values as stored rather than as recomputed by the rsa crate.
+
+use std::fs;
+
+use base64ct::{Base64, Encoding};
+use rsa::pkcs8::DecodePublicKey;
+use rsa::RsaPublicKey;
+
+use crate::hash::HashAlg;
+use crate::keys::load_public_key;
+use crate::signing::{verify_digest, PssSalt, Scheme};
+
+const OID_SHA1: &[u8] = &[0x2b, 0x0e, 0x03, 0x02, 0x1a];
+const OID_SHA256: &[u8] = &[0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x01];
+const OID_SHA384: &[u8] = &[0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x02];
+const OID_SHA512: &[u8] = &[0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x03];
+/// 1.2.840.113549.1.1, the PKCS#1 arc; the last arc selects the algorithm
+const OID_PKCS1: &[u8] = &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x01];
+/// 1.2.840.10045.4, ECDSA signatures
+const OID_ECDSA: &[u8] = &[0x2a, 0x86, 0x48, 0xce, 0x3d, 0x04];
+
+const TAG_INTEGER: u8 = 0x02;
+const TAG_BIT_STRING: u8 = 0x03;
+const TAG_OCTET_STRING: u8 = 0x04;
+const TAG_OID: u8 = 0x06;
+const TAG_SEQUENCE: u8 = 0x30;
+
+/// Minimal DER reader over a sequence of TLVs
+struct Der<'a>(&'a [u8]);
+
+/// One TLV: its tag, its contents and its complete encoding
+struct Tlv<'a> {
+    tag: u8,
+    contents: &'a [u8],
+    encoded: &'a [u8],
+}
+
+impl<'a> Der<'a> {
+    fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    fn peek_tag(&self) -> Option<u8> {
+        self.0.first().copied()
+    }
+
+    fn read(&mut self) -> Result<Tlv<'a>, String> {
+        let bytes = self.0;
+        let (&tag, rest) = bytes.split_first().ok_or("unexpected end of DER")?;
+        let (&first, rest) = rest.split_first().ok_or("unexpected end of DER")?;
+        let (len, rest) = if first < 0x80 {
+            (first as usize, rest)
+        } else {
+            let num_bytes = (first & 0x7f) as usize;
+            if num_bytes == 0 || num_bytes > 4 || rest.len() < num_bytes {
+                return Err("unsupported DER length".to_string());
+            }
+            let len = rest[..num_bytes].iter().fold(0usize, |acc, &b| (acc << 8) | b as usize);
+            (len, &rest[num_bytes..])
+        };
+        if rest.len() < len {
+            return Err("DER length exceeds the input".to_string());
+        }
+        let header_len = bytes.len() - rest.len();
+        self.0 = &rest[len..];
+        Ok(Tlv {
+            tag,
+            contents: &rest[..len],
+            encoded: &bytes[..header_len + len],
+        })
+    }
+
+    fn expect(&mut self, tag: u8, what: &str) -> Result<Tlv<'a>, String> {
+        let tlv = self.read().map_err(|err| format!("{}: {}", what, err))?;
+        if tlv.tag != tag {
+            return Err(format!("{}: expected tag 0x{:02x}, found 0x{:02x}", what, tag, tlv.tag));
+        }
+        Ok(tlv)
+    }
+
+    /// The contents of an optional `[n] EXPLICIT` field
+    fn explicit(&mut self, n: u8) -> Result<Option<Der<'a>>, String> {
+        if self.peek_tag() == Some(0xa0 | n) {
+            Ok(Some(Der(self.read()?.contents)))
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+fn small_integer(tlv: &Tlv, what: &str) -> Result<usize, String> {
+    if tlv.tag != TAG_INTEGER || tlv.contents.is_empty() || tlv.contents.len() > 4 || tlv.contents[0] & 0x80 != 0 {
+        return Err(format!("{} must be a small non-negative INTEGER", what));
+    }
+    Ok(tlv.contents.iter().fold(0usize, |acc, &b| (acc << 8) | b as usize))
+}
+
+fn hash_from_oid(oid: &[u8]) -> Result<HashAlg, String> {
+    match oid {
+        OID_SHA1 => Ok(HashAlg::Sha1),
+        OID_SHA256 => Ok(HashAlg::Sha256),
+        OID_SHA384 => Ok(HashAlg::Sha384),
+        OID_SHA512 => Ok(HashAlg::Sha512),
+        _ => Err(format!("unsupported hash algorithm OID {}", hex::encode(oid))),
+    }
+}
+
+/// A hash AlgorithmIdentifier, whose parameters are absent or NULL
+fn hash_algorithm(der: &mut Der) -> Result<HashAlg, String> {
+    let mut algorithm = Der(der.expect(TAG_SEQUENCE, "hash AlgorithmIdentifier")?.contents);
+    hash_from_oid(algorithm.expect(TAG_OID, "hash algorithm")?.contents)
+}
+
+/// RSASSA-PSS-params (RFC 4055 section 3.1)
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PssParams {
+    pub hash: HashAlg,
+    pub mgf1_hash: HashAlg,
+    pub salt_len: usize,
+    /// Fields that were absent and took their default value
+    pub defaulted: Vec<&'static str>,
+}
+
+impl PssParams {
+    pub fn warnings(&self) -> Vec<String> {
+        self.defaulted
+            .iter()
+            .map(|field| {
+                let default = match *field {
+                    "saltLength" => "20".to_string(),
+                    _ => "sha1".to_string(),
+                };
+                format!("RSASSA-PSS parameters omit {}, using the RFC 4055 default {}", field, default)
+            })
+            .collect()
+    }
+}
+
+/// Decode RSASSA-PSS-params, applying the defaults of absent fields
+/// (SHA-1, MGF1 with SHA-1, a 20 byte salt and trailer field 1)
+pub fn parse_pss_params(der: &[u8]) -> Result<PssParams, String> {
+    let mut outer = Der(der);
+    let mut params = Der(outer.expect(TAG_SEQUENCE, "RSASSA-PSS-params")?.contents);
+    let mut defaulted = Vec::new();
+
+    let hash = match params.explicit(0)? {
+        Some(mut field) => hash_algorithm(&mut field)?,
+        None => {
+            defaulted.push("hashAlgorithm");
+            HashAlg::Sha1
+        }
+    };
+    let mgf1_hash = match params.explicit(1)? {
+        Some(mut field) => {
+            let mut algorithm = Der(field.expect(TAG_SEQUENCE, "maskGenAlgorithm")?.contents);
+            let oid = algorithm.expect(TAG_OID, "mask generation function")?;
+            if oid.contents != [OID_PKCS1, &[0x08]].concat() {
+                return Err(format!("unsupported mask generation function OID {}", hex::encode(oid.contents)));
+            }
+            hash_algorithm(&mut algorithm)?
+        }
+        None => {
+            defaulted.push("maskGenAlgorithm");
+            HashAlg::Sha1
+        }
+    };
+    let salt_len = match params.explicit(2)? {
+        Some(mut field) => small_integer(&field.read()?, "saltLength")?,
+        None => {
+            defaulted.push("saltLength");
+            20
+        }
+    };
+    if let Some(mut field) = params.explicit(3)? {
+        let trailer = small_integer(&field.read()?, "trailerField")?;
+        if trailer != 1 {
+            return Err(format!("unsupported RSASSA-PSS trailerField {}, only 1 (0xbc) is defined", trailer));
+        }
+    }
+    if !params.is_empty() {
+        return Err("unexpected data after RSASSA-PSS-params".to_string());
+    }
+
+    Ok(PssParams {
+        hash,
+        mgf1_hash,
+        salt_len,
+        defaulted,
+    })
+}
+
+/// Signature algorithm of a certificate
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SignatureAlgorithm {
+    Pkcs1v15(HashAlg),
+    Pss(PssParams),
+}
+
+impl SignatureAlgorithm {
+    pub fn hash_alg(&self) -> HashAlg {
+        match self {
+            SignatureAlgorithm::Pkcs1v15(hash_alg) => *hash_alg,
+            SignatureAlgorithm::Pss(params) => params.hash,
+        }
+    }
+
+    /// Scheme verifying the signature; the Noir verifier hashes with the same
+    /// function in MGF1, so differing hashes are rejected
+    pub fn scheme(&self) -> Result<Scheme, String> {
+        match self {
+            SignatureAlgorithm::Pkcs1v15(_) => Ok(Scheme::Pkcs1v15),
+            SignatureAlgorithm::Pss(params) if params.mgf1_hash != params.hash => Err(format!(
+                "RSASSA-PSS with {} and MGF1 with {} is not supported, the verifier uses one hash for both",
+                params.hash.name(),
+                params.mgf1_hash.name()
+            )),
+            SignatureAlgorithm::Pss(params) => Ok(Scheme::Pss(PssSalt::Random(params.salt_len))),
+        }
+    }
+}
+
+/// Decode a signature AlgorithmIdentifier
+pub fn parse_signature_algorithm(der: &[u8]) -> Result<SignatureAlgorithm, String> {
+    let mut outer = Der(der);
+    let mut algorithm = Der(outer.expect(TAG_SEQUENCE, "signature AlgorithmIdentifier")?.contents);
+    let oid = algorithm.expect(TAG_OID, "signature algorithm")?.contents;
+
+    if oid.starts_with(OID_ECDSA) {
+        return Err("the certificate is signed with ECDSA, not RSA".to_string());
+    }
+    match oid.strip_prefix(OID_PKCS1) {
+        Some([0x05]) => Ok(SignatureAlgorithm::Pkcs1v15(HashAlg::Sha1)),
+        Some([0x0b]) => Ok(SignatureAlgorithm::Pkcs1v15(HashAlg::Sha256)),
+        Some([0x0c]) => Ok(SignatureAlgorithm::Pkcs1v15(HashAlg::Sha384)),
+        Some([0x0d]) => Ok(SignatureAlgorithm::Pkcs1v15(HashAlg::Sha512)),
+        Some([0x0a]) => {
+            // Absent parameters mean every field takes its default
+            let params = if algorithm.is_empty() {
+                &[TAG_SEQUENCE, 0x00][..]
+            } else {
+                algorithm.read()?.encoded
+            };
+            Ok(SignatureAlgorithm::Pss(parse_pss_params(params)?))
+        }
+        _ => Err(format!("unsupported signature algorithm OID {}, not RSA", hex::encode(oid))),
+    }
+}
+
+/// The PKCS#1 `RSAPrivateKey` inside an unencrypted PKCS#8 `PrivateKeyInfo`
+pub fn pkcs8_rsa_private_key(der: &[u8]) -> Result<&[u8], String> {
+    let mut outer = Der(der);
+    let mut info = Der(outer.expect(TAG_SEQUENCE, "PrivateKeyInfo")?.contents);
+    small_integer(&info.read()?, "version")?;
+    let mut algorithm = Der(info.expect(TAG_SEQUENCE, "privateKeyAlgorithm")?.contents);
+    if algorithm.expect(TAG_OID, "algorithm")?.contents != [OID_PKCS1, &[0x01]].concat() {
+        return Err("private key is not an rsaEncryption key".to_string());
+    }
+    Ok(info.expect(TAG_OCTET_STRING, "privateKey")?.contents)
+}
+
+/// n, e, d, p, q, dp, dq and qinv of a two-prime PKCS#1 `RSAPrivateKey`, big-endian as stored
+pub fn rsa_private_key_integers(der: &[u8]) -> Result<Vec<&[u8]>, String> {
+    let mut outer = Der(der);
+    let mut key = Der(outer.expect(TAG_SEQUENCE, "RSAPrivateKey")?.contents);
+    if small_integer(&key.read()?, "version")? != 0 {
+        return Err("multi-prime RSAPrivateKey is not supported".to_string());
+    }
+    ["modulus", "publicExponent", "privateExponent", "prime1", "prime2", "exponent1", "exponent2", "coefficient"]
+        .iter()
+        .map(|field| {
+            let integer = key.expect(TAG_INTEGER, field)?;
+            match integer.contents.first() {
+                Some(&first) if first & 0x80 == 0 => Ok(integer.contents),
+                _ => Err(format!("{} must be a positive INTEGER", field)),
+            }
+        })
+        .collect()
+}
+
+/// The bytes of BIT STRING contents, which must not have unused bits
+fn bit_string_bytes(contents: &[u8]) -> Result<&[u8], String> {
+    match contents.split_first() {
+        Some((0, bytes)) => Ok(bytes),
+        _ => Err("has unused bits".to_string()),
+    }
+}
+
+/// The bytes of a DER `BIT STRING`, such as a certificate's signatureValue, with nothing after it
+pub fn bit_string(der: &[u8]) -> Result<&[u8], String> {
+    let mut der = Der(der);
+    let bytes = bit_string_bytes(der.expect(TAG_BIT_STRING, "BIT STRING")?.contents)
+        .map_err(|err| format!("BIT STRING {}", err))?;
+    if !der.is_empty() {
+        return Err("trailing data after the BIT STRING".to_string());
+    }
+    Ok(bytes)
+}
+
+/// The parts of a certificate covered by, and making up, its signature
+pub struct Certificate {
+    /// DER encoding of `tbsCertificate`, the signed bytes
+    pub tbs: Vec<u8>,
+    pub signature_algorithm: SignatureAlgorithm,
+    pub signature: Vec<u8>,
+    /// DER encoding of the certificate's own SubjectPublicKeyInfo
+    pub subject_public_key_info: Vec<u8>,
+}
+
+impl Certificate {
+    /// Parse a DER or PEM certificate
+    pub fn parse(bytes: &[u8]) -> Result<Certificate, String> {
+        match std::str::from_utf8(bytes) {
+            Ok(text) if text.contains("-----BEGIN CERTIFICATE-----") => {
+                let body: String = text
+                    .lines()
+                    .skip_while(|line| !line.starts_with("-----BEGIN CERTIFICATE-----"))
+                    .skip(1)
+                    .take_while(|line| !line.starts_with("-----END"))
+                    .collect();
+                let der = Base64::decode_vec(body.trim()).map_err(|err| format!("invalid PEM certificate: {}", err))?;
+                Certificate::from_der(&der)
+            }
+            _ => Certificate::from_der(bytes),
+        }
+    }
+
+    pub fn from_der(der: &[u8]) -> Result<Certificate, String> {
+        let mut outer = Der(der);
+        let mut certificate = Der(outer.expect(TAG_SEQUENCE, "Certificate")?.contents);
+        let tbs = certificate.expect(TAG_SEQUENCE, "tbsCertificate")?;
+        let signature_algorithm = parse_signature_algorithm(certificate.read()?.encoded)?;
+        let signature = bit_string_bytes(certificate.expect(TAG_BIT_STRING, "signatureValue")?.contents)
+            .map_err(|err| format!("signatureValue {}", err))?
+            .to_vec();
+
+        // version, serialNumber, signature, issuer, validity, subject, subjectPublicKeyInfo
+        let mut fields = Der(tbs.contents);
+        fields.explicit(0)?;
+        for field in ["serialNumber", "signature", "issuer", "validity", "subject"] {
+            fields.read().map_err(|err| format!("{}: {}", field, err))?;
+        }
+        let spki = fields.expect(TAG_SEQUENCE, "subjectPublicKeyInfo")?;
+
+        Ok(Certificate {
+            tbs: tbs.encoded.to_vec(),
+            signature_algorithm,
+            signature,
+            subject_public_key_info: spki.encoded.to_vec(),
+        })
+    }
+
+    /// The RSA public key of the certificate's subject
+    pub fn public_key(&self) -> Result<RsaPublicKey, String> {
+        RsaPublicKey::from_public_key_der(&self.subject_public_key_info)
+            .map_err(|err| format!("the certificate does not hold an RSA public key: {}", err))
+    }
+
+    /// Hash of the signed bytes with the digest of the signature algorithm
+    pub fn tbs_hash(&self) -> Vec<u8> {
+        self.signature_algorithm.hash_alg().digest(&self.tbs)
+    }
+
+    /// Check the signature with the issuer's key and the parameters of the signature algorithm
+    pub fn verify(&self, issuer: &RsaPublicKey) -> Result<(), String> {
+        let hash_alg = self.signature_algorithm.hash_alg();
+        verify_digest(issuer, hash_alg, &self.tbs_hash(), &self.signature, &self.signature_algorithm.scheme()?)
+            .map_err(|err| format!("the certificate signature does not verify with the issuer key: {}", err))
+    }
+}
+
+/// Whether `der` opens like a Certificate: a SEQUENCE whose first element, the
+/// tbsCertificate, is a SEQUENCE starting with a version or serial number. The
+/// AlgorithmIdentifier opening a SubjectPublicKeyInfo starts with an OID, and
+/// an RSAPublicKey holds INTEGERs only.
+fn looks_like_certificate(der: &[u8]) -> bool {
+    let mut rest = der;
+    for _ in 0..2 {
+        rest = match rest {
+            [TAG_SEQUENCE, len, tail @ ..] => {
+                let len_bytes = if len & 0x80 == 0 { 0 } else { (len & 0x7f) as usize };
+                match tail.get(len_bytes..) {
+                    Some(tail) => tail,
+                    None => return false,
+                }
+            }
+            _ => return false,
+        };
+    }
+    matches!(rest.first(), Some(&0xa0) | Some(&TAG_INTEGER))
+}
+
+/// The key of an issuer, read from its certificate or from a public key file.
+/// Which one it is comes from the PEM label or the DER structure, so a broken
+/// certificate is reported as such rather than as a key that does not parse.
+pub fn read_issuer_key(path: &str) -> Result<RsaPublicKey, String> {
+    let bytes = fs::read(path).map_err(|err| format!("Failed to read {}: {}", path, err))?;
+    let certificate = match std::str::from_utf8(&bytes) {
+        Ok(text) if text.contains("-----BEGIN ") => text.contains("-----BEGIN CERTIFICATE-----"),
+        _ => looks_like_certificate(&bytes),
+    };
+    if certificate {
+        Certificate::parse(&bytes)
+            .and_then(|certificate| certificate.public_key())
+            .map_err(|err| format!("{}: {}", path, err))
+    } else {
+        load_public_key(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rsa::pkcs1::EncodeRsaPublicKey;
+    use rsa::pkcs8::{DecodePrivateKey, EncodePublicKey};
+    use rsa::RsaPrivateKey;
+
+    use crate::keys::parse_public_key;
+
+    const PSS_CERT: &[u8] = include_bytes!("../tests/fixtures/cert_pss_sha256.der");
+    const ISSUER_KEY: &str = include_str!("../tests/fixtures/rsa_2048.pem");
+
+    #[test]
+    fn test_pss_certificate_parameters_are_read_and_verified() {
+        let cert = Certificate::parse(PSS_CERT).unwrap();
+        assert_eq!(
+            cert.signature_algorithm,
+            SignatureAlgorithm::Pss(PssParams {
+                hash: HashAlg::Sha256,
+                mgf1_hash: HashAlg::Sha256,
+                salt_len: 32,
+                defaulted: vec![],
+            })
+        );
+
+        let issuer = RsaPrivateKey::from_pkcs8_pem(ISSUER_KEY).unwrap().to_public_key();
+        cert.verify(&issuer).unwrap();
+        // The fixture is self-signed
+        assert_eq!(cert.public_key().unwrap(), issuer);
+
+        // A guessed salt length would not have verified
+        let wrong_salt = Scheme::Pss(PssSalt::Random(20));
+        assert!(verify_digest(&issuer, HashAlg::Sha256, &cert.tbs_hash(), &cert.signature, &wrong_salt).is_err());
+
+        let mut tampered = Certificate::parse(PSS_CERT).unwrap();
+        tampered.tbs[20] ^= 1;
+        assert!(tampered.verify(&issuer).is_err());
+    }
+
+    #[test]
+    fn test_absent_pss_parameters_take_the_defaults() {
+        let params = parse_pss_params(&[TAG_SEQUENCE, 0x00]).unwrap();
+        assert_eq!((params.hash, params.mgf1_hash, params.salt_len), (HashAlg::Sha1, HashAlg::Sha1, 20));
+        assert_eq!(params.defaulted, ["hashAlgorithm", "maskGenAlgorithm", "saltLength"]);
+        assert_eq!(params.warnings().len(), 3);
+
+        // Only the salt length given
+        let params = parse_pss_params(&[TAG_SEQUENCE, 0x05, 0xa2, 0x03, 0x02, 0x01, 0x40]).unwrap();
+        assert_eq!((params.hash, params.salt_len), (HashAlg::Sha1, 64));
+        assert_eq!(params.defaulted, ["hashAlgorithm", "maskGenAlgorithm"]);
+
+        // id-RSASSA-PSS without any parameters at all
+        let algorithm = [&[TAG_SEQUENCE, 0x0b, TAG_OID, 0x09][..], OID_PKCS1, &[0x0a]].concat();
+        match parse_signature_algorithm(&algorithm).unwrap() {
+            SignatureAlgorithm::Pss(params) => assert_eq!(params.defaulted.len(), 3),
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_unsupported_algorithms_are_rejected() {
+        // trailerField 2
+        assert!(parse_pss_params(&[TAG_SEQUENCE, 0x05, 0xa3, 0x03, 0x02, 0x01, 0x02]).is_err());
+
+        // ecdsa-with-SHA256
+        let ecdsa = [TAG_SEQUENCE, 0x0a, TAG_OID, 0x08, 0x2a, 0x86, 0x48, 0xce, 0x3d, 0x04, 0x03, 0x02];
+        let err = parse_signature_algorithm(&ecdsa).unwrap_err();
+        assert!(err.contains("not RSA"), "{}", err);
+
+        let mismatched = SignatureAlgorithm::Pss(PssParams {
+            hash: HashAlg::Sha256,
+            mgf1_hash: HashAlg::Sha1,
+            salt_len: 32,
+            defaulted: vec![],
+        });
+        assert!(mismatched.scheme().is_err());
+    }
+
+    #[test]
+    fn test_sha1_and_sha512_certificates_verify_under_their_issuers() {
+        let fixture = |name: &str| format!("{}/tests/fixtures/{}", env!("CARGO_MANIFEST_DIR"), name);
+        let self_signed = Certificate::parse(include_bytes!("../tests/fixtures/cert_sha1_self_signed.der")).unwrap();
+        assert_eq!(self_signed.signature_algorithm, SignatureAlgorithm::Pkcs1v15(HashAlg::Sha1));
+        self_signed.verify(&self_signed.public_key().unwrap()).unwrap();
+
+        let issued = Certificate::parse(include_bytes!("../tests/fixtures/cert_sha512_issued.der")).unwrap();
+        assert_eq!(issued.signature_algorithm, SignatureAlgorithm::Pkcs1v15(HashAlg::Sha512));
+        assert_eq!(issued.tbs_hash().len(), 64);
+        // The issuer is given as its certificate or as its bare public key, and the subject key is not it
+        let issuer = read_issuer_key(&fixture("cert_ca_sha256.pem")).unwrap();
+        assert_eq!(read_issuer_key(&fixture("rsa_2048_pub.pem")).unwrap(), issuer);
+        issued.verify(&issuer).unwrap();
+        assert!(issued.verify(&issued.public_key().unwrap()).is_err());
+
+        let err = Certificate::parse(include_bytes!("../tests/fixtures/cert_ecdsa_p256.der")).err().unwrap();
+        assert!(err.contains("not RSA"), "{}", err);
+    }
+
+    #[test]
+    fn test_a_broken_issuer_certificate_keeps_its_own_error() {
+        let scratch = tempfile::tempdir().unwrap();
+        let write = |name: &str, contents: &[u8]| {
+            let path = scratch.path().join(name);
+            fs::write(&path, contents).unwrap();
+            path.to_str().unwrap().to_string()
+        };
+        let der = include_bytes!("../tests/fixtures/cert_sha512_issued.der");
+        assert!(looks_like_certificate(der));
+        let truncated = write("truncated.der", &der[..der.len() - 10]);
+        let err = read_issuer_key(&truncated).unwrap_err();
+        assert!(err.starts_with(&format!("{}: ", truncated)) && !err.contains("public key"), "{}", err);
+
+        let pem = write("garbled.pem", b"-----BEGIN CERTIFICATE-----\n!!!\n-----END CERTIFICATE-----\n");
+        assert!(read_issuer_key(&pem).unwrap_err().contains("invalid PEM certificate"));
+
+        // Keys in either DER encoding are still read as keys
+        let pub_key = parse_public_key(include_str!("../tests/fixtures/rsa_2048_pub.pem")).unwrap();
+        let spki = write("key.der", pub_key.to_public_key_der().unwrap().as_bytes());
+        let pkcs1 = write("key.pkcs1.der", pub_key.to_pkcs1_der().unwrap().as_bytes());
+        assert!(!looks_like_certificate(&fs::read(&spki).unwrap()));
+        assert_eq!(read_issuer_key(&spki).unwrap(), pub_key);
+        assert_eq!(read_issuer_key(&pkcs1).unwrap(), pub_key);
+    }
+}
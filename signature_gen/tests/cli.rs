@@ -0,0 +1,323 @@
+use std::io::Write;
+use std::process::{Command, Output, Stdio};
+
+use base64ct::{Base64UrlUnpadded, Encoding};
+use rand::SeedableRng;
+use rsa::pkcs8::DecodePrivateKey;
+use rsa::RsaPrivateKey;
+
+use signature_gen::hash::HashAlg;
+use signature_gen::keys::{export_public_key, ExportFormat};
+use signature_gen::signing::{sign_digest, Scheme};
+
+const CERTIFICATE: &str = "tests/fixtures/cert_sha1_self_signed.der";
+
+fn run(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_signature_gen"))
+        .args(args)
+        .output()
+        .expect("failed to run signature_gen")
+}
+
+/// The output of a run that must succeed
+fn stdout(output: &Output) -> String {
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    String::from_utf8(output.stdout.clone()).unwrap()
+}
+
+/// The error output of a run that must fail
+fn stderr(output: &Output) -> String {
+    assert!(!output.status.success(), "{}", String::from_utf8_lossy(&output.stdout));
+    String::from_utf8(output.stderr.clone()).unwrap()
+}
+
+#[test]
+fn test_keygen_saves_the_key_sign_would_generate_from_the_seed() {
+    let scratch = tempfile::tempdir().unwrap();
+    let dir = scratch.path();
+    let (key, public) = (dir.join("key.pem"), dir.join("key.pub.pem"));
+    let (key, public) = (key.to_str().unwrap(), public.to_str().unwrap());
+    let printed = stdout(&run(&["keygen", "--bits", "1024", "--seed", "7", "--out", key, "--public-out", public]));
+    assert!(printed.starts_with("sha256:") && printed.trim_end().ends_with(key));
+    assert!(std::fs::read_to_string(public).unwrap().starts_with("-----BEGIN PUBLIC KEY-----"));
+
+    let sign = |options: &[&str]| {
+        let mut args = vec!["sign", "-m", "hello", "--toml", "--no-meta"];
+        args.extend_from_slice(options);
+        stdout(&run(&args))
+    };
+    assert_eq!(sign(&["--key", key]), sign(&["--bits", "1024", "--seed", "7"]));
+}
+
+#[test]
+fn test_a_seed_pins_the_pss_salt() {
+    let sign = |seed: &str| -> serde_json::Value {
+        let args = ["sign", "-m", "hello", "--key", "tests/fixtures/rsa_1024.pem", "--pss", "--seed", seed, "--json"];
+        serde_json::from_str(&stdout(&run(&args))).unwrap()
+    };
+    // The salt is the first 32 bytes ChaCha20 draws from the seed, so these limbs only change with the seed
+    let json = sign("42");
+    assert_eq!(json["modulus_limbs"][0], "0x5e0a556235a6382ba992c8b4a28e13");
+    assert_eq!(json["signature_limbs"][0], "0x3ed267384467ffe32191e80c3c89a2");
+    assert_eq!(json["signature_limbs"][8], "0x55413037eea69342");
+    assert_eq!(sign("0x2a"), json);
+    assert_eq!(sign("43")["signature_limbs"][0], "0x7516658ba6b07ae2f0e04f959db99c");
+}
+
+#[test]
+fn test_signed_inputs_verify() {
+    let scratch = tempfile::tempdir().unwrap();
+    let dir = scratch.path();
+    let inputs = dir.join("Prover.toml");
+    let inputs = inputs.to_str().unwrap();
+    stdout(&run(&["sign", "-m", "hello", "--bits", "1024", "--seed", "1", "--pss", "--output", inputs]));
+    stdout(&run(&["verify", inputs]));
+}
+
+#[test]
+fn test_each_batch_file_is_what_sign_prints() {
+    let scratch = tempfile::tempdir().unwrap();
+    let (list, out_dir) = (scratch.path().join("list.toml"), scratch.path().join("out"));
+    let entries = "[[entries]]\nname = \"plain\"\nmessage = \"hello\"\n\n\
+                   [[entries]]\nname = \"sha512\"\nmessage = \"-dash\"\nhash = \"sha512\"\n";
+    std::fs::write(&list, entries).unwrap();
+    let key = ["--key", "tests/fixtures/rsa_1024.pem", "--no-meta"];
+    let printed = stdout(&run(&[
+        &["sign", "--batch", list.to_str().unwrap(), "--out-dir", out_dir.to_str().unwrap()][..],
+        &key[..],
+    ]
+    .concat()));
+    assert!(printed.contains("plain: saved to") && printed.contains("sha512: saved to"), "{}", printed);
+
+    let sign = |options: &[&str]| stdout(&run(&[&["sign", "--toml"][..], &key[..], options].concat()));
+    let read = |name: &str| std::fs::read_to_string(out_dir.join(name)).unwrap();
+    assert_eq!(read("plain.toml"), sign(&["--msg=hello"]));
+    assert_eq!(read("sha512.toml"), sign(&["--msg=-dash", "--hash", "sha512"]));
+}
+
+#[test]
+fn test_importers_and_options_without_a_subcommand_run_as_sign() {
+    let from_cert = stdout(&run(&["from-cert", CERTIFICATE, "--toml", "--no-meta"]));
+    assert_eq!(from_cert, stdout(&run(&["sign", "--x509", CERTIFICATE, "--toml", "--no-meta"])));
+
+    let options = ["-m", "hello", "--bits", "1024", "--seed", "3", "--toml", "--no-meta"];
+    let legacy = run(&options);
+    assert_eq!(stdout(&legacy), stdout(&run(&[&["sign"][..], &options[..]].concat())));
+    assert!(String::from_utf8_lossy(&legacy.stderr).contains("deprecated"));
+}
+
+#[test]
+fn test_a_key_count_below_one_is_an_invalid_argument() {
+    for keys in ["0", "two"] {
+        let output = run(&["--error-format", "json", "sign", "-m", "hello", "--keys", keys]);
+        assert_eq!(output.status.code(), Some(2));
+        let err: serde_json::Value = serde_json::from_str(&stderr(&output)).unwrap();
+        assert_eq!((err["code"].as_str(), err["keys"].as_str()), (Some("invalid_argument"), Some(keys)));
+    }
+}
+
+#[test]
+fn test_the_recorded_command_starts_after_the_subcommand() {
+    let options = ["-m", "hello", "--bits", "1024", "--seed", "3", "--toml"];
+    let output = stdout(&run(&[&["--error-format", "json", "sign"][..], &options[..]].concat()));
+    let toml: toml::Value = output.parse().unwrap();
+    let command = toml["meta"]["command"].as_array().unwrap();
+    let command: Vec<&str> = command.iter().map(|arg| arg.as_str().unwrap()).collect();
+    assert_eq!(command, [&["sign"][..], &options[..]].concat());
+}
+
+#[test]
+fn test_regen_reruns_the_recorded_command() {
+    let scratch = tempfile::tempdir().unwrap();
+    let dir = scratch.path().to_str().unwrap();
+    let seeded = stdout(&run(&["sign", "-m", "hello", "--bits", "1024", "--seed", "5", "--pss", "--toml"]));
+    let keyed = stdout(&run(&["sign", "-m", "hello", "--key", "tests/fixtures/rsa_1024.pem", "--toml"]));
+    let (seeded_path, keyed_path) = (scratch.path().join("seeded.toml"), scratch.path().join("keyed.toml"));
+    std::fs::write(&seeded_path, &seeded).unwrap();
+    std::fs::write(&keyed_path, keyed.replacen("0x", "0x1", 1)).unwrap();
+
+    let printed = stdout(&run(&["regen", dir]));
+    assert!(printed.contains("1 updated, 1 unchanged, 0 skipped, 0 failed"), "{}", printed);
+    // The rerun restores the file byte for byte, but for the time it was made at
+    let without_timestamp = |toml: &str| -> String {
+        toml.lines().filter(|line| !line.starts_with("timestamp = ")).map(|line| format!("{}\n", line)).collect()
+    };
+    assert_eq!(std::fs::read_to_string(&seeded_path).unwrap(), seeded);
+    assert_eq!(without_timestamp(&std::fs::read_to_string(&keyed_path).unwrap()), without_timestamp(&keyed));
+}
+
+#[test]
+fn test_sign_records_its_files_in_the_manifest() {
+    let scratch = tempfile::tempdir().unwrap();
+    let path = |name: &str| scratch.path().join(name).to_str().unwrap().to_string();
+    let (manifest, vector, list, out_dir) = (path("manifest.toml"), path("vector.toml"), path("list.txt"), path("out"));
+    std::fs::write(&list, "hello\nworld\n").unwrap();
+    let seeded = ["--bits", "1024", "--seed", "5", "--no-meta", "--manifest", manifest.as_str()];
+    stdout(&run(&[&["sign", "-m", "hello", "--output", &vector][..], &seeded[..]].concat()));
+    stdout(&run(&[&["sign", "--batch", &list, "--out-dir", &out_dir][..], &seeded[..]].concat()));
+
+    let entries: toml::Value = std::fs::read_to_string(&manifest).unwrap().parse().unwrap();
+    let files = entries["files"].as_array().unwrap();
+    let paths: Vec<&str> = files.iter().map(|file| file["path"].as_str().unwrap()).collect();
+    assert_eq!(paths, ["out/hello.toml", "out/index.toml", "out/world.toml", "vector.toml"]);
+    stdout(&run(&["verify-manifest", &manifest, "--regenerate"]));
+
+    std::fs::write(&vector, "edited").unwrap();
+    let output = run(&["verify-manifest", &manifest]);
+    assert_eq!(output.status.code(), Some(5));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("vector.toml: sha256 is"));
+    let without_output = stderr(&run(&["sign", "-m", "hello", "--manifest", &manifest]));
+    assert!(without_output.contains("--output or the files of --batch"), "{}", without_output);
+    let own_manifest = path("out/manifest.toml");
+    let corpus = ["dkim-corpus", "--out-dir", &out_dir, "--count", "1", "--bits", "1024", "--manifest", &own_manifest];
+    let collision = stderr(&run(&corpus));
+    assert!(collision.contains("the corpus's own manifest.toml"), "{}", collision);
+}
+
+#[test]
+fn test_a_streamed_file_records_the_sha256_of_the_file_alone() {
+    let scratch = tempfile::tempdir().unwrap();
+    let path = scratch.path().join("message.bin");
+    std::fs::write(&path, b"hello").unwrap();
+    let path = path.to_str().unwrap();
+    let key = "tests/fixtures/rsa_1024.pem";
+    let output = stdout(&run(&["sign", "--msg-file", path, "--prefix", "p:", "--key", key, "--toml"]));
+    let toml: toml::Value = output.parse().unwrap();
+    assert_eq!(toml["meta"]["message_sha256"].as_str(), Some(hex::encode(HashAlg::Sha256.digest(b"hello")).as_str()));
+    assert_eq!(toml["meta"]["prefix_hex"].as_str(), Some(hex::encode(b"p:").as_str()));
+}
+
+#[test]
+fn test_msg_max_len_emits_every_in_memory_message() {
+    let args = ["sign", "--key", "tests/fixtures/rsa_1024.pem", "--no-meta", "--json", "--msg-max-len", "4"];
+    let emitted = |json: &str| -> (serde_json::Value, serde_json::Value) {
+        let json: serde_json::Value = serde_json::from_str(json).unwrap();
+        (json["message"].clone(), json["message_len"].clone())
+    };
+    let hex = stdout(&run(&[&args[..], &["--msg-hex", "00ff"]].concat()));
+    assert_eq!(emitted(&hex), (serde_json::json!([0, 255, 0, 0]), serde_json::json!(2)));
+
+    let mut child = Command::new(env!("CARGO_BIN_EXE_signature_gen"))
+        .args([&args[..], &["--msg-file", "-"]].concat())
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(&[0, 255]).unwrap();
+    assert_eq!(stdout(&child.wait_with_output().unwrap()), hex);
+
+    let err = stderr(&run(&[&args[..], &["-m", "hello"]].concat()));
+    assert!(err.contains("The message is 5 bytes which exceeds --msg-max-len 4"), "{}", err);
+}
+
+#[test]
+fn test_the_exponent_input_is_emitted_in_every_format() {
+    let sign = |options: &[&str]| {
+        let args = ["sign", "-m", "hello", "--key", "tests/fixtures/rsa_1024.pem", "--no-meta"];
+        stdout(&run(&[&args[..], options].concat()))
+    };
+    let toml: toml::Value = sign(&["--emit-exponent-input", "integer", "--toml"]).parse().unwrap();
+    assert_eq!(toml["exponent"].as_integer(), Some(65537));
+    let abi: serde_json::Value =
+        serde_json::from_str(&sign(&["--emit-exponent-input", "integer", "--format", "abi-json"])).unwrap();
+    assert_eq!(abi["exponent"], 65537);
+    assert!(sign(&["--emit-exponent-input", "integer"]).contains("let exponent: u32 = 65537;"));
+    let json: serde_json::Value = serde_json::from_str(&sign(&["--emit-exponent-input", "limbs", "--json"])).unwrap();
+    assert_eq!(json["exponent"][0], "0x10001");
+    assert_eq!(json["exponent"].as_array().unwrap().len(), 9);
+
+    // Without the flag no format has the input, whatever the key
+    let toml: toml::Value = sign(&["--toml"]).parse().unwrap();
+    assert!(toml.get("exponent").is_none());
+}
+
+#[test]
+fn test_dkim_signs_the_headers_of_a_real_email_with_its_dns_key() {
+    let args = [
+        "sign", "--dkim", "tests/fixtures/dkim_rfc8463.eml", "--dkim-dns", "tests/fixtures/dkim_rfc8463_dns.txt",
+        "--msg-max-len", "512", "--json",
+    ];
+    let json: serde_json::Value = serde_json::from_str(&stdout(&run(&args))).unwrap();
+    assert_eq!((json["key_size_bits"].as_u64(), json["hash_alg"].as_str()), (Some(1024), Some("sha256")));
+    let message: Vec<u8> = json["message"].as_array().unwrap().iter().map(|b| b.as_u64().unwrap() as u8).collect();
+    let message = &message[..json["message_len"].as_u64().unwrap() as usize];
+    assert!(message.starts_with(b"from:Joe SixPack <joe@football.example.com>\r\nto:Suzie Q"));
+    assert_eq!(json["hash"], serde_json::json!(HashAlg::Sha256.digest(message)));
+
+    let scratch = tempfile::tempdir().unwrap();
+    let tampered = scratch.path().join("tampered.eml");
+    let eml = std::fs::read_to_string(args[2]).unwrap();
+    std::fs::write(&tampered, eml.replacen("Is dinner ready?", "Is dinner ready?!", 1)).unwrap();
+    let err = stderr(&run(&[&args[..2], &[tampered.to_str().unwrap()], &args[3..]].concat()));
+    assert!(err.contains("does not verify"), "{}", err);
+}
+
+#[test]
+fn test_from_jwt_signs_the_token_with_its_jwk() {
+    let key = RsaPrivateKey::from_pkcs8_pem(include_str!("fixtures/rsa_2048.pem")).unwrap();
+    let scratch = tempfile::tempdir().unwrap();
+    let dir = scratch.path();
+    let jwk = dir.join("key.jwk");
+    std::fs::write(&jwk, export_public_key(&key.to_public_key(), ExportFormat::Jwk).unwrap()).unwrap();
+    let header = Base64UrlUnpadded::encode_string(br#"{"alg":"RS256","typ":"JWT"}"#);
+    let input = format!("{}.{}", header, Base64UrlUnpadded::encode_string(b"{}"));
+    let digest = HashAlg::Sha256.digest(input.as_bytes());
+    let mut rng = rand_chacha::ChaCha20Rng::seed_from_u64(0);
+    let signature = sign_digest(&mut rng, &key, HashAlg::Sha256, &digest, &Scheme::Pkcs1v15).unwrap();
+    let token = format!("{}.{}", input, Base64UrlUnpadded::encode_string(&signature));
+
+    let json: serde_json::Value =
+        serde_json::from_str(&stdout(&run(&["from-jwt", &token, "--jwk", jwk.to_str().unwrap(), "--json"]))).unwrap();
+    assert_eq!(json["hash"], serde_json::json!(digest));
+    assert_eq!(json["scheme"], "pkcs1v15");
+    let inputs = dir.join("Prover.toml");
+    let inputs = inputs.to_str().unwrap();
+    stdout(&run(&["from-jwt", &token, "--jwk", jwk.to_str().unwrap(), "--output", inputs]));
+    stdout(&run(&["verify", inputs]));
+
+    // A token whose payload was changed after signing does not verify under the key
+    let tampered = token.replacen(".e30.", ".e30K.", 1);
+    assert!(!run(&["from-jwt", &tampered, "--jwk", jwk.to_str().unwrap(), "--json"]).status.success());
+}
+
+#[test]
+fn test_invalid_arguments_are_rejected_when_parsed() {
+    // An option of another subcommand is named with the subcommands taking it
+    let err = stderr(&run(&["params", "--bits", "2048", "--pss"]));
+    assert!(err.contains("--pss is an option of") && err.contains("sign"), "{}", err);
+
+    assert!(stderr(&run(&["keygen", "--bits", "2048"])).contains("--out"));
+    assert!(stderr(&run(&["from-jwt", "a.b.c"])).contains("--jwk"));
+    assert!(stderr(&run(&["from-cert"])).contains("<input>"));
+    assert!(stderr(&run(&["sign", "-m", "hello", "--x509", CERTIFICATE])).contains("cannot be used with"));
+
+    let output = run(&["keygen", "--bits", "1000", "--out", "never-written.pem"]);
+    assert!(stderr(&output).contains("1024, 2048, 3072 or 4096"));
+    assert_eq!(output.status.code(), Some(2));
+
+    // Every failure is reported through its code, also the unknown option and the checks made after parsing
+    for args in [&["params", "--pss"][..], &["sign", "-m", "hello", "--annotate", "--format", "abi-json"]] {
+        let output = run(&[&["--error-format", "json"][..], args].concat());
+        assert_eq!(output.status.code(), Some(2));
+        let err: serde_json::Value = serde_json::from_str(&stderr(&output)).unwrap();
+        assert_eq!(err["code"], "invalid_argument", "{}", err);
+    }
+}
+
+#[test]
+fn test_values_that_do_not_parse_are_invalid_arguments() {
+    let cases: [(&[&str], &str, &str); 5] = [
+        (&["sign", "-m", "hello", "--bits", "abc"], "bits", "abc"),
+        (&["params", "--pubkey", "zz"], "pubkey", "zz"),
+        (&["sign", "-m", "hello", "--bits", "1024", "--iterations", "x"], "iterations", "x"),
+        (&["sign", "-m", "hello", "--bits", "1024", "--aggregate", "x"], "aggregate", "x"),
+        (&["dkim-corpus", "--bits", "1024", "--count", "x"], "count", "x"),
+    ];
+    for (args, flag, value) in cases {
+        let output = run(&[&["--error-format", "json"][..], args].concat());
+        assert_eq!(output.status.code(), Some(2), "{:?}", args);
+        let err: serde_json::Value = serde_json::from_str(&stderr(&output)).unwrap();
+        assert_eq!((err["code"].as_str(), err[flag].as_str()), (Some("invalid_argument"), Some(value)), "{}", err);
+    }
+}
@@ -0,0 +1,86 @@
+use std::net::TcpListener;
+use std::process::{Child, Command};
+use std::thread::sleep;
+use std::time::Duration;
+
+use serde_json::{json, Value};
+
+const PUBLIC_KEY: &str = include_str!("fixtures/rsa_2048_pub.pem");
+const SIGNATURE: &[u8] = include_bytes!("fixtures/rsa_2048_sha256_pkcs1v15.sig");
+const MESSAGE: &str = "Hello World! This is Noir-RSA";
+
+/// Kills the server when the test ends, even on failure
+struct ServerProcess {
+    child: Child,
+    url: String,
+}
+
+impl Drop for ServerProcess {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+fn start_server() -> ServerProcess {
+    let port = TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+    let listen = format!("127.0.0.1:{}", port);
+    let child = Command::new(env!("CARGO_BIN_EXE_signature_gen"))
+        .args(["serve", "--listen", &listen])
+        .spawn()
+        .expect("failed to start signature_gen serve");
+    let server = ServerProcess {
+        child,
+        url: format!("http://{}", listen),
+    };
+
+    for _ in 0..100 {
+        if reqwest::blocking::get(format!("{}/health", server.url)).is_ok() {
+            return server;
+        }
+        sleep(Duration::from_millis(50));
+    }
+    panic!("server did not start on {}", listen);
+}
+
+#[test]
+fn test_serve_params() {
+    let server = start_server();
+    let client = reqwest::blocking::Client::new();
+    let endpoint = format!("{}/v1/params", server.url);
+
+    let response = client
+        .post(&endpoint)
+        .json(&json!({
+            "public_key": PUBLIC_KEY,
+            "signature_hex": hex::encode(SIGNATURE),
+            "message": MESSAGE,
+        }))
+        .send()
+        .unwrap();
+    assert_eq!(response.status(), 200);
+    let body: Value = response.json().unwrap();
+    assert_eq!(body["bits"], 2048);
+    assert_eq!(body["modulus_limbs"].as_array().unwrap().len(), 18);
+    assert_eq!(body["modulus_limbs"][0], "0xd9408f681f2744650b2ffb8b8ece1b");
+    assert_eq!(body["signature_limbs"][0], "0xdfd2ecd8d18e88f761b181a3c25b12");
+
+    let response = client
+        .post(&endpoint)
+        .json(&json!({
+            "public_key": PUBLIC_KEY,
+            "signature_hex": hex::encode(SIGNATURE),
+            "message": "tampered",
+        }))
+        .send()
+        .unwrap();
+    assert_eq!(response.status(), 422);
+    let body: Value = response.json().unwrap();
+    assert_eq!(body["error"]["code"], "verification_failed");
+
+    let response = client.post(&endpoint).body(vec![b' '; 128 * 1024]).send().unwrap();
+    assert_eq!(response.status(), 413);
+
+    let response = client.get(format!("{}/v1/keygen", server.url)).send().unwrap();
+    assert_eq!(response.status(), 404);
+}
@@ -0,0 +1,30 @@
+//! Smoke test of the wasm bindings on the fixture signature, run with
+//! `wasm-pack test --node -- --no-default-features --features wasm --test wasm`
+#![cfg(all(target_arch = "wasm32", feature = "wasm"))]
+
+use rsa::traits::PublicKeyParts;
+use serde_json::Value;
+use wasm_bindgen_test::wasm_bindgen_test;
+
+use signature_gen::keys::parse_public_key;
+use signature_gen::wasm::generate_circuit_inputs;
+
+#[wasm_bindgen_test]
+fn test_generate_circuit_inputs_for_the_fixture_signature() {
+    let modulus = parse_public_key(include_str!("fixtures/rsa_2048_pub.pem")).unwrap().n().to_bytes_be();
+    let signature = include_bytes!("fixtures/rsa_2048_sha256_pkcs1v15.sig");
+    let msg = b"Hello World! This is Noir-RSA";
+    let inputs = generate_circuit_inputs(msg, &modulus, signature, "sha256", "pkcs1v15").unwrap();
+    let inputs: Value = serde_wasm_bindgen::from_value(inputs).unwrap();
+
+    assert_eq!(inputs["hash"], "0x5bcf2e3c1699d990027fe08fb52d20787a83a64fa6b72b9e746949cfc44d2105");
+    for (limbs, first, last) in [
+        ("modulus_limbs", "0xd9408f681f2744650b2ffb8b8ece1b", "0xa1"),
+        ("redc_limbs", "0x99d5ce601fd9e05cc4af880b3459d7", "0x65a9"),
+        ("signature_limbs", "0xdfd2ecd8d18e88f761b181a3c25b12", "0x11"),
+    ] {
+        let limbs = inputs[limbs].as_array().unwrap();
+        assert_eq!((limbs.len(), &limbs[0], &limbs[17]), (18, &Value::from(first), &Value::from(last)));
+    }
+    assert!(generate_circuit_inputs(b"tampered", &modulus, signature, "sha256", "pkcs1v15").is_err());
+}